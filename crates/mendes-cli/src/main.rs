@@ -1,15 +1,17 @@
 //! Mendes compiler CLI
 
 use clap::{Parser, Subcommand, ValueEnum};
-use mendes_error::{DiagnosticRenderer, SourceCache};
-use mendes_lexer::{Lexer, TokenKind};
-use mendes_parser::parse;
-use mendes_semantic::{analyze, SemanticContext};
-use mendes_ir::lower_program;
-use mendes_codegen::{CBackend, RustBackend, CodeGen};
+use mendes_error::{DiagnosticRenderer, Diagnostics, SourceCache};
+use mendes_lexer::{Lexer, Token, TokenKind};
+use mendes_parser::{parse, Program};
+use mendes_semantic::{analyze, analyze_with_cfg, SemanticContext};
+use mendes_ir::{lower_program_with_cfg, lower_program_with_options, LowerOptions};
+use mendes_codegen::{CBackend, GraphBackend, JsonBackend, RustBackend, CodeGen};
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{Duration, Instant};
 
 /// Code generation backend
 #[derive(Debug, Clone, Copy, Default, ValueEnum)]
@@ -21,6 +23,17 @@ enum Backend {
     C,
 }
 
+/// What `build` produces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum EmitStage {
+    /// A linked executable (default)
+    #[default]
+    Exe,
+    /// An object file (`cc -c`) for linking from another build system;
+    /// C backend only
+    Obj,
+}
+
 #[derive(Parser)]
 #[command(name = "mendes")]
 #[command(author = "Guilherme Mendes")]
@@ -47,9 +60,21 @@ enum Commands {
         #[arg(short, long, default_value = "rust")]
         backend: Backend,
 
+        /// Stage to stop at: a linked executable or an object file
+        #[arg(long, default_value = "exe")]
+        emit: EmitStage,
+
         /// Release mode (optimized)
         #[arg(long)]
         release: bool,
+
+        /// Enables `@cfg(NAME)` items (repeatable)
+        #[arg(long = "cfg", value_name = "NAME")]
+        cfg: Vec<String>,
+
+        /// Prints the time spent in each compiler phase
+        #[arg(long)]
+        profile: bool,
     },
 
     /// Checks for errors without compiling
@@ -57,6 +82,14 @@ enum Commands {
         /// Input file
         #[arg(value_name = "FILE")]
         input: PathBuf,
+
+        /// Fails when there are warnings, not just errors
+        #[arg(long)]
+        deny_warnings: bool,
+
+        /// Enables `@cfg(NAME)` items (repeatable)
+        #[arg(long = "cfg", value_name = "NAME")]
+        cfg: Vec<String>,
     },
 
     /// Shows file tokens (debug)
@@ -64,6 +97,10 @@ enum Commands {
         /// Input file
         #[arg(value_name = "FILE")]
         input: PathBuf,
+
+        /// Prints the tokens as JSON, for editor integrations
+        #[arg(long)]
+        json: bool,
     },
 
     /// Shows file AST (debug)
@@ -78,6 +115,17 @@ enum Commands {
         /// Input file
         #[arg(value_name = "FILE")]
         input: PathBuf,
+
+        /// Enables `@cfg(NAME)` items (repeatable)
+        #[arg(long = "cfg", value_name = "NAME")]
+        cfg: Vec<String>,
+    },
+
+    /// Shows the inferred type of every expression (debug)
+    Types {
+        /// Input file
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
     },
 
     /// Generates C code from file
@@ -89,6 +137,10 @@ enum Commands {
         /// Output file (default: stdout)
         #[arg(short, long, value_name = "FILE")]
         output: Option<PathBuf>,
+
+        /// Enables `@cfg(NAME)` items (repeatable)
+        #[arg(long = "cfg", value_name = "NAME")]
+        cfg: Vec<String>,
     },
 
     /// Generates Rust code from file
@@ -100,6 +152,47 @@ enum Commands {
         /// Output file (default: stdout)
         #[arg(short, long, value_name = "FILE")]
         output: Option<PathBuf>,
+
+        /// Enables `@cfg(NAME)` items (repeatable)
+        #[arg(long = "cfg", value_name = "NAME")]
+        cfg: Vec<String>,
+    },
+
+    /// Generates the IR as JSON, for external tools
+    EmitJson {
+        /// Input file
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// Output file (default: stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Enables `@cfg(NAME)` items (repeatable)
+        #[arg(long = "cfg", value_name = "NAME")]
+        cfg: Vec<String>,
+    },
+
+    /// Generates the call and route graph as Graphviz DOT
+    Graph {
+        /// Input file
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// Output file (default: stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Enables `@cfg(NAME)` items (repeatable)
+        #[arg(long = "cfg", value_name = "NAME")]
+        cfg: Vec<String>,
+    },
+
+    /// Describes an error code, e.g. `ET001`
+    Explain {
+        /// Error code
+        #[arg(value_name = "CODE")]
+        code: String,
     },
 
     /// Runs a .ms file (future: JIT or interpreter)
@@ -114,7 +207,11 @@ fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Build { input, output, backend, release } => {
+        Commands::Build { input, output, backend, emit, release, cfg, profile } => {
+            if emit == EmitStage::Obj && !matches!(backend, Backend::C) {
+                eprintln!("Error: `--emit obj` requires `--backend c`");
+                std::process::exit(1);
+            }
             println!("Compiling: {}", input.display());
 
             match fs::read_to_string(&input) {
@@ -123,48 +220,23 @@ fn main() {
                     let file_id = cache.add(input.display().to_string(), &source);
                     let renderer = DiagnosticRenderer::new(&cache);
 
-                    // Phase 1: Lexical analysis
-                    let mut lexer = Lexer::new(&source, file_id);
-                    let tokens = lexer.tokenize();
-                    let lex_diags = lexer.take_diagnostics();
-
-                    if lex_diags.has_errors() {
-                        eprintln!("Lexer errors:\n");
-                        for diag in lex_diags.iter() {
-                            eprintln!("{}", renderer.render(diag));
-                        }
-                        std::process::exit(1);
-                    }
-                    println!("  [ok] Lexer: {} tokens", tokens.len());
-
-                    // Phase 2: Parsing
-                    let (program, parse_diags) = parse(tokens);
-
-                    if parse_diags.has_errors() {
-                        eprintln!("\nSyntax errors:\n");
-                        for diag in parse_diags.iter() {
-                            eprintln!("{}", renderer.render(diag));
+                    // Phases 1-4: lexing, parsing, semantic analysis and IR
+                    let mut timings = PhaseTimings::default();
+                    let cfg = cfg.iter().cloned().collect();
+                    let mut ir_module = match front_end(&source, file_id, &cfg, &mut timings, &mut std::io::stdout()) {
+                        Ok(module) => module,
+                        Err((heading, diagnostics)) => {
+                            eprintln!("{}", heading);
+                            for diag in diagnostics.iter() {
+                                eprintln!("{}", renderer.render(diag));
+                            }
+                            std::process::exit(1);
                         }
-                        std::process::exit(1);
-                    }
-                    println!("  [ok] Parser: {} statements", program.statements.len());
+                    };
 
-                    // Phase 3: Semantic analysis
-                    let mut ctx = SemanticContext::new();
-                    let semantic_diags = analyze(&program, &mut ctx);
-
-                    if semantic_diags.has_errors() {
-                        eprintln!("\nSemantic errors:\n");
-                        for diag in semantic_diags.iter() {
-                            eprintln!("{}", renderer.render(diag));
-                        }
-                        std::process::exit(1);
-                    }
-                    println!("  [ok] Semantic: types verified");
-
-                    // Phase 4: Generate IR
-                    let ir_module = lower_program(&program);
-                    println!("  [ok] IR: {} functions, {} routes", ir_module.functions.len(), ir_module.routes.len());
+                    // Release builds clean up the IR before code generation
+                    let opt_level = if release { 3 } else { 0 };
+                    mendes_ir::optimize(&mut ir_module, opt_level);
 
                     // Determine output name
                     let output_name = output
@@ -175,12 +247,26 @@ fn main() {
                                 .unwrap_or_else(|| "output".to_string())
                         });
 
+                    // Phase 5: code generation; release builds skip bounds
+                    // checks proven unnecessary
+                    let code = timings.time("codegen", || match backend {
+                        Backend::Rust => RustBackend::new().with_opt_level(opt_level).generate(&ir_module),
+                        Backend::C => CBackend::new().generate(&ir_module),
+                    });
+                    println!("  [ok] Codegen: {} code generated", match backend {
+                        Backend::Rust => "Rust",
+                        Backend::C => "C",
+                    });
+                    if profile {
+                        print!("\n{}", timings.summary());
+                    }
+
                     match backend {
                         Backend::Rust => {
-                            build_with_rust_backend(&ir_module, &output_name, release);
+                            build_with_rust_backend(&code, &output_name, release);
                         }
                         Backend::C => {
-                            build_with_c_backend(&ir_module, &output_name);
+                            build_with_c_backend(&code, &output_name, emit);
                         }
                     }
                 }
@@ -191,7 +277,7 @@ fn main() {
             }
         }
 
-        Commands::Check { input } => {
+        Commands::Check { input, deny_warnings, cfg } => {
             println!("Checking: {}\n", input.display());
 
             match fs::read_to_string(&input) {
@@ -228,7 +314,7 @@ fn main() {
 
                     // Phase 3: Semantic analysis
                     let mut ctx = SemanticContext::new();
-                    let semantic_diags = analyze(&program, &mut ctx);
+                    let semantic_diags = analyze_with_cfg(&program, &mut ctx, &cfg.into_iter().collect());
 
                     if semantic_diags.has_errors() {
                         eprintln!("\nSemantic errors:\n");
@@ -248,6 +334,11 @@ fn main() {
                         println!("  [ok] Semantic: types verified");
                     }
 
+                    if check_fails(&semantic_diags, deny_warnings) {
+                        eprintln!("\nerror: warnings are denied by --deny-warnings");
+                        std::process::exit(1);
+                    }
+
                     println!("\nNo errors found!");
                 }
                 Err(e) => {
@@ -257,8 +348,10 @@ fn main() {
             }
         }
 
-        Commands::Lex { input } => {
-            println!("Tokenizing: {}\n", input.display());
+        Commands::Lex { input, json } => {
+            if !json {
+                println!("Tokenizing: {}\n", input.display());
+            }
 
             match fs::read_to_string(&input) {
                 Ok(source) => {
@@ -269,6 +362,19 @@ fn main() {
                     let tokens = lexer.tokenize();
                     let diagnostics = lexer.take_diagnostics();
 
+                    // Only the JSON goes to stdout; errors are reported on stderr
+                    if json {
+                        println!("{}", tokens_json(&source, &tokens));
+                        let renderer = DiagnosticRenderer::new(&cache);
+                        for diag in diagnostics.iter() {
+                            eprintln!("{}", renderer.render(diag));
+                        }
+                        if diagnostics.has_errors() {
+                            std::process::exit(1);
+                        }
+                        return;
+                    }
+
                     // Show tokens
                     for token in &tokens {
                         let kind_str = format!("{:?}", token.kind);
@@ -354,7 +460,7 @@ fn main() {
             }
         }
 
-        Commands::Ir { input } => {
+        Commands::Ir { input, cfg } => {
             println!("Generating IR: {}\n", input.display());
 
             match fs::read_to_string(&input) {
@@ -387,7 +493,7 @@ fn main() {
                     }
 
                     // Generate IR
-                    let ir_module = lower_program(&program);
+                    let ir_module = lower_program_with_cfg(&program, &cfg.iter().cloned().collect());
 
                     // Show the IR
                     println!("{}", ir_module);
@@ -408,7 +514,57 @@ fn main() {
             }
         }
 
-        Commands::Emit { input, output } => {
+        Commands::Types { input } => {
+            println!("Inferring types: {}\n", input.display());
+
+            match fs::read_to_string(&input) {
+                Ok(source) => {
+                    let mut cache = SourceCache::new();
+                    let file_id = cache.add(input.display().to_string(), &source);
+                    let renderer = DiagnosticRenderer::new(&cache);
+
+                    let mut lexer = Lexer::new(&source, file_id);
+                    let tokens = lexer.tokenize();
+                    let lex_diags = lexer.take_diagnostics();
+
+                    if lex_diags.has_errors() {
+                        eprintln!("Lexer errors:\n");
+                        for diag in lex_diags.iter() {
+                            eprintln!("{}", renderer.render(diag));
+                        }
+                        std::process::exit(1);
+                    }
+
+                    let (program, parse_diags) = parse(tokens);
+
+                    if parse_diags.has_errors() {
+                        eprintln!("Syntax errors:\n");
+                        for diag in parse_diags.iter() {
+                            eprintln!("{}", renderer.render(diag));
+                        }
+                        std::process::exit(1);
+                    }
+
+                    // Semantic errors don't stop the dump: the types usually
+                    // explain them
+                    let mut ctx = SemanticContext::new();
+                    let semantic_diags = analyze(&program, &mut ctx);
+                    for diag in semantic_diags.iter() {
+                        eprintln!("{}", renderer.render(diag));
+                    }
+
+                    for line in ctx.type_dump() {
+                        println!("  {}", line);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error reading file: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Emit { input, output, cfg } => {
             println!("Generating C code: {}\n", input.display());
 
             match fs::read_to_string(&input) {
@@ -442,7 +598,8 @@ fn main() {
 
                     // Semantic analysis
                     let mut ctx = SemanticContext::new();
-                    let semantic_diags = analyze(&program, &mut ctx);
+                    let cfg: HashSet<String> = cfg.into_iter().collect();
+                    let semantic_diags = analyze_with_cfg(&program, &mut ctx, &cfg);
 
                     if semantic_diags.has_errors() {
                         eprintln!("Semantic errors:\n");
@@ -454,7 +611,7 @@ fn main() {
                     }
 
                     // Generate IR
-                    let ir_module = lower_program(&program);
+                    let ir_module = lower_checked(&program, &cfg, &ctx);
 
                     // Generate C code
                     let backend = CBackend::new();
@@ -486,7 +643,7 @@ fn main() {
             }
         }
 
-        Commands::EmitRust { input, output } => {
+        Commands::EmitRust { input, output, cfg } => {
             println!("Generating Rust code: {}\n", input.display());
 
             match fs::read_to_string(&input) {
@@ -520,7 +677,8 @@ fn main() {
 
                     // Semantic analysis
                     let mut ctx = SemanticContext::new();
-                    let semantic_diags = analyze(&program, &mut ctx);
+                    let cfg: HashSet<String> = cfg.into_iter().collect();
+                    let semantic_diags = analyze_with_cfg(&program, &mut ctx, &cfg);
 
                     if semantic_diags.has_errors() {
                         eprintln!("Semantic errors:\n");
@@ -532,7 +690,7 @@ fn main() {
                     }
 
                     // Generate IR
-                    let ir_module = lower_program(&program);
+                    let ir_module = lower_checked(&program, &cfg, &ctx);
 
                     // Generate Rust code
                     let backend = RustBackend::new();
@@ -561,6 +719,140 @@ fn main() {
             }
         }
 
+        Commands::EmitJson { input, output, cfg } => {
+            // Status goes to stderr so stdout is only the JSON
+            eprintln!("Generating IR JSON: {}\n", input.display());
+
+            match fs::read_to_string(&input) {
+                Ok(source) => {
+                    let mut cache = SourceCache::new();
+                    let file_id = cache.add(input.display().to_string(), &source);
+
+                    let mut lexer = Lexer::new(&source, file_id);
+                    let tokens = lexer.tokenize();
+                    let lex_diags = lexer.take_diagnostics();
+
+                    if lex_diags.has_errors() {
+                        eprintln!("Lexer errors:\n");
+                        let renderer = DiagnosticRenderer::new(&cache);
+                        for diag in lex_diags.iter() {
+                            eprintln!("{}", renderer.render(diag));
+                        }
+                        std::process::exit(1);
+                    }
+
+                    let (program, parse_diags) = parse(tokens);
+
+                    if parse_diags.has_errors() {
+                        eprintln!("Syntax errors:\n");
+                        let renderer = DiagnosticRenderer::new(&cache);
+                        for diag in parse_diags.iter() {
+                            eprintln!("{}", renderer.render(diag));
+                        }
+                        std::process::exit(1);
+                    }
+
+                    // Semantic analysis
+                    let mut ctx = SemanticContext::new();
+                    let cfg: HashSet<String> = cfg.into_iter().collect();
+                    let semantic_diags = analyze_with_cfg(&program, &mut ctx, &cfg);
+
+                    if semantic_diags.has_errors() {
+                        eprintln!("Semantic errors:\n");
+                        let renderer = DiagnosticRenderer::new(&cache);
+                        for diag in semantic_diags.iter() {
+                            eprintln!("{}", renderer.render(diag));
+                        }
+                        std::process::exit(1);
+                    }
+
+                    // Generate IR
+                    let ir_module = lower_checked(&program, &cfg, &ctx);
+
+                    // Serialize the IR
+                    let json = JsonBackend::new().generate(&ir_module);
+
+                    // Write output
+                    if let Some(output_path) = output {
+                        match fs::write(&output_path, &json) {
+                            Ok(_) => {
+                                eprintln!("IR JSON generated at: {}", output_path.display());
+                            }
+                            Err(e) => {
+                                eprintln!("Error writing file: {}", e);
+                                std::process::exit(1);
+                            }
+                        }
+                    } else {
+                        // Output to stdout
+                        println!("{}", json);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error reading file: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Graph { input, output, cfg } => {
+            // Status goes to stderr so stdout is only the graph
+            eprintln!("Generating graph: {}\n", input.display());
+
+            match fs::read_to_string(&input) {
+                Ok(source) => {
+                    let mut cache = SourceCache::new();
+                    let file_id = cache.add(input.display().to_string(), &source);
+
+                    let cfg = cfg.iter().cloned().collect();
+                    let mut timings = PhaseTimings::default();
+                    let ir_module = match front_end(&source, file_id, &cfg, &mut timings, &mut std::io::stderr()) {
+                        Ok(module) => module,
+                        Err((heading, diagnostics)) => {
+                            eprintln!("{}", heading);
+                            let renderer = DiagnosticRenderer::new(&cache);
+                            for diag in diagnostics.iter() {
+                                eprintln!("{}", renderer.render(diag));
+                            }
+                            std::process::exit(1);
+                        }
+                    };
+
+                    let dot = GraphBackend::new().generate(&ir_module);
+
+                    // Write output
+                    if let Some(output_path) = output {
+                        match fs::write(&output_path, &dot) {
+                            Ok(_) => {
+                                eprintln!("Graph generated at: {}", output_path.display());
+                            }
+                            Err(e) => {
+                                eprintln!("Error writing file: {}", e);
+                                std::process::exit(1);
+                            }
+                        }
+                    } else {
+                        // Output to stdout
+                        print!("{}", dot);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error reading file: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::Explain { code } => {
+            match mendes_error::ErrorCode::lookup(&code) {
+                Some(code) => println!("{}: {}", code, code.title()),
+                None => {
+                    eprintln!("Unknown error code: {}", code);
+                    std::process::exit(1);
+                }
+            }
+        }
+
         Commands::Run { input } => {
             println!("Running: {}", input.display());
             println!("   [TODO] Execution not yet implemented");
@@ -568,15 +860,111 @@ fn main() {
     }
 }
 
+/// Token as printed by `mendes lex --json`: the token's kind, value and
+/// span, plus the source text it covers
+#[derive(serde::Serialize)]
+struct JsonToken<'a> {
+    #[serde(flatten)]
+    token: &'a Token,
+    lexeme: &'a str,
+}
+
+/// Serializes `tokens` as a JSON array; each entry reads back as a `Token`
+fn tokens_json(source: &str, tokens: &[Token]) -> String {
+    let entries: Vec<_> = tokens.iter()
+        .map(|token| JsonToken {
+            token,
+            lexeme: source.get(token.span.start.offset..token.span.end.offset).unwrap_or(""),
+        })
+        .collect();
+    serde_json::to_string_pretty(&entries).expect("tokens serialize to JSON")
+}
+
+/// Lexes, parses, checks and lowers `source`, timing each phase and writing
+/// a line per phase to `status`. On errors, returns the heading to print and
+/// the phase's diagnostics.
+fn front_end(
+    source: &str,
+    file_id: u32,
+    cfg: &HashSet<String>,
+    timings: &mut PhaseTimings,
+    status: &mut dyn std::io::Write,
+) -> Result<mendes_ir::Module, (&'static str, Diagnostics)> {
+    let mut lexer = Lexer::new(source, file_id);
+    let tokens = timings.time("lex", || lexer.tokenize());
+    let lex_diags = lexer.take_diagnostics();
+    if lex_diags.has_errors() {
+        return Err(("Lexer errors:\n", lex_diags));
+    }
+    let _ = writeln!(status, "  [ok] Lexer: {} tokens", tokens.len());
+
+    let (program, parse_diags) = timings.time("parse", || parse(tokens));
+    if parse_diags.has_errors() {
+        return Err(("\nSyntax errors:\n", parse_diags));
+    }
+    let _ = writeln!(status, "  [ok] Parser: {} statements", program.statements.len());
+
+    let mut ctx = SemanticContext::new();
+    let semantic_diags = timings.time("semantic", || analyze_with_cfg(&program, &mut ctx, cfg));
+    if semantic_diags.has_errors() {
+        return Err(("\nSemantic errors:\n", semantic_diags));
+    }
+    let _ = writeln!(status, "  [ok] Semantic: types verified");
+
+    let ir_module = timings.time("lower", || lower_checked(&program, cfg, &ctx));
+    let _ = writeln!(status, "  [ok] IR: {} functions, {} routes", ir_module.functions.len(), ir_module.routes.len());
+    Ok(ir_module)
+}
+
+/// Lowers a checked program, passing on the expression types the checker
+/// resolved
+fn lower_checked(program: &Program, cfg: &HashSet<String>, ctx: &SemanticContext) -> mendes_ir::Module {
+    let options = LowerOptions { cfg: cfg.clone(), expr_types: ctx.written_expr_types(), ..Default::default() };
+    lower_program_with_options(program, &options)
+}
+
+/// Wall-clock time spent in each compiler phase, in the order they ran
+#[derive(Debug, Default)]
+struct PhaseTimings {
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl PhaseTimings {
+    /// Runs `f`, recording how long it took under `phase`
+    fn time<T>(&mut self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let value = f();
+        self.phases.push((phase, start.elapsed()));
+        value
+    }
+
+    fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    /// A table with each phase's time and share of the total
+    fn summary(&self) -> String {
+        let total = self.total();
+        let mut out = String::from("Phase timings:\n");
+        for (phase, duration) in &self.phases {
+            let share = if total.is_zero() { 0.0 } else { duration.as_secs_f64() / total.as_secs_f64() * 100.0 };
+            out.push_str(&format!("  {:<10} {:>10.3} ms {:>6.1}%\n", phase, duration.as_secs_f64() * 1000.0, share));
+        }
+        out.push_str(&format!("  {:<10} {:>10.3} ms\n", "total", total.as_secs_f64() * 1000.0));
+        out
+    }
+}
+
+/// Whether `check` fails: on any error, or also on any warning under
+/// `--deny-warnings`
+fn check_fails(diagnostics: &mendes_error::Diagnostics, deny_warnings: bool) -> bool {
+    diagnostics.has_errors() || (deny_warnings && diagnostics.has_warnings())
+}
+
 /// Compiles using the Rust backend
-fn build_with_rust_backend(ir_module: &mendes_ir::Module, output_name: &str, release: bool) {
+fn build_with_rust_backend(rust_code: &str, output_name: &str, release: bool) {
     use std::io::Write;
 
-    // Generate Rust code
-    let backend = RustBackend::new();
-    let rust_code = backend.generate(ir_module);
-    println!("  [ok] Codegen: Rust code generated");
-
     // Create temporary directory for the Cargo project
     let temp_dir = std::env::temp_dir().join(format!("mendes_build_{}", std::process::id()));
     let src_dir = temp_dir.join("src");
@@ -616,7 +1004,7 @@ serde_json = "1.0"
 
     // Write the source code
     let main_path = src_dir.join("main.rs");
-    if let Err(e) = fs::write(&main_path, &rust_code) {
+    if let Err(e) = fs::write(&main_path, rust_code) {
         eprintln!("Error writing main.rs: {}", e);
         std::process::exit(1);
     }
@@ -685,44 +1073,49 @@ serde_json = "1.0"
     // fs::remove_dir_all(&temp_dir).ok();
 }
 
-/// Compiles using the C backend
-fn build_with_c_backend(ir_module: &mendes_ir::Module, output_name: &str) {
-    // Generate C code
-    let backend = CBackend::new();
-    let c_code = backend.generate(ir_module);
-    println!("  [ok] Codegen: C code generated");
+/// C compiler invocation for `c_file`: links an executable, or with
+/// `EmitStage::Obj` stops at the object file (`-c`)
+fn c_compile_command(compiler: &str, c_file: &Path, out_path: &Path, emit: EmitStage) -> Command {
+    let mut cmd = Command::new(compiler);
+    if emit == EmitStage::Obj {
+        cmd.arg("-c");
+    }
+    cmd.arg("-o").arg(out_path).arg(c_file);
+    if emit == EmitStage::Exe {
+        cmd.arg("-lpthread");  // For thread support
+    }
+    cmd
+}
 
+/// Compiles using the C backend
+fn build_with_c_backend(c_code: &str, output_name: &str, emit: EmitStage) {
     // Create temporary .c file
     let temp_dir = std::env::temp_dir();
     let c_file = temp_dir.join(format!("{}.c", output_name));
 
-    if let Err(e) = fs::write(&c_file, &c_code) {
+    if let Err(e) = fs::write(&c_file, c_code) {
         eprintln!("Error creating C file: {}", e);
         std::process::exit(1);
     }
 
-    // Determine executable name
-    let exe_name = if cfg!(windows) {
-        format!("{}.exe", output_name)
-    } else {
-        output_name.to_string()
+    // Determine output file name; an explicit `-o lib.o` is kept as given
+    let out_name = match emit {
+        EmitStage::Exe if cfg!(windows) => format!("{}.exe", output_name),
+        EmitStage::Exe => output_name.to_string(),
+        EmitStage::Obj if Path::new(output_name).extension().is_some() => output_name.to_string(),
+        EmitStage::Obj => format!("{}.{}", output_name, if cfg!(windows) { "obj" } else { "o" }),
     };
 
-    let exe_path = std::env::current_dir()
+    let out_path = std::env::current_dir()
         .unwrap_or_default()
-        .join(&exe_name);
+        .join(&out_name);
 
     // Compile with gcc or clang
     println!("\nCompiling with gcc...\n");
 
     let compiler = if cfg!(windows) { "gcc" } else { "cc" };
 
-    let output = Command::new(compiler)
-        .arg("-o")
-        .arg(&exe_path)
-        .arg(&c_file)
-        .arg("-lpthread")  // For thread support
-        .output();
+    let output = c_compile_command(compiler, &c_file, &out_path, emit).output();
 
     match output {
         Ok(output) => {
@@ -730,26 +1123,31 @@ fn build_with_c_backend(ir_module: &mendes_ir::Module, output_name: &str) {
                 eprintln!("{}", String::from_utf8_lossy(&output.stderr));
             }
 
-            if output.status.success() {
-                println!("Compilation completed!");
-                println!("   Executable: {}", exe_path.display());
-                println!("\n   To run:");
-                if cfg!(windows) {
-                    println!("   .\\{}", exe_name);
-                } else {
-                    println!("   ./{}", exe_name);
-                }
-            } else {
+            if !output.status.success() {
                 eprintln!("C compilation failed");
                 eprintln!("   C code available at: {}", c_file.display());
                 std::process::exit(1);
             }
+
+            println!("Compilation completed!");
+            if emit == EmitStage::Obj {
+                println!("   Object file: {}", out_path.display());
+                return;
+            }
+            println!("   Executable: {}", out_path.display());
+            println!("\n   To run:");
+            if cfg!(windows) {
+                println!("   .\\{}", out_name);
+            } else {
+                println!("   ./{}", out_name);
+            }
         }
         Err(e) => {
+            let flag = if emit == EmitStage::Obj { "-c " } else { "" };
             eprintln!("Error executing {}: {}", compiler, e);
             eprintln!("   C code available at: {}", c_file.display());
             eprintln!("   Compile manually:");
-            eprintln!("   {} -o {} {}", compiler, exe_name, c_file.display());
+            eprintln!("   {} {}-o {} {}", compiler, flag, out_name, c_file.display());
             std::process::exit(1);
         }
     }
@@ -786,6 +1184,17 @@ fn format_stmt(stmt: &mendes_parser::Stmt, indent: usize) -> String {
             let ty_str = ty.as_ref().map(|t| format!(": {}", format_type(t))).unwrap_or_default();
             format!("{}Let {}{}{}", pad, mut_str, name, ty_str)
         }
+        mendes_parser::Stmt::LetTuple { names, mutable, .. } => {
+            let mut_str = if *mutable { "mut " } else { "" };
+            format!("{}Let {}({})", pad, mut_str, names.join(", "))
+        }
+        mendes_parser::Stmt::Static { name, ty, mutable, .. } => {
+            let mut_str = if *mutable { "mut " } else { "" };
+            format!("{}Static {}{}: {}", pad, mut_str, name, format_type(ty))
+        }
+        mendes_parser::Stmt::Const { name, ty, .. } => {
+            format!("{}Const {}: {}", pad, name, format_type(ty))
+        }
         mendes_parser::Stmt::Fn(f) => {
             let async_str = if f.is_async { " async" } else { "" };
             let ret_str = f.return_type.as_ref().map(|t| format!(" -> {}", format_type(t))).unwrap_or_default();
@@ -815,6 +1224,19 @@ fn format_stmt(stmt: &mendes_parser::Stmt, indent: usize) -> String {
             format!("{}Api {} {}{}{}\n{}  middlewares: {:?}\n{}  handler: {} statements",
                 pad, method, api.path, ret_str, async_str, pad, api.middlewares, pad, api.handler.len())
         }
+        mendes_parser::Stmt::Group(group) => {
+            let routes: Vec<_> = group.routes.iter()
+                .map(|api| format_stmt(&mendes_parser::Stmt::Api(api.clone()), indent + 1))
+                .collect();
+            format!("{}Group {}\n{}  middlewares: {:?}\n{}",
+                pad, group.prefix, pad, group.middlewares, routes.join("\n"))
+        }
+        mendes_parser::Stmt::Attributed { attrs, item, .. } => {
+            let attrs: Vec<_> = attrs.iter()
+                .map(|attr| format!("{}@{}({})", pad, attr.name, attr.args.join(", ")))
+                .collect();
+            format!("{}\n{}", attrs.join("\n"), format_stmt(item, indent))
+        }
         mendes_parser::Stmt::WebSocket(ws) => {
             let handlers = vec![
                 ws.on_connect.as_ref().map(|_| "on_connect"),
@@ -842,12 +1264,25 @@ fn format_stmt(stmt: &mendes_parser::Stmt, indent: usize) -> String {
             let else_str = else_block.as_ref().map(|b| format!(", else: {} stmts", b.len())).unwrap_or_default();
             format!("{}If (then: {} stmts{})", pad, then_block.len(), else_str)
         }
+        mendes_parser::Stmt::IfLet { then_block, else_block, .. } => {
+            let else_str = else_block.as_ref().map(|b| format!(", else: {} stmts", b.len())).unwrap_or_default();
+            format!("{}IfLet (then: {} stmts{})", pad, then_block.len(), else_str)
+        }
         mendes_parser::Stmt::For { var, body, .. } => {
             format!("{}For {} in ... ({} stmts)", pad, var, body.len())
         }
         mendes_parser::Stmt::While { body, .. } => {
             format!("{}While ... ({} stmts)", pad, body.len())
         }
+        mendes_parser::Stmt::WhileLet { body, .. } => {
+            format!("{}WhileLet ... ({} stmts)", pad, body.len())
+        }
+        mendes_parser::Stmt::TryCatch { body, error_var, handler, .. } => {
+            format!("{}Try ({} stmts) catch {} ({} stmts)", pad, body.len(), error_var, handler.len())
+        }
+        mendes_parser::Stmt::With { name, body, .. } => {
+            format!("{}With <expr> as {} ({} stmts)", pad, name, body.len())
+        }
         mendes_parser::Stmt::Return { value, .. } => {
             let val_str = if value.is_some() { " <expr>" } else { "" };
             format!("{}Return{}", pad, val_str)
@@ -865,6 +1300,9 @@ fn format_stmt(stmt: &mendes_parser::Stmt, indent: usize) -> String {
         mendes_parser::Stmt::TypeAlias { name, ty, .. } => {
             format!("{}Type {} = {}", pad, name, format_type(ty))
         }
+        mendes_parser::Stmt::Yield { .. } => {
+            format!("{}Yield <expr>", pad)
+        }
         mendes_parser::Stmt::Break { .. } => {
             format!("{}Break", pad)
         }
@@ -897,5 +1335,137 @@ fn format_type(ty: &mendes_parser::Type) -> String {
             let params_str: Vec<_> = params.iter().map(format_type).collect();
             format!("fn({}) -> {}", params_str.join(", "), format_type(return_type))
         }
+        mendes_parser::Type::Dyn(trait_name) => format!("dyn {}", trait_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_source(source: &str) -> mendes_error::Diagnostics {
+        let tokens = Lexer::new(source, 0).tokenize();
+        let (program, parse_diags) = parse(tokens);
+        assert!(!parse_diags.has_errors());
+        analyze(&program, &mut SemanticContext::new())
+    }
+
+    #[test]
+    fn test_phase_timings() {
+        let source = r#"
+struct Point:
+    x: int
+    y: int
+
+fn dist(p: Point) -> int:
+    let mut total = 0
+    for i in 0..p.x:
+        total += i * p.y
+    return total
+
+fn main():
+    print(dist(Point { x: 3, y: 4 }))
+"#;
+        let mut timings = PhaseTimings::default();
+        let module = front_end(source, 0, &HashSet::new(), &mut timings, &mut std::io::sink()).expect("program compiles");
+        let code = timings.time("codegen", || RustBackend::new().generate(&module));
+        assert!(code.contains("fn dist"));
+
+        let phases: Vec<_> = timings.phases.iter().map(|(name, _)| *name).collect();
+        assert_eq!(phases, vec!["lex", "parse", "semantic", "lower", "codegen"]);
+        assert!(timings.total() > Duration::ZERO);
+        let summary = timings.summary();
+        for phase in phases.iter().chain(&["total"]) {
+            assert!(summary.contains(phase), "{}", summary);
+        }
+    }
+
+    #[test]
+    fn test_deny_warnings_fails_on_warnings_only() {
+        let diags = check_source("fn print(x: int) -> int:\n    return x\n");
+        assert!(diags.has_warnings() && !diags.has_errors());
+        assert!(!check_fails(&diags, false));
+        assert!(check_fails(&diags, true));
+
+        let diags = check_source("fn one() -> int:\n    return 1\n");
+        assert!(!check_fails(&diags, true));
+    }
+
+    #[test]
+    fn test_c_backend_emits_object_file() {
+        // Needs a C compiler on the machine running the tests
+        if Command::new("cc").arg("--version").output().is_err() {
+            return;
+        }
+
+        let source = "fn answer() -> int:\n    return 42\n";
+        let module = front_end(source, 0, &HashSet::new(), &mut PhaseTimings::default(), &mut std::io::sink()).expect("program compiles");
+        let dir = std::env::temp_dir().join(format!("mendes-obj-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let c_file = dir.join("answer.c");
+        let obj_file = dir.join("answer.o");
+        fs::write(&c_file, CBackend::new().generate(&module)).unwrap();
+
+        let status = c_compile_command("cc", &c_file, &obj_file, EmitStage::Obj).status().unwrap();
+        assert!(status.success());
+        assert!(fs::metadata(&obj_file).is_ok_and(|m| m.len() > 0));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_async_block_compiles_with_rustc() {
+        // Needs rustc on the machine running the tests
+        if Command::new("rustc").arg("--version").output().is_err() {
+            return;
+        }
+
+        let source = r#"
+fn compute(base: int) -> int async:
+    let offset = 2
+    let task = async:
+        return base + offset
+    return await task
+"#;
+        let module = front_end(source, 0, &HashSet::new(), &mut PhaseTimings::default(), &mut std::io::sink()).expect("program compiles");
+        let code = RustBackend::new().generate(&module);
+        // Only the block and its caller; the rest of the file needs the runtime
+        let start = code.find("fn __async_block_0").unwrap();
+        let caller = code.find("async fn compute").unwrap();
+        let end = caller + code[caller..].find("\n}\n").unwrap() + 3;
+        let dir = std::env::temp_dir().join(format!("mendes-async-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let rs_file = dir.join("block.rs");
+        fs::write(&rs_file, format!("#![allow(unused_mut)]\n{}", &code[start..end])).unwrap();
+
+        let output = Command::new("rustc")
+            .args(["--edition", "2021", "--crate-type", "lib", "--emit", "metadata", "--out-dir"])
+            .arg(&dir)
+            .arg(&rs_file)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}\n{}", String::from_utf8_lossy(&output.stderr), &code[start..end]);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_tokens_json_round_trip() {
+        let source = "fn main():\n    let name = \"mendes\"\n    print(name)\n";
+        let tokens = Lexer::new(source, 0).tokenize();
+        let json = tokens_json(source, &tokens);
+
+        let parsed: Vec<Token> = serde_json::from_str(&json).expect("JSON reads back as tokens");
+        assert_eq!(parsed.len(), tokens.len());
+        assert_eq!(parsed, tokens);
+
+        let entries: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        let ident = entries.iter().find(|e| e["kind"] == "Ident").unwrap();
+        assert_eq!(ident["value"], "main");
+        assert_eq!(ident["lexeme"], "main");
+        assert_eq!(ident["span"]["start"]["line"], 1);
+        assert_eq!(ident["span"]["start"]["column"], 4);
+        assert_eq!(ident["span"]["start"]["offset"], 3);
+        for layout in ["Newline", "Indent", "Dedent", "Eof"] {
+            assert!(entries.iter().any(|e| e["kind"] == layout && e.get("value").is_none()), "{}", layout);
+        }
     }
 }
@@ -4,7 +4,7 @@
 //! any modern C compiler (gcc, clang, MSVC).
 
 use crate::CodeGen;
-use mendes_ir::{Module, Function, Instruction, Value, BinaryOp, CompareOp, IrType};
+use mendes_ir::{Module, Function, Instruction, Value, BinaryOp, CompareOp, IrType, FormatPart};
 use std::fmt::Write;
 
 /// C code generation backend
@@ -43,6 +43,12 @@ impl CBackend {
                 format!("struct {{ {} }}", type_names.join("; "))
             }
             IrType::Range(_inner) => "mendes_range_t".to_string(),
+            IrType::Generator(inner) => format!("mendes_generator_t /* {} */", self.emit_type(inner)),
+            IrType::Option(inner) => format!("mendes_option_t /* {} */", self.emit_type(inner)),
+            IrType::Result(ok, err) => format!("mendes_result_t /* {}, {} */", self.emit_type(ok), self.emit_type(err)),
+            IrType::Map(key, value) => format!("mendes_map_t /* {}, {} */", self.emit_type(key), self.emit_type(value)),
+            IrType::Set(inner) => format!("mendes_set_t /* {} */", self.emit_type(inner)),
+            IrType::Task(inner) => format!("mendes_task_t /* {} */", self.emit_type(inner)),
         }
     }
 
@@ -234,6 +240,11 @@ impl CBackend {
                     indent, dest, self.emit_value(left), self.emit_binary_op(op), self.emit_value(right)).unwrap();
             }
 
+            Instruction::CheckedBinary { dest, op, left, right } => {
+                writeln!(output, "{}if (__builtin_{}_overflow({}, {}, &__t{})) {{ fprintf(stderr, \"integer overflow in {}\\n\"); abort(); }}",
+                    indent, op, self.emit_value(left), self.emit_value(right), dest, op).unwrap();
+            }
+
             Instruction::Compare { dest, op, left, right } => {
                 writeln!(output, "{}__t{} = {} {} {};",
                     indent, dest, self.emit_value(left), self.emit_compare_op(op), self.emit_value(right)).unwrap();
@@ -289,7 +300,7 @@ impl CBackend {
                     indent, struct_name, self.emit_value(ptr), field_index, self.emit_value(value)).unwrap();
             }
 
-            Instruction::GetElement { dest, ptr, index } => {
+            Instruction::GetElement { dest, ptr, index, .. } => {
                 writeln!(output, "{}__t{} = ((int64_t*){})[{}];",
                     indent, dest, self.emit_value(ptr), self.emit_value(index)).unwrap();
             }
@@ -319,6 +330,16 @@ impl CBackend {
                     indent, dest, self.emit_type(to_type), self.emit_value(value)).unwrap();
             }
 
+            Instruction::FormatString { dest, parts } => {
+                let parts: Vec<String> = parts.iter()
+                    .map(|part| match part {
+                        FormatPart::Literal(text) => format!("{:?}", text),
+                        FormatPart::Value(value) => self.emit_value(value),
+                    })
+                    .collect();
+                writeln!(output, "{}__t{} = __string_format({});", indent, dest, parts.join(", ")).unwrap();
+            }
+
             Instruction::Comment(text) => {
                 writeln!(output, "{}/* {} */", indent, text).unwrap();
             }
@@ -0,0 +1,127 @@
+//! Graph backend - emits the call and route graph as Graphviz DOT
+//!
+//! A read-only view of the IR for understanding a program: one node per
+//! function, an edge for every call between functions of the module, and a
+//! node per HTTP route pointing at its handler and the middlewares it uses.
+//! Render it with `dot -Tsvg graph.dot -o graph.svg`.
+//!
+//! ```dot
+//! digraph "main" {
+//!     "GET /users" [shape=ellipse];
+//!     "GET /users" -> "__http_get__users0" [label="handler"];
+//!     "GET /users" -> "__middleware_auth" [style=dashed, label="use"];
+//!     "__http_get__users0" -> "load_users";
+//! }
+//! ```
+
+use crate::CodeGen;
+use mendes_ir::{Instruction, Module};
+use std::fmt::Write;
+
+/// Graphviz DOT backend
+#[derive(Debug, Default)]
+pub struct GraphBackend;
+
+impl GraphBackend {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl CodeGen for GraphBackend {
+    type Output = String;
+
+    fn generate(&self, module: &Module) -> String {
+        let mut output = String::new();
+        writeln!(output, "digraph {} {{", quote(&module.name)).unwrap();
+        writeln!(output, "    rankdir=LR;").unwrap();
+        writeln!(output, "    node [shape=box];").unwrap();
+
+        for func in &module.functions {
+            match func.name.strip_prefix("__middleware_") {
+                Some(name) => writeln!(output, "    {} [label={}, shape=hexagon];", quote(&func.name), quote(&format!("middleware {}", name))).unwrap(),
+                None => writeln!(output, "    {};", quote(&func.name)).unwrap(),
+            }
+        }
+
+        for route in &module.routes {
+            let node = format!("{} {}", route.method, route.path);
+            writeln!(output, "    {} [shape=ellipse];", quote(&node)).unwrap();
+            writeln!(output, "    {} -> {} [label=\"handler\"];", quote(&node), quote(&route.handler)).unwrap();
+            for middleware in &route.middlewares {
+                let target = format!("__middleware_{}", middleware);
+                writeln!(output, "    {} -> {} [style=dashed, label=\"use\"];", quote(&node), quote(&target)).unwrap();
+            }
+        }
+
+        for (caller, callee) in call_edges(module) {
+            writeln!(output, "    {} -> {};", quote(caller), quote(callee)).unwrap();
+        }
+
+        writeln!(output, "}}").unwrap();
+        output
+    }
+}
+
+/// Calls between functions of the module, once per caller and callee, in
+/// the order they first appear
+pub fn call_edges(module: &Module) -> Vec<(&str, &str)> {
+    let mut edges = Vec::new();
+    for func in &module.functions {
+        let callees = func.blocks.iter()
+            .flat_map(|block| &block.instructions)
+            .filter_map(|inst| match inst {
+                Instruction::Call { func: callee, .. } => Some(callee.as_str()),
+                _ => None,
+            })
+            .filter(|callee| module.functions.iter().any(|f| f.name == *callee));
+        for callee in callees {
+            let edge = (func.name.as_str(), callee);
+            if !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+    }
+    edges
+}
+
+/// DOT identifier for any name, e.g. `"GET /users"`
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mendes_ir::{Function, HttpRoute, IrType, Value};
+
+    fn calling(name: &str, callees: &[&str]) -> Function {
+        let mut func = Function::new(name, IrType::Void, false);
+        for callee in callees {
+            func.emit(Instruction::Call { dest: None, func: callee.to_string(), args: Vec::new() });
+        }
+        func.emit(Instruction::Return(Value::Void));
+        func
+    }
+
+    #[test]
+    fn test_graph_edges() {
+        let mut module = Module::new("main");
+        module.add_function(calling("helper", &["println"]));
+        module.add_function(calling("__http_get__users0", &["helper", "helper"]));
+        module.add_function(calling("__middleware_auth", &[]));
+        let mut route = HttpRoute::new("GET", "/users", "__http_get__users0");
+        route.middlewares.push("auth".to_string());
+        module.add_route(route);
+
+        // Builtins are not nodes, and repeated calls give one edge
+        assert_eq!(call_edges(&module), vec![("__http_get__users0", "helper")]);
+
+        let dot = GraphBackend::new().generate(&module);
+        assert!(dot.starts_with("digraph \"main\" {"));
+        assert!(dot.contains("\"GET /users\" -> \"__http_get__users0\" [label=\"handler\"];"));
+        assert!(dot.contains("\"GET /users\" -> \"__middleware_auth\" [style=dashed, label=\"use\"];"));
+        assert!(dot.contains("\"__middleware_auth\" [label=\"middleware auth\", shape=hexagon];"));
+        assert!(!dot.contains("println"));
+    }
+}
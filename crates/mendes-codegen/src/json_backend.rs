@@ -0,0 +1,348 @@
+//! JSON backend - emits the IR as structured JSON
+//!
+//! Lets tools consume the IR without linking against the compiler. Every
+//! basic block carries its label and every instruction an `op`, so control
+//! flow can be rebuilt from the targets of `branch` and `cond_branch`.
+//! Types are written in their IR notation (`i64`, `%Point`, `*string`).
+//!
+//! ```json
+//! {
+//!   "schema_version": 2,
+//!   "module": "main",
+//!   "functions": [{
+//!     "name": "add", "params": [{ "name": "a", "type": "i64" }], "return_type": "i64",
+//!     "blocks": [{ "label": "entry", "instructions": [
+//!       { "op": "binary", "dest": 0, "operator": "add",
+//!         "left": { "kind": "local", "value": "a" }, "right": { "kind": "int", "value": 1 } },
+//!       { "op": "return", "value": { "kind": "temp", "value": 0 } }
+//!     ] }]
+//!   }]
+//! }
+//! ```
+
+use crate::CodeGen;
+use mendes_ir::{BasicBlock, FormatPart, Function, Global, HttpRoute, Instruction, IrType, Module, StructDef, Value};
+use serde::Serialize;
+
+/// Version of the output schema, bumped whenever its shape changes
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// JSON backend
+#[derive(Debug, Default)]
+pub struct JsonBackend;
+
+impl JsonBackend {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl CodeGen for JsonBackend {
+    type Output = String;
+
+    fn generate(&self, module: &Module) -> String {
+        // Structs are kept in a map; sort them so the output is stable
+        let mut structs: Vec<&StructDef> = module.structs.values().collect();
+        structs.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let json = JsonModule {
+            schema_version: SCHEMA_VERSION,
+            module: &module.name,
+            string_table: &module.string_table,
+            globals: module.globals.iter().map(JsonGlobal::from).collect(),
+            structs: structs.into_iter().map(JsonStruct::from).collect(),
+            routes: module.routes.iter().map(JsonRoute::from).collect(),
+            functions: module.functions.iter().map(JsonFunction::from).collect(),
+        };
+        serde_json::to_string_pretty(&json).expect("IR serializes to JSON")
+    }
+}
+
+#[derive(Serialize)]
+struct JsonModule<'a> {
+    schema_version: u32,
+    module: &'a str,
+    string_table: &'a [String],
+    globals: Vec<JsonGlobal<'a>>,
+    structs: Vec<JsonStruct<'a>>,
+    routes: Vec<JsonRoute<'a>>,
+    functions: Vec<JsonFunction<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonGlobal<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    ty: String,
+    initializer: Option<JsonValue<'a>>,
+    is_const: bool,
+    mutable: bool,
+}
+
+impl<'a> From<&'a Global> for JsonGlobal<'a> {
+    fn from(global: &'a Global) -> Self {
+        Self {
+            name: &global.name,
+            ty: global.ty.to_string(),
+            initializer: global.initializer.as_ref().map(JsonValue::from),
+            is_const: global.is_const,
+            mutable: global.mutable,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonStruct<'a> {
+    name: &'a str,
+    fields: Vec<JsonBinding<'a>>,
+}
+
+impl<'a> From<&'a StructDef> for JsonStruct<'a> {
+    fn from(def: &'a StructDef) -> Self {
+        Self {
+            name: &def.name,
+            fields: def.fields.iter().map(JsonBinding::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonRoute<'a> {
+    method: &'a str,
+    path: &'a str,
+    handler: &'a str,
+    middlewares: &'a [String],
+    is_async: bool,
+}
+
+impl<'a> From<&'a HttpRoute> for JsonRoute<'a> {
+    fn from(route: &'a HttpRoute) -> Self {
+        Self {
+            method: &route.method,
+            path: &route.path,
+            handler: &route.handler,
+            middlewares: &route.middlewares,
+            is_async: route.is_async,
+        }
+    }
+}
+
+/// A named, typed slot: a parameter or a struct field
+#[derive(Serialize)]
+struct JsonBinding<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    ty: String,
+}
+
+impl<'a> From<&'a (String, IrType)> for JsonBinding<'a> {
+    fn from((name, ty): &'a (String, IrType)) -> Self {
+        Self { name, ty: ty.to_string() }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonFunction<'a> {
+    name: &'a str,
+    params: Vec<JsonBinding<'a>>,
+    return_type: String,
+    is_async: bool,
+    is_const: bool,
+    blocks: Vec<JsonBlock<'a>>,
+}
+
+impl<'a> From<&'a Function> for JsonFunction<'a> {
+    fn from(func: &'a Function) -> Self {
+        Self {
+            name: &func.name,
+            params: func.params.iter().map(JsonBinding::from).collect(),
+            return_type: func.return_type.to_string(),
+            is_async: func.is_async,
+            is_const: func.is_const,
+            blocks: func.blocks.iter().map(JsonBlock::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonBlock<'a> {
+    label: &'a str,
+    instructions: Vec<JsonInstruction<'a>>,
+}
+
+impl<'a> From<&'a BasicBlock> for JsonBlock<'a> {
+    fn from(block: &'a BasicBlock) -> Self {
+        Self {
+            label: &block.label,
+            instructions: block.instructions.iter().map(JsonInstruction::from).collect(),
+        }
+    }
+}
+
+/// An operand: `{ "kind": "temp", "value": 3 }`
+#[derive(Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+enum JsonValue<'a> {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// Index into the string table
+    String(usize),
+    Local(&'a str),
+    Param(usize),
+    Global(&'a str),
+    Temp(u32),
+    Void,
+}
+
+impl<'a> From<&'a Value> for JsonValue<'a> {
+    fn from(value: &'a Value) -> Self {
+        match value {
+            Value::ConstInt(n) => JsonValue::Int(*n),
+            Value::ConstFloat(bits) => JsonValue::Float(f64::from_bits(*bits)),
+            Value::ConstBool(b) => JsonValue::Bool(*b),
+            Value::ConstString(idx) => JsonValue::String(*idx),
+            Value::Local(name) => JsonValue::Local(name),
+            Value::Param(idx) => JsonValue::Param(*idx),
+            Value::Global(name) => JsonValue::Global(name),
+            Value::Temp(id) => JsonValue::Temp(*id),
+            Value::Void => JsonValue::Void,
+        }
+    }
+}
+
+/// An instruction, tagged by its opcode: `{ "op": "call", ... }`
+#[derive(Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JsonInstruction<'a> {
+    Alloca { dest: &'a str, #[serde(rename = "type")] ty: String },
+    Store { value: JsonValue<'a>, ptr: JsonValue<'a> },
+    Load { dest: u32, ptr: JsonValue<'a>, #[serde(rename = "type")] ty: String },
+    Binary { dest: u32, operator: String, left: JsonValue<'a>, right: JsonValue<'a> },
+    CheckedBinary { dest: u32, operator: String, left: JsonValue<'a>, right: JsonValue<'a> },
+    Compare { dest: u32, operator: String, left: JsonValue<'a>, right: JsonValue<'a> },
+    Not { dest: u32, value: JsonValue<'a> },
+    Neg { dest: u32, value: JsonValue<'a> },
+    Call { dest: Option<u32>, func: &'a str, args: Vec<JsonValue<'a>> },
+    Return { value: JsonValue<'a> },
+    Branch { target: &'a str },
+    CondBranch { cond: JsonValue<'a>, then_label: &'a str, else_label: &'a str },
+    Phi { dest: u32, incoming: Vec<JsonIncoming<'a>> },
+    GetField { dest: u32, ptr: JsonValue<'a>, struct_name: &'a str, field_index: usize, field_name: &'a str },
+    SetField { ptr: JsonValue<'a>, struct_name: &'a str, field_index: usize, field_name: &'a str, value: JsonValue<'a> },
+    GetElement { dest: u32, ptr: JsonValue<'a>, index: JsonValue<'a>, in_bounds: bool },
+    SetElement { ptr: JsonValue<'a>, index: JsonValue<'a>, value: JsonValue<'a> },
+    Await { dest: u32, future: JsonValue<'a> },
+    NewStruct { dest: u32, struct_name: &'a str },
+    NewArray { dest: u32, elem_type: String, size: JsonValue<'a> },
+    Cast { dest: u32, value: JsonValue<'a>, to_type: String },
+    FormatString { dest: u32, parts: Vec<JsonFormatPart<'a>> },
+    Comment { text: &'a str },
+}
+
+/// A piece of a `format_string`: `{ "kind": "literal", "value": "n = " }`
+#[derive(Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+enum JsonFormatPart<'a> {
+    Literal(&'a str),
+    Value(JsonValue<'a>),
+}
+
+/// A `phi` input: the value flowing in from the block `label`
+#[derive(Serialize)]
+struct JsonIncoming<'a> {
+    value: JsonValue<'a>,
+    label: &'a str,
+}
+
+impl<'a> From<&'a Instruction> for JsonInstruction<'a> {
+    fn from(inst: &'a Instruction) -> Self {
+        use JsonInstruction as J;
+        let v = JsonValue::from;
+        match inst {
+            Instruction::Alloca { dest, ty } => J::Alloca { dest, ty: ty.to_string() },
+            Instruction::Store { value, ptr } => J::Store { value: v(value), ptr: v(ptr) },
+            Instruction::Load { dest, ptr, ty } => J::Load { dest: *dest, ptr: v(ptr), ty: ty.to_string() },
+            Instruction::Binary { dest, op, left, right } => {
+                J::Binary { dest: *dest, operator: op.to_string(), left: v(left), right: v(right) }
+            }
+            Instruction::CheckedBinary { dest, op, left, right } => {
+                J::CheckedBinary { dest: *dest, operator: op.to_string(), left: v(left), right: v(right) }
+            }
+            Instruction::Compare { dest, op, left, right } => {
+                J::Compare { dest: *dest, operator: op.to_string(), left: v(left), right: v(right) }
+            }
+            Instruction::Not { dest, value } => J::Not { dest: *dest, value: v(value) },
+            Instruction::Neg { dest, value } => J::Neg { dest: *dest, value: v(value) },
+            Instruction::Call { dest, func, args } => J::Call { dest: *dest, func, args: args.iter().map(v).collect() },
+            Instruction::Return(value) => J::Return { value: v(value) },
+            Instruction::Branch { target } => J::Branch { target },
+            Instruction::CondBranch { cond, then_label, else_label } => {
+                J::CondBranch { cond: v(cond), then_label, else_label }
+            }
+            Instruction::Phi { dest, incoming } => J::Phi {
+                dest: *dest,
+                incoming: incoming.iter().map(|(value, label)| JsonIncoming { value: v(value), label }).collect(),
+            },
+            Instruction::GetField { dest, ptr, struct_name, field_index, field_name } => J::GetField {
+                dest: *dest, ptr: v(ptr), struct_name, field_index: *field_index, field_name,
+            },
+            Instruction::SetField { ptr, struct_name, field_index, field_name, value } => J::SetField {
+                ptr: v(ptr), struct_name, field_index: *field_index, field_name, value: v(value),
+            },
+            Instruction::GetElement { dest, ptr, index, in_bounds } => J::GetElement {
+                dest: *dest, ptr: v(ptr), index: v(index), in_bounds: *in_bounds,
+            },
+            Instruction::SetElement { ptr, index, value } => J::SetElement { ptr: v(ptr), index: v(index), value: v(value) },
+            Instruction::Await { dest, future } => J::Await { dest: *dest, future: v(future) },
+            Instruction::NewStruct { dest, struct_name } => J::NewStruct { dest: *dest, struct_name },
+            Instruction::NewArray { dest, elem_type, size } => J::NewArray {
+                dest: *dest, elem_type: elem_type.to_string(), size: v(size),
+            },
+            Instruction::Cast { dest, value, to_type } => J::Cast { dest: *dest, value: v(value), to_type: to_type.to_string() },
+            Instruction::FormatString { dest, parts } => J::FormatString {
+                dest: *dest,
+                parts: parts.iter()
+                    .map(|part| match part {
+                        FormatPart::Literal(text) => JsonFormatPart::Literal(text),
+                        FormatPart::Value(value) => JsonFormatPart::Value(v(value)),
+                    })
+                    .collect(),
+            },
+            Instruction::Comment(text) => J::Comment { text },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mendes_ir::BinaryOp;
+
+    #[test]
+    fn test_json_function() {
+        let mut module = Module::new("main");
+        let mut func = Function::new("add_one", IrType::I64, false);
+        func.params.push(("a".to_string(), IrType::I64));
+        func.emit(Instruction::Binary {
+            dest: 0,
+            op: BinaryOp::Add,
+            left: Value::Local("a".to_string()),
+            right: Value::ConstInt(1),
+        });
+        func.emit(Instruction::Return(Value::Temp(0)));
+        module.add_function(func);
+
+        let json: serde_json::Value = serde_json::from_str(&JsonBackend::new().generate(&module)).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        let function = &json["functions"][0];
+        assert_eq!(function["name"], "add_one");
+        assert_eq!(function["params"][0]["type"], "i64");
+        let block = &function["blocks"][0];
+        assert_eq!(block["label"], "entry");
+        assert_eq!(block["instructions"][0]["op"], "binary");
+        assert_eq!(block["instructions"][0]["operator"], "add");
+        assert_eq!(block["instructions"][0]["right"], serde_json::json!({ "kind": "int", "value": 1 }));
+        assert_eq!(block["instructions"][1]["op"], "return");
+    }
+}
@@ -3,6 +3,8 @@
 //! Supports multiple backends:
 //! - **C Backend**: Generates portable C code (default)
 //! - **LLVM Backend**: Generates LLVM IR directly (requires LLVM installed)
+//! - **JSON Backend**: Emits the IR as structured JSON for external tools
+//! - **Graph Backend**: Emits the call and route graph as Graphviz DOT
 //!
 //! # Example
 //!
@@ -16,12 +18,16 @@
 //! ```
 
 pub mod c_backend;
+pub mod graph_backend;
+pub mod json_backend;
 pub mod rust_backend;
 
 #[cfg(feature = "llvm")]
 pub mod llvm_backend;
 
 pub use c_backend::CBackend;
+pub use graph_backend::GraphBackend;
+pub use json_backend::JsonBackend;
 pub use rust_backend::RustBackend;
 
 #[cfg(feature = "llvm")]
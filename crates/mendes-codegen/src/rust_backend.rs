@@ -4,7 +4,7 @@
 //! together with mendes-runtime to create native executables.
 
 use crate::CodeGen;
-use mendes_ir::{Module, Function, Instruction, Value, BinaryOp, CompareOp, IrType, GenericParam};
+use mendes_ir::{Module, Function, Global, Instruction, Value, BinaryOp, CompareOp, IrType, GenericParam, VariantData, Visibility, FormatPart, StructDef, DERIVABLE_TRAITS};
 use std::fmt::Write;
 
 /// Information about a detected for loop pattern
@@ -25,13 +25,83 @@ struct WhileLoopInfo {
     end_label: String,
 }
 
+/// Information about a detected user-iterator loop pattern
+struct IterLoopInfo {
+    loop_id: String,
+    iter: Value,
+    /// Whether `next()` returns a `MendesOption` rather than a Rust
+    /// `Option`, as it does for a user iterator
+    mendes_option: bool,
+    next_dest: u32,
+    body_label: String,
+    end_label: String,
+}
+
+/// Rust storage backing a Mendes `static`
+enum StaticCell {
+    /// Mutable `int` or `bool`, updated with atomic operations
+    Atomic(&'static str),
+    /// Other mutable types, guarded by a mutex
+    Locked,
+    /// Immutable `string`, built on first use
+    Lazy,
+    /// Other immutable types
+    Plain,
+}
+
+impl StaticCell {
+    fn of(global: &Global) -> Self {
+        match (&global.ty, global.mutable) {
+            (IrType::I64, true) => StaticCell::Atomic("AtomicI64"),
+            (IrType::Bool, true) => StaticCell::Atomic("AtomicBool"),
+            (_, true) => StaticCell::Locked,
+            (IrType::String, false) => StaticCell::Lazy,
+            (_, false) => StaticCell::Plain,
+        }
+    }
+}
+
+/// Rust name of a Mendes `fn main`, which the generated async `main` calls
+const USER_MAIN: &str = "__mendes_main";
+
+/// Name of the Rust item holding a static, so `let` bindings in Rust can
+/// still reuse the Mendes name
+fn static_item(name: &str) -> String {
+    format!("__static_{}", name)
+}
+
+/// Name of the Rust item holding a constant; Rust rejects a `let` or
+/// parameter that reuses a const's name, so it cannot keep the Mendes one
+fn const_item(name: &str) -> String {
+    format!("__const_{}", name)
+}
+
+/// Rust path of a matched variant: `Option` and `Result` variants live on
+/// the runtime's own enums, user variants are already `Enum::Variant`
+fn runtime_variant_path(path: &str) -> String {
+    match path {
+        "Some" | "None" => format!("MendesOption::{}", path),
+        "Ok" | "Err" => format!("MendesResult::{}", path),
+        _ => path.to_string(),
+    }
+}
+
 /// Rust code generation backend
 #[derive(Debug, Default)]
-pub struct RustBackend;
+pub struct RustBackend {
+    /// Optimization level (0-3); 3 drops bounds checks on proven-safe indexing
+    opt_level: u8,
+}
 
 impl RustBackend {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Sets the optimization level
+    pub fn with_opt_level(mut self, opt_level: u8) -> Self {
+        self.opt_level = opt_level;
+        self
     }
 
     /// Format generic parameters for Rust output: <T, U: Trait>
@@ -75,46 +145,47 @@ impl RustBackend {
                 let params_str: Vec<_> = params.iter().map(|p| self.emit_type(p)).collect();
                 format!("fn({}) -> {}", params_str.join(", "), self.emit_type(ret))
             }
-            IrType::Future(inner) => format!("impl Future<Output = {}>", self.emit_type(inner)),
+            // Boxed so that a future can be stored in a local
+            IrType::Future(inner) => format!(
+                "std::pin::Pin<Box<dyn std::future::Future<Output = {}> + Send>>",
+                self.emit_type(inner)
+            ),
             IrType::Tuple(elems) => {
                 let elem_types: Vec<_> = elems.iter().map(|e| self.emit_type(e)).collect();
                 format!("({})", elem_types.join(", "))
             }
             IrType::Range(inner) => format!("std::ops::Range<{}>", self.emit_type(inner)),
+            IrType::Generator(inner) => format!("mendes_runtime::Generator<{}>", self.emit_type(inner)),
+            IrType::Option(inner) => format!("MendesOption<{}>", self.emit_type_arg(inner)),
+            IrType::Result(ok, err) => format!("MendesResult<{}, {}>", self.emit_type_arg(ok), self.emit_type_arg(err)),
+            IrType::Map(key, value) => format!("std::collections::BTreeMap<{}, {}>", self.emit_type_arg(key), self.emit_type_arg(value)),
+            IrType::Set(inner) => format!("std::collections::BTreeSet<{}>", self.emit_type_arg(inner)),
+            IrType::Task(inner) => format!("tokio::task::JoinHandle<{}>", self.emit_type_arg(inner)),
         }
     }
 
     fn emit_struct_type(&self, name: &str) -> String {
-        // Handle Result<T, E> and Option<T> encoded types
-        if name.starts_with("Result_") {
-            // Result_Int_String -> MendesResult<i64, MendesString>
-            let parts: Vec<&str> = name[7..].split('_').collect();
-            if parts.len() >= 2 {
-                let ok_type = self.convert_type_name(parts[0]);
-                let err_type = self.convert_type_name(parts[1]);
-                return format!("MendesResult<{}, {}>", ok_type, err_type);
-            }
-        } else if name.starts_with("Option_") {
-            // Option_Int -> MendesOption<i64>
-            let inner = &name[7..];
-            let inner_type = self.convert_type_name(inner);
-            return format!("MendesOption<{}>", inner_type);
+        if name == "Duration" {
+            return "std::time::Duration".to_string();
+        }
+        // dyn Trait -> Box<dyn Trait>
+        if let Some(trait_name) = name.strip_prefix("dyn ") {
+            return format!("Box<dyn {}>", trait_name);
         }
         // Default: return name as-is
         name.to_string()
     }
 
-    fn convert_type_name(&self, name: &str) -> String {
-        match name.to_lowercase().as_str() {
-            "int" => "i64".to_string(),
-            "float" => "f64".to_string(),
-            "bool" => "bool".to_string(),
-            "string" => "MendesString".to_string(),
-            _ => name.to_string(),
+    /// A type argument of `Option`, `Result`, `Map` or `Set`, where `[T]`
+    /// is an owned array rather than a borrowed one
+    fn emit_type_arg(&self, ty: &IrType) -> String {
+        match ty {
+            IrType::Ptr(inner) => format!("MendesArray<{}>", self.emit_type_arg(inner)),
+            _ => self.emit_type(ty),
         }
     }
 
-    fn emit_value(&self, value: &Value) -> String {
+    fn emit_value(&self, value: &Value, module: &Module) -> String {
         match value {
             Value::ConstInt(v) => format!("{}", v),
             Value::ConstFloat(bits) => format!("{:.6}_f64", f64::from_bits(*bits)),
@@ -122,6 +193,7 @@ impl RustBackend {
             Value::ConstString(idx) => format!("__str_{}()", idx),
             Value::Local(name) => name.clone(),
             Value::Param(idx) => format!("__arg{}", idx),
+            Value::Global(name) if module.get_global(name).is_some_and(|g| g.is_const) => const_item(name),
             Value::Global(name) => format!("{}", name),
             Value::Temp(id) => format!("__t{}", id),
             Value::Void => "()".to_string(),
@@ -204,6 +276,115 @@ impl RustBackend {
         writeln!(output).unwrap();
     }
 
+    fn emit_statics(&self, module: &Module, output: &mut String) {
+        if module.globals.is_empty() {
+            return;
+        }
+
+        writeln!(output, "// Statics").unwrap();
+        for global in &module.globals {
+            let item = static_item(&global.name);
+            let ty = self.emit_type(&global.ty);
+            let init = global.initializer.as_ref()
+                .map(|v| self.emit_value(v, module))
+                .unwrap_or_else(|| "Default::default()".to_string());
+            writeln!(output, "#[allow(non_upper_case_globals)]").unwrap();
+            if global.is_const {
+                let visibility = match global.visibility {
+                    Visibility::Private => "",
+                    Visibility::Public => "pub ",
+                    Visibility::Crate => "pub(crate) ",
+                };
+                writeln!(output, "{}const {}: {} = {};", visibility, const_item(&global.name), ty, init).unwrap();
+                continue;
+            }
+            match StaticCell::of(global) {
+                StaticCell::Atomic(atomic) => writeln!(output,
+                    "static {}: std::sync::atomic::{} = std::sync::atomic::{}::new({});",
+                    item, atomic, atomic, init).unwrap(),
+                StaticCell::Locked => writeln!(output,
+                    "static {}: std::sync::LazyLock<std::sync::Mutex<{}>> = std::sync::LazyLock::new(|| std::sync::Mutex::new({}));",
+                    item, ty, init).unwrap(),
+                StaticCell::Lazy => writeln!(output,
+                    "static {}: std::sync::LazyLock<{}> = std::sync::LazyLock::new(|| {});",
+                    item, ty, init).unwrap(),
+                StaticCell::Plain => writeln!(output, "static {}: {} = {};", item, ty, init).unwrap(),
+            }
+        }
+        writeln!(output).unwrap();
+    }
+
+    /// Rust for a call to an item of a bundled `std` module
+    fn std_call(func: &str, args: &[String]) -> Option<String> {
+        let arg = |i: usize| args.get(i).map(String::as_str).unwrap_or_default();
+        let code = match func {
+            "__std_math_sqrt" => format!("f64::sqrt({})", arg(0)),
+            "__std_math_pow" => format!("f64::powf({}, {})", arg(0), arg(1)),
+            "__std_math_abs" => format!("f64::abs({})", arg(0)),
+            "__std_math_floor" => format!("f64::floor({})", arg(0)),
+            "__std_math_ceil" => format!("f64::ceil({})", arg(0)),
+            "__std_math_pi" => "std::f64::consts::PI".to_string(),
+            "__std_math_e" => "std::f64::consts::E".to_string(),
+            "__std_string_upper" => format!("{}.to_upper()", arg(0)),
+            "__std_string_lower" => format!("{}.to_lower()", arg(0)),
+            "__std_string_trim" => format!("{}.trim()", arg(0)),
+            "__std_string_contains" => format!("{}.contains({}.as_str())", arg(0), arg(1)),
+            "__std_collections_range" => format!("({}..{}).collect::<MendesArray<i64>>()", arg(0), arg(1)),
+            _ => return None,
+        };
+        Some(code)
+    }
+
+    /// Emits a read or update of a static through its cell
+    fn emit_static_access(&self, func: &str, global: &Global, dest: Option<u32>, value: Option<String>, indent: &str, output: &mut String) {
+        let item = static_item(&global.name);
+        let seq_cst = "std::sync::atomic::Ordering::SeqCst";
+        let cell = StaticCell::of(global);
+        if func == "__static_load" {
+            let read = match cell {
+                StaticCell::Atomic(_) => format!("{}.load({})", item, seq_cst),
+                StaticCell::Locked => format!("{}.lock().unwrap().clone()", item),
+                StaticCell::Lazy => format!("(*{}).clone()", item),
+                StaticCell::Plain => item,
+            };
+            if let Some(d) = dest {
+                writeln!(output, "{}let __t{} = {};", indent, d, read).unwrap();
+            }
+            return;
+        }
+
+        let value = value.unwrap_or_default();
+        let op = match func {
+            "__static_add" => Some("+"),
+            "__static_sub" => Some("-"),
+            "__static_mul" => Some("*"),
+            "__static_div" => Some("/"),
+            _ => None,
+        };
+        match (cell, op) {
+            (StaticCell::Atomic(_), None) => {
+                writeln!(output, "{}{}.store({}, {});", indent, item, value, seq_cst).unwrap();
+            }
+            (StaticCell::Atomic(_), Some("+")) => {
+                writeln!(output, "{}{}.fetch_add({}, {});", indent, item, value, seq_cst).unwrap();
+            }
+            (StaticCell::Atomic(_), Some("-")) => {
+                writeln!(output, "{}{}.fetch_sub({}, {});", indent, item, value, seq_cst).unwrap();
+            }
+            (StaticCell::Atomic(_), Some(op)) => {
+                writeln!(output, "{}let _ = {}.fetch_update({}, {}, |__v| Some(__v {} {}));",
+                    indent, item, seq_cst, seq_cst, op, value).unwrap();
+            }
+            (_, None) => {
+                writeln!(output, "{}*{}.lock().unwrap() = {};", indent, item, value).unwrap();
+            }
+            (_, Some(op)) => {
+                writeln!(output, "{}{{ let mut __guard = {}.lock().unwrap(); *__guard = __guard.clone() {} {}; }}",
+                    indent, item, op, value).unwrap();
+            }
+        }
+    }
+
     fn emit_structs(&self, module: &Module, output: &mut String) {
         if module.structs.is_empty() {
             return;
@@ -212,8 +393,18 @@ impl RustBackend {
         writeln!(output, "// Struct definitions").unwrap();
         for (name, def) in &module.structs {
             let generic_params = self.emit_generic_params(&def.generic_params);
-            writeln!(output, "#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]").unwrap();
-            writeln!(output, "pub struct {}{} {{", name, generic_params).unwrap();
+            let mut derives = self.struct_derives(def, module);
+            // Only structs that routes send or receive go through JSON
+            if module.api_structs.contains(name) {
+                derives.extend(["serde::Serialize", "serde::Deserialize"]);
+            }
+            writeln!(output, "#[derive({})]", derives.join(", ")).unwrap();
+            // Structs are exported unless restricted to the crate
+            let visibility = match def.visibility {
+                Visibility::Crate => "pub(crate) ",
+                Visibility::Private | Visibility::Public => "pub ",
+            };
+            writeln!(output, "{}struct {}{} {{", visibility, name, generic_params).unwrap();
             for (field_name, field_type) in &def.fields {
                 writeln!(output, "    pub {}: {},", field_name, self.emit_type(field_type)).unwrap();
             }
@@ -222,6 +413,140 @@ impl RustBackend {
         }
     }
 
+    /// The traits derived for a struct: those its usage needs, merged with
+    /// the ones requested by `@derive(...)` that its fields allow
+    fn struct_derives(&self, def: &StructDef, module: &Module) -> Vec<&'static str> {
+        let mut wanted = vec!["Debug", "Clone"];
+        if self.is_defaultable(&IrType::Struct(def.name.clone()), module, &mut Vec::new()) {
+            wanted.push("Default");
+        }
+        // Map keys are ordered, so `keys()` and `values()` come out sorted
+        if def.derive_hash {
+            wanted.extend(["PartialEq", "Eq", "Hash", "PartialOrd", "Ord"]);
+        }
+
+        let has_float = def.fields.iter().any(|(_, ty)| *ty == IrType::F64);
+        let all_copy = def.fields.iter().all(|(_, ty)| match ty {
+            IrType::Struct(inner) => module.get_struct(inner).is_some_and(|d| d.derives.iter().any(|t| t == "Copy")),
+            other => other.is_primitive(),
+        });
+        for requested in &def.derives {
+            match requested.as_str() {
+                "Copy" if !all_copy => {}
+                "Eq" | "Hash" | "Ord" if has_float => {}
+                // Derives that need others to compile bring them along
+                "Eq" => wanted.extend(["PartialEq", "Eq"]),
+                "PartialOrd" => wanted.extend(["PartialEq", "PartialOrd"]),
+                "Ord" => wanted.extend(["PartialEq", "Eq", "PartialOrd", "Ord"]),
+                other => wanted.extend(DERIVABLE_TRAITS.iter().find(|t| **t == other)),
+            }
+        }
+
+        // A user `impl Eq` or `impl Ord` replaces the derived comparison.
+        // `impl Ord` is emitted as `PartialOrd`, which needs `PartialEq`
+        let user_impl = |trait_name: &str| module.impls.iter().any(|i| i.trait_name == trait_name && i.type_name == def.name);
+        let (user_eq, user_ord) = (user_impl("Eq"), user_impl("Ord"));
+        if user_ord {
+            wanted.push("PartialEq");
+        }
+        DERIVABLE_TRAITS.iter()
+            .copied()
+            .filter(|t| wanted.contains(t))
+            .filter(|t| !(*t == "PartialEq" && user_eq || *t == "PartialOrd" && user_ord))
+            .collect()
+    }
+
+    /// Whether a type can derive `Default`: numbers, bools, strings and
+    /// structs made only of those
+    fn is_defaultable(&self, ty: &IrType, module: &Module, visiting: &mut Vec<String>) -> bool {
+        match ty {
+            IrType::I64 | IrType::F64 | IrType::Bool | IrType::String => true,
+            IrType::Struct(name) => {
+                let def = match module.get_struct(name) {
+                    Some(def) if !visiting.contains(name) => def,
+                    _ => return false,
+                };
+                visiting.push(name.clone());
+                let defaultable = def.fields.iter().all(|(_, t)| self.is_defaultable(t, module, visiting));
+                visiting.pop();
+                defaultable
+            }
+            _ => false,
+        }
+    }
+
+    fn emit_enums(&self, module: &Module, output: &mut String) {
+        if module.enums.is_empty() {
+            return;
+        }
+
+        writeln!(output, "// Enum definitions").unwrap();
+        for (name, def) in &module.enums {
+            let generic_params = self.emit_generic_params(&def.generic_params);
+            // Enums without data convert to `i64` with `as`, which needs `Copy`
+            if def.discriminants.is_some() {
+                writeln!(output, "#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]").unwrap();
+                writeln!(output, "#[repr(i64)]").unwrap();
+            } else {
+                writeln!(output, "#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]").unwrap();
+            }
+            writeln!(output, "pub enum {}{} {{", name, generic_params).unwrap();
+            for (i, (variant_name, data)) in def.variants.iter().enumerate() {
+                match data {
+                    VariantData::Unit => match &def.discriminants {
+                        Some(values) => writeln!(output, "    {} = {},", variant_name, values[i]).unwrap(),
+                        None => writeln!(output, "    {},", variant_name).unwrap(),
+                    },
+                    VariantData::Tuple(types) => {
+                        let types: Vec<_> = types.iter().map(|t| self.emit_type(t)).collect();
+                        writeln!(output, "    {}({}),", variant_name, types.join(", ")).unwrap();
+                    }
+                    VariantData::Struct(fields) => {
+                        let fields: Vec<_> = fields.iter()
+                            .map(|(n, t)| format!("{}: {}", n, self.emit_type(t)))
+                            .collect();
+                        writeln!(output, "    {} {{ {} }},", variant_name, fields.join(", ")).unwrap();
+                    }
+                }
+            }
+            writeln!(output, "}}").unwrap();
+            writeln!(output).unwrap();
+
+            if let Some(values) = &def.discriminants {
+                writeln!(output, "impl {}{} {{", name, generic_params).unwrap();
+                writeln!(output, "    pub fn from_int(value: i64) -> MendesOption<{}{}> {{", name, generic_params).unwrap();
+                writeln!(output, "        match value {{").unwrap();
+                for ((variant_name, _), value) in def.variants.iter().zip(values) {
+                    writeln!(output, "            {} => MendesOption::Some({}::{}),", value, name, variant_name).unwrap();
+                }
+                writeln!(output, "            _ => MendesOption::None,").unwrap();
+                writeln!(output, "        }}").unwrap();
+                writeln!(output, "    }}").unwrap();
+                writeln!(output, "}}").unwrap();
+                writeln!(output).unwrap();
+            }
+
+            // Lets the enum be used as an error: returned from `main` and
+            // propagated with `?` into handlers that return `HttpError`
+            if def.generic_params.is_empty() {
+                writeln!(output, "impl std::fmt::Display for {} {{", name).unwrap();
+                writeln!(output, "    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{").unwrap();
+                writeln!(output, "        write!(f, \"{{:?}}\", self)").unwrap();
+                writeln!(output, "    }}").unwrap();
+                writeln!(output, "}}").unwrap();
+                writeln!(output).unwrap();
+                writeln!(output, "impl std::error::Error for {} {{}}", name).unwrap();
+                writeln!(output).unwrap();
+                writeln!(output, "impl From<{}> for mendes_runtime::HttpError {{", name).unwrap();
+                writeln!(output, "    fn from(e: {}) -> Self {{", name).unwrap();
+                writeln!(output, "        mendes_runtime::HttpError::internal(e.to_string())").unwrap();
+                writeln!(output, "    }}").unwrap();
+                writeln!(output, "}}").unwrap();
+                writeln!(output).unwrap();
+            }
+        }
+    }
+
     fn emit_traits(&self, module: &Module, output: &mut String) {
         if module.traits.is_empty() {
             return;
@@ -279,8 +604,38 @@ impl RustBackend {
             // Get the trait definition to know method signatures
             let trait_def = module.get_trait(&impl_def.trait_name);
 
+            // Operator traits map onto the std traits behind the operator
+            let operator = match impl_def.trait_name.as_str() {
+                "Add" => Some("std::ops::Add"),
+                "Sub" => Some("std::ops::Sub"),
+                "Mul" => Some("std::ops::Mul"),
+                "Eq" => Some("PartialEq"),
+                "Ord" => Some("PartialOrd"),
+                _ => None,
+            };
+            let trait_path = operator.unwrap_or(&impl_def.trait_name);
+
             writeln!(output, "impl{} {} for {}{} {{",
-                generic_params, impl_def.trait_name, impl_def.type_name, generic_args).unwrap();
+                generic_params, trait_path, impl_def.type_name, generic_args).unwrap();
+
+            match impl_def.trait_name.as_str() {
+                "Add" | "Sub" | "Mul" => {
+                    let output_type = impl_def.methods.first()
+                        .and_then(|m| module.get_function(m))
+                        .map(|f| self.emit_type(&f.return_type))
+                        .unwrap_or_else(|| "Self".to_string());
+                    writeln!(output, "    type Output = {};", output_type).unwrap();
+                }
+                // `<`, `>`, `<=` and `>=` all derive from the user's `lt`
+                "Ord" => {
+                    writeln!(output, "    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {{").unwrap();
+                    writeln!(output, "        if self.lt(other) {{ Some(std::cmp::Ordering::Less) }}").unwrap();
+                    writeln!(output, "        else if other.lt(self) {{ Some(std::cmp::Ordering::Greater) }}").unwrap();
+                    writeln!(output, "        else {{ Some(std::cmp::Ordering::Equal) }}").unwrap();
+                    writeln!(output, "    }}").unwrap();
+                }
+                _ => {}
+            }
 
             // For each method, find the corresponding function and emit it inline
             for method_name in &impl_def.methods {
@@ -291,13 +646,16 @@ impl RustBackend {
                         t.methods.iter().find(|m| method_name.ends_with(&format!("::{}", m.name)))
                     );
 
-                    // Receiver
+                    // Receiver; arithmetic operators consume their operands
                     let receiver = trait_method.map(|m| match m.receiver {
                         0 => "&self",
                         1 => "&mut self",
                         2 => "self",
                         _ => "&self",
-                    }).unwrap_or("&self");
+                    }).unwrap_or(match trait_path {
+                        "std::ops::Add" | "std::ops::Sub" | "std::ops::Mul" => "self",
+                        _ => "&self",
+                    });
 
                     // Extract just the method name (after last ::)
                     let short_name = method_name.rsplit("::").next().unwrap_or(method_name);
@@ -348,14 +706,30 @@ impl RustBackend {
 
     fn emit_function(&self, func: &Function, module: &Module, output: &mut String) {
         let _is_handler = func.name.starts_with("__http_");
+        if func.name.starts_with("__async_block_") {
+            self.emit_async_block(func, module, output);
+            return;
+        }
         let return_type = self.emit_type(&func.return_type);
         let generic_params = self.emit_generic_params(&func.generic_params);
+        let name = if func.name == "main" { USER_MAIN } else { func.name.as_str() };
+
+        for hint in &func.hints {
+            writeln!(output, "#[{}]", hint).unwrap();
+        }
 
         // Signature
+        let visibility = match func.visibility {
+            Visibility::Private => "",
+            Visibility::Public => "pub ",
+            Visibility::Crate => "pub(crate) ",
+        };
         if func.is_async {
-            write!(output, "async fn {}{}(", func.name, generic_params).unwrap();
+            write!(output, "{}async fn {}{}(", visibility, name, generic_params).unwrap();
+        } else if func.is_const {
+            write!(output, "{}const fn {}{}(", visibility, name, generic_params).unwrap();
         } else {
-            write!(output, "fn {}{}(", func.name, generic_params).unwrap();
+            write!(output, "{}fn {}{}(", visibility, name, generic_params).unwrap();
         }
 
         // Parameters - use original names for proper reference in function body
@@ -373,6 +747,13 @@ impl RustBackend {
 
         writeln!(output, ") -> {} {{", return_type).unwrap();
 
+        if matches!(func.return_type, IrType::Generator(_)) {
+            self.emit_generator_body(func, module, output);
+            writeln!(output, "}}").unwrap();
+            writeln!(output).unwrap();
+            return;
+        }
+
         // Local variables
         for (name, ty) in &func.locals {
             writeln!(output, "    let mut {}: {};", name, self.emit_type(ty)).unwrap();
@@ -390,6 +771,55 @@ impl RustBackend {
         writeln!(output).unwrap();
     }
 
+    /// Emit the body of a generator function as a closure that runs lazily,
+    /// handing each `yield`ed value to the consumer through `__yield`
+    fn emit_generator_body(&self, func: &Function, module: &Module, output: &mut String) {
+        writeln!(output, "    mendes_runtime::Generator::new(move |__yield| {{").unwrap();
+
+        let mut body = String::new();
+        for (name, ty) in &func.locals {
+            writeln!(body, "    let mut {}: {};", name, self.emit_type(ty)).unwrap();
+        }
+        self.emit_structured_blocks(func, module, &mut body);
+        for line in body.lines() {
+            if line.is_empty() {
+                writeln!(output).unwrap();
+            } else {
+                writeln!(output, "    {}", line).unwrap();
+            }
+        }
+
+        writeln!(output, "    }})").unwrap();
+    }
+
+    /// Emit a lowered `async:` block as a function returning a boxed
+    /// `async move { ... }` future; the outer locals it reads are its parameters
+    fn emit_async_block(&self, func: &Function, module: &Module, output: &mut String) {
+        let params: Vec<String> = func.params.iter()
+            .map(|(name, ty)| format!("{}: {}", name, self.emit_type(ty)))
+            .collect();
+        let future = IrType::Future(Box::new(func.return_type.clone()));
+        writeln!(output, "fn {}({}) -> {} {{", func.name, params.join(", "), self.emit_type(&future)).unwrap();
+        writeln!(output, "    Box::pin(async move {{").unwrap();
+
+        let mut body = String::new();
+        for (name, ty) in &func.locals {
+            writeln!(body, "    let mut {}: {};", name, self.emit_type(ty)).unwrap();
+        }
+        self.emit_structured_blocks(func, module, &mut body);
+        for line in body.lines() {
+            if line.is_empty() {
+                writeln!(output).unwrap();
+            } else {
+                writeln!(output, "    {}", line).unwrap();
+            }
+        }
+
+        writeln!(output, "    }})").unwrap();
+        writeln!(output, "}}").unwrap();
+        writeln!(output).unwrap();
+    }
+
     /// Emit blocks with proper structured control flow (if/else, for, while)
     fn emit_structured_blocks(&self, func: &Function, module: &Module, output: &mut String) {
         use std::collections::{HashMap, HashSet};
@@ -403,7 +833,8 @@ impl RustBackend {
         // Identify loop headers (for_cond_*, while_cond_*)
         let mut loop_headers: HashSet<&str> = HashSet::new();
         for block in &func.blocks {
-            if block.label.starts_with("for_cond_") || block.label.starts_with("while_cond_") {
+            if block.label.starts_with("for_cond_") || block.label.starts_with("while_cond_")
+                || block.label.starts_with("iter_cond_") || block.label.starts_with("whilelet_cond_") {
                 loop_headers.insert(&block.label);
             }
         }
@@ -486,6 +917,65 @@ impl RustBackend {
         })
     }
 
+    /// Detect a `while let` loop
+    fn detect_while_let_loop(&self, cond_label: &str, block_map: &std::collections::HashMap<&str, &mendes_ir::BasicBlock>)
+        -> Option<WhileLoopInfo>
+    {
+        let loop_id = cond_label.strip_prefix("whilelet_cond_")?;
+
+        let body_label = format!("whilelet_body_{}", loop_id);
+        let end_label = format!("whilelet_end_{}", loop_id);
+
+        if !block_map.contains_key(body_label.as_str()) ||
+           !block_map.contains_key(end_label.as_str()) {
+            return None;
+        }
+
+        Some(WhileLoopInfo {
+            loop_id: loop_id.to_string(),
+            body_label,
+            end_label,
+        })
+    }
+
+    /// Detect a loop over a user iterator and find its `next()` call
+    fn detect_iter_loop(&self, cond_label: &str, block_map: &std::collections::HashMap<&str, &mendes_ir::BasicBlock>)
+        -> Option<IterLoopInfo>
+    {
+        let loop_id = cond_label.strip_prefix("iter_cond_")?;
+
+        let body_label = format!("iter_body_{}", loop_id);
+        let end_label = format!("iter_end_{}", loop_id);
+
+        if !block_map.contains_key(body_label.as_str()) ||
+           !block_map.contains_key(end_label.as_str()) {
+            return None;
+        }
+
+        let (next_dest, iter) = block_map.get(cond_label)?.instructions.iter()
+            .find_map(|inst| match inst {
+                Instruction::Call { dest: Some(dest), func, args } if func == "__iter_next" => {
+                    Some((*dest, args.first()?.clone()))
+                }
+                _ => None,
+            })?;
+
+        // Generators implement Rust's `Iterator`
+        let is_generator = block_map.values()
+            .flat_map(|block| &block.instructions)
+            .any(|inst| matches!(inst, Instruction::Alloca { dest, ty: IrType::Generator(_) }
+                if matches!(&iter, Value::Local(name) if name == dest)));
+
+        Some(IterLoopInfo {
+            loop_id: loop_id.to_string(),
+            iter,
+            mendes_option: !is_generator,
+            next_dest,
+            body_label,
+            end_label,
+        })
+    }
+
     fn emit_block_with_loops<'a>(
         &self,
         block: &'a mendes_ir::BasicBlock,
@@ -526,6 +1016,42 @@ impl RustBackend {
                     return;
                 }
 
+                // Check if we're branching to a user iterator loop header
+                if let Some(loop_info) = self.detect_iter_loop(target, block_map) {
+                    for inst2 in &block.instructions {
+                        if matches!(inst2, Instruction::Branch { .. }) {
+                            break;
+                        }
+                        self.emit_instruction_indented(inst2, module, output, depth);
+                    }
+                    emitted.insert(&block.label);
+
+                    self.emit_iter_loop(&loop_info, block_map, emitted, module, output, depth);
+
+                    if let Some(end_block) = block_map.get(loop_info.end_label.as_str()) {
+                        self.emit_block_with_loops(end_block, block_map, loop_headers, emitted, module, output, depth);
+                    }
+                    return;
+                }
+
+                // Check if we're branching to a `while let` header
+                if let Some(loop_info) = self.detect_while_let_loop(target, block_map) {
+                    for inst2 in &block.instructions {
+                        if matches!(inst2, Instruction::Branch { .. }) {
+                            break;
+                        }
+                        self.emit_instruction_indented(inst2, module, output, depth);
+                    }
+                    emitted.insert(&block.label);
+
+                    self.emit_while_let_loop(&loop_info, block_map, emitted, module, output, depth);
+
+                    if let Some(end_block) = block_map.get(loop_info.end_label.as_str()) {
+                        self.emit_block_with_loops(end_block, block_map, loop_headers, emitted, module, output, depth);
+                    }
+                    return;
+                }
+
                 // Check if we're branching to a while loop header
                 if let Some(loop_info) = self.detect_while_loop(target, block_map) {
                     for inst2 in &block.instructions {
@@ -562,13 +1088,14 @@ impl RustBackend {
                 }
                 Instruction::CondBranch { cond, then_label, else_label } => {
                     // Check if this is the condition of a for/while loop (we're inside the cond block)
-                    if block.label.starts_with("for_cond_") || block.label.starts_with("while_cond_") {
+                    if block.label.starts_with("for_cond_") || block.label.starts_with("while_cond_")
+                        || block.label.starts_with("iter_cond_") || block.label.starts_with("whilelet_cond_") {
                         // Skip - handled by loop emitter
                         continue;
                     }
 
                     // Regular if/else
-                    writeln!(output, "{}if {} {{", indent, self.emit_value(cond)).unwrap();
+                    writeln!(output, "{}if {} {{", indent, self.emit_value(cond, module)).unwrap();
 
                     if let Some(then_block) = block_map.get(then_label.as_str()) {
                         if !emitted.contains(then_label.as_str()) {
@@ -626,7 +1153,7 @@ impl RustBackend {
             indent,
             loop_info.loop_var,
             cmp_str,
-            self.emit_value(&loop_info.end_val)
+            self.emit_value(&loop_info.end_val, module)
         ).unwrap();
 
         // Emit body block contents
@@ -660,7 +1187,7 @@ impl RustBackend {
                         } else {
                             writeln!(output, "{}let __t{} = {} {} {};",
                                 "    ".repeat(depth + 1), dest, loop_info.loop_var,
-                                self.emit_binary_op(op), self.emit_value(right)).unwrap();
+                                self.emit_binary_op(op), self.emit_value(right, module)).unwrap();
                             writeln!(output, "{}{} = __t{};", "    ".repeat(depth + 1), loop_info.loop_var, dest).unwrap();
                         }
                     }
@@ -699,9 +1226,9 @@ impl RustBackend {
             for inst in &cond_block.instructions {
                 if let Instruction::Compare { dest, op, left, right } = inst {
                     condition = format!("{} {} {}",
-                        self.emit_value(left),
+                        self.emit_value(left, module),
                         self.emit_compare_op(op),
-                        self.emit_value(right));
+                        self.emit_value(right, module));
                     break;
                 }
             }
@@ -724,6 +1251,89 @@ impl RustBackend {
         writeln!(output, "{}}}", indent).unwrap();
     }
 
+    /// Emits `while let` as a `loop` that re-evaluates and matches the value
+    /// on every iteration, leaving once the pattern no longer matches
+    fn emit_while_let_loop(
+        &self,
+        loop_info: &WhileLoopInfo,
+        block_map: &std::collections::HashMap<&str, &mendes_ir::BasicBlock>,
+        emitted: &mut std::collections::HashSet<&str>,
+        module: &Module,
+        output: &mut String,
+        depth: usize,
+    ) {
+        let indent = "    ".repeat(depth);
+        let inner = "    ".repeat(depth + 1);
+        let cond_label = format!("whilelet_cond_{}", loop_info.loop_id);
+
+        // Mark loop blocks as emitted
+        emitted.insert(Box::leak(cond_label.clone().into_boxed_str()));
+        emitted.insert(Box::leak(loop_info.body_label.clone().into_boxed_str()));
+
+        writeln!(output, "{}loop {{", indent).unwrap();
+
+        if let Some(cond_block) = block_map.get(cond_label.as_str()) {
+            for inst in &cond_block.instructions {
+                match inst {
+                    Instruction::CondBranch { cond, .. } => {
+                        writeln!(output, "{}if !{} {{", inner, self.emit_value(cond, module)).unwrap();
+                        writeln!(output, "{}    break;", inner).unwrap();
+                        writeln!(output, "{}}}", inner).unwrap();
+                    }
+                    _ => self.emit_instruction_indented(inst, module, output, depth + 1),
+                }
+            }
+        }
+
+        if let Some(body_block) = block_map.get(loop_info.body_label.as_str()) {
+            for inst in &body_block.instructions {
+                match inst {
+                    Instruction::Branch { .. } => {}
+                    _ => {
+                        self.emit_instruction_indented(inst, module, output, depth + 1);
+                    }
+                }
+            }
+        }
+
+        writeln!(output, "{}}}", indent).unwrap();
+    }
+
+    fn emit_iter_loop(
+        &self,
+        loop_info: &IterLoopInfo,
+        block_map: &std::collections::HashMap<&str, &mendes_ir::BasicBlock>,
+        emitted: &mut std::collections::HashSet<&str>,
+        module: &Module,
+        output: &mut String,
+        depth: usize,
+    ) {
+        let indent = "    ".repeat(depth);
+        let cond_label = format!("iter_cond_{}", loop_info.loop_id);
+
+        // Mark loop blocks as emitted
+        emitted.insert(Box::leak(cond_label.into_boxed_str()));
+        emitted.insert(Box::leak(loop_info.body_label.clone().into_boxed_str()));
+
+        let some = if loop_info.mendes_option { "MendesOption::Some" } else { "Some" };
+        writeln!(output, "{}while let {}(__t{}) = {}.next() {{",
+            indent, some, loop_info.next_dest, self.emit_value(&loop_info.iter, module)).unwrap();
+
+        // Emit body
+        if let Some(body_block) = block_map.get(loop_info.body_label.as_str()) {
+            for inst in &body_block.instructions {
+                match inst {
+                    Instruction::Branch { .. } => {}
+                    _ => {
+                        self.emit_instruction_indented(inst, module, output, depth + 1);
+                    }
+                }
+            }
+        }
+
+        writeln!(output, "{}}}", indent).unwrap();
+    }
+
     fn emit_instruction_indented(&self, inst: &Instruction, module: &Module, output: &mut String, depth: usize) {
         let indent = "    ".repeat(depth);
         match inst {
@@ -732,33 +1342,51 @@ impl RustBackend {
             }
 
             Instruction::Store { value, ptr } => {
-                writeln!(output, "{}{} = {};", indent, self.emit_value(ptr), self.emit_value(value)).unwrap();
+                writeln!(output, "{}{} = {};", indent, self.emit_value(ptr, module), self.emit_value(value, module)).unwrap();
+            }
+
+            // Futures, task handles and boxed trait objects cannot be
+            // cloned; reading the local moves the value out of it
+            Instruction::Load { dest, ptr, ty } if matches!(ty, IrType::Future(_) | IrType::Task(_))
+                || matches!(ty, IrType::Struct(name) if name.starts_with("dyn ")) => {
+                writeln!(output, "{}let __t{} = {};", indent, dest, self.emit_value(ptr, module)).unwrap();
             }
 
             Instruction::Load { dest, ptr, .. } => {
-                writeln!(output, "{}let __t{} = {}.clone();", indent, dest, self.emit_value(ptr)).unwrap();
+                writeln!(output, "{}let __t{} = {}.clone();", indent, dest, self.emit_value(ptr, module)).unwrap();
             }
 
             Instruction::Binary { dest, op, left, right } => {
                 writeln!(output, "{}let __t{} = {} {} {};",
-                    indent, dest, self.emit_value(left), self.emit_binary_op(op), self.emit_value(right)).unwrap();
+                    indent, dest, self.emit_value(left, module), self.emit_binary_op(op), self.emit_value(right, module)).unwrap();
+            }
+
+            Instruction::CheckedBinary { dest, op, left, right } => {
+                let verb = match op {
+                    BinaryOp::Add => "add",
+                    BinaryOp::Sub => "subtract",
+                    BinaryOp::Mul => "multiply",
+                    _ => "divide",
+                };
+                writeln!(output, "{}let __t{} = i64::checked_{}({}, {}).expect(\"attempt to {} with overflow\");",
+                    indent, dest, op, self.emit_value(left, module), self.emit_value(right, module), verb).unwrap();
             }
 
             Instruction::Compare { dest, op, left, right } => {
                 writeln!(output, "{}let __t{} = {} {} {};",
-                    indent, dest, self.emit_value(left), self.emit_compare_op(op), self.emit_value(right)).unwrap();
+                    indent, dest, self.emit_value(left, module), self.emit_compare_op(op), self.emit_value(right, module)).unwrap();
             }
 
             Instruction::Not { dest, value } => {
-                writeln!(output, "{}let __t{} = !{};", indent, dest, self.emit_value(value)).unwrap();
+                writeln!(output, "{}let __t{} = !{};", indent, dest, self.emit_value(value, module)).unwrap();
             }
 
             Instruction::Neg { dest, value } => {
-                writeln!(output, "{}let __t{} = -{};", indent, dest, self.emit_value(value)).unwrap();
+                writeln!(output, "{}let __t{} = -{};", indent, dest, self.emit_value(value, module)).unwrap();
             }
 
             Instruction::Call { dest, func, args } => {
-                let args_str: Vec<String> = args.iter().map(|a| self.emit_value(a)).collect();
+                let args_str: Vec<String> = args.iter().map(|a| self.emit_value(a, module)).collect();
 
                 // Check for built-in functions
                 match func.as_str() {
@@ -774,6 +1402,10 @@ impl RustBackend {
                         }
                         return;
                     }
+                    "__yield" => {
+                        writeln!(output, "{}__yield.send({});", indent, args_str[0]).unwrap();
+                        return;
+                    }
                     "log" | "__builtin_log" => {
                         writeln!(output, "{}eprintln!(\"[LOG] {{}}\", {});", indent, args_str.join(", ")).unwrap();
                         return;
@@ -786,7 +1418,11 @@ impl RustBackend {
                     }
                     "str" | "__builtin_str" => {
                         if let Some(d) = dest {
-                            writeln!(output, "{}let __t{} = MendesString::new(&format!(\"{{}}\", {}));", indent, d, args_str[0]).unwrap();
+                            if let [value, precision] = args_str.as_slice() {
+                                writeln!(output, "{}let __t{} = MendesString::new(&format!(\"{{:.*}}\", {} as usize, {}));", indent, d, precision, value).unwrap();
+                            } else {
+                                writeln!(output, "{}let __t{} = MendesString::new(&format!(\"{{}}\", {}));", indent, d, args_str[0]).unwrap();
+                            }
                         }
                         return;
                     }
@@ -802,6 +1438,99 @@ impl RustBackend {
                         }
                         return;
                     }
+                    "__parse_int" | "__parse_float" | "__parse_bool" => {
+                        if let Some(d) = dest {
+                            let target = match func.as_str() {
+                                "__parse_int" => "i64",
+                                "__parse_float" => "f64",
+                                _ => "bool",
+                            };
+                            writeln!(output, "{}let __t{} = MendesResult::from({}.as_str().parse::<{}>().map_err(|e| MendesString::new(&e.to_string())));", indent, d, args_str[0], target).unwrap();
+                        }
+                        return;
+                    }
+                    "__str_char_at" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = {}.char_at({});", indent, d, args_str[0], args_str[1]).unwrap();
+                        }
+                        return;
+                    }
+                    "__str_slice" => {
+                        if let Some(d) = dest {
+                            let end = args_str.get(2).map(|end| format!("Some({})", end)).unwrap_or_else(|| "None".to_string());
+                            writeln!(output, "{}let __t{} = {}.slice({}, {});", indent, d, args_str[0], args_str[1], end).unwrap();
+                        }
+                        return;
+                    }
+                    "__array_repeat" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = vec![{}; {} as usize];", indent, d, args_str[0], args_str[1]).unwrap();
+                        }
+                        return;
+                    }
+                    "__duration_ms" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = std::time::Duration::from_millis({} as u64);", indent, d, args_str[0]).unwrap();
+                        }
+                        return;
+                    }
+                    "__drop" => {
+                        writeln!(output, "{}drop({});", indent, args_str[0]).unwrap();
+                        return;
+                    }
+                    f if f.starts_with("__std_") => {
+                        if let (Some(d), Some(expr)) = (dest, Self::std_call(f, &args_str)) {
+                            writeln!(output, "{}let __t{} = {};", indent, d, expr).unwrap();
+                            return;
+                        }
+                    }
+                    "__static_load" | "__static_store" | "__static_add" | "__static_sub" | "__static_mul" | "__static_div" => {
+                        let global = match &args[0] {
+                            Value::Global(name) => module.get_global(name),
+                            _ => None,
+                        };
+                        if let Some(global) = global {
+                            self.emit_static_access(func, global, *dest, args_str.get(1).cloned(), &indent, output);
+                            return;
+                        }
+                    }
+                    "__box" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = Box::new({});", indent, d, args_str[0]).unwrap();
+                        }
+                        return;
+                    }
+                    "stream" | "__builtin_stream" if module.get_function(func).is_none() => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = Response::stream_file({}.as_str());", indent, d, args_str[0]).unwrap();
+                        }
+                        return;
+                    }
+                    "response" | "__builtin_response" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = Response::from({});", indent, d, args_str[0]).unwrap();
+                        }
+                        return;
+                    }
+                    // A user function of the same name shadows the builtin
+                    "redirect" | "redirect_permanent" if module.get_function(func).is_none() => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = Response::{}({}.as_str());", indent, d, func, args_str[0]).unwrap();
+                        }
+                        return;
+                    }
+                    "spawn" | "__builtin_spawn" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = tokio::spawn({});", indent, d, args_str[0]).unwrap();
+                        }
+                        return;
+                    }
+                    "join" | "__builtin_join" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = {}.await.unwrap();", indent, d, args_str[0]).unwrap();
+                        }
+                        return;
+                    }
                     // Result/Option constructors
                     "__result_ok" => {
                         if let Some(d) = dest {
@@ -821,6 +1550,76 @@ impl RustBackend {
                         }
                         return;
                     }
+                    "__option_none" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = MendesOption::None;", indent, d).unwrap();
+                        }
+                        return;
+                    }
+                    // Map methods: the map is the first argument
+                    "__map_lit" => {
+                        if let Some(d) = dest {
+                            let entries: Vec<_> = args_str.chunks(2)
+                                .map(|entry| format!("({}, {})", entry[0], entry[1]))
+                                .collect();
+                            writeln!(output, "{}let __t{} = std::collections::BTreeMap::from([{}]);", indent, d, entries.join(", ")).unwrap();
+                        }
+                        return;
+                    }
+                    "__map_get" | "__map_contains_key" | "__map_insert" | "__map_remove" | "__map_keys" | "__map_values" => {
+                        if let Some(d) = dest {
+                            let map = &args_str[0];
+                            let call = match func.as_str() {
+                                "__map_get" => format!("MendesOption::from({}.get(&{}).cloned())", map, args_str[1]),
+                                "__map_contains_key" => format!("{}.contains_key(&{})", map, args_str[1]),
+                                "__map_insert" => format!("MendesOption::from({}.insert({}, {}))", map, args_str[1], args_str[2]),
+                                "__map_remove" => format!("MendesOption::from({}.remove(&{}))", map, args_str[1]),
+                                "__map_keys" => format!("{}.keys().cloned().collect::<MendesArray<_>>()", map),
+                                _ => format!("{}.values().cloned().collect::<MendesArray<_>>()", map),
+                            };
+                            writeln!(output, "{}let __t{} = {};", indent, d, call).unwrap();
+                        }
+                        return;
+                    }
+                    // Array reductions: the array is the first argument and
+                    // the closure the last; comparators return an `int`
+                    "__array_fold" | "__array_reduce" | "__array_min_by" | "__array_max_by" => {
+                        if let Some(d) = dest {
+                            let array = &args_str[0];
+                            let f = &args_str[args_str.len() - 1];
+                            let call = match func.as_str() {
+                                "__array_fold" => format!("{}.iter().cloned().fold({}, {})", array, args_str[1], f),
+                                "__array_reduce" => format!("MendesOption::from({}.iter().cloned().reduce({}))", array, f),
+                                _ => format!("MendesOption::from({}.iter().cloned().{}(|a, b| {}(a.clone(), b.clone()).cmp(&0)))",
+                                    array, &func["__array_".len()..], f),
+                            };
+                            writeln!(output, "{}let __t{} = {};", indent, d, call).unwrap();
+                        }
+                        return;
+                    }
+                    // An inclusive range is stored as the exclusive one past
+                    // its end, so every range value has the same Rust type
+                    "__range" => {
+                        if let Some(d) = dest {
+                            let end = if args_str[2] == "true" { format!("{} + 1", args_str[1]) } else { args_str[1].clone() };
+                            writeln!(output, "{}let __t{} = {}..{};", indent, d, args_str[0], end).unwrap();
+                        }
+                        return;
+                    }
+                    "__range_collect" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = {}.collect::<MendesArray<_>>();", indent, d, args_str[0]).unwrap();
+                        }
+                        return;
+                    }
+                    "__range_to_array" => {
+                        if let Some(d) = dest {
+                            let op = if args_str[2] == "true" { "..=" } else { ".." };
+                            writeln!(output, "{}let __t{} = ({}{}{}).collect::<MendesArray<_>>();",
+                                indent, d, args_str[0], op, args_str[1]).unwrap();
+                        }
+                        return;
+                    }
                     // Pattern matching helpers
                     "__is_none" => {
                         if let Some(d) = dest {
@@ -846,10 +1645,20 @@ impl RustBackend {
                         }
                         return;
                     }
-                    "__extract_variant_field" => {
-                        // Extract field from variant (simplified - assumes unwrap is safe after pattern match)
+                    // `__is_variant_Shape::Circle(s)`: whether `s` is that variant
+                    name if name.starts_with("__is_variant_") => {
                         if let Some(d) = dest {
-                            writeln!(output, "{}let __t{} = {}.unwrap_field({});", indent, d, args_str[0], args_str[1]).unwrap();
+                            let path = runtime_variant_path(&name["__is_variant_".len()..]);
+                            writeln!(output, "{}let __t{} = matches!({}, {} {{ .. }});", indent, d, args_str[0], path).unwrap();
+                        }
+                        return;
+                    }
+                    // `__variant_field_Shape::Rect.1(s)`: a field of `s` once the
+                    // variant has been checked; tuple fields go by position
+                    name if name.starts_with("__variant_field_") => {
+                        if let (Some(d), Some((path, field))) = (dest, name["__variant_field_".len()..].rsplit_once('.')) {
+                            writeln!(output, "{}let __t{} = match &{} {{ {} {{ {}: __field, .. }} => __field.clone(), _ => unreachable!() }};",
+                                indent, d, args_str[0], runtime_variant_path(path), field).unwrap();
                         }
                         return;
                     }
@@ -869,34 +1678,66 @@ impl RustBackend {
                     "__try_propagate" => {
                         if let Some(d) = dest {
                             // Convert error type for propagation
-                            writeln!(output, "{}let __t{} = {}.map_err(|e| e.into()).err().unwrap();", indent, d, args_str[0]).unwrap();
+                            writeln!(output, "{}let __t{} = MendesResult::Err({}.err().unwrap().into());", indent, d, args_str[0]).unwrap();
                         }
                         return;
                     }
-                    "__try_unwrap" => {
+                    "__tuple" if args_str.is_empty() => {
                         if let Some(d) = dest {
-                            writeln!(output, "{}let __t{} = {}.unwrap();", indent, d, args_str[0]).unwrap();
+                            writeln!(output, "{}let __t{} = ();", indent, d).unwrap();
                         }
                         return;
                     }
-                    // String interpolation
-                    "__string_format" => {
+                    "__tuple" => {
                         if let Some(d) = dest {
-                            // Generate format! call
-                            if args_str.is_empty() {
-                                writeln!(output, "{}let __t{} = MendesString::new(\"\");", indent, d).unwrap();
-                            } else {
-                                // Build format string with placeholders
-                                let format_args: Vec<_> = args_str.iter().map(|s| format!("{{{}}}", s)).collect();
-                                writeln!(output, "{}let __t{} = MendesString::new(&format!(\"{}\"));",
-                                    indent, d, format_args.join("")).unwrap();
-                            }
+                            writeln!(output, "{}let __t{} = ({},);", indent, d, args_str.join(", ")).unwrap();
                         }
                         return;
                     }
+                    "__tuple_field" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = {}.{}.clone();", indent, d, args_str[0], args_str[1]).unwrap();
+                        }
+                        return;
+                    }
+                    "__try_error" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = {}.err().unwrap();", indent, d, args_str[0]).unwrap();
+                        }
+                        return;
+                    }
+                    "__try_unwrap" => {
+                        if let Some(d) = dest {
+                            writeln!(output, "{}let __t{} = {}.unwrap();", indent, d, args_str[0]).unwrap();
+                        }
+                        return;
+                    }
+                    // String interpolation
                     _ => {}
                 }
 
+                // Optional chaining reads the field through a borrow of the
+                // option; `and_then` keeps an optional field from nesting
+                if let Some(field) = func.strip_prefix("__optional_field_") {
+                    if let Some(d) = dest {
+                        let adapter = if args_str[1] == "true" { "and_then" } else { "map" };
+                        writeln!(output, "{}let __t{} = {}.as_ref().{}(|v| v.{}.clone());",
+                            indent, d, args_str[0], adapter, field).unwrap();
+                    }
+                    return;
+                }
+
+                // Method calls: the receiver is the first argument; trait
+                // objects dispatch through the box
+                if let Some(method) = func.strip_prefix("__method_") {
+                    let call = format!("{}.{}({})", args_str[0], method, args_str[1..].join(", "));
+                    match dest {
+                        Some(d) => writeln!(output, "{}let __t{} = {};", indent, d, call).unwrap(),
+                        None => writeln!(output, "{}{};", indent, call).unwrap(),
+                    }
+                    return;
+                }
+
                 if let Some(d) = dest {
                     writeln!(output, "{}let __t{} = {}({});", indent, d, func, args_str.join(", ")).unwrap();
                 } else {
@@ -907,12 +1748,12 @@ impl RustBackend {
             Instruction::Return(value) => {
                 match value {
                     Value::Void => writeln!(output, "{}return;", indent).unwrap(),
-                    _ => writeln!(output, "{}return {};", indent, self.emit_value(value)).unwrap(),
+                    _ => writeln!(output, "{}return {};", indent, self.emit_value(value, module)).unwrap(),
                 }
             }
 
             Instruction::Await { dest, future } => {
-                writeln!(output, "{}let __t{} = {}.await;", indent, dest, self.emit_value(future)).unwrap();
+                writeln!(output, "{}let __t{} = {}.await;", indent, dest, self.emit_value(future, module)).unwrap();
             }
 
             Instruction::GetField { dest, ptr, struct_name, field_index, field_name } => {
@@ -921,7 +1762,7 @@ impl RustBackend {
                     .and_then(|s| s.fields.get(*field_index))
                     .map(|(name, _)| name.as_str())
                     .unwrap_or(field_name.as_str());
-                writeln!(output, "{}let __t{} = {}.{};", indent, dest, self.emit_value(ptr), resolved_name).unwrap();
+                writeln!(output, "{}let __t{} = {}.{};", indent, dest, self.emit_value(ptr, module), resolved_name).unwrap();
             }
 
             Instruction::SetField { ptr, struct_name, field_index, field_name, value } => {
@@ -930,15 +1771,19 @@ impl RustBackend {
                     .and_then(|s| s.fields.get(*field_index))
                     .map(|(name, _)| name.as_str())
                     .unwrap_or(field_name.as_str());
-                writeln!(output, "{}{}.{} = {};", indent, self.emit_value(ptr), resolved_name, self.emit_value(value)).unwrap();
+                writeln!(output, "{}{}.{} = {};", indent, self.emit_value(ptr, module), resolved_name, self.emit_value(value, module)).unwrap();
             }
 
-            Instruction::GetElement { dest, ptr, index } => {
-                writeln!(output, "{}let __t{} = {}[{} as usize];", indent, dest, self.emit_value(ptr), self.emit_value(index)).unwrap();
+            Instruction::GetElement { dest, ptr, index, in_bounds } => {
+                if *in_bounds && self.opt_level >= 3 {
+                    writeln!(output, "{}let __t{} = unsafe {{ *{}.get_unchecked({} as usize) }};", indent, dest, self.emit_value(ptr, module), self.emit_value(index, module)).unwrap();
+                } else {
+                    writeln!(output, "{}let __t{} = {}[{} as usize];", indent, dest, self.emit_value(ptr, module), self.emit_value(index, module)).unwrap();
+                }
             }
 
             Instruction::SetElement { ptr, index, value } => {
-                writeln!(output, "{}{}[{} as usize] = {};", indent, self.emit_value(ptr), self.emit_value(index), self.emit_value(value)).unwrap();
+                writeln!(output, "{}{}[{} as usize] = {};", indent, self.emit_value(ptr, module), self.emit_value(index, module), self.emit_value(value, module)).unwrap();
             }
 
             Instruction::NewStruct { dest, struct_name } => {
@@ -966,11 +1811,42 @@ impl RustBackend {
 
             Instruction::NewArray { dest, elem_type, size } => {
                 writeln!(output, "{}let __t{}: Vec<{}> = Vec::with_capacity({} as usize);",
-                    indent, dest, self.emit_type(elem_type), self.emit_value(size)).unwrap();
+                    indent, dest, self.emit_type(elem_type), self.emit_value(size, module)).unwrap();
             }
 
             Instruction::Cast { dest, value, to_type } => {
-                writeln!(output, "{}let __t{} = {} as {};", indent, dest, self.emit_value(value), self.emit_type(to_type)).unwrap();
+                writeln!(output, "{}let __t{} = {} as {};", indent, dest, self.emit_value(value, module), self.emit_type(to_type)).unwrap();
+            }
+
+            Instruction::FormatString { dest, parts } => {
+                // Literal text goes into the template, escaped for a Rust
+                // string literal and for `format!` braces
+                let mut template = String::new();
+                let mut args = Vec::new();
+                for part in parts {
+                    match part {
+                        FormatPart::Literal(text) => {
+                            let quoted = format!("{:?}", text);
+                            template.push_str(&quoted[1..quoted.len() - 1].replace('{', "{{").replace('}', "}}"));
+                        }
+                        FormatPart::Value(value) => {
+                            template.push_str("{}");
+                            args.push(self.emit_value(value, module));
+                        }
+                    }
+                }
+                if args.is_empty() {
+                    let text: String = parts.iter()
+                        .filter_map(|part| match part {
+                            FormatPart::Literal(text) => Some(text.as_str()),
+                            FormatPart::Value(_) => None,
+                        })
+                        .collect();
+                    writeln!(output, "{}let __t{} = MendesString::new({:?});", indent, dest, text).unwrap();
+                } else {
+                    writeln!(output, "{}let __t{} = MendesString::new(&format!(\"{}\", {}));",
+                        indent, dest, template, args.join(", ")).unwrap();
+                }
             }
 
             Instruction::Comment(text) => {
@@ -1022,7 +1898,7 @@ impl RustBackend {
                         writeln!(output, "            let {}: i64 = req.param_int(\"{}\").unwrap_or(0);",
                             param_name, param_name).unwrap();
                     }
-                    "string" => {
+                    "string" | "uuid" => {
                         writeln!(output, "            let {}: MendesString = MendesString::new(&req.param(\"{}\").cloned().unwrap_or_default());",
                             param_name, param_name).unwrap();
                     }
@@ -1093,8 +1969,9 @@ impl RustBackend {
 
             // on_message handler
             if let Some(message_handler) = &ws_route.on_message {
+                // The handler's `message` parameter picks the frame conversion
                 writeln!(output, "            // Message loop").unwrap();
-                writeln!(output, "            while let Some(msg) = ws.recv().await {{").unwrap();
+                writeln!(output, "            while let Some(msg) = ws.recv_as().await {{").unwrap();
                 if path_params.is_empty() {
                     writeln!(output, "                {}(ws.clone(), msg).await;", message_handler).unwrap();
                 } else {
@@ -1176,11 +2053,29 @@ impl RustBackend {
         writeln!(output, "}}").unwrap();
         writeln!(output).unwrap();
 
+        // A database that cannot be reached is left unset rather than
+        // stopping the server
         writeln!(output, "impl DbContext {{").unwrap();
         writeln!(output, "    pub async fn new() -> Self {{").unwrap();
         writeln!(output, "        Self {{").unwrap();
         for db in &module.databases {
-            writeln!(output, "            {}: None, // TODO: Initialize from env", db.name).unwrap();
+            let pool_type = match db.db_type.as_str() {
+                "mysql" => "MysqlPool",
+                "sqlite" => "SqlitePool",
+                _ => "PostgresPool",
+            };
+            let timeout = match db.timeout {
+                Some(timeout) => format!("Some(std::time::Duration::from_millis({}))", timeout),
+                None => "None".to_string(),
+            };
+            writeln!(output, "            {}: match {}::connect({:?}, {:?}, {}, {}).await {{",
+                db.name, pool_type, db.name, db.url, db.pool_size, timeout).unwrap();
+            writeln!(output, "                Ok(pool) => Some(pool),").unwrap();
+            writeln!(output, "                Err(e) => {{").unwrap();
+            writeln!(output, "                    eprintln!(\"database '{}': {{}}\", e);", db.name).unwrap();
+            writeln!(output, "                    None").unwrap();
+            writeln!(output, "                }}").unwrap();
+            writeln!(output, "            }},").unwrap();
         }
         writeln!(output, "        }}").unwrap();
         writeln!(output, "    }}").unwrap();
@@ -1190,19 +2085,41 @@ impl RustBackend {
 
     fn emit_main(&self, module: &Module, output: &mut String) {
         let has_db = !module.databases.is_empty();
+        let user_main = module.functions.iter().find(|f| f.name == "main");
+        let returns_result = user_main.is_some_and(|f| matches!(&f.return_type, IrType::Result(..)));
 
         writeln!(output, "#[tokio::main]").unwrap();
-        writeln!(output, "async fn main() {{").unwrap();
+        if returns_result {
+            writeln!(output, "async fn main() -> Result<(), Box<dyn std::error::Error>> {{").unwrap();
+        } else {
+            writeln!(output, "async fn main() {{").unwrap();
+        }
         writeln!(output, "    mendes_runtime::init();").unwrap();
         writeln!(output).unwrap();
 
+        // The Mendes `main` runs before the server starts; its error, if
+        // any, is printed by the Rust runtime and exits with status 1
+        if let Some(func) = user_main {
+            let call = format!("{}(){}", USER_MAIN, if func.is_async { ".await" } else { "" });
+            if returns_result {
+                writeln!(output, "    Result::from({}).map_err(|e| e.to_string())?;", call).unwrap();
+            } else {
+                writeln!(output, "    {};", call).unwrap();
+            }
+            writeln!(output).unwrap();
+        }
+
         // Database connections
         if has_db {
             writeln!(output, "    // Initialize database connections").unwrap();
             writeln!(output, "    let db = Arc::new(DbContext::new().await);").unwrap();
             for db in &module.databases {
-                writeln!(output, "    // Database '{}': {} (pool_size: {})",
-                    db.name, db.db_type, db.pool_size).unwrap();
+                match db.timeout {
+                    Some(timeout) => writeln!(output, "    // Database '{}': {} (pool_size: {}, timeout: {}ms)",
+                        db.name, db.db_type, db.pool_size, timeout).unwrap(),
+                    None => writeln!(output, "    // Database '{}': {} (pool_size: {})",
+                        db.name, db.db_type, db.pool_size).unwrap(),
+                }
             }
             writeln!(output).unwrap();
         }
@@ -1234,14 +2151,26 @@ impl RustBackend {
             }
             writeln!(output).unwrap();
             writeln!(output, "    Server::new(\"{}:{}\")", server.host, server.port).unwrap();
+            if let Some(max_body) = server.max_body {
+                writeln!(output, "        .max_body_size({})", max_body).unwrap();
+            }
+            if let Some(timeout) = server.timeout {
+                writeln!(output, "        .request_timeout(std::time::Duration::from_millis({}))", timeout).unwrap();
+            }
+            if let (Some(cert), Some(key)) = (&server.tls_cert, &server.tls_key) {
+                writeln!(output, "        .tls({:?}, {:?})", cert, key).unwrap();
+            }
             writeln!(output, "        .router(router)").unwrap();
             writeln!(output, "        .run()").unwrap();
             writeln!(output, "        .await").unwrap();
             writeln!(output, "        .expect(\"Server error\");").unwrap();
-        } else {
+        } else if user_main.is_none() {
             writeln!(output, "    println!(\"No server configuration found\");").unwrap();
         }
 
+        if returns_result {
+            writeln!(output, "    Ok(())").unwrap();
+        }
         writeln!(output, "}}").unwrap();
     }
 }
@@ -1255,7 +2184,9 @@ impl CodeGen for RustBackend {
         self.emit_prelude(module, &mut output);
         self.emit_string_table(module, &mut output);
         self.emit_type_aliases(module, &mut output);
+        self.emit_statics(module, &mut output);
         self.emit_structs(module, &mut output);
+        self.emit_enums(module, &mut output);
         self.emit_traits(module, &mut output);
         self.emit_db_context(module, &mut output);
 
@@ -1318,4 +2249,23 @@ mod tests {
         assert!(code.contains("pub id: i64"));
         assert!(code.contains("pub name: MendesString"));
     }
+
+    #[test]
+    fn test_checked_binary() {
+        let mut module = Module::new("test");
+
+        let mut func = Function::new("total", IrType::I64, false);
+        func.add_param("price", IrType::I64);
+        func.emit(Instruction::CheckedBinary {
+            dest: 0,
+            op: BinaryOp::Mul,
+            left: Value::Local("price".to_string()),
+            right: Value::ConstInt(3),
+        });
+        func.emit(Instruction::Return(Value::Temp(0)));
+        module.add_function(func);
+
+        let code = RustBackend::new().generate(&module);
+        assert!(code.contains("let __t0 = i64::checked_mul(price, 3).expect(\"attempt to multiply with overflow\");"));
+    }
 }
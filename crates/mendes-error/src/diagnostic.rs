@@ -108,7 +108,7 @@ impl Label {
 /// Structured error code
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ErrorCode {
-    /// Category (L = Lexer, P = Parser, T = Type, O = Ownership)
+    /// Category (L = Lexer, P = Parser, T = Type, O = Ownership, W = Warning)
     pub category: char,
     /// Error number
     pub number: u16,
@@ -135,12 +135,106 @@ impl ErrorCode {
     pub const TYPE_MISMATCH: Self = Self::new('T', 1);
     pub const UNKNOWN_TYPE: Self = Self::new('T', 2);
     pub const UNKNOWN_VARIABLE: Self = Self::new('T', 3);
+    pub const DUPLICATE_DEFINITION: Self = Self::new('T', 4);
+    pub const WRONG_TYPE_ARG_COUNT: Self = Self::new('T', 5);
+    pub const BREAK_OUTSIDE_LOOP: Self = Self::new('T', 6);
+    pub const NON_EXHAUSTIVE_MATCH: Self = Self::new('T', 7);
+    pub const INVALID_INDEX: Self = Self::new('T', 8);
+    pub const UNHASHABLE_KEY: Self = Self::new('T', 9);
+    pub const UNKNOWN_MODULE: Self = Self::new('T', 10);
+    pub const AMBIGUOUS_VARIANT: Self = Self::new('T', 11);
 
     // Ownership errors
     pub const USE_AFTER_MOVE: Self = Self::new('O', 1);
     pub const BORROW_AFTER_MOVE: Self = Self::new('O', 2);
     pub const MUT_BORROW_CONFLICT: Self = Self::new('O', 3);
     pub const BORROW_ACROSS_AWAIT: Self = Self::new('O', 4);
+
+    // Warnings
+    pub const SHADOWED_BUILTIN: Self = Self::new('W', 1);
+    pub const UNUSED_PATH_PARAM: Self = Self::new('W', 2);
+    pub const EMPTY_BODY: Self = Self::new('W', 3);
+    pub const SHADOWED_PATH_PARAM: Self = Self::new('W', 4);
+    pub const STATIC_DATA_RACE: Self = Self::new('W', 5);
+    pub const UNKNOWN_ATTRIBUTE: Self = Self::new('W', 6);
+    pub const UNREACHABLE_ARM: Self = Self::new('W', 7);
+    pub const INVALID_DERIVE: Self = Self::new('W', 8);
+    pub const BODY_ON_BODYLESS_METHOD: Self = Self::new('W', 9);
+    pub const UNUSED_VARIABLE: Self = Self::new('W', 10);
+    pub const UNKNOWN_LINT: Self = Self::new('W', 11);
+
+    /// Every known code with its title; codes are never renumbered or reused
+    pub const REGISTRY: &'static [(ErrorCode, &'static str)] = &[
+        (Self::UNEXPECTED_CHAR, "unexpected character"),
+        (Self::UNTERMINATED_STRING, "unterminated string literal"),
+        (Self::INVALID_NUMBER, "invalid number literal"),
+        (Self::INVALID_INDENT, "inconsistent indentation"),
+        (Self::UNEXPECTED_TOKEN, "unexpected token"),
+        (Self::EXPECTED_EXPRESSION, "expected an expression"),
+        (Self::EXPECTED_TYPE, "expected a type"),
+        (Self::INVALID_SYNTAX, "invalid syntax"),
+        (Self::TYPE_MISMATCH, "mismatched types"),
+        (Self::UNKNOWN_TYPE, "unknown type"),
+        (Self::UNKNOWN_VARIABLE, "unknown variable"),
+        (Self::DUPLICATE_DEFINITION, "duplicate definition"),
+        (Self::WRONG_TYPE_ARG_COUNT, "wrong number of type arguments"),
+        (Self::BREAK_OUTSIDE_LOOP, "`break` or `continue` outside of a loop"),
+        (Self::NON_EXHAUSTIVE_MATCH, "non-exhaustive match"),
+        (Self::INVALID_INDEX, "invalid array index"),
+        (Self::UNHASHABLE_KEY, "map key or set element cannot be hashed"),
+        (Self::UNKNOWN_MODULE, "unknown module or module item"),
+        (Self::AMBIGUOUS_VARIANT, "ambiguous enum variant"),
+        (Self::USE_AFTER_MOVE, "use of a moved value"),
+        (Self::BORROW_AFTER_MOVE, "borrow of a moved value"),
+        (Self::MUT_BORROW_CONFLICT, "conflicting mutable borrow"),
+        (Self::BORROW_ACROSS_AWAIT, "borrow held across `await`"),
+        (Self::SHADOWED_BUILTIN, "declaration shadows a builtin"),
+        (Self::UNUSED_PATH_PARAM, "unused path parameter"),
+        (Self::EMPTY_BODY, "empty function body"),
+        (Self::SHADOWED_PATH_PARAM, "local shadows a path parameter"),
+        (Self::STATIC_DATA_RACE, "mutable static guarded by a lock"),
+        (Self::UNKNOWN_ATTRIBUTE, "unknown attribute"),
+        (Self::UNREACHABLE_ARM, "unreachable match arm"),
+        (Self::INVALID_DERIVE, "trait cannot be derived"),
+        (Self::BODY_ON_BODYLESS_METHOD, "request body on a method without one"),
+        (Self::UNUSED_VARIABLE, "unused variable"),
+        (Self::UNKNOWN_LINT, "unknown lint in `@allow`"),
+    ];
+
+    /// Lint names accepted by `@allow(...)`, with the warnings each one silences
+    pub const LINTS: &'static [(&'static str, &'static [ErrorCode])] = &[
+        ("unused", &[Self::UNUSED_VARIABLE, Self::UNUSED_PATH_PARAM]),
+        ("shadowing", &[Self::SHADOWED_BUILTIN, Self::SHADOWED_PATH_PARAM]),
+        ("empty_body", &[Self::EMPTY_BODY]),
+        ("data_race", &[Self::STATIC_DATA_RACE]),
+        ("unreachable", &[Self::UNREACHABLE_ARM]),
+        ("request_body", &[Self::BODY_ON_BODYLESS_METHOD]),
+    ];
+
+    /// Short description of the code
+    pub fn title(&self) -> &'static str {
+        Self::REGISTRY
+            .iter()
+            .find(|(code, _)| code == self)
+            .map(|(_, title)| *title)
+            .unwrap_or("unregistered error code")
+    }
+
+    /// Warnings silenced by the lint `name`, or `None` for an unknown lint
+    pub fn lint(name: &str) -> Option<&'static [ErrorCode]> {
+        Self::LINTS
+            .iter()
+            .find(|(lint, _)| *lint == name)
+            .map(|(_, codes)| *codes)
+    }
+
+    /// Looks up a code by its identifier, e.g. `ET001`
+    pub fn lookup(id: &str) -> Option<Self> {
+        Self::REGISTRY
+            .iter()
+            .map(|(code, _)| *code)
+            .find(|code| code.to_string().eq_ignore_ascii_case(id))
+    }
 }
 
 impl fmt::Display for ErrorCode {
@@ -403,6 +497,14 @@ impl<'a> DiagnosticRenderer<'a> {
             ));
         }
 
+        // Pointer to the code's explanation
+        if let Some(code) = &diagnostic.code {
+            output.push_str(&format!(
+                " {} = {}: {}; run `mendes explain {}` for details\n",
+                " ", code, code.title(), code
+            ));
+        }
+
         output
     }
 }
@@ -434,5 +536,18 @@ mod tests {
         assert!(output.contains("error[ET001]"));
         assert!(output.contains("invalid type"));
         assert!(output.contains("test.ms:1:5"));
+        assert!(output.contains("ET001: mismatched types; run `mendes explain ET001`"));
+    }
+
+    #[test]
+    fn test_error_codes_are_unique_and_titled() {
+        let mut seen = std::collections::HashSet::new();
+        for (code, title) in ErrorCode::REGISTRY {
+            assert!(seen.insert(code.to_string()), "duplicate error code {}", code);
+            assert!(!title.is_empty(), "error code {} has no title", code);
+            assert_eq!(ErrorCode::lookup(&code.to_string()), Some(*code));
+        }
+        assert_eq!(ErrorCode::lookup("et004"), Some(ErrorCode::DUPLICATE_DEFINITION));
+        assert_eq!(ErrorCode::lookup("EX999"), None);
     }
 }
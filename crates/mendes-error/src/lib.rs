@@ -52,18 +52,22 @@ impl Diagnostics {
         self.items.push(diagnostic);
     }
 
-    pub fn error(&mut self, message: impl Into<String>) {
-        self.items.push(Diagnostic::error(message));
+    pub fn error(&mut self, code: ErrorCode, message: impl Into<String>) {
+        self.items.push(Diagnostic::error(message).with_code(code));
     }
 
-    pub fn warning(&mut self, message: impl Into<String>) {
-        self.items.push(Diagnostic::warning(message));
+    pub fn warning(&mut self, code: ErrorCode, message: impl Into<String>) {
+        self.items.push(Diagnostic::warning(message).with_code(code));
     }
 
     pub fn has_errors(&self) -> bool {
         self.items.iter().any(|d| d.level == Level::Error)
     }
 
+    pub fn has_warnings(&self) -> bool {
+        self.items.iter().any(|d| d.level == Level::Warning)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.items.is_empty()
     }
@@ -4,7 +4,8 @@
 //! report errors with precision.
 
 /// Represents a position in the source code
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
     /// Line (1-indexed)
     pub line: u32,
@@ -21,7 +22,8 @@ impl Position {
 }
 
 /// Represents a region in the source code (start to end)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
     /// Start position
     pub start: Position,
@@ -72,6 +74,11 @@ impl Span {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Checks if a position falls inside the span (end is exclusive)
+    pub fn contains(&self, pos: Position) -> bool {
+        self.start.offset <= pos.offset && pos.offset < self.end.offset
+    }
 }
 
 /// Trait for types that have a location in the code
@@ -110,4 +117,17 @@ mod tests {
         );
         assert_eq!(span.len(), 9);
     }
+
+    #[test]
+    fn test_span_contains() {
+        let span = Span::new(
+            Position::new(1, 5, 4),
+            Position::new(1, 10, 9),
+            0,
+        );
+        assert!(span.contains(Position::new(1, 5, 4)));
+        assert!(span.contains(Position::new(1, 9, 8)));
+        assert!(!span.contains(Position::new(1, 10, 9)));
+        assert!(!span.contains(Position::new(1, 1, 0)));
+    }
 }
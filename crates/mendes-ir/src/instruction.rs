@@ -124,6 +124,24 @@ impl fmt::Display for CompareOp {
     }
 }
 
+/// Piece of an interpolated string
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatPart {
+    /// Text copied as is
+    Literal(String),
+    /// Value formatted with `Display`
+    Value(Value),
+}
+
+impl fmt::Display for FormatPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatPart::Literal(text) => write!(f, "{:?}", text),
+            FormatPart::Value(value) => write!(f, "{}", value),
+        }
+    }
+}
+
 /// IR Instruction
 #[derive(Debug, Clone)]
 pub enum Instruction {
@@ -158,6 +176,15 @@ pub enum Instruction {
         right: Value,
     },
 
+    /// Integer operation that traps on overflow
+    /// %dest = checked_op left, right
+    CheckedBinary {
+        dest: u32,
+        op: BinaryOp,
+        left: Value,
+        right: Value,
+    },
+
     /// Comparison
     /// %dest = cmp op left, right
     Compare {
@@ -242,6 +269,8 @@ pub enum Instruction {
         dest: u32,
         ptr: Value,
         index: Value,
+        /// The index is proven to be within the array's bounds
+        in_bounds: bool,
     },
 
     /// Array element write
@@ -282,6 +311,13 @@ pub enum Instruction {
         to_type: IrType,
     },
 
+    /// String interpolation
+    /// %dest = format parts...
+    FormatString {
+        dest: u32,
+        parts: Vec<FormatPart>,
+    },
+
     /// Comment / debug info
     Comment(String),
 }
@@ -292,6 +328,7 @@ impl Instruction {
         match self {
             Instruction::Load { dest, .. } => Some(*dest),
             Instruction::Binary { dest, .. } => Some(*dest),
+            Instruction::CheckedBinary { dest, .. } => Some(*dest),
             Instruction::Compare { dest, .. } => Some(*dest),
             Instruction::Not { dest, .. } => Some(*dest),
             Instruction::Neg { dest, .. } => Some(*dest),
@@ -303,6 +340,7 @@ impl Instruction {
             Instruction::NewStruct { dest, .. } => Some(*dest),
             Instruction::NewArray { dest, .. } => Some(*dest),
             Instruction::Cast { dest, .. } => Some(*dest),
+            Instruction::FormatString { dest, .. } => Some(*dest),
             _ => None,
         }
     }
@@ -328,6 +366,9 @@ impl fmt::Display for Instruction {
             Instruction::Binary { dest, op, left, right } => {
                 write!(f, "%t{} = {} {}, {}", dest, op, left, right)
             }
+            Instruction::CheckedBinary { dest, op, left, right } => {
+                write!(f, "%t{} = checked_{} {}, {}", dest, op, left, right)
+            }
             Instruction::Compare { dest, op, left, right } => {
                 write!(f, "%t{} = cmp {} {}, {}", dest, op, left, right)
             }
@@ -371,8 +412,9 @@ impl fmt::Display for Instruction {
             Instruction::SetField { ptr, struct_name, field_index, field_name, value } => {
                 write!(f, "setfield {} %{}.{} ({}), {}", ptr, struct_name, field_index, field_name, value)
             }
-            Instruction::GetElement { dest, ptr, index } => {
-                write!(f, "%t{} = getelem {}, {}", dest, ptr, index)
+            Instruction::GetElement { dest, ptr, index, in_bounds } => {
+                let inbounds = if *in_bounds { " inbounds" } else { "" };
+                write!(f, "%t{} = getelem{} {}, {}", dest, inbounds, ptr, index)
             }
             Instruction::SetElement { ptr, index, value } => {
                 write!(f, "setelem {}, {}, {}", ptr, index, value)
@@ -389,6 +431,14 @@ impl fmt::Display for Instruction {
             Instruction::Cast { dest, value, to_type } => {
                 write!(f, "%t{} = cast {} to {}", dest, value, to_type)
             }
+            Instruction::FormatString { dest, parts } => {
+                write!(f, "%t{} = format ", dest)?;
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}", part)?;
+                }
+                Ok(())
+            }
             Instruction::Comment(text) => {
                 write!(f, "; {}", text)
             }
@@ -420,4 +470,13 @@ mod tests {
         };
         assert_eq!(inst.to_string(), "%t1 = call @foo(1, 2)");
     }
+
+    #[test]
+    fn test_format_display() {
+        let inst = Instruction::FormatString {
+            dest: 2,
+            parts: vec![FormatPart::Literal("n = ".to_string()), FormatPart::Value(Value::Local("n".to_string()))],
+        };
+        assert_eq!(inst.to_string(), "%t2 = format \"n = \", %n");
+    }
 }
@@ -31,8 +31,11 @@ pub mod types;
 pub mod instruction;
 pub mod module;
 pub mod lower;
+pub mod optimize;
 
-pub use types::{IrType, GenericParam, StructDef};
-pub use instruction::{Instruction, Value, BinaryOp, CompareOp};
-pub use module::{Module, Function, BasicBlock, HttpRoute, WsRoute, Global, TraitDef, TraitMethodDef, ImplDef, TypeAlias};
-pub use lower::lower_program;
+pub use types::{IrType, GenericParam, StructDef, EnumDef, VariantData};
+pub use instruction::{Instruction, Value, BinaryOp, CompareOp, FormatPart};
+pub use module::{Module, Function, BasicBlock, HttpRoute, WsRoute, Global, TraitDef, TraitMethodDef, ImplDef, TypeAlias, parse_ir_debug, IrParseError};
+pub use lower::{lower_program, lower_program_with_cfg, lower_program_with_options, LowerOptions};
+pub use optimize::optimize;
+pub use mendes_parser::{Visibility, DERIVABLE_TRAITS};
@@ -2,11 +2,13 @@
 //!
 //! Converts the high-level AST to intermediate representation.
 
-use crate::types::{IrType, StructDef, GenericParam};
-use crate::instruction::{Instruction, Value, BinaryOp, CompareOp};
-use crate::module::{Module, Function, HttpRoute, WsRoute, ServerConfig, DatabaseConfig, TraitDef, TraitMethodDef, ImplDef, TypeAlias};
+use crate::types::{IrType, StructDef, EnumDef, VariantData, GenericParam};
+use crate::instruction::{Instruction, Value, BinaryOp, CompareOp, FormatPart};
+use crate::module::{Module, Function, HttpRoute, WsRoute, ServerConfig, DatabaseConfig, TraitDef, TraitMethodDef, ImplDef, TypeAlias, Global};
 use mendes_parser::*;
-use std::collections::HashMap;
+use mendes_parser::visit::{self, Visitor};
+use mendes_error::Span;
+use std::collections::{HashMap, HashSet};
 
 /// Lowering context
 pub struct LoweringContext {
@@ -19,6 +21,27 @@ pub struct LoweringContext {
     label_counter: u32,
     /// HTTP handler counter
     handler_counter: u32,
+    /// Flags enabled with `--cfg`
+    cfg: HashSet<String>,
+    /// Declared return types of top-level functions, by name
+    fn_returns: HashMap<String, IrType>,
+    /// Declared parameter types of top-level functions, by name
+    fn_params: HashMap<String, Vec<IrType>>,
+    /// Element types of user types iterable through `next() -> Option<T>`
+    iterators: HashMap<String, IrType>,
+    /// `const fn`s by name, folded into array lengths
+    const_fns: HashMap<String, FnDecl>,
+    /// Values of module-level `const`s, folded into array lengths
+    const_values: HashMap<String, ConstValue>,
+    /// Lower `int` `+`, `-` and `*` to instructions that trap on overflow
+    checked_arithmetic: bool,
+    /// Default values of struct fields, filled into literals that omit them
+    field_defaults: HashMap<String, Vec<(String, Expr)>>,
+    /// Names imported from bundled `std` modules, mapped to the calls they
+    /// lower to (`sqrt` -> `__std_math_sqrt`)
+    std_imports: HashMap<String, String>,
+    /// Expression types resolved by the checker, by span
+    expr_types: HashMap<Span, Type>,
 }
 
 impl LoweringContext {
@@ -28,9 +51,27 @@ impl LoweringContext {
             locals: HashMap::new(),
             label_counter: 0,
             handler_counter: 0,
+            cfg: HashSet::new(),
+            fn_returns: HashMap::new(),
+            fn_params: HashMap::new(),
+            iterators: HashMap::new(),
+            const_fns: HashMap::new(),
+            const_values: HashMap::new(),
+            checked_arithmetic: false,
+            field_defaults: HashMap::new(),
+            std_imports: HashMap::new(),
+            expr_types: HashMap::new(),
         }
     }
 
+    /// Whether every `@cfg` flag among the attributes is enabled
+    fn is_enabled(&self, attrs: &[Attribute]) -> bool {
+        attrs.iter()
+            .filter(|attr| attr.name == "cfg")
+            .flat_map(|attr| &attr.args)
+            .all(|flag| self.cfg.contains(flag))
+    }
+
     /// Generates a unique label
     fn new_label(&mut self, prefix: &str) -> String {
         let label = format!("{}_{}", prefix, self.label_counter);
@@ -47,20 +88,55 @@ impl LoweringContext {
     }
 }
 
+/// Options that change how a program is lowered
+#[derive(Debug, Clone, Default)]
+pub struct LowerOptions {
+    /// Flags enabled with `--cfg`; `@cfg(flag)` items without one are dropped
+    pub cfg: HashSet<String>,
+    /// Trap on `int` overflow in `+`, `-` and `*` instead of using the
+    /// backend's native arithmetic
+    pub checked_arithmetic: bool,
+    /// Expression types resolved by the checker, by span. Lowering infers
+    /// the types of expressions missing here from their syntax.
+    pub expr_types: HashMap<Span, Type>,
+}
+
 /// Converts an AST program to an IR module
 pub fn lower_program(program: &Program) -> Module {
+    lower_program_with_options(program, &LowerOptions::default())
+}
+
+/// Converts an AST program to an IR module, dropping `@cfg(flag)` items
+/// whose flag is not in `cfg`
+pub fn lower_program_with_cfg(program: &Program, cfg: &HashSet<String>) -> Module {
+    lower_program_with_options(program, &LowerOptions { cfg: cfg.clone(), ..Default::default() })
+}
+
+/// Converts an AST program to an IR module with the given options
+pub fn lower_program_with_options(program: &Program, options: &LowerOptions) -> Module {
     let mut ctx = LoweringContext::new("main");
+    ctx.cfg = options.cfg.clone();
+    ctx.checked_arithmetic = options.checked_arithmetic;
+    ctx.expr_types = options.expr_types.clone();
 
     // First pass: collect declarations
     for stmt in &program.statements {
         collect_declarations(&mut ctx, stmt);
     }
 
+    for stmt in &program.statements {
+        fold_constant(&mut ctx, stmt);
+    }
+
+    mark_hashed_structs(&mut ctx, program);
+
     // Second pass: generate code
     for stmt in &program.statements {
         lower_statement(&mut ctx, stmt);
     }
 
+    mark_api_structs(&mut ctx, program);
+
     ctx.module
 }
 
@@ -75,8 +151,36 @@ fn convert_generic_param(ast_param: &mendes_parser::GenericParam) -> GenericPara
 /// Collects declarations (structs, functions, etc)
 fn collect_declarations(ctx: &mut LoweringContext, stmt: &Stmt) {
     match stmt {
+        // Items whose `@cfg` is disabled are dropped silently
+        Stmt::Attributed { attrs, item, .. } if ctx.is_enabled(attrs) => {
+            collect_declarations(ctx, item);
+            if let Stmt::Struct(s) = item.unattributed() {
+                let derives = attrs.iter().flat_map(|attr| attr.derives()).map(str::to_string);
+                if let Some(def) = ctx.module.structs.get_mut(&s.name) {
+                    def.derives.extend(derives);
+                }
+            }
+        }
+        Stmt::FromImport { module, items, .. } => {
+            let Some(exports) = stdlib::std_module(module) else { return };
+            for export in &exports {
+                let binding = match items {
+                    ImportItems::All => Some(export.name),
+                    ImportItems::Names(names) => names.iter()
+                        .find(|item| item.name == export.name)
+                        .map(|item| item.alias.as_deref().unwrap_or(&item.name)),
+                };
+                if let Some(binding) = binding {
+                    ctx.std_imports.insert(binding.to_string(), export.lowered_name(module));
+                    if export.params.is_some() {
+                        ctx.fn_returns.insert(binding.to_string(), IrType::from_mendes_type(&export.ty));
+                    }
+                }
+            }
+        }
         Stmt::Struct(s) => {
             let mut def = StructDef::new(s.name.clone());
+            def.visibility = s.visibility;
             // Add generic parameters
             for gp in &s.generic_params {
                 def.add_generic_param(convert_generic_param(gp));
@@ -84,12 +188,42 @@ fn collect_declarations(ctx: &mut LoweringContext, stmt: &Stmt) {
             for field in &s.fields {
                 def.add_field(field.name.clone(), IrType::from_mendes_type(&field.ty));
             }
+            let defaults: Vec<_> = s.fields.iter()
+                .filter_map(|f| Some((f.name.clone(), f.default.clone()?)))
+                .collect();
+            if !defaults.is_empty() {
+                ctx.field_defaults.insert(s.name.clone(), defaults);
+            }
             // Register method names
             for method in &s.methods {
                 def.add_method(format!("{}::{}", s.name, method.name));
             }
+            register_iterator(ctx, &s.name, &s.methods);
             ctx.module.add_struct(def);
         }
+        Stmt::Enum(e) => {
+            let mut def = EnumDef::new(e.name.clone());
+            for gp in &e.generic_params {
+                def.add_generic_param(convert_generic_param(gp));
+            }
+            for variant in &e.variants {
+                let data = match &variant.data {
+                    EnumVariantData::Unit => VariantData::Unit,
+                    EnumVariantData::Tuple(types) => {
+                        VariantData::Tuple(types.iter().map(IrType::from_mendes_type).collect())
+                    }
+                    EnumVariantData::Struct(fields) => VariantData::Struct(
+                        fields.iter()
+                            .map(|f| (f.name.clone(), IrType::from_mendes_type(&f.ty)))
+                            .collect()
+                    ),
+                };
+                def.add_variant(variant.name.clone(), data);
+            }
+            def.discriminants = e.discriminants()
+                .map(|values| values.into_iter().map(|(_, value)| value).collect());
+            ctx.module.add_enum(def);
+        }
         Stmt::Trait(t) => {
             let mut def = TraitDef::new(t.name.clone());
             // Add generic parameters
@@ -123,6 +257,10 @@ fn collect_declarations(ctx: &mut LoweringContext, stmt: &Stmt) {
             ctx.module.server = Some(ServerConfig {
                 host: s.host.clone(),
                 port: s.port,
+                max_body: s.max_body,
+                tls_cert: s.tls_cert.clone(),
+                tls_key: s.tls_key.clone(),
+                timeout: s.timeout,
             });
         }
         Stmt::Db(db) => {
@@ -136,18 +274,207 @@ fn collect_declarations(ctx: &mut LoweringContext, stmt: &Stmt) {
                 db_type: db_type.to_string(),
                 url: db.url.clone(),
                 pool_size: db.pool_size,
+                timeout: db.timeout,
             });
         }
         Stmt::Middleware(m) => {
             ctx.module.middlewares.push(m.name.clone());
         }
+        Stmt::Static { name, ty, mutable, .. } => {
+            // Registered up front so functions declared earlier resolve it;
+            // the initializer may call `const fn`s, folded in the second pass
+            let global = Global::new(name.clone(), IrType::from_mendes_type(ty));
+            ctx.module.add_global(if *mutable { global.mutable() } else { global });
+        }
+        Stmt::Const { name, ty, visibility, .. } => {
+            let global = Global::new(name.clone(), IrType::from_mendes_type(ty)).constant();
+            ctx.module.add_global(Global { visibility: *visibility, ..global });
+        }
+        Stmt::Fn(f) => {
+            if let Some(ret) = function_return_type(f) {
+                ctx.fn_returns.insert(f.name.clone(), ret);
+            }
+            let params = f.params.iter().map(|p| IrType::from_mendes_type(&p.ty)).collect();
+            ctx.fn_params.insert(f.name.clone(), params);
+            if f.is_const {
+                ctx.const_fns.insert(f.name.clone(), f.clone());
+            }
+        }
+        Stmt::ImplTrait(impl_decl) => {
+            register_iterator(ctx, &impl_decl.type_name, &impl_decl.methods);
+        }
+        _ => {}
+    }
+}
+
+/// Collects the struct names used as map keys or set elements in type annotations
+#[derive(Default)]
+struct HashedKeyTypes {
+    names: Vec<String>,
+}
+
+impl HashedKeyTypes {
+    fn visit_type(&mut self, ty: &Type) {
+        match ty {
+            Type::Generic { name, args } => {
+                if name == "Map" || name == "Set" {
+                    if let Some(key) = args.first() {
+                        collect_struct_names(key, &mut self.names);
+                    }
+                }
+                args.iter().for_each(|a| self.visit_type(a));
+            }
+            Type::Ref(inner) | Type::MutRef(inner) | Type::Array(inner) => self.visit_type(inner),
+            Type::Tuple(types) => types.iter().for_each(|t| self.visit_type(t)),
+            Type::Function { params, return_type } => {
+                params.iter().for_each(|p| self.visit_type(p));
+                self.visit_type(return_type);
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_signature(&mut self, params: &[Param], return_type: Option<&Type>) {
+        params.iter().for_each(|p| self.visit_type(&p.ty));
+        return_type.into_iter().for_each(|t| self.visit_type(t));
+    }
+}
+
+impl Visitor for HashedKeyTypes {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { ty: Some(ty), .. } => self.visit_type(ty),
+            Stmt::Fn(f) => self.visit_signature(&f.params, f.return_type.as_ref()),
+            Stmt::Struct(s) => {
+                s.fields.iter().for_each(|f| self.visit_type(&f.ty));
+                for m in &s.methods {
+                    self.visit_signature(&m.params, m.return_type.as_ref());
+                }
+            }
+            Stmt::ImplTrait(i) => {
+                for m in &i.methods {
+                    self.visit_signature(&m.params, m.return_type.as_ref());
+                }
+            }
+            _ => {}
+        }
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Closure { params, return_type, .. } = expr {
+            params.iter().filter_map(|p| p.ty.as_ref()).for_each(|t| self.visit_type(t));
+            return_type.iter().for_each(|t| self.visit_type(t));
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+/// `Option<T>` for an element type
+fn option_of(element: &IrType) -> IrType {
+    IrType::Option(Box::new(element.clone()))
+}
+
+fn collect_struct_names(ty: &Type, names: &mut Vec<String>) {
+    match ty {
+        Type::Named(name) => names.push(name.clone()),
+        Type::Generic { args, .. } | Type::Tuple(args) => args.iter().for_each(|a| collect_struct_names(a, names)),
+        Type::Ref(inner) | Type::MutRef(inner) | Type::Array(inner) => collect_struct_names(inner, names),
         _ => {}
     }
 }
 
+/// Marks every struct hashed as a map key or set element, and the structs
+/// nested in their fields, so the backend derives `Hash` and `Eq` for them
+fn mark_hashed_structs(ctx: &mut LoweringContext, program: &Program) {
+    let mut keys = HashedKeyTypes::default();
+    keys.visit_program(program);
+    let mut pending = keys.names;
+    while let Some(name) = pending.pop() {
+        let def = match ctx.module.structs.get_mut(&name) {
+            Some(def) => def,
+            None => continue,
+        };
+        if def.derive_hash {
+            continue;
+        }
+        def.derive_hash = true;
+        for (_, field) in &def.fields {
+            if let IrType::Struct(inner) = field {
+                pending.push(inner.clone());
+            }
+        }
+    }
+}
+
+/// Extends the structs named by route bodies and return types with the
+/// structs nested in their fields and in enum payloads, which always derive
+/// serde, and drops names that are not structs
+fn mark_api_structs(ctx: &mut LoweringContext, program: &Program) {
+    let mut pending: Vec<_> = ctx.module.api_structs.drain().collect();
+    let mut field_types = HashMap::new();
+    for stmt in &program.statements {
+        match stmt.unattributed() {
+            Stmt::Struct(s) => {
+                field_types.insert(s.name.as_str(), s.fields.iter().map(|f| &f.ty).collect::<Vec<_>>());
+            }
+            Stmt::Enum(e) => {
+                for variant in &e.variants {
+                    match &variant.data {
+                        EnumVariantData::Unit => {}
+                        EnumVariantData::Tuple(types) => types.iter().for_each(|t| collect_struct_names(t, &mut pending)),
+                        EnumVariantData::Struct(fields) => fields.iter().for_each(|f| collect_struct_names(&f.ty, &mut pending)),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    while let Some(name) = pending.pop() {
+        if !ctx.module.structs.contains_key(&name) || !ctx.module.api_structs.insert(name.clone()) {
+            continue;
+        }
+        for ty in field_types.get(name.as_str()).into_iter().flatten() {
+            collect_struct_names(ty, &mut pending);
+        }
+    }
+}
+
+/// Records `type_name` as iterable in `for` when it has a `next() -> Option<T>` method
+fn register_iterator(ctx: &mut LoweringContext, type_name: &str, methods: &[MethodDecl]) {
+    let item = methods.iter()
+        .filter(|m| m.name == "next" && m.params.is_empty())
+        .find_map(|m| match &m.return_type {
+            Some(Type::Generic { name, args }) if name == "Option" && args.len() == 1 => {
+                Some(IrType::from_mendes_type(&args[0]))
+            }
+            _ => None,
+        });
+    if let Some(item) = item {
+        ctx.iterators.insert(type_name.to_string(), item);
+    }
+}
+
 /// Converts a statement to IR
 fn lower_statement(ctx: &mut LoweringContext, stmt: &Stmt) {
     match stmt {
+        Stmt::Attributed { attrs, item, .. } if ctx.is_enabled(attrs) => {
+            // `@route` functions stay callable and also serve the route
+            if let Some(api) = stmt.attributed_route() {
+                lower_api(ctx, &api);
+            }
+            lower_statement(ctx, item);
+            if let Stmt::Fn(f) = item.unattributed() {
+                let hints = attrs.iter().filter(|attr| attr.is_hint()).map(|attr| match attr.args.first() {
+                    Some(mode) => format!("{}({})", attr.name, mode),
+                    None => attr.name.clone(),
+                });
+                if let Some(func) = ctx.module.get_function_mut(&f.name) {
+                    func.hints.extend(hints);
+                }
+            }
+        }
         Stmt::Fn(f) => {
             lower_function(ctx, f);
         }
@@ -170,24 +497,87 @@ fn lower_statement(ctx: &mut LoweringContext, stmt: &Stmt) {
         Stmt::Api(api) => {
             lower_api(ctx, api);
         }
+        Stmt::Group(group) => {
+            for api in group.expanded_routes() {
+                lower_api(ctx, &api);
+            }
+        }
         Stmt::WebSocket(ws) => {
             lower_websocket(ctx, ws);
         }
         Stmt::Middleware(m) => {
             lower_middleware(ctx, m);
         }
+        Stmt::Static { name, value, .. } => {
+            let initializer = lower_static_initializer(ctx, value);
+            if let Some(global) = ctx.module.globals.iter_mut().find(|g| g.name == *name) {
+                global.initializer = initializer;
+            }
+        }
         // Server, Db have already been processed
         _ => {}
     }
 }
 
+/// Folds the value of a `const`, in declaration order so later constants
+/// and array lengths can refer to earlier ones
+fn fold_constant(ctx: &mut LoweringContext, stmt: &Stmt) {
+    match stmt {
+        Stmt::Attributed { attrs, item, .. } if ctx.is_enabled(attrs) => fold_constant(ctx, item),
+        Stmt::Const { name, value, .. } => {
+            let initializer = lower_static_initializer(ctx, value);
+            if let Some(Value::ConstInt(n)) = initializer {
+                ctx.const_values.insert(name.clone(), ConstValue::Int(n));
+            }
+            if let Some(Value::ConstBool(b)) = initializer {
+                ctx.const_values.insert(name.clone(), ConstValue::Bool(b));
+            }
+            if let Some(global) = ctx.module.globals.iter_mut().find(|g| g.name == *name) {
+                global.initializer = initializer;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Folds the constant initializer of a `static` or `const`
+fn lower_static_initializer(ctx: &mut LoweringContext, value: &Expr) -> Option<Value> {
+    if let Expr::Unary { op: UnaryOp::Neg, expr, .. } = value {
+        if let Expr::FloatLit(v, _) = **expr {
+            return Some(Value::const_float(-v));
+        }
+    }
+    // A literal, or literals joined with `+`
+    if let Some(s) = concat_string_literals(value) {
+        return Some(Value::ConstString(ctx.module.add_string(s)));
+    }
+    match value {
+        Expr::FloatLit(v, _) => Some(Value::const_float(*v)),
+        _ => match ConstEvaluator::new(&ctx.const_fns).with_consts(&ctx.const_values).eval(value).ok()? {
+            ConstValue::Int(n) => Some(Value::ConstInt(n)),
+            ConstValue::Bool(b) => Some(Value::ConstBool(b)),
+        },
+    }
+}
+
 /// Converts a function to IR
+/// The IR return type of a function; generators declared `-> [T]` return a
+/// lazy `generator<T>` instead of an array
+fn function_return_type(f: &FnDecl) -> Option<IrType> {
+    match f.return_type.as_ref()? {
+        Type::Array(item) if f.is_generator() => {
+            Some(IrType::Generator(Box::new(IrType::from_mendes_type(item))))
+        }
+        ret => Some(IrType::from_mendes_type(ret)),
+    }
+}
+
 fn lower_function(ctx: &mut LoweringContext, f: &FnDecl) {
-    let return_type = f.return_type.as_ref()
-        .map(IrType::from_mendes_type)
-        .unwrap_or(IrType::Void);
+    let return_type = function_return_type(f).unwrap_or(IrType::Void);
 
     let mut func = Function::new(&f.name, return_type, f.is_async);
+    func.visibility = f.visibility;
+    func.is_const = f.is_const;
 
     // Generic parameters
     for gp in &f.generic_params {
@@ -200,7 +590,8 @@ fn lower_function(ctx: &mut LoweringContext, f: &FnDecl) {
     }
 
     // Body
-    let mut lowerer = FunctionLowerer::new(ctx, &mut func);
+    let mut lowerer = FunctionLowerer::new(ctx, &mut func).with_return_type(f.return_type.as_ref());
+    lowerer.unpack_param_patterns(&f.params);
     for stmt in &f.body {
         lowerer.lower_stmt(stmt);
     }
@@ -231,6 +622,13 @@ fn lower_api(ctx: &mut LoweringContext, api: &ApiDecl) {
 
     let mut func = Function::new(&handler_name, return_type, api.is_async);
 
+    // Structs crossing the HTTP boundary need to (de)serialize as JSON
+    let mut api_structs = Vec::new();
+    for ty in api.body_type.iter().chain(&api.return_type) {
+        collect_struct_names(ty, &mut api_structs);
+    }
+    ctx.module.api_structs.extend(api_structs);
+
     // Implicit API parameters
     func.add_param("__request", IrType::Struct("Request".to_string()));
 
@@ -243,7 +641,7 @@ fn lower_api(ctx: &mut LoweringContext, api: &ApiDecl) {
     }
 
     // Handler body
-    let mut lowerer = FunctionLowerer::new(ctx, &mut func);
+    let mut lowerer = FunctionLowerer::new(ctx, &mut func).with_return_type(api.return_type.as_ref());
     for stmt in &api.handler {
         lowerer.lower_stmt(stmt);
     }
@@ -286,7 +684,13 @@ fn lower_websocket(ctx: &mut LoweringContext, ws: &WsDecl) {
         let handler_name = format!("__ws_message__{}", ws.path.replace('/', "_"));
         let mut func = Function::new(&handler_name, IrType::Void, true);
         func.add_param("conn", IrType::Struct("WsConnection".to_string()));
-        func.add_param("message", IrType::String);
+        // Received arrays are owned by the handler, not pointers
+        let message_type = match &ws.message_type {
+            Some(Type::Array(inner)) => IrType::Array(Box::new(IrType::from_mendes_type(inner)), 0),
+            Some(ty) => IrType::from_mendes_type(ty),
+            None => IrType::String,
+        };
+        func.add_param("message", message_type);
         extract_path_params(&ws.path, &mut func);
 
         let mut lowerer = FunctionLowerer::new(ctx, &mut func);
@@ -351,7 +755,8 @@ fn extract_path_params(path: &str, func: &mut Function) {
             if !param.is_empty() {
                 let ty = match ty_str.as_str() {
                     "int" => IrType::I64,
-                    "string" | "" => IrType::String,
+                    // UUIDs arrive as strings the router has already validated
+                    "string" | "uuid" | "" => IrType::String,
                     _ => IrType::String,
                 };
                 func.add_param(&param, ty);
@@ -360,6 +765,149 @@ fn extract_path_params(path: &str, func: &mut Function) {
     }
 }
 
+/// Finds the first value returned by a closure body, skipping nested
+/// closures and async blocks, whose `return`s are their own
+#[derive(Default)]
+struct FirstReturn {
+    value: Option<Expr>,
+}
+
+impl Visitor for FirstReturn {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let (Stmt::Return { value: Some(value), .. }, None) = (stmt, &self.value) {
+            self.value = Some(value.clone());
+        }
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if !matches!(expr, Expr::Closure { .. } | Expr::AsyncBlock { .. }) {
+            visit::walk_expr(self, expr);
+        }
+    }
+}
+
+/// Operands of the `?`s whose errors reach a `try` block's handler; nested
+/// `try` bodies, closures and async blocks catch their own
+#[derive(Default)]
+struct CaughtOperands {
+    operands: Vec<Expr>,
+}
+
+impl Visitor for CaughtOperands {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::TryCatch { handler, .. } => self.visit_block(handler),
+            _ => visit::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Closure { .. } | Expr::AsyncBlock { .. } => {}
+            Expr::Try { expr: operand, .. } => {
+                self.operands.push(operand.as_ref().clone());
+                visit::walk_expr(self, expr);
+            }
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
+
+/// Names an `async:` block refers to, each at its first use
+#[derive(Default)]
+struct NamesUsed {
+    uses: Vec<Expr>,
+}
+
+impl Visitor for NamesUsed {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Ident(name, _) = expr {
+            if !self.uses.iter().any(|used| matches!(used, Expr::Ident(n, _) if n == name)) {
+                self.uses.push(expr.clone());
+            }
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+/// Finds statements in a loop body that could change the length of `array`
+/// or the value of `index`, which would invalidate a bounds proof. Any
+/// binding of either name counts, since the later uses may see the new one
+struct IndexInvalidation<'a> {
+    array: &'a str,
+    index: &'a str,
+    found: bool,
+}
+
+impl IndexInvalidation<'_> {
+    fn rebinds(&mut self, name: &str) {
+        self.found |= name == self.array || name == self.index;
+    }
+}
+
+impl Visitor for IndexInvalidation<'_> {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { name, .. }
+            | Stmt::For { var: name, .. }
+            | Stmt::With { name, .. }
+            | Stmt::TryCatch { error_var: name, .. } => self.rebinds(name),
+            Stmt::LetTuple { names, .. } => {
+                for name in names {
+                    self.rebinds(name);
+                }
+            }
+            Stmt::IfLet { pattern, .. } | Stmt::WhileLet { pattern, .. } => self.visit_pattern(pattern),
+            _ => {}
+        }
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Ident { name, .. } => self.rebinds(name),
+            // `{ x }` binds the field's name
+            Pattern::Struct { fields, .. } | Pattern::Variant { data: VariantPatternData::Struct(fields), .. } => {
+                for (field, _) in fields.iter().filter(|(_, p)| p.is_none()) {
+                    self.rebinds(field);
+                }
+            }
+            _ => {}
+        }
+        visit::walk_pattern(self, pattern);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Closure { params, .. } => {
+                for param in params {
+                    self.rebinds(&param.name);
+                }
+            }
+            Expr::Binary { left, op: BinOp::Assign | BinOp::AddAssign | BinOp::SubAssign | BinOp::MulAssign | BinOp::DivAssign, .. } => {
+                if let Expr::Ident(name, _) = left.as_ref() {
+                    self.found |= name == self.array || name == self.index;
+                }
+            }
+            Expr::MethodCall { object, method, .. } if method != "len" => {
+                self.found |= matches!(object.as_ref(), Expr::Ident(name, _) if name == self.array);
+            }
+            Expr::Borrow { expr, mutable: true, .. } => {
+                self.found |= matches!(expr.as_ref(), Expr::Ident(name, _) if name == self.array);
+            }
+            _ => {}
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+fn body_may_invalidate_index(body: &[Stmt], array: &str, index: &str) -> bool {
+    let mut check = IndexInvalidation { array, index, found: false };
+    check.visit_block(body);
+    check.found
+}
+
 /// Converts a method to IR
 fn lower_method(ctx: &mut LoweringContext, struct_name: &str, m: &MethodDecl, struct_generic_params: &[mendes_parser::GenericParam]) {
     let return_type = m.return_type.as_ref()
@@ -369,6 +917,7 @@ fn lower_method(ctx: &mut LoweringContext, struct_name: &str, m: &MethodDecl, st
     // Method name is StructName::method_name
     let method_name = format!("{}::{}", struct_name, m.name);
     let mut func = Function::new(&method_name, return_type, m.is_async);
+    func.visibility = m.visibility;
 
     // Inherit generic parameters from struct
     for gp in struct_generic_params {
@@ -384,7 +933,8 @@ fn lower_method(ctx: &mut LoweringContext, struct_name: &str, m: &MethodDecl, st
     }
 
     // Body
-    let mut lowerer = FunctionLowerer::new(ctx, &mut func);
+    let mut lowerer = FunctionLowerer::new(ctx, &mut func).with_return_type(m.return_type.as_ref());
+    lowerer.unpack_param_patterns(&m.params);
     for stmt in &m.body {
         lowerer.lower_stmt(stmt);
     }
@@ -434,7 +984,8 @@ fn lower_impl_trait(ctx: &mut LoweringContext, impl_decl: &ImplTraitDecl) {
         }
 
         // Body
-        let mut lowerer = FunctionLowerer::new(ctx, &mut func);
+        let mut lowerer = FunctionLowerer::new(ctx, &mut func).with_return_type(method.return_type.as_ref());
+        lowerer.unpack_param_patterns(&method.params);
         for stmt in &method.body {
             lowerer.lower_stmt(stmt);
         }
@@ -477,6 +1028,23 @@ struct FunctionLowerer<'a, 'b> {
     func: &'b mut Function,
     /// Map of variables to values
     vars: HashMap<String, Value>,
+    /// Declared return type of the function being lowered
+    return_type: Option<Type>,
+    /// Type the next lowered expression is expected to have
+    expected: Option<Type>,
+    /// Lengths of the immutable array-literal locals in scope; a nested block
+    /// restores the outer lengths when it ends
+    fixed_arrays: HashMap<String, usize>,
+    /// `(array, index)` pairs known to be in bounds, from enclosing `for i in 0..array.len()` loops
+    bounded_indices: HashSet<(String, String)>,
+    /// `(catch label, error local, open resources)` of each enclosing `try`
+    /// block; the resources are the `with` blocks already open around it
+    catch_targets: Vec<(String, String, usize)>,
+    /// Locals bound by enclosing `with` blocks, innermost last
+    open_resources: Vec<String>,
+    /// Parameter and return types of the next lowered closure, when it is
+    /// passed to a method that fixes them
+    closure_hint: Option<(Vec<IrType>, IrType)>,
 }
 
 impl<'a, 'b> FunctionLowerer<'a, 'b> {
@@ -485,6 +1053,87 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
             ctx,
             func,
             vars: HashMap::new(),
+            return_type: None,
+            expected: None,
+            fixed_arrays: HashMap::new(),
+            bounded_indices: HashSet::new(),
+            catch_targets: Vec::new(),
+            open_resources: Vec::new(),
+            closure_hint: None,
+        }
+    }
+
+    /// Sets the declared return type, used as the expected type of returned values
+    fn with_return_type(mut self, return_type: Option<&Type>) -> Self {
+        self.return_type = return_type.cloned();
+        self
+    }
+
+    /// Unpacks each destructured parameter into locals for the variables
+    /// its pattern binds
+    fn unpack_param_patterns(&mut self, params: &[Param]) {
+        for param in params {
+            let Some(pattern) = &param.pattern else { continue };
+            // Methods take `self` first, so the position is looked up by name
+            let Some(index) = self.func.params.iter().position(|(name, _)| *name == param.name) else { continue };
+            let ty = IrType::from_mendes_type(&param.ty);
+            self.bind_pattern(pattern, Value::Param(index), ty);
+        }
+    }
+
+    /// Binds the variables of an irrefutable pattern to the parts of `value`
+    fn bind_pattern(&mut self, pattern: &Pattern, value: Value, ty: IrType) {
+        match pattern {
+            Pattern::Ident { name, .. } => {
+                self.fixed_arrays.remove(name);
+                self.func.add_local(name.clone(), ty.clone());
+                self.func.emit(Instruction::Alloca {
+                    dest: name.clone(),
+                    ty,
+                });
+                self.func.emit(Instruction::Store {
+                    value: value.clone(),
+                    ptr: Value::Local(name.clone()),
+                });
+                self.vars.insert(name.clone(), value);
+            }
+            Pattern::Tuple(patterns, _) => {
+                let types = match ty {
+                    IrType::Tuple(types) if types.len() == patterns.len() => types,
+                    _ => vec![IrType::I64; patterns.len()],
+                };
+                for (i, (pattern, ty)) in patterns.iter().zip(types).enumerate() {
+                    if matches!(pattern, Pattern::Wildcard(_)) {
+                        continue;
+                    }
+                    let dest = self.func.new_temp();
+                    self.func.emit(Instruction::Call {
+                        dest: Some(dest),
+                        func: "__tuple_field".to_string(),
+                        args: vec![value.clone(), Value::ConstInt(i as i64)],
+                    });
+                    self.bind_pattern(pattern, Value::Temp(dest), ty);
+                }
+            }
+            Pattern::Struct { name, fields, span } => {
+                for (field, pattern) in fields {
+                    let def = self.ctx.module.get_struct(name);
+                    let field_index = def.and_then(|s| s.field_index(field)).unwrap_or(0);
+                    let field_ty = def.and_then(|s| s.field_type(field)).cloned().unwrap_or(IrType::I64);
+                    let dest = self.func.new_temp();
+                    self.func.emit(Instruction::GetField {
+                        dest,
+                        ptr: value.clone(),
+                        struct_name: name.clone(),
+                        field_index,
+                        field_name: field.clone(),
+                    });
+                    // `{ x }` binds the field's name
+                    let shorthand = Pattern::Ident { name: field.clone(), mutable: false, span: *span };
+                    self.bind_pattern(pattern.as_ref().unwrap_or(&shorthand), Value::Temp(dest), field_ty);
+                }
+            }
+            _ => {}
         }
     }
 
@@ -493,10 +1142,60 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
         self.ctx.new_label(prefix)
     }
 
-    /// Infers the IR type from an expression (used for type inference in let statements)
+    /// Whether `name` refers to a module-level `static` rather than a local
+    /// or a `const`, which is used by name like any other value
+    fn is_static(&self, name: &str) -> bool {
+        !self.vars.contains_key(name)
+            && !self.func.params.iter().any(|(n, _)| n == name)
+            && self.ctx.module.get_global(name).is_some_and(|g| !g.is_const)
+    }
+
+    /// Return type of a closure: the declared one, or the type of the value
+    /// its body produces or first returns
+    fn closure_return_type(&self, return_type: Option<&Type>, body: &ClosureBody) -> IrType {
+        if let Some(ty) = return_type {
+            return IrType::from_mendes_type(ty);
+        }
+        match body {
+            ClosureBody::Expr(expr) => self.infer_expr_type(expr).unwrap_or(IrType::I64),
+            ClosureBody::Block(stmts) => {
+                let mut first = FirstReturn::default();
+                first.visit_block(stmts);
+                match first.value {
+                    Some(value) => self.infer_expr_type(&value).unwrap_or(IrType::I64),
+                    None => IrType::Void,
+                }
+            }
+        }
+    }
+
+    /// Output type of an `async:` block's future, from its final `return`
+    fn async_block_output(&self, body: &[Stmt]) -> IrType {
+        body.iter().rev()
+            .find_map(|stmt| match stmt {
+                Stmt::Return { value, .. } => Some(value.as_ref()
+                    .and_then(|v| self.infer_expr_type(v))
+                    .unwrap_or(IrType::I64)),
+                _ => None,
+            })
+            .unwrap_or(IrType::Void)
+    }
+
+    /// Infers the IR type from an expression (used for type inference in let
+    /// statements), falling back to the type the checker resolved
     fn infer_expr_type(&self, expr: &Expr) -> Option<IrType> {
+        self.infer_from_syntax(expr).or_else(|| self.checked_type(expr))
+    }
+
+    /// Type the checker resolved for `expr`, when lowering runs after checking
+    fn checked_type(&self, expr: &Expr) -> Option<IrType> {
+        self.ctx.expr_types.get(&expr.span()).map(IrType::from_mendes_type)
+    }
+
+    /// Infers the IR type from the form of an expression
+    fn infer_from_syntax(&self, expr: &Expr) -> Option<IrType> {
         match expr {
-            Expr::Closure { params, return_type, body: _, span: _ } => {
+            Expr::Closure { params, return_type, body, span: _ } => {
                 // Build function type from closure
                 let param_types: Vec<IrType> = params.iter()
                     .map(|p| p.ty.as_ref()
@@ -504,9 +1203,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                         .unwrap_or(IrType::I64))
                     .collect();
 
-                let ret_type = return_type.as_ref()
-                    .map(IrType::from_mendes_type)
-                    .unwrap_or(IrType::I64);
+                let ret_type = self.closure_return_type(return_type.as_ref(), body);
 
                 Some(IrType::Function {
                     params: param_types,
@@ -515,8 +1212,67 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
             }
             Expr::IntLit(_, _) => Some(IrType::I64),
             Expr::FloatLit(_, _) => Some(IrType::F64),
+            Expr::DurationLit(_, _) => Some(IrType::Struct("Duration".to_string())),
             Expr::BoolLit(_, _) => Some(IrType::Bool),
             Expr::StringLit(_, _) => Some(IrType::String),
+            Expr::AssocCall { type_name, func, .. } if func == "default" => {
+                Some(IrType::Struct(type_name.clone()))
+            }
+            Expr::StructLit { name, .. } => Some(IrType::Struct(name.clone())),
+            Expr::AsyncBlock { body, .. } => Some(IrType::Future(Box::new(self.async_block_output(body)))),
+            Expr::Await { expr, .. } => match self.infer_expr_type(expr)? {
+                IrType::Future(output) => Some(*output),
+                // Calls of `async fn`s are typed with their declared return type
+                ty => Some(ty),
+            },
+            Expr::OptionalFieldAccess { object, field, span } => match self.propagating_access(object, field, *span) {
+                Some(access) => self.infer_expr_type(&access),
+                None => self.optional_field(object, field).map(|(_, result)| result),
+            },
+            Expr::TupleIndex { object, index, .. } => match self.operand_type(object)? {
+                IrType::Tuple(types) => types.get(*index).cloned(),
+                _ => None,
+            },
+            Expr::Tuple { elements, .. } => Some(IrType::Tuple(
+                elements.iter()
+                    .map(|e| self.infer_expr_type(e).unwrap_or(IrType::I64))
+                    .collect()
+            )),
+            Expr::Call { func, .. } => match func.as_ref() {
+                Expr::Ident(name, _) => match self.func.locals.get(name) {
+                    // A local bound to a closure
+                    Some(IrType::Function { ret, .. }) => Some((**ret).clone()),
+                    _ => self.ctx.fn_returns.get(name).cloned(),
+                },
+                _ => None,
+            },
+            Expr::MethodCall { object, method, args, .. } => {
+                if let Some(element) = self.array_element(object) {
+                    return match method.as_str() {
+                        "fold" => args.first().and_then(|init| self.operand_type(init)),
+                        "reduce" | "min_by" | "max_by" => Some(option_of(&element)),
+                        _ => None,
+                    };
+                }
+                // Map<K, V>: `get`, `insert` and `remove` return Option<V>
+                let value = match self.operand_type(object)? {
+                    IrType::String => return match method.as_str() {
+                        "split" | "lines" => Some(IrType::Ptr(Box::new(IrType::String))),
+                        "to_upper" | "to_lower" | "trim" => Some(IrType::String),
+                        _ => None,
+                    },
+                    IrType::Range(element) => {
+                        return matches!(method.as_str(), "to_array" | "collect").then_some(IrType::Ptr(element));
+                    }
+                    IrType::Map(_, value) => *value,
+                    _ => return None,
+                };
+                match method.as_str() {
+                    "contains_key" => Some(IrType::Bool),
+                    "get" | "insert" | "remove" => Some(option_of(&value)),
+                    _ => None,
+                }
+            }
             Expr::ArrayLit(elements, _) => {
                 // Infer element type from first element
                 if let Some(first) = elements.first() {
@@ -526,6 +1282,41 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                     Some(IrType::Array(Box::new(IrType::I64), 0))
                 }
             }
+            Expr::MapLit { entries, .. } => {
+                let (key, value) = entries.first()?;
+                // An array literal entry is stored as a `[T]`, whatever its length
+                let entry = |ty: IrType| match ty {
+                    IrType::Array(element, _) => IrType::Ptr(element),
+                    ty => ty,
+                };
+                let key = entry(self.operand_type(key)?);
+                let value = entry(self.operand_type(value)?);
+                Some(IrType::Map(Box::new(key), Box::new(value)))
+            }
+            Expr::Cast { ty, .. } => Some(IrType::from_mendes_type(ty)),
+            Expr::Range { .. } => Some(IrType::Range(Box::new(IrType::I64))),
+            Expr::Index { object, .. } => match self.operand_type(object)? {
+                IrType::String => Some(IrType::String),
+                IrType::Array(elem, _) | IrType::Ptr(elem) => Some(*elem),
+                _ => None,
+            },
+            Expr::FieldAccess { object, field, .. } => {
+                let struct_name = match self.operand_type(object)? {
+                    IrType::Struct(name) => name,
+                    IrType::Ptr(inner) => match *inner {
+                        IrType::Struct(name) => name,
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                let def = self.ctx.module.get_struct(&struct_name)?;
+                def.fields.iter().find(|(name, _)| name == field).map(|(_, ty)| ty.clone())
+            }
+            Expr::ArrayRepeat { value, count, .. } => {
+                let elem_type = self.infer_expr_type(value).unwrap_or(IrType::I64);
+                let len = ConstEvaluator::new(&self.ctx.const_fns).with_consts(&self.ctx.const_values).eval_int(count).ok()?;
+                Some(IrType::Array(Box::new(elem_type), len as usize))
+            }
             _ => None, // Fall back to default type
         }
     }
@@ -533,7 +1324,16 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
     /// Converts statement
     fn lower_stmt(&mut self, stmt: &Stmt) {
         match stmt {
-            Stmt::Let { name, ty, value, mutable: _, span: _ } => {
+            Stmt::Let { name, ty, value, mutable, span: _ } => {
+                match value {
+                    Expr::ArrayLit(elements, _) if !mutable => {
+                        self.fixed_arrays.insert(name.clone(), elements.len());
+                    }
+                    _ => {
+                        self.fixed_arrays.remove(name);
+                    }
+                }
+
                 // Infer type from expression if no type annotation
                 let ir_ty = ty.as_ref()
                     .map(IrType::from_mendes_type)
@@ -544,11 +1344,13 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 self.func.add_local(name.clone(), ir_ty.clone());
                 self.func.emit(Instruction::Alloca {
                     dest: name.clone(),
-                    ty: ir_ty,
+                    ty: ir_ty.clone(),
                 });
 
                 // Evaluate value and store
+                self.expected = ty.clone();
                 let val = self.lower_expr(value);
+                let val = self.coerce_to_dyn(val, value, &ir_ty);
                 self.func.emit(Instruction::Store {
                     value: val.clone(),
                     ptr: Value::Local(name.clone()),
@@ -557,13 +1359,71 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 self.vars.insert(name.clone(), val);
             }
 
+            Stmt::LetTuple { names, value, mutable: _, span: _ } => {
+                for name in names {
+                    self.fixed_arrays.remove(name);
+                }
+
+                // A local or parameter holding the tuple has its declared type
+                let element_types = match self.operand_type(value) {
+                    Some(IrType::Tuple(types)) if types.len() == names.len() => types,
+                    _ => vec![IrType::I64; names.len()],
+                };
+
+                self.expected = None;
+                let tuple = self.lower_expr(value);
+
+                for (i, (name, ty)) in names.iter().zip(element_types).enumerate() {
+                    if name == "_" {
+                        continue;
+                    }
+                    let dest = self.func.new_temp();
+                    self.func.emit(Instruction::Call {
+                        dest: Some(dest),
+                        func: "__tuple_field".to_string(),
+                        args: vec![tuple.clone(), Value::ConstInt(i as i64)],
+                    });
+
+                    self.func.add_local(name.clone(), ty.clone());
+                    self.func.emit(Instruction::Alloca {
+                        dest: name.clone(),
+                        ty,
+                    });
+                    self.func.emit(Instruction::Store {
+                        value: Value::Temp(dest),
+                        ptr: Value::Local(name.clone()),
+                    });
+                    self.vars.insert(name.clone(), Value::Temp(dest));
+                }
+            }
+
             Stmt::Return { value, span: _ } => {
                 let val = value.as_ref()
-                    .map(|e| self.lower_expr(e))
+                    .map(|e| {
+                        self.expected = self.return_type.clone();
+                        let val = self.lower_expr(e);
+                        let ret = self.func.return_type.clone();
+                        self.coerce_to_dyn(val, e, &ret)
+                    })
                     .unwrap_or(Value::Void);
+                self.close_resources(0);
                 self.func.emit(Instruction::Return(val));
             }
 
+            Stmt::Yield { value, span: _ } => {
+                // The backend hands the value to the consumer of the generator
+                self.expected = match &self.return_type {
+                    Some(Type::Array(item)) => Some((**item).clone()),
+                    _ => None,
+                };
+                let val = self.lower_expr(value);
+                self.func.emit(Instruction::Call {
+                    dest: None,
+                    func: "__yield".to_string(),
+                    args: vec![val],
+                });
+            }
+
             Stmt::If { condition, then_block, else_block, span: _ } => {
                 let cond = self.lower_expr(condition);
 
@@ -579,9 +1439,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
 
                 // Then block
                 self.func.new_block(&then_label);
-                for s in then_block {
-                    self.lower_stmt(s);
-                }
+                self.lower_block(then_block);
                 if !self.func.current_block().is_terminated() {
                     self.func.emit(Instruction::Branch { target: end_label.clone() });
                 }
@@ -589,9 +1447,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 // Else block
                 if let Some(else_stmts) = else_block {
                     self.func.new_block(&else_label);
-                    for s in else_stmts {
-                        self.lower_stmt(s);
-                    }
+                    self.lower_block(else_stmts);
                     if !self.func.current_block().is_terminated() {
                         self.func.emit(Instruction::Branch { target: end_label.clone() });
                     }
@@ -601,6 +1457,41 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 self.func.new_block(&end_label);
             }
 
+            Stmt::IfLet { pattern, value, then_block, else_block, span: _ } => {
+                let scrutinee = self.lower_expr(value);
+                let matched = self.lower_pattern_check(pattern, &scrutinee);
+
+                let then_label = self.new_label("iflet_then");
+                let else_label = self.new_label("iflet_else");
+                let end_label = self.new_label("iflet_end");
+
+                self.func.emit(Instruction::CondBranch {
+                    cond: matched,
+                    then_label: then_label.clone(),
+                    else_label: if else_block.is_some() { else_label.clone() } else { end_label.clone() },
+                });
+
+                // Then block, with the pattern's bindings
+                self.func.new_block(&then_label);
+                let outer_arrays = self.fixed_arrays.clone();
+                self.bind_pattern_vars(pattern, &scrutinee);
+                self.lower_block(then_block);
+                self.fixed_arrays = outer_arrays;
+                if !self.func.current_block().is_terminated() {
+                    self.func.emit(Instruction::Branch { target: end_label.clone() });
+                }
+
+                if let Some(else_stmts) = else_block {
+                    self.func.new_block(&else_label);
+                    self.lower_block(else_stmts);
+                    if !self.func.current_block().is_terminated() {
+                        self.func.emit(Instruction::Branch { target: end_label.clone() });
+                    }
+                }
+
+                self.func.new_block(&end_label);
+            }
+
             Stmt::While { condition, body, span: _ } => {
                 let cond_label = self.new_label("while_cond");
                 let body_label = self.new_label("while_body");
@@ -619,9 +1510,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
 
                 // Body block
                 self.func.new_block(&body_label);
-                for s in body {
-                    self.lower_stmt(s);
-                }
+                self.lower_block(body);
                 if !self.func.current_block().is_terminated() {
                     self.func.emit(Instruction::Branch { target: cond_label.clone() });
                 }
@@ -630,7 +1519,109 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 self.func.new_block(&end_label);
             }
 
+            Stmt::WhileLet { pattern, value, body, span: _ } => {
+                let loop_id = self.func.new_temp();
+                let cond_label = format!("whilelet_cond_{}", loop_id);
+                let body_label = format!("whilelet_body_{}", loop_id);
+                let end_label = format!("whilelet_end_{}", loop_id);
+
+                self.func.emit(Instruction::Branch { target: cond_label.clone() });
+
+                // The value is evaluated and matched again on every iteration
+                self.func.new_block(&cond_label);
+                let scrutinee = self.lower_expr(value);
+                let matched = self.lower_pattern_check(pattern, &scrutinee);
+                self.func.emit(Instruction::CondBranch {
+                    cond: matched,
+                    then_label: body_label.clone(),
+                    else_label: end_label.clone(),
+                });
+
+                self.func.new_block(&body_label);
+                let outer_arrays = self.fixed_arrays.clone();
+                self.bind_pattern_vars(pattern, &scrutinee);
+                self.lower_block(body);
+                self.fixed_arrays = outer_arrays;
+                if !self.func.current_block().is_terminated() {
+                    self.func.emit(Instruction::Branch { target: cond_label.clone() });
+                }
+
+                self.func.new_block(&end_label);
+            }
+
+            Stmt::TryCatch { body, error_var, handler, span: _ } => {
+                // Desugars to a match on each `?` operand: `Err(e)` stores `e`
+                // in the catch binding and jumps to the handler
+                let catch_label = self.new_label("catch");
+                let end_label = self.new_label("try_catch_end");
+
+                // The catch binding has the error type of the `Result`s the body propagates
+                let mut caught = CaughtOperands::default();
+                caught.visit_block(body);
+                let error_type = caught.operands.iter()
+                    .find_map(|operand| match self.checked_type(operand).or_else(|| self.operand_type(operand)) {
+                        Some(IrType::Result(_, error)) => Some(*error),
+                        _ => None,
+                    })
+                    .unwrap_or(IrType::String);
+                self.func.add_local(error_var.clone(), error_type.clone());
+                self.func.emit(Instruction::Alloca {
+                    dest: error_var.clone(),
+                    ty: error_type,
+                });
+
+                self.fixed_arrays.remove(error_var);
+
+                self.catch_targets.push((catch_label.clone(), error_var.clone(), self.open_resources.len()));
+                self.lower_block(body);
+                self.catch_targets.pop();
+                if !self.func.current_block().is_terminated() {
+                    self.func.emit(Instruction::Branch { target: end_label.clone() });
+                }
+
+                // Handler block
+                self.func.new_block(&catch_label);
+                self.vars.insert(error_var.clone(), Value::Local(error_var.clone()));
+                self.lower_block(handler);
+                if !self.func.current_block().is_terminated() {
+                    self.func.emit(Instruction::Branch { target: end_label.clone() });
+                }
+
+                self.func.new_block(&end_label);
+            }
+
+            Stmt::With { resource, name, body, span: _ } => {
+                // The resource is closed and dropped on every exit from the
+                // block, including `return` and `?` inside the body
+                let ty = self.infer_expr_type(resource).unwrap_or(IrType::I64);
+                self.func.add_local(name.clone(), ty.clone());
+                self.func.emit(Instruction::Alloca {
+                    dest: name.clone(),
+                    ty,
+                });
+                let val = self.lower_expr(resource);
+                self.func.emit(Instruction::Store {
+                    value: val.clone(),
+                    ptr: Value::Local(name.clone()),
+                });
+                self.vars.insert(name.clone(), val);
+                self.fixed_arrays.remove(name);
+
+                self.open_resources.push(name.clone());
+                self.lower_block(body);
+                let depth = self.open_resources.len() - 1;
+                if !self.func.current_block().is_terminated() {
+                    self.close_resources(depth);
+                }
+                self.open_resources.pop();
+                self.vars.remove(name);
+            }
+
             Stmt::For { var, iter, body, span: _ } => {
+                // The loop variable and the body's bindings end with the loop
+                let outer_arrays = self.fixed_arrays.clone();
+                self.fixed_arrays.remove(var);
+
                 // Handle different iterator types
                 match iter {
                     // Range iteration: for i in 0..10 or for i in 0..=10
@@ -638,21 +1629,48 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                         self.lower_for_range(var, start, end, *inclusive, body);
                     }
                     // Array iteration: for item in array
-                    _ => {
-                        self.lower_for_iter(var, iter, body);
+                    _ => match self.iterator_item(iter) {
+                        // User type with `next() -> Option<T>`, or a generator
+                        Some((iter_ty, item_ty)) => {
+                            self.lower_for_next(var, iter, iter_ty, item_ty, body);
+                        }
+                        None => {
+                            self.lower_for_iter(var, iter, body);
+                        }
                     }
                 }
+                self.fixed_arrays = outer_arrays;
             }
 
             Stmt::Expr(expr) => {
                 self.lower_expr(expr);
             }
 
+            Stmt::Attributed { attrs, item, .. } if self.ctx.is_enabled(attrs) => {
+                self.lower_stmt(item);
+            }
+
             // Other statements are not relevant inside functions
             _ => {}
         }
     }
 
+    /// Closes and drops the `with` resources above `depth`, innermost first
+    fn close_resources(&mut self, depth: usize) {
+        for name in self.open_resources[depth..].iter().rev() {
+            self.func.emit(Instruction::Call {
+                dest: None,
+                func: "__method_close".to_string(),
+                args: vec![Value::Local(name.clone())],
+            });
+            self.func.emit(Instruction::Call {
+                dest: None,
+                func: "__drop".to_string(),
+                args: vec![Value::Local(name.clone())],
+            });
+        }
+    }
+
     /// Lower for loop over a range: for i in start..end or for i in start..=end
     fn lower_for_range(
         &mut self,
@@ -731,11 +1749,18 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
             else_label: end_label.clone(),
         });
 
-        // Body block
+        // Body block: `for i in 0..arr.len()` keeps `arr[i]` in bounds
         self.func.new_block(&body_label);
+        let outer_bounded = self.bounded_indices.clone();
+        if let Some(array) = Self::length_bounded_array(start, end, inclusive) {
+            if !body_may_invalidate_index(body, &array, var) {
+                self.bounded_indices.insert((array, var.to_string()));
+            }
+        }
         for s in body {
             self.lower_stmt(s);
         }
+        self.bounded_indices = outer_bounded;
         // Jump to increment
         self.func.emit(Instruction::Branch {
             target: inc_label.clone(),
@@ -769,6 +1794,293 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
         self.func.new_block(&end_label);
     }
 
+    /// The array whose length bounds a `0..array.len()` or `0..len(array)` range
+    fn length_bounded_array(start: &Option<Box<Expr>>, end: &Option<Box<Expr>>, inclusive: bool) -> Option<String> {
+        if inclusive || !matches!(start.as_deref(), None | Some(Expr::IntLit(0.., _))) {
+            return None;
+        }
+        match end.as_deref()? {
+            Expr::MethodCall { object, method, args, .. } if method == "len" && args.is_empty() => {
+                match object.as_ref() {
+                    Expr::Ident(array, _) => Some(array.clone()),
+                    _ => None,
+                }
+            }
+            Expr::Call { func, args, .. } if matches!(func.as_ref(), Expr::Ident(f, _) if f == "len") => {
+                match args.as_slice() {
+                    [Expr::Ident(array, _)] => Some(array.clone()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Lowers a nested block. Arrays bound inside it go out of scope at its
+    /// end, where the lengths known before it apply again.
+    fn lower_block(&mut self, stmts: &[Stmt]) {
+        let outer_arrays = self.fixed_arrays.clone();
+        for stmt in stmts {
+            self.lower_stmt(stmt);
+        }
+        self.fixed_arrays = outer_arrays;
+    }
+
+    /// Whether `object[index]` is proven to be within bounds
+    fn index_in_bounds(&self, object: &Expr, index: &Expr) -> bool {
+        let Expr::Ident(array, _) = object else {
+            return false;
+        };
+        match index {
+            Expr::IntLit(i, _) => self.fixed_arrays.get(array).is_some_and(|len| (0..*len as i64).contains(i)),
+            Expr::Ident(var, _) => self.bounded_indices.contains(&(array.clone(), var.clone())),
+            // `arr[-k]` is in bounds when `k <= len`
+            _ => match (index.negative_constant(), self.fixed_arrays.get(array)) {
+                (Some(offset), Some(len)) => -offset <= *len as i64,
+                _ => false,
+            },
+        }
+    }
+
+    /// Indexes or slices a string by character through the runtime, which
+    /// walks it rather than indexing raw bytes
+    fn lower_string_index(&mut self, object: &Expr, index: &Expr) -> Value {
+        let string = self.lower_expr(object);
+        let (func, args) = match index {
+            Expr::Range { start, end, inclusive, .. } => {
+                let start = match start {
+                    Some(start) => self.lower_expr(start),
+                    None => Value::ConstInt(0),
+                };
+                let mut args = vec![string, start];
+                if let Some(end) = end {
+                    let end = self.lower_expr(end);
+                    args.push(if let (true, Value::ConstInt(n)) = (*inclusive, &end) {
+                        Value::ConstInt(n + 1)
+                    } else if *inclusive {
+                        let past_end = self.func.new_temp();
+                        self.func.emit(Instruction::Binary {
+                            dest: past_end,
+                            op: BinaryOp::Add,
+                            left: end,
+                            right: Value::ConstInt(1),
+                        });
+                        Value::Temp(past_end)
+                    } else {
+                        end
+                    });
+                }
+                ("__str_slice", args)
+            }
+            _ => {
+                // `s[-k]` counts from the end in the runtime itself
+                let index = match index.negative_constant() {
+                    Some(offset) => Value::ConstInt(offset),
+                    None => self.lower_expr(index),
+                };
+                ("__str_char_at", vec![string, index])
+            }
+        };
+
+        let dest = self.func.new_temp();
+        self.func.emit(Instruction::Call {
+            dest: Some(dest),
+            func: func.to_string(),
+            args,
+        });
+        Value::Temp(dest)
+    }
+
+    /// The type of a local or parameter, or the inferred type of any other expression
+    fn operand_type(&self, expr: &Expr) -> Option<IrType> {
+        match expr {
+            Expr::Ident(name, _) => self.func.locals.get(name).cloned()
+                .or_else(|| self.func.params.iter().find(|(p, _)| p == name).map(|(_, ty)| ty.clone()))
+                .or_else(|| self.ctx.module.get_global(name).map(|g| g.ty.clone())),
+            // Both operands of arithmetic share the result's type
+            Expr::Binary { left, op: BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod, right, .. } => {
+                self.operand_type(left).or_else(|| self.operand_type(right))
+            }
+            _ => self.infer_expr_type(expr),
+        }
+    }
+
+    /// Whether a call of `name` reaches the builtin: no function, import,
+    /// local or parameter of the same name hides it
+    fn is_builtin(&self, name: &str) -> bool {
+        !self.ctx.fn_returns.contains_key(name)
+            && !self.vars.contains_key(name)
+            && !self.func.params.iter().any(|(param, _)| param == name)
+    }
+
+    /// The element type when `expr` is an array: a local array, or a `[T]`
+    /// parameter, which lowers to a pointer to its elements
+    fn array_element(&self, expr: &Expr) -> Option<IrType> {
+        if let Some(Type::Array(element)) = self.ctx.expr_types.get(&expr.span()) {
+            return Some(IrType::from_mendes_type(element));
+        }
+        match self.operand_type(expr)? {
+            IrType::Array(element, _) => Some(*element),
+            // Without the checker's types a `ref Point` is told apart by its pointee
+            IrType::Ptr(element) if !matches!(*element, IrType::Struct(_)) => Some(*element),
+            _ => None,
+        }
+    }
+
+    /// Boxes a concrete value stored where a `dyn Trait` is expected: a
+    /// local, a parameter or the return value
+    fn coerce_to_dyn(&mut self, value: Value, expr: &Expr, target: &IrType) -> Value {
+        let is_dyn = |ty: &IrType| matches!(ty, IrType::Struct(name) if name.starts_with("dyn "));
+        if !is_dyn(target) || self.operand_type(expr).is_some_and(|ty| is_dyn(&ty)) {
+            return value;
+        }
+        let boxed = self.func.new_temp();
+        self.func.emit(Instruction::Call {
+            dest: Some(boxed),
+            func: "__box".to_string(),
+            args: vec![value],
+        });
+        Value::Temp(boxed)
+    }
+
+    /// `load()?.count` on a `Result` is `(load()?).count`
+    fn propagating_access(&self, object: &Expr, field: &str, span: Span) -> Option<Expr> {
+        let object_type = self.checked_type(object).or_else(|| self.operand_type(object));
+        matches!(object_type, Some(IrType::Result(..))).then(|| Expr::FieldAccess {
+            object: Box::new(Expr::Try { expr: Box::new(object.clone()), span: object.span() }),
+            field: field.to_string(),
+            span,
+        })
+    }
+
+    /// The type of `field` in the struct held by the option `object`, and
+    /// the type of `object?.field`
+    fn optional_field(&self, object: &Expr, field: &str) -> Option<(IrType, IrType)> {
+        let struct_name = match self.operand_type(object)? {
+            IrType::Option(inner) => match *inner {
+                IrType::Struct(name) => name,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let def = self.ctx.module.get_struct(&struct_name)?;
+        let field_type = def.fields.iter().find(|(name, _)| name == field)?.1.clone();
+        let result = match field_type {
+            IrType::Option(_) => field_type.clone(),
+            _ => option_of(&field_type),
+        };
+        Some((field_type, result))
+    }
+
+    /// Lowers `fold`, `reduce`, `min_by` and `max_by` on an array to a call
+    /// of `__array_<method>` taking the array, the initial value of `fold`
+    /// and the closure
+    fn lower_array_reduction(&mut self, object: &Expr, method: &str, args: &[Expr], element: IrType) -> Value {
+        let mut values = vec![self.lower_expr(object)];
+        let (params, ret) = match (method, args.first()) {
+            ("fold", Some(init)) => {
+                let acc = self.operand_type(init).unwrap_or(IrType::I64);
+                values.push(self.lower_expr(init));
+                (vec![acc.clone(), element], acc)
+            }
+            ("reduce", _) => (vec![element.clone(), element.clone()], element),
+            _ => (vec![element.clone(), element], IrType::I64),
+        };
+        if let Some(closure) = args.last() {
+            if matches!(closure, Expr::Closure { .. }) {
+                self.closure_hint = Some((params, ret));
+            }
+            values.push(self.lower_expr(closure));
+        }
+
+        let dest = self.func.new_temp();
+        self.func.emit(Instruction::Call {
+            dest: Some(dest),
+            func: format!("__array_{}", method),
+            args: values,
+        });
+        Value::Temp(dest)
+    }
+
+    /// The iterator and element types when `iter` is a generator or a user
+    /// type with `next()`
+    fn iterator_item(&self, iter: &Expr) -> Option<(IrType, IrType)> {
+        let ty = self.operand_type(iter);
+        let type_name = match ty? {
+            IrType::Generator(item) => return Some((IrType::Generator(item.clone()), *item)),
+            IrType::Struct(name) => name,
+            IrType::Ptr(inner) => match *inner {
+                IrType::Struct(name) => name,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let item = self.ctx.iterators.get(&type_name)?.clone();
+        Some((IrType::Struct(type_name), item))
+    }
+
+    /// Lower for loop over a user iterator: calls `next()` until it returns `None`
+    fn lower_for_next(&mut self, var: &str, iter: &Expr, iter_ty: IrType, item_ty: IrType, body: &[Stmt]) {
+        let loop_id = self.func.new_temp();
+        let cond_label = format!("iter_cond_{}", loop_id);
+        let body_label = format!("iter_body_{}", loop_id);
+        let end_label = format!("iter_end_{}", loop_id);
+
+        // `next()` mutates the iterator, so it gets a local of its own
+        let iter_val = self.lower_expr(iter);
+        let iter_var = format!("__iter_{}", loop_id);
+        self.func.add_local(iter_var.clone(), iter_ty.clone());
+        self.func.emit(Instruction::Alloca {
+            dest: iter_var.clone(),
+            ty: iter_ty,
+        });
+        self.func.emit(Instruction::Store {
+            value: iter_val,
+            ptr: Value::Local(iter_var.clone()),
+        });
+
+        // Allocate loop variable
+        self.func.add_local(var.to_string(), item_ty.clone());
+        self.func.emit(Instruction::Alloca {
+            dest: var.to_string(),
+            ty: item_ty,
+        });
+        self.vars.insert(var.to_string(), Value::Local(var.to_string()));
+
+        self.func.emit(Instruction::Branch {
+            target: cond_label.clone(),
+        });
+
+        // Condition block: continue while `next()` yields a value
+        self.func.new_block(&cond_label);
+        let next = self.func.new_temp();
+        self.func.emit(Instruction::Call {
+            dest: Some(next),
+            func: "__iter_next".to_string(),
+            args: vec![Value::Local(iter_var)],
+        });
+        self.func.emit(Instruction::CondBranch {
+            cond: Value::Temp(next),
+            then_label: body_label.clone(),
+            else_label: end_label.clone(),
+        });
+
+        // Body block: bind the yielded value
+        self.func.new_block(&body_label);
+        self.func.emit(Instruction::Store {
+            value: Value::Temp(next),
+            ptr: Value::Local(var.to_string()),
+        });
+        for s in body {
+            self.lower_stmt(s);
+        }
+        self.func.emit(Instruction::Branch {
+            target: cond_label,
+        });
+
+        self.func.new_block(&end_label);
+    }
+
     /// Lower for loop over an iterable (array, etc.)
     fn lower_for_iter(&mut self, var: &str, iter: &Expr, body: &[Stmt]) {
         let loop_id = self.func.new_temp();
@@ -778,6 +2090,10 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
         let end_label = format!("for_end_{}", loop_id);
 
         // Evaluate the iterator
+        let elem_ty = match self.operand_type(iter) {
+            Some(IrType::Ptr(elem) | IrType::Array(elem, _)) => *elem,
+            _ => IrType::I64,
+        };
         let iter_val = self.lower_expr(iter);
 
         // Allocate index variable (__idx)
@@ -793,10 +2109,10 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
         });
 
         // Allocate loop variable
-        self.func.add_local(var.to_string(), IrType::I64); // Simplified to i64
+        self.func.add_local(var.to_string(), elem_ty.clone());
         self.func.emit(Instruction::Alloca {
             dest: var.to_string(),
-            ty: IrType::I64,
+            ty: elem_ty,
         });
         self.vars.insert(var.to_string(), Value::Local(var.to_string()));
 
@@ -843,6 +2159,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
             dest: elem_temp,
             ptr: iter_val,
             index: Value::Temp(current_idx),
+            in_bounds: false,
         });
         self.func.emit(Instruction::Store {
             value: Value::Temp(elem_temp),
@@ -888,11 +2205,23 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
 
     /// Converts expression and returns the resulting Value
     fn lower_expr(&mut self, expr: &Expr) -> Value {
+        // The expected type only applies to the outermost expression
+        let expected = self.expected.take();
         match expr {
             Expr::IntLit(v, _) => Value::ConstInt(*v),
 
             Expr::FloatLit(v, _) => Value::const_float(*v),
 
+            Expr::DurationLit(ms, _) => {
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Call {
+                    dest: Some(dest),
+                    func: "__duration_ms".to_string(),
+                    args: vec![Value::const_int(*ms as i64)],
+                });
+                Value::Temp(dest)
+            }
+
             Expr::BoolLit(v, _) => Value::ConstBool(*v),
 
             Expr::StringLit(s, _) => {
@@ -900,21 +2229,50 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 Value::ConstString(idx)
             }
 
-            Expr::None(_) => Value::Void,
+            Expr::None(_) => {
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Call {
+                    dest: Some(dest),
+                    func: "__option_none".to_string(),
+                    args: vec![],
+                });
+                Value::Temp(dest)
+            }
 
-            Expr::Ident(name, _) => {
+            Expr::Ident(name, span) => {
                 // Check if it's a local variable
                 if self.vars.contains_key(name) {
                     let temp = self.func.new_temp();
                     self.func.emit(Instruction::Load {
                         dest: temp,
                         ptr: Value::Local(name.clone()),
-                        ty: IrType::I64, // Simplificado
+                        ty: self.func.locals.get(name).cloned().unwrap_or(IrType::I64),
                     });
                     Value::Temp(temp)
                 } else if self.func.params.iter().any(|(n, _)| n == name) {
                     // Parameters are accessed by name (like local variables)
                     Value::Local(name.clone())
+                } else if self.is_static(name) {
+                    // Statics are read through the cell that synchronizes them
+                    let dest = self.func.new_temp();
+                    self.func.emit(Instruction::Call {
+                        dest: Some(dest),
+                        func: "__static_load".to_string(),
+                        args: vec![Value::Global(name.clone())],
+                    });
+                    Value::Temp(dest)
+                } else if let Some(def) = self.variant_enum(name, expected.as_ref(), *span) {
+                    // Unit enum variant
+                    Value::Global(format!("{}::{}", def.name, name))
+                } else if let Some(func) = self.ctx.std_imports.get(name) {
+                    // Constant from a `std` module
+                    let dest = self.func.new_temp();
+                    self.func.emit(Instruction::Call {
+                        dest: Some(dest),
+                        func: func.clone(),
+                        args: vec![],
+                    });
+                    Value::Temp(dest)
                 } else {
                     // Global or unknown
                     Value::Global(name.clone())
@@ -922,6 +2280,31 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
             }
 
             Expr::Binary { left, op, right, span: _ } => {
+                // `"a" + "b"` is joined at compile time
+                if let Some(joined) = concat_string_literals(expr) {
+                    return Value::ConstString(self.ctx.module.add_string(joined));
+                }
+
+                // Writes to a static become a single atomic or locked update
+                if let Expr::Ident(name, _) = left.as_ref() {
+                    if (*op == BinOp::Assign || op.compound_base().is_some()) && self.is_static(name) {
+                        let value = self.lower_expr(right);
+                        let func = match op.compound_base() {
+                            Some(BinOp::Add) => "__static_add",
+                            Some(BinOp::Sub) => "__static_sub",
+                            Some(BinOp::Mul) => "__static_mul",
+                            Some(BinOp::Div) => "__static_div",
+                            _ => "__static_store",
+                        };
+                        self.func.emit(Instruction::Call {
+                            dest: None,
+                            func: func.to_string(),
+                            args: vec![Value::Global(name.clone()), value],
+                        });
+                        return Value::Void;
+                    }
+                }
+
                 let left_val = self.lower_expr(left);
                 let right_val = self.lower_expr(right);
                 let dest = self.func.new_temp();
@@ -938,12 +2321,35 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 };
 
                 if let Some(binary_op) = ir_op {
-                    self.func.emit(Instruction::Binary {
-                        dest,
-                        op: binary_op,
-                        left: left_val,
-                        right: right_val,
-                    });
+                    let is_int = |operand: &Expr| {
+                        self.checked_type(operand).or_else(|| self.operand_type(operand)) == Some(IrType::I64)
+                    };
+                    let checked = self.ctx.checked_arithmetic
+                        && matches!(binary_op, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul)
+                        && (is_int(left) || is_int(right));
+                    if checked {
+                        self.func.emit(Instruction::CheckedBinary {
+                            dest,
+                            op: binary_op,
+                            left: left_val,
+                            right: right_val,
+                        });
+                    } else {
+                        self.func.emit(Instruction::Binary {
+                            dest,
+                            op: binary_op,
+                            left: left_val,
+                            right: right_val,
+                        });
+                    }
+                    // `x += v` stores the result back into `x`
+                    if let (Some(_), Expr::Ident(name, _)) = (op.compound_base(), left.as_ref()) {
+                        self.func.emit(Instruction::Store {
+                            value: Value::Temp(dest),
+                            ptr: Value::Local(name.clone()),
+                        });
+                        return Value::Void;
+                    }
                     return Value::Temp(dest);
                 }
 
@@ -971,6 +2377,8 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 // Assignment
                 if matches!(op, BinOp::Assign) {
                     if let Expr::Ident(name, _) = left.as_ref() {
+                        let target = self.operand_type(left).unwrap_or(IrType::I64);
+                        let right_val = self.coerce_to_dyn(right_val, right, &target);
                         self.func.emit(Instruction::Store {
                             value: right_val,
                             ptr: Value::Local(name.clone()),
@@ -998,9 +2406,13 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 Value::Temp(dest)
             }
 
-            Expr::Call { func: callee, args, span: _ } => {
+            Expr::Call { func: callee, args, span } => {
                 let func_name = match callee.as_ref() {
-                    Expr::Ident(name, _) => name.clone(),
+                    Expr::Ident(name, _) => match self.variant_enum(name, expected.as_ref(), *span) {
+                        // Tuple enum variant constructor
+                        Some(def) => format!("{}::{}", def.name, name),
+                        None => self.ctx.std_imports.get(name).cloned().unwrap_or_else(|| name.clone()),
+                    },
                     Expr::FieldAccess { object, field, .. } => {
                         // db.main.query -> __db_main_query
                         let obj_name = match object.as_ref() {
@@ -1013,7 +2425,29 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                     _ => "unknown".to_string(),
                 };
 
-                let arg_values: Vec<_> = args.iter().map(|a| self.lower_expr(a)).collect();
+                // The builtin `parse` picks its target from the expected `Result<T, _>`
+                let func_name = match (func_name.as_str(), expected) {
+                    ("parse", Some(Type::Generic { name, args: type_args })) if name == "Result" && self.is_builtin("parse") => {
+                        match type_args.first() {
+                            Some(Type::Int) => "__parse_int".to_string(),
+                            Some(Type::Float) => "__parse_float".to_string(),
+                            Some(Type::Bool) => "__parse_bool".to_string(),
+                            _ => func_name,
+                        }
+                    }
+                    _ => func_name,
+                };
+
+                let params = self.ctx.fn_params.get(&func_name).cloned().unwrap_or_default();
+                let arg_values: Vec<_> = args.iter().enumerate()
+                    .map(|(i, a)| {
+                        let val = self.lower_expr(a);
+                        match params.get(i) {
+                            Some(param) => self.coerce_to_dyn(val, a, param),
+                            None => val,
+                        }
+                    })
+                    .collect();
 
                 let dest = self.func.new_temp();
                 self.func.emit(Instruction::Call {
@@ -1025,6 +2459,22 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 Value::Temp(dest)
             }
 
+            // Unit enum variant: Color::Red
+            Expr::Path { type_name, name, .. } => Value::Global(format!("{}::{}", type_name, name)),
+
+            Expr::AssocCall { type_name, func, args, span: _ } => {
+                let arg_values: Vec<_> = args.iter().map(|a| self.lower_expr(a)).collect();
+
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Call {
+                    dest: Some(dest),
+                    func: format!("{}::{}", type_name, func),
+                    args: arg_values,
+                });
+
+                Value::Temp(dest)
+            }
+
             Expr::Await { expr, span: _ } => {
                 let future = self.lower_expr(expr);
                 let dest = self.func.new_temp();
@@ -1034,6 +2484,17 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 Value::Temp(dest)
             }
 
+            Expr::TupleIndex { object, index, span: _ } => {
+                let tuple = self.lower_expr(object);
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Call {
+                    dest: Some(dest),
+                    func: "__tuple_field".to_string(),
+                    args: vec![tuple, Value::ConstInt(*index as i64)],
+                });
+                Value::Temp(dest)
+            }
+
             Expr::FieldAccess { object, field, span: _ } => {
                 let obj_val = self.lower_expr(object);
                 let dest = self.func.new_temp();
@@ -1071,15 +2532,60 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 Value::Temp(dest)
             }
 
+            Expr::OptionalFieldAccess { object, field, span } => {
+                if let Some(access) = self.propagating_access(object, field, *span) {
+                    return self.lower_expr(&access);
+                }
+                // `__optional_field_<field>(option, flatten)`: `flatten` is set
+                // when the field is itself an option, so it is not wrapped again
+                let flatten = self.optional_field(object, field)
+                    .is_some_and(|(field_type, _)| matches!(field_type, IrType::Option(_)));
+                let obj_val = self.lower_expr(object);
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Call {
+                    dest: Some(dest),
+                    func: format!("__optional_field_{}", field),
+                    args: vec![obj_val, Value::ConstBool(flatten)],
+                });
+                Value::Temp(dest)
+            }
+
+            Expr::Index { object, index, span: _ }
+                if self.checked_type(object).or_else(|| self.operand_type(object)) == Some(IrType::String) => {
+                self.lower_string_index(object, index)
+            }
+
             Expr::Index { object, index, span: _ } => {
+                let in_bounds = self.index_in_bounds(object, index);
                 let obj_val = self.lower_expr(object);
-                let idx_val = self.lower_expr(index);
+                let idx_val = match index.negative_constant() {
+                    // `arr[-k]` reads `arr[arr.len() - k]`; reaching past the
+                    // start fails the runtime bounds check
+                    Some(offset) => {
+                        let len = self.func.new_temp();
+                        self.func.emit(Instruction::Call {
+                            dest: Some(len),
+                            func: "len".to_string(),
+                            args: vec![obj_val.clone()],
+                        });
+                        let from_end = self.func.new_temp();
+                        self.func.emit(Instruction::Binary {
+                            dest: from_end,
+                            op: BinaryOp::Sub,
+                            left: Value::Temp(len),
+                            right: Value::ConstInt(-offset),
+                        });
+                        Value::Temp(from_end)
+                    }
+                    None => self.lower_expr(index),
+                };
                 let dest = self.func.new_temp();
 
                 self.func.emit(Instruction::GetElement {
                     dest,
                     ptr: obj_val,
                     index: idx_val,
+                    in_bounds,
                 });
 
                 Value::Temp(dest)
@@ -1103,7 +2609,12 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 // Initialize fields
                 if let Some(struct_def) = self.ctx.module.get_struct(name) {
                     let struct_def = struct_def.clone(); // Clone to avoid borrow conflict
-                    for (field_name, field_expr) in fields {
+                    let omitted: Vec<_> = self.ctx.field_defaults.get(name).into_iter()
+                        .flatten()
+                        .filter(|(field_name, _)| !fields.iter().any(|(n, _)| n == field_name))
+                        .cloned()
+                        .collect();
+                    for (field_name, field_expr) in fields.iter().chain(&omitted) {
                         let val = self.lower_expr(field_expr);
                         if let Some(idx) = struct_def.field_index(field_name) {
                             self.func.emit(Instruction::SetField {
@@ -1120,17 +2631,73 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 Value::Temp(dest)
             }
 
+            Expr::Cast { expr, ty, span: _ } => {
+                let value = self.lower_expr(expr);
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Cast {
+                    dest,
+                    value,
+                    to_type: IrType::from_mendes_type(ty),
+                });
+                Value::Temp(dest)
+            }
+
+            Expr::ArrayRepeat { value, count, .. } => {
+                // The checker already rejected lengths that do not fold
+                let count = match ConstEvaluator::new(&self.ctx.const_fns).with_consts(&self.ctx.const_values).eval_int(count) {
+                    Ok(n) => Value::ConstInt(n),
+                    Err(_) => self.lower_expr(count),
+                };
+                let value = self.lower_expr(value);
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Call {
+                    dest: Some(dest),
+                    func: "__array_repeat".to_string(),
+                    args: vec![value, count],
+                });
+                Value::Temp(dest)
+            }
+
+            Expr::MapLit { entries, span: _ } => {
+                let args = entries.iter()
+                    .flat_map(|(key, value)| [key, value])
+                    .map(|e| self.lower_expr(e))
+                    .collect();
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Call {
+                    dest: Some(dest),
+                    func: "__map_lit".to_string(),
+                    args,
+                });
+                Value::Temp(dest)
+            }
+
             Expr::ArrayLit(elements, _) => {
+                // Elements of a `[dyn Trait]` are boxed trait objects
+                let dyn_elem = match &expected {
+                    Some(Type::Array(elem)) if matches!(**elem, Type::Dyn(_)) => Some(IrType::from_mendes_type(elem)),
+                    _ => None,
+                };
+
                 let dest = self.func.new_temp();
                 self.func.emit(Instruction::NewArray {
                     dest,
-                    elem_type: IrType::I64, // Simplificado
+                    elem_type: dyn_elem.clone().unwrap_or(IrType::I64), // Simplificado
                     size: Value::ConstInt(elements.len() as i64),
                 });
 
                 // Initialize elements
                 for (i, elem) in elements.iter().enumerate() {
-                    let val = self.lower_expr(elem);
+                    let mut val = self.lower_expr(elem);
+                    if dyn_elem.is_some() {
+                        let boxed = self.func.new_temp();
+                        self.func.emit(Instruction::Call {
+                            dest: Some(boxed),
+                            func: "__box".to_string(),
+                            args: vec![val],
+                        });
+                        val = Value::Temp(boxed);
+                    }
                     self.func.emit(Instruction::SetElement {
                         ptr: Value::Temp(dest),
                         index: Value::ConstInt(i as i64),
@@ -1177,13 +2744,98 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 Value::Temp(dest)
             }
 
+            Expr::NamedArg { value, .. } => self.lower_expr(value),
+            // Rejected by the checker: no parameter is variadic
+            Expr::Spread { expr, .. } => self.lower_expr(expr),
+
             Expr::MethodCall { object, method, args, span: _ } => {
+                // "Hello, {name}!".format(name: value) splices the values
+                // into the template like an interpolated string
+                if let (Expr::StringLit(text, _), "format") = (object.as_ref(), method.as_str()) {
+                    if let Ok(pieces) = parse_format_template(text) {
+                        let mut values = HashMap::new();
+                        for arg in args {
+                            if let Expr::NamedArg { name, value, .. } = arg {
+                                let val = self.lower_expr(value);
+                                values.insert(name.as_str(), val);
+                            }
+                        }
+
+                        let parts = pieces.iter()
+                            .map(|piece| match piece {
+                                FormatPiece::Literal(s) => FormatPart::Literal(s.clone()),
+                                FormatPiece::Placeholder(name) => {
+                                    FormatPart::Value(values.get(name.as_str()).cloned().unwrap_or(Value::Void))
+                                }
+                            })
+                            .collect();
+
+                        let dest = self.func.new_temp();
+                        self.func.emit(Instruction::FormatString { dest, parts });
+                        return Value::Temp(dest);
+                    }
+                }
+
+                // Materialize a range literal: (start..end).to_array()
+                if let Expr::Range { start, end, inclusive, .. } = object.as_ref() {
+                    if matches!(method.as_str(), "to_array" | "collect") {
+                        let start_val = start.as_ref()
+                            .map(|e| self.lower_expr(e))
+                            .unwrap_or(Value::ConstInt(0));
+                        let end_val = end.as_ref()
+                            .map(|e| self.lower_expr(e))
+                            .unwrap_or(Value::ConstInt(i64::MAX));
+
+                        let dest = self.func.new_temp();
+                        self.func.emit(Instruction::Call {
+                            dest: Some(dest),
+                            func: "__range_to_array".to_string(),
+                            args: vec![start_val, end_val, Value::ConstBool(*inclusive)],
+                        });
+                        return Value::Temp(dest);
+                    }
+                }
+
+                // Materialize a range held in a local: r.to_array()
+                if matches!(method.as_str(), "to_array" | "collect")
+                    && matches!(self.operand_type(object), Some(IrType::Range(_))) {
+                    let range = self.lower_expr(object);
+                    let dest = self.func.new_temp();
+                    self.func.emit(Instruction::Call {
+                        dest: Some(dest),
+                        func: "__range_collect".to_string(),
+                        args: vec![range],
+                    });
+                    return Value::Temp(dest);
+                }
+
+                // Array reductions: the closure is typed from the elements
+                // and, for `fold`, the initial value
+                if matches!(method.as_str(), "fold" | "reduce" | "min_by" | "max_by") {
+                    if let Some(element) = self.array_element(object) {
+                        return self.lower_array_reduction(object, method, args, element);
+                    }
+                }
+
                 // Lower the object (receiver)
                 let obj_val = self.lower_expr(object);
 
                 // Lower arguments
                 let mut arg_values: Vec<_> = args.iter().map(|a| self.lower_expr(a)).collect();
 
+                // Map methods wrap Rust's `BTreeMap` results in Mendes types
+                let is_map = matches!(self.operand_type(object), Some(IrType::Map(..)));
+                if is_map && matches!(method.as_str(), "get" | "contains_key" | "insert" | "remove" | "keys" | "values") {
+                    arg_values.insert(0, obj_val);
+                    let dest = self.func.new_temp();
+                    self.func.emit(Instruction::Call {
+                        dest: Some(dest),
+                        func: format!("__map_{}", method),
+                        args: arg_values,
+                    });
+                    return Value::Temp(dest);
+                }
+
                 // Get struct name for method resolution
                 // For now, we generate a generic method call
                 // In a full implementation, we'd resolve the struct type
@@ -1209,6 +2861,13 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
             }
 
             Expr::Match { expr, arms, span: _ } => {
+                // Bare variant names in the patterns belong to the matched
+                // enum, as resolved by the checker when lowering after it
+                let scrutinee_enum = match self.checked_type(expr).or_else(|| self.operand_type(expr)) {
+                    Some(IrType::Struct(name)) if self.ctx.module.enums.contains_key(&name) => Some(name),
+                    _ => None,
+                };
+
                 // Lower the scrutinee (expression being matched)
                 let scrutinee = self.lower_expr(expr);
 
@@ -1223,43 +2882,63 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 for i in 0..arms.len() {
                     arm_labels.push(self.new_label(&format!("match_arm{}", i)));
                 }
-                arm_labels.push(end_label.clone()); // Fallback to end if no match
+
+                // Every arm after the first tests its pattern in its own block,
+                // so a failed pattern or guard falls through to the next test
+                let mut check_labels: Vec<String> = Vec::new();
+                for i in 1..arms.len() {
+                    check_labels.push(self.new_label(&format!("match_check{}", i)));
+                }
+                check_labels.push(end_label.clone()); // Fallback to end if no match
 
                 // For each arm, generate comparison and branch
                 for (i, arm) in arms.iter().enumerate() {
                     let arm_label = arm_labels[i].clone();
-                    let next_label = arm_labels.get(i + 1).cloned().unwrap_or_else(|| end_label.clone());
+                    let next_label = check_labels[i].clone();
+                    if i > 0 {
+                        self.func.new_block(&check_labels[i - 1]);
+                    }
+
+                    // Pattern bindings and the arm's own bindings end with the arm
+                    let outer_arrays = self.fixed_arrays.clone();
 
                     // Generate pattern match check
-                    let matched = self.lower_pattern_check(&arm.pattern, &scrutinee);
+                    let pattern = self.resolve_variant_pattern(&arm.pattern, scrutinee_enum.as_deref());
+                    let matched = self.lower_pattern_check(&pattern, &scrutinee);
+
+                    if let Some(guard) = &arm.guard {
+                        // The guard may read pattern bindings, so bind them once
+                        // the pattern has matched and test the guard afterwards
+                        let guard_label = self.new_label(&format!("match_guard{}", i));
+                        self.func.emit(Instruction::CondBranch {
+                            cond: matched,
+                            then_label: guard_label.clone(),
+                            else_label: next_label.clone(),
+                        });
 
-                    // Handle guard
-                    let final_cond = if let Some(guard) = &arm.guard {
+                        self.func.new_block(&guard_label);
+                        self.bind_pattern_vars(&pattern, &scrutinee);
                         let guard_val = self.lower_expr(guard);
-                        // matched AND guard
-                        let temp = self.func.new_temp();
-                        self.func.emit(Instruction::Binary {
-                            dest: temp,
-                            op: BinaryOp::And,
-                            left: matched,
-                            right: guard_val,
+                        self.func.emit(Instruction::CondBranch {
+                            cond: guard_val,
+                            then_label: arm_label.clone(),
+                            else_label: next_label.clone(),
                         });
-                        Value::Temp(temp)
-                    } else {
-                        matched
-                    };
 
-                    self.func.emit(Instruction::CondBranch {
-                        cond: final_cond,
-                        then_label: arm_label.clone(),
-                        else_label: next_label.clone(),
-                    });
+                        self.func.new_block(&arm_label);
+                    } else {
+                        self.func.emit(Instruction::CondBranch {
+                            cond: matched,
+                            then_label: arm_label.clone(),
+                            else_label: next_label.clone(),
+                        });
 
-                    // Arm body block
-                    self.func.new_block(&arm_label);
+                        // Arm body block
+                        self.func.new_block(&arm_label);
 
-                    // Bind pattern variables
-                    self.bind_pattern_vars(&arm.pattern, &scrutinee);
+                        // Bind pattern variables
+                        self.bind_pattern_vars(&pattern, &scrutinee);
+                    }
 
                     // Lower arm body
                     let mut arm_result = Value::Void;
@@ -1279,6 +2958,8 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                         }
                     }
 
+                    self.fixed_arrays = outer_arrays;
+
                     // Store result in phi destination
                     self.func.emit(Instruction::Store {
                         value: arm_result,
@@ -1296,7 +2977,11 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
             }
 
             Expr::Try { expr, span: _ } => {
-                // Lower the inner expression
+                // Lower the inner expression, which yields `Result<expected, _>`
+                self.expected = expected.map(|ok| Type::Generic {
+                    name: "Result".to_string(),
+                    args: vec![ok, Type::String],
+                });
                 let inner_val = self.lower_expr(expr);
 
                 // Generate labels for success and error paths
@@ -1318,15 +3003,31 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                     else_label: err_label.clone(),
                 });
 
-                // Error path - early return
+                // Error path - early return, or jump to the enclosing catch handler
                 self.func.new_block(&err_label);
                 let err_val = self.func.new_temp();
-                self.func.emit(Instruction::Call {
-                    dest: Some(err_val),
-                    func: "__try_propagate".to_string(),
-                    args: vec![inner_val.clone()],
-                });
-                self.func.emit(Instruction::Return(Value::Temp(err_val)));
+                if let Some((catch_label, error_local, depth)) = self.catch_targets.last().cloned() {
+                    self.func.emit(Instruction::Call {
+                        dest: Some(err_val),
+                        func: "__try_error".to_string(),
+                        args: vec![inner_val.clone()],
+                    });
+                    self.func.emit(Instruction::Store {
+                        value: Value::Temp(err_val),
+                        ptr: Value::Local(error_local),
+                    });
+                    // `with` blocks opened inside the `try` end with the jump to its handler
+                    self.close_resources(depth);
+                    self.func.emit(Instruction::Branch { target: catch_label });
+                } else {
+                    self.func.emit(Instruction::Call {
+                        dest: Some(err_val),
+                        func: "__try_propagate".to_string(),
+                        args: vec![inner_val.clone()],
+                    });
+                    self.close_resources(0);
+                    self.func.emit(Instruction::Return(Value::Temp(err_val)));
+                }
 
                 // Ok path - unwrap and continue
                 self.func.new_block(&ok_label);
@@ -1344,36 +3045,21 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
             }
 
             Expr::StringInterpolation { parts, span: _ } => {
-                // String interpolation is lowered as a format! call
-                let dest = self.func.new_temp();
-
-                // Collect all the parts and expressions
-                let mut format_parts: Vec<Value> = Vec::new();
-
-                for part in parts {
-                    match part {
-                        StringPart::Literal(s) => {
-                            let idx = self.ctx.module.add_string(s.clone());
-                            format_parts.push(Value::ConstString(idx));
-                        }
-                        StringPart::Expr(expr) => {
-                            let val = self.lower_expr(expr);
-                            format_parts.push(val);
-                        }
-                    }
-                }
-
-                // Call the string formatting function
-                self.func.emit(Instruction::Call {
-                    dest: Some(dest),
-                    func: "__string_format".to_string(),
-                    args: format_parts,
-                });
+                // Literal text stays inline so backends can build the whole
+                // string in one step
+                let format_parts = parts.iter()
+                    .map(|part| match part {
+                        StringPart::Literal(s) => FormatPart::Literal(s.clone()),
+                        StringPart::Expr(expr) => FormatPart::Value(self.lower_expr(expr)),
+                    })
+                    .collect();
 
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::FormatString { dest, parts: format_parts });
                 Value::Temp(dest)
             }
 
-            Expr::Closure { params, return_type: _, body, span: _ } => {
+            Expr::Closure { params, return_type, body, span: _ } => {
                 // For closures, we generate a unique name and create a separate function
                 // Then return a reference to that function
 
@@ -1382,13 +3068,20 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 self.ctx.label_counter += 1;
                 let closure_name = format!("__closure_{}", closure_id);
 
-                // Create closure function
-                let mut closure_func = Function::new(&closure_name, IrType::I64, false);
+                // Create closure function; its `return`s are lowered into it,
+                // so they never leave the enclosing function
+                let (hint_params, hint_ret) = self.closure_hint.take().unzip();
+                let ret_type = match (return_type, hint_ret) {
+                    (None, Some(ret)) => ret,
+                    _ => self.closure_return_type(return_type.as_ref(), body),
+                };
+                let mut closure_func = Function::new(&closure_name, ret_type, false);
 
                 // Add parameters
-                for param in params {
+                for (i, param) in params.iter().enumerate() {
                     let ty = param.ty.as_ref()
                         .map(IrType::from_mendes_type)
+                        .or_else(|| hint_params.as_ref().and_then(|hint| hint.get(i).cloned()))
                         .unwrap_or(IrType::I64);
                     closure_func.add_param(&param.name, ty);
                 }
@@ -1419,76 +3112,79 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 Value::Global(closure_name)
             }
 
-            Expr::Tuple { elements, span: _ } => {
-                // Lower tuple as a struct with fields named field0, field1, etc.
-                let tuple_type = format!("tuple_{}", elements.len());
-                let dest = self.func.new_temp();
-                let dest_name = format!("__t{}", dest);
+            Expr::AsyncBlock { body, span: _ } => {
+                // Async blocks are lowered to a separate async function;
+                // calling it without awaiting produces the future
+                let block_id = self.ctx.label_counter;
+                self.ctx.label_counter += 1;
+                let block_name = format!("__async_block_{}", block_id);
+
+                let ret_type = self.async_block_output(body);
+                let mut block_func = Function::new(&block_name, ret_type, true);
+
+                // The outer locals the block reads are passed in by value
+                let mut used = NamesUsed::default();
+                used.visit_block(body);
+                let mut captures = Vec::new();
+                for ident in &used.uses {
+                    let Expr::Ident(name, _) = ident else { continue };
+                    let outer = self.vars.contains_key(name)
+                        || self.func.params.iter().any(|(n, _)| n == name);
+                    if outer {
+                        block_func.add_param(name, self.operand_type(ident).unwrap_or(IrType::I64));
+                        captures.push(self.lower_expr(ident));
+                    }
+                }
 
-                // Lower each element
-                let element_values: Vec<Value> = elements.iter().map(|e| self.lower_expr(e)).collect();
+                let mut block_lowerer = FunctionLowerer::new(self.ctx, &mut block_func);
+                for stmt in body {
+                    block_lowerer.lower_stmt(stmt);
+                }
+                if !block_lowerer.func.current_block().is_terminated() {
+                    block_lowerer.func.emit(Instruction::Return(Value::Void));
+                }
 
-                // Create a struct allocation for the tuple
-                self.func.emit(Instruction::Alloca {
-                    dest: dest_name.clone(),
-                    ty: IrType::Struct(tuple_type.clone()),
-                });
+                self.ctx.module.add_function(block_func);
 
-                // Set each field
-                for (i, val) in element_values.into_iter().enumerate() {
-                    self.func.emit(Instruction::SetField {
-                        ptr: Value::Temp(dest),
-                        struct_name: tuple_type.clone(),
-                        field_index: i,
-                        field_name: format!("field{}", i),
-                        value: val,
-                    });
-                }
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Call {
+                    dest: Some(dest),
+                    func: block_name,
+                    args: captures,
+                });
 
                 Value::Temp(dest)
             }
 
-            Expr::Range { start, end, inclusive, span: _ } => {
-                // Lower range as a struct with start, end, inclusive fields
-                let dest = self.func.new_temp();
-                let dest_name = format!("__t{}", dest);
-
-                let start_val = start.as_ref().map(|e| self.lower_expr(e));
-                let end_val = end.as_ref().map(|e| self.lower_expr(e));
+            Expr::Tuple { elements, span: _ } => {
+                let element_values: Vec<Value> = elements.iter().map(|e| self.lower_expr(e)).collect();
 
-                // Create range struct
-                self.func.emit(Instruction::Alloca {
-                    dest: dest_name.clone(),
-                    ty: IrType::Struct("Range".to_string()),
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Call {
+                    dest: Some(dest),
+                    func: "__tuple".to_string(),
+                    args: element_values,
                 });
 
-                // Set start field (0 if not provided)
-                self.func.emit(Instruction::SetField {
-                    ptr: Value::Temp(dest),
-                    struct_name: "Range".to_string(),
-                    field_index: 0,
-                    field_name: "start".to_string(),
-                    value: start_val.unwrap_or(Value::ConstInt(0)),
-                });
+                Value::Temp(dest)
+            }
 
-                // Set end field (max if not provided)
-                self.func.emit(Instruction::SetField {
-                    ptr: Value::Temp(dest),
-                    struct_name: "Range".to_string(),
-                    field_index: 1,
-                    field_name: "end".to_string(),
-                    value: end_val.unwrap_or(Value::ConstInt(i64::MAX)),
-                });
+            Expr::Range { start, end, inclusive, span: _ } => {
+                // A range value keeps its bounds; a missing start is 0 and a
+                // missing end is the largest int
+                let start_val = start.as_ref()
+                    .map(|e| self.lower_expr(e))
+                    .unwrap_or(Value::ConstInt(0));
+                let end_val = end.as_ref()
+                    .map(|e| self.lower_expr(e))
+                    .unwrap_or(Value::ConstInt(i64::MAX));
 
-                // Set inclusive flag
-                self.func.emit(Instruction::SetField {
-                    ptr: Value::Temp(dest),
-                    struct_name: "Range".to_string(),
-                    field_index: 2,
-                    field_name: "inclusive".to_string(),
-                    value: Value::ConstBool(*inclusive),
+                let dest = self.func.new_temp();
+                self.func.emit(Instruction::Call {
+                    dest: Some(dest),
+                    func: "__range".to_string(),
+                    args: vec![start_val, end_val, Value::ConstBool(*inclusive)],
                 });
-
                 Value::Temp(dest)
             }
         }
@@ -1520,7 +3216,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                 Value::ConstBool(true)
             }
 
-            Pattern::Variant { enum_name: _, variant, data, span: _ } => {
+            Pattern::Variant { enum_name, variant, data, span: _ } => {
                 // For Option/Result, we check the tag
                 let tag_check = match variant.as_str() {
                     "None" => {
@@ -1564,7 +3260,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                         let dest = self.func.new_temp();
                         self.func.emit(Instruction::Call {
                             dest: Some(dest),
-                            func: format!("__is_variant_{}", variant),
+                            func: format!("__is_variant_{}", self.variant_path(enum_name.as_deref(), variant)),
                             args: vec![scrutinee.clone()],
                         });
                         Value::Temp(dest)
@@ -1631,6 +3327,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                         dest: elem_dest,
                         ptr: scrutinee.clone(),
                         index: Value::ConstInt(i as i64),
+                        in_bounds: false,
                     });
 
                     let elem_check = self.lower_pattern_check(pattern, &Value::Temp(elem_dest));
@@ -1661,6 +3358,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                             dest: field_dest,
                             ptr: scrutinee.clone(),
                             index: Value::ConstInt(i as i64),
+                            in_bounds: false,
                         });
 
                         let field_check = self.lower_pattern_check(pat, &Value::Temp(field_dest));
@@ -1724,59 +3422,153 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
         }
     }
 
+    /// Path of a variant as written in Rust: `Shape::Circle` for a user enum
+    /// variant, `Some`, `Ok` and `Err` as they are
+    fn variant_path(&self, enum_name: Option<&str>, variant: &str) -> String {
+        if matches!(variant, "Some" | "None" | "Ok" | "Err") {
+            return variant.to_string();
+        }
+        let owner = enum_name.or_else(|| self.ctx.module.enum_for_variant(variant).map(|def| def.name.as_str()));
+        match owner {
+            Some(owner) => format!("{}::{}", owner, variant),
+            None => variant.to_string(),
+        }
+    }
+
+    /// The enum declaring `variant` in the expression at `span`: the one the
+    /// checker resolved, else the expected enum, else any declaring enum
+    fn variant_enum(&self, variant: &str, expected: Option<&Type>, span: Span) -> Option<&EnumDef> {
+        let declaring = |ty: Option<&Type>| match ty {
+            Some(Type::Named(name) | Type::Generic { name, .. }) => {
+                self.ctx.module.enums.get(name).filter(|def| def.has_variant(variant))
+            }
+            _ => None,
+        };
+        declaring(self.ctx.expr_types.get(&span))
+            .or_else(|| declaring(expected))
+            .or_else(|| self.ctx.module.enum_for_variant(variant))
+    }
+
+    /// Reads bare unit variant names in a match pattern as variants rather
+    /// than bindings, and names the enum of unqualified variants after the
+    /// matched enum when it is known
+    fn resolve_variant_pattern(&self, pattern: &Pattern, scrutinee_enum: Option<&str>) -> Pattern {
+        let owner = |variant: &str| match scrutinee_enum {
+            Some(name) => self.ctx.module.enums.get(name).filter(|def| def.has_variant(variant)),
+            None => self.ctx.module.enum_for_variant(variant),
+        };
+        match pattern {
+            Pattern::Ident { name, span, .. } => match owner(name) {
+                Some(def) if def.variants.iter().any(|(n, data)| n == name && matches!(data, VariantData::Unit)) => {
+                    Pattern::Variant {
+                        enum_name: Some(def.name.clone()),
+                        variant: name.clone(),
+                        data: VariantPatternData::Unit,
+                        span: *span,
+                    }
+                }
+                _ => pattern.clone(),
+            },
+            Pattern::Variant { enum_name: None, variant, data, span } if !matches!(variant.as_str(), "Some" | "None" | "Ok" | "Err") => {
+                Pattern::Variant {
+                    enum_name: owner(variant).map(|def| def.name.clone()),
+                    variant: variant.clone(),
+                    data: data.clone(),
+                    span: *span,
+                }
+            }
+            // `Rect { w, h }` names a struct variant unless a struct is called `Rect`
+            Pattern::Struct { name, fields, span } if !self.ctx.module.structs.contains_key(name) => match owner(name) {
+                Some(def) => Pattern::Variant {
+                    enum_name: Some(def.name.clone()),
+                    variant: name.clone(),
+                    data: VariantPatternData::Struct(fields.clone()),
+                    span: *span,
+                },
+                None => pattern.clone(),
+            },
+            Pattern::Or(patterns, span) => Pattern::Or(
+                patterns.iter().map(|p| self.resolve_variant_pattern(p, scrutinee_enum)).collect(),
+                *span,
+            ),
+            _ => pattern.clone(),
+        }
+    }
+
+    /// Declared data of a user enum variant, if the enum is known
+    fn variant_data(&self, enum_name: Option<&str>, variant: &str) -> Option<VariantData> {
+        let enum_name = enum_name.or_else(|| self.ctx.module.enum_for_variant(variant).map(|def| def.name.as_str()))?;
+        self.ctx.module.get_variant(enum_name, variant).cloned()
+    }
+
+    /// Bind a pattern variable to a value
+    fn bind_local(&mut self, name: &str, ty: IrType, value: Value) {
+        self.fixed_arrays.remove(name);
+        self.func.add_local(name.to_string(), ty.clone());
+        self.func.emit(Instruction::Alloca {
+            dest: name.to_string(),
+            ty,
+        });
+        self.func.emit(Instruction::Store {
+            value: value.clone(),
+            ptr: Value::Local(name.to_string()),
+        });
+        self.vars.insert(name.to_string(), value);
+    }
+
     /// Bind pattern variables (extract values and store in locals)
     fn bind_pattern_vars(&mut self, pattern: &Pattern, scrutinee: &Value) {
+        self.bind_pattern_vars_as(pattern, scrutinee, IrType::I64);
+    }
+
+    /// Bind pattern variables, typing an identifier pattern as `ty`
+    fn bind_pattern_vars_as(&mut self, pattern: &Pattern, scrutinee: &Value, ty: IrType) {
         match pattern {
             Pattern::Ident { name, mutable: _, span: _ } => {
                 // Bind the scrutinee to the variable
-                self.func.add_local(name.clone(), IrType::I64); // Simplified type
-                self.func.emit(Instruction::Alloca {
-                    dest: name.clone(),
-                    ty: IrType::I64,
-                });
-                self.func.emit(Instruction::Store {
-                    value: scrutinee.clone(),
-                    ptr: Value::Local(name.clone()),
-                });
-                self.vars.insert(name.clone(), scrutinee.clone());
+                self.bind_local(name, ty, scrutinee.clone());
             }
 
-            Pattern::Variant { data, .. } => {
-                // Extract inner value and bind nested patterns
+            Pattern::Variant { enum_name, variant, data, .. } => {
+                // Extract each field by position or name and bind the nested
+                // patterns with the field's declared type
+                let path = self.variant_path(enum_name.as_deref(), variant);
+                let declared = self.variant_data(enum_name.as_deref(), variant);
                 match data {
                     VariantPatternData::Tuple(patterns) => {
                         for (i, pat) in patterns.iter().enumerate() {
+                            let field_ty = match &declared {
+                                Some(VariantData::Tuple(types)) => types.get(i).cloned(),
+                                _ => None,
+                            };
                             let inner_dest = self.func.new_temp();
                             self.func.emit(Instruction::Call {
                                 dest: Some(inner_dest),
-                                func: "__extract_variant_field".to_string(),
-                                args: vec![scrutinee.clone(), Value::ConstInt(i as i64)],
+                                func: format!("__variant_field_{}.{}", path, i),
+                                args: vec![scrutinee.clone()],
                             });
-                            self.bind_pattern_vars(pat, &Value::Temp(inner_dest));
+                            self.bind_pattern_vars_as(pat, &Value::Temp(inner_dest), field_ty.unwrap_or(IrType::I64));
                         }
                     }
                     VariantPatternData::Struct(fields) => {
-                        for (i, (name, pat)) in fields.iter().enumerate() {
+                        for (name, pat) in fields {
+                            let field_ty = match &declared {
+                                Some(VariantData::Struct(declared)) => {
+                                    declared.iter().find(|(n, _)| n == name).map(|(_, t)| t.clone())
+                                }
+                                _ => None,
+                            };
+                            let field_ty = field_ty.unwrap_or(IrType::I64);
                             let inner_dest = self.func.new_temp();
                             self.func.emit(Instruction::Call {
                                 dest: Some(inner_dest),
-                                func: "__extract_variant_field".to_string(),
-                                args: vec![scrutinee.clone(), Value::ConstInt(i as i64)],
+                                func: format!("__variant_field_{}.{}", path, name),
+                                args: vec![scrutinee.clone()],
                             });
-                            if let Some(p) = pat {
-                                self.bind_pattern_vars(p, &Value::Temp(inner_dest));
-                            } else {
+                            match pat {
+                                Some(p) => self.bind_pattern_vars_as(p, &Value::Temp(inner_dest), field_ty),
                                 // Shorthand: bind field name directly
-                                self.func.add_local(name.clone(), IrType::I64);
-                                self.func.emit(Instruction::Alloca {
-                                    dest: name.clone(),
-                                    ty: IrType::I64,
-                                });
-                                self.func.emit(Instruction::Store {
-                                    value: Value::Temp(inner_dest),
-                                    ptr: Value::Local(name.clone()),
-                                });
-                                self.vars.insert(name.clone(), Value::Temp(inner_dest));
+                                None => self.bind_local(name, field_ty, Value::Temp(inner_dest)),
                             }
                         }
                     }
@@ -1791,6 +3583,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                         dest: elem_dest,
                         ptr: scrutinee.clone(),
                         index: Value::ConstInt(i as i64),
+                        in_bounds: false,
                     });
                     self.bind_pattern_vars(pat, &Value::Temp(elem_dest));
                 }
@@ -1803,6 +3596,7 @@ impl<'a, 'b> FunctionLowerer<'a, 'b> {
                         dest: field_dest,
                         ptr: scrutinee.clone(),
                         index: Value::ConstInt(i as i64),
+                        in_bounds: false,
                     });
                     if let Some(p) = pat {
                         self.bind_pattern_vars(p, &Value::Temp(field_dest));
@@ -1860,6 +3654,26 @@ mod tests {
         assert_eq!(module.functions[0].params.len(), 2);
     }
 
+    #[test]
+    fn test_fold_string_literal_concat() {
+        let source = r#"static GREETING: string = "Hello, " + "world"
+
+fn shout(name: string) -> string:
+    return "Hey " + "there, " + name
+"#;
+        let module = lower_source(source);
+        assert!(module.string_table.contains(&"Hello, world".to_string()));
+        assert!(module.string_table.contains(&"Hey there, ".to_string()));
+        assert!(!module.string_table.contains(&"Hey ".to_string()));
+
+        // Only the concatenation with the runtime `name` is left
+        let concats = module.functions[0].blocks.iter()
+            .flat_map(|block| &block.instructions)
+            .filter(|inst| matches!(inst, Instruction::Binary { op: BinaryOp::Add, .. }))
+            .count();
+        assert_eq!(concats, 1);
+    }
+
     #[test]
     fn test_lower_server() {
         let source = r#"server:
@@ -1872,6 +3686,114 @@ mod tests {
         let server = module.server.unwrap();
         assert_eq!(server.host, "0.0.0.0");
         assert_eq!(server.port, 8080);
+        assert_eq!(server.max_body, None);
+    }
+
+    #[test]
+    fn test_lower_server_max_body() {
+        let source = r#"server:
+    port 8080
+    max_body 1048576
+"#;
+        let module = lower_source(source);
+        assert_eq!(module.server.unwrap().max_body, Some(1048576));
+    }
+
+    #[test]
+    fn test_lower_config_timeouts() {
+        let source = r#"server:
+    port 8080
+    timeout 2m
+
+db postgres main:
+    url "postgres://localhost/app"
+    pool 5
+    timeout 500ms
+"#;
+        let module = lower_source(source);
+        assert_eq!(module.server.unwrap().timeout, Some(120_000));
+        assert_eq!(module.databases[0].timeout, Some(500));
+    }
+
+    #[test]
+    fn test_cfg_items_dropped_unless_enabled() {
+        let source = r#"@cfg(test)
+fn helper() -> int:
+    return 42
+
+fn main_fn() -> int:
+    return 0
+"#;
+        let mut lexer = Lexer::new(source, 0);
+        let (program, _) = parse(lexer.tokenize());
+
+        let module = lower_program(&program);
+        assert!(module.functions.iter().all(|f| f.name != "helper"));
+        assert!(module.functions.iter().any(|f| f.name == "main_fn"));
+
+        let cfg: HashSet<String> = ["test".to_string()].into_iter().collect();
+        let module = lower_program_with_cfg(&program, &cfg);
+        assert!(module.functions.iter().any(|f| f.name == "helper"));
+    }
+
+    #[test]
+    fn test_checked_arithmetic_option() {
+        let source = r#"fn total(price: int, qty: int, label: string) -> int:
+    let name = label + "!"
+    let mut sum = price * qty + 1
+    sum -= 2
+    return sum
+"#;
+        let mut lexer = Lexer::new(source, 0);
+        let (program, _) = parse(lexer.tokenize());
+
+        let ir = lower_program(&program).to_string();
+        assert!(!ir.contains("checked_"), "{}", ir);
+
+        let options = LowerOptions { checked_arithmetic: true, ..Default::default() };
+        let ir = lower_program_with_options(&program, &options).to_string();
+        assert!(ir.contains("checked_mul %price, %qty"), "{}", ir);
+        assert!(ir.contains("checked_add"), "{}", ir);
+        assert!(ir.contains("checked_sub"), "{}", ir);
+        // String concatenation is never checked
+        assert_eq!(ir.matches("checked_").count(), 3, "{}", ir);
+    }
+
+    #[test]
+    fn test_checked_arithmetic_on_fields_and_elements() {
+        let source = r#"struct Account:
+    balance: int
+    rate: int
+
+fn interest(a: Account) -> int:
+    return a.balance * a.rate
+
+fn pair_sum(xs: [int]) -> int:
+    return xs[0] + xs[1]
+
+fn scale(a: Account, f: float) -> float:
+    return f * 2.0
+"#;
+        let mut lexer = Lexer::new(source, 0);
+        let (program, _) = parse(lexer.tokenize());
+        let options = LowerOptions { checked_arithmetic: true, ..Default::default() };
+        let module = lower_program_with_options(&program, &options);
+        let body = |name: &str| module.get_function(name).unwrap().to_string();
+        assert!(body("interest").contains("checked_mul"), "{}", body("interest"));
+        assert!(body("pair_sum").contains("checked_add"), "{}", body("pair_sum"));
+        assert!(!body("scale").contains("checked_"), "{}", body("scale"));
+    }
+
+    #[test]
+    fn test_lower_server_tls() {
+        let source = r#"server:
+    port 8443
+    tls_cert "certs/server.pem"
+    tls_key "certs/server.key"
+"#;
+        let server = lower_source(source).server.unwrap();
+        assert_eq!(server.tls_cert.as_deref(), Some("certs/server.pem"));
+        assert_eq!(server.tls_key.as_deref(), Some("certs/server.key"));
     }
 
     #[test]
@@ -1888,6 +3810,27 @@ mod tests {
         assert_eq!(module.functions.len(), 1);
     }
 
+    #[test]
+    fn test_lower_route_attribute() {
+        let source = r#"@route(POST, "/users/{id:int}")
+fn update_user(id: int, body: string) -> int:
+    return id
+"#;
+        let module = lower_source(source);
+
+        assert_eq!(module.routes.len(), 1);
+        assert_eq!(module.routes[0].method, "POST");
+        assert_eq!(module.routes[0].path, "/users/{id:int}");
+
+        // The handler takes the path parameter and the body like an `api` block
+        let handler = module.get_function(&module.routes[0].handler).unwrap();
+        let params: Vec<_> = handler.params.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(params, vec!["__request", "id", "body"]);
+
+        // The function itself stays callable
+        assert!(module.get_function("update_user").is_some());
+    }
+
     #[test]
     fn test_lower_struct() {
         let source = r#"struct User:
@@ -1900,4 +3843,224 @@ mod tests {
         let user = module.get_struct("User").unwrap();
         assert_eq!(user.fields.len(), 2);
     }
+
+    #[test]
+    fn test_lower_async_block() {
+        let source = r#"fn compute() -> int async:
+    let task = async:
+        return 42
+    return await task
+"#;
+        let module = lower_source(source);
+
+        let block = module.get_function("__async_block_0").unwrap();
+        assert!(block.is_async);
+        assert_eq!(block.return_type, IrType::I64);
+        assert!(module.to_string().contains("call @__async_block_0()"));
+    }
+
+    #[test]
+    fn test_lower_let_tuple_from_local() {
+        let source = r#"fn pair() -> (int, string):
+    return (1, "a")
+
+fn split() -> string:
+    let t = pair()
+    let (n, s) = t
+    return s
+"#;
+        let module = lower_source(source);
+        let split = module.get_function("split").unwrap();
+        assert_eq!(split.locals.get("n"), Some(&IrType::I64));
+        assert_eq!(split.locals.get("s"), Some(&IrType::String));
+    }
+
+    #[test]
+    fn test_lower_tuple_index_on_param() {
+        let source = r#"fn second(p: (int, string)) -> string:
+    let s = p.1
+    return s
+"#;
+        let module = lower_source(source);
+        let second = module.get_function("second").unwrap();
+        assert_eq!(second.locals.get("s"), Some(&IrType::String));
+    }
+
+    #[test]
+    fn test_lower_match_guard_sees_bindings() {
+        let source = r#"fn classify(x: Option<int>) -> int:
+    let r = match x:
+        Some(n) if n > 0:
+            n
+        None:
+            0
+    return r
+"#;
+        let ir = lower_source(source).to_string();
+
+        // `n` is bound inside the guard block, before the comparison reads it
+        let guard = &ir[ir.find("match_guard0").unwrap()..];
+        let guard = &guard[guard.find(":\n").unwrap()..guard.find("\nmatch_arm0").unwrap()];
+        let store = guard.find(", %n").unwrap();
+        assert!(store < guard.find("cmp gt").unwrap());
+        assert!(!ir.contains("@n"));
+
+        // A failed guard falls through to the next arm's pattern test
+        assert!(guard.contains("match_check1"));
+    }
+
+    #[test]
+    fn test_lower_for_iterates_in_index_order() {
+        let source = r#"fn total(items: [int]) -> int:
+    let mut sum = 0
+    for item in items:
+        sum = sum + item
+    return sum
+"#;
+        let module = lower_source(source);
+        let func = module.get_function("total").unwrap();
+        let block = |label: &str| func.blocks.iter().find(|b| b.label.starts_with(label)).unwrap();
+
+        // The index starts at 0 and reads `items[idx]` while `idx < len`
+        let entry = func.blocks[0].instructions.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+        assert!(entry.iter().any(|i| i.starts_with("store 0, %__idx_")));
+        assert!(block("for_cond_").instructions.iter().any(|i| i.to_string().contains("cmp lt")));
+        assert!(block("for_body_").instructions.iter().any(|i| matches!(i, Instruction::GetElement { .. })));
+
+        // ...and advances it by exactly one per iteration
+        assert!(block("for_inc_").instructions.iter().any(|i| matches!(
+            i,
+            Instruction::Binary { op: BinaryOp::Add, right: Value::ConstInt(1), .. }
+        )));
+    }
+
+    #[test]
+    fn test_lower_local_compound_assignment_stores_back() {
+        let source = r#"fn step() -> int:
+    let mut n = 1
+    n += 2
+    n *= 3
+    return n
+"#;
+        let module = lower_source(source);
+        let func = module.get_function("step").unwrap();
+        let instructions = &func.blocks[0].instructions;
+
+        // Each update is followed by a store of its result into `n`
+        for op in [BinaryOp::Add, BinaryOp::Mul] {
+            let at = instructions.iter()
+                .position(|i| matches!(i, Instruction::Binary { op: o, .. } if *o == op))
+                .unwrap();
+            let Instruction::Binary { dest, .. } = &instructions[at] else { unreachable!() };
+            assert!(matches!(
+                &instructions[at + 1],
+                Instruction::Store { value: Value::Temp(t), ptr: Value::Local(name) } if t == dest && name == "n"
+            ), "{:?}", instructions[at + 1]);
+        }
+    }
+
+    #[test]
+    fn test_lower_destructured_param_unpacks_into_locals() {
+        let source = r#"fn label((name, (a, _)): (string, (int, bool))) -> int:
+    return a
+"#;
+        let module = lower_source(source);
+        let func = module.get_function("label").unwrap();
+        assert_eq!(func.params[0].0, "__param0");
+        assert_eq!(func.locals.get("name"), Some(&IrType::String));
+        assert_eq!(func.locals.get("a"), Some(&IrType::I64));
+        assert!(func.blocks[0].instructions.iter().any(|i| matches!(
+            i,
+            Instruction::Call { func, args, .. } if func == "__tuple_field" && args[0] == Value::Param(0)
+        )));
+
+        // The printed IR reads back
+        let lines = module.to_debug_lines();
+        assert_eq!(crate::parse_ir_debug(&module.to_string()).unwrap().to_debug_lines(), lines);
+    }
+
+    #[test]
+    fn test_lower_try_catch_routes_errors_to_handler() {
+        let source = r#"fn load(s: string) -> int:
+    let mut total = 0
+    try:
+        let n: int = parse(s)?
+        total = n
+    catch e:
+        total = 0 - 1
+    return total
+"#;
+        let module = lower_source(source);
+        let func = module.get_function("load").unwrap();
+        let err_block = func.blocks.iter().find(|b| b.label.starts_with("try_err")).unwrap();
+
+        // A failing `?` stores the error in `e` and runs the handler instead of returning
+        assert!(err_block.instructions.iter().any(|i| matches!(
+            i,
+            Instruction::Store { ptr: Value::Local(name), .. } if name == "e"
+        )));
+        assert!(matches!(
+            err_block.instructions.last(),
+            Some(Instruction::Branch { target }) if target.starts_with("catch")
+        ));
+        assert!(!err_block.instructions.iter().any(|i| matches!(i, Instruction::Return(_))));
+        assert!(func.blocks.iter().any(|b| b.label.starts_with("catch")));
+    }
+
+    #[test]
+    fn test_lower_try_closes_inner_resources_before_handler() {
+        let source = r#"struct Conn:
+    open: bool
+
+    fn close(&mut self):
+        self.open = false
+
+fn load(s: string) -> int:
+    with Conn { open: true } as outer:
+        try:
+            with Conn { open: true } as inner:
+                let n: int = parse(s)?
+                return n
+        catch e:
+            return 0
+    return 1
+"#;
+        let module = lower_source(source);
+        let func = module.get_function("load").unwrap();
+        let err_block = func.blocks.iter().find(|b| b.label.starts_with("try_err")).unwrap();
+        let closed: Vec<_> = err_block.instructions.iter()
+            .filter_map(|i| match i {
+                Instruction::Call { func, args, .. } if func == "__method_close" => Some(&args[0]),
+                _ => None,
+            })
+            .collect();
+
+        // Only the resource opened inside the `try` is closed; the handler still runs inside `outer`
+        assert_eq!(closed, vec![&Value::Local("inner".to_string())]);
+        assert!(matches!(
+            err_block.instructions.last(),
+            Some(Instruction::Branch { target }) if target.starts_with("catch")
+        ));
+    }
+
+    #[test]
+    fn test_lower_catch_binding_has_result_error_type() {
+        let source = r#"enum LoadError:
+    Missing
+    Invalid
+
+fn fetch(key: int) -> Result<int, LoadError>:
+    return Ok(key)
+
+fn load() -> int:
+    try:
+        let n = fetch(1)?
+        return n
+    catch e:
+        return 0
+"#;
+        let module = lower_source(source);
+        let func = module.get_function("load").unwrap();
+        assert_eq!(func.locals.get("e"), Some(&IrType::Struct("LoadError".to_string())));
+    }
 }
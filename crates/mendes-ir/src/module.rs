@@ -2,9 +2,10 @@
 //!
 //! Contains module definition, functions, basic blocks and HTTP routes.
 
-use crate::types::{IrType, StructDef, GenericParam};
-use crate::instruction::{Instruction, Value};
-use std::collections::HashMap;
+use crate::types::{IrType, StructDef, EnumDef, VariantData, GenericParam};
+use crate::instruction::{Instruction, Value, BinaryOp, CompareOp, FormatPart};
+use mendes_parser::Visibility;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 /// IR Module - represents a complete program
@@ -22,6 +23,8 @@ pub struct Module {
     pub server: Option<ServerConfig>,
     /// Defined structs
     pub structs: HashMap<String, StructDef>,
+    /// Defined enums
+    pub enums: HashMap<String, EnumDef>,
     /// Trait definitions
     pub traits: HashMap<String, TraitDef>,
     /// Trait implementations
@@ -36,6 +39,9 @@ pub struct Module {
     pub databases: Vec<DatabaseConfig>,
     /// Middlewares
     pub middlewares: Vec<String>,
+    /// Structs sent or received as JSON by HTTP routes, with the structs
+    /// nested in their fields
+    pub api_structs: HashSet<String>,
 }
 
 impl Module {
@@ -47,6 +53,7 @@ impl Module {
             websocket_routes: Vec::new(),
             server: None,
             structs: HashMap::new(),
+            enums: HashMap::new(),
             traits: HashMap::new(),
             impls: Vec::new(),
             type_aliases: HashMap::new(),
@@ -54,6 +61,7 @@ impl Module {
             string_table: Vec::new(),
             databases: Vec::new(),
             middlewares: Vec::new(),
+            api_structs: HashSet::new(),
         }
     }
 
@@ -93,6 +101,36 @@ impl Module {
         self.structs.get(name)
     }
 
+    /// Adds a global
+    pub fn add_global(&mut self, global: Global) {
+        self.globals.push(global);
+    }
+
+    /// Finds a global by name
+    pub fn get_global(&self, name: &str) -> Option<&Global> {
+        self.globals.iter().find(|g| g.name == name)
+    }
+
+    /// Adds an enum
+    pub fn add_enum(&mut self, def: EnumDef) {
+        self.enums.insert(def.name.clone(), def);
+    }
+
+    /// Finds the enum declaring a variant; when several do, the first by name,
+    /// so the choice does not depend on the map's order
+    pub fn enum_for_variant(&self, variant: &str) -> Option<&EnumDef> {
+        self.enums.values()
+            .filter(|e| e.has_variant(variant))
+            .min_by(|a, b| a.name.cmp(&b.name))
+    }
+
+    /// Finds the data of the variant `variant` of the enum `enum_name`
+    pub fn get_variant(&self, enum_name: &str, variant: &str) -> Option<&VariantData> {
+        self.enums.get(enum_name)?.variants.iter()
+            .find(|(name, _)| name == variant)
+            .map(|(_, data)| data)
+    }
+
     /// Adds a trait definition
     pub fn add_trait(&mut self, def: TraitDef) {
         self.traits.insert(def.name.clone(), def);
@@ -127,6 +165,17 @@ impl Module {
     pub fn get_function_mut(&mut self, name: &str) -> Option<&mut Function> {
         self.functions.iter_mut().find(|f| f.name == name)
     }
+
+    /// The textual form line by line, trimmed and without blank lines, for
+    /// snapshot tests that should not depend on indentation or spacing
+    pub fn to_debug_lines(&self) -> Vec<String> {
+        self.to_string()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
 }
 
 impl fmt::Display for Module {
@@ -143,8 +192,10 @@ impl fmt::Display for Module {
             writeln!(f)?;
         }
 
-        // Structs
-        for (name, def) in &self.structs {
+        // Structs and enums are listed by name so the output is stable
+        let mut structs: Vec<_> = self.structs.iter().collect();
+        structs.sort_by_key(|(name, _)| *name);
+        for (name, def) in structs {
             writeln!(f, "; Struct {}", name)?;
             write!(f, "%{} = type {{ ", name)?;
             for (i, (fname, fty)) in def.fields.iter().enumerate() {
@@ -157,6 +208,32 @@ impl fmt::Display for Module {
             writeln!(f)?;
         }
 
+        let mut enums: Vec<_> = self.enums.iter().collect();
+        enums.sort_by_key(|(name, _)| *name);
+        for (name, def) in enums {
+            writeln!(f, "; Enum {}", name)?;
+            write!(f, "%{} = enum {{ ", name)?;
+            for (i, (vname, data)) in def.variants.iter().enumerate() {
+                if i > 0 { write!(f, " | ")?; }
+                write!(f, "{}", vname)?;
+                match data {
+                    VariantData::Unit => {}
+                    VariantData::Tuple(types) => {
+                        let types: Vec<_> = types.iter().map(|t| t.to_string()).collect();
+                        write!(f, "({})", types.join(", "))?;
+                    }
+                    VariantData::Struct(fields) => {
+                        let fields: Vec<_> = fields.iter().map(|(n, t)| format!("{} {}", t, n)).collect();
+                        write!(f, " {{ {} }}", fields.join(", "))?;
+                    }
+                }
+            }
+            writeln!(f, " }}")?;
+        }
+        if !self.enums.is_empty() {
+            writeln!(f)?;
+        }
+
         // Server config
         if let Some(server) = &self.server {
             writeln!(f, "; Server: {}:{}", server.host, server.port)?;
@@ -194,6 +271,12 @@ pub struct Function {
     pub return_type: IrType,
     /// Whether it is async
     pub is_async: bool,
+    /// Whether it is a `const fn`
+    pub is_const: bool,
+    /// Declared visibility
+    pub visibility: Visibility,
+    /// Optimizer hints from `@inline` and `@cold`, e.g. `inline(always)`
+    pub hints: Vec<String>,
     /// Basic blocks
     pub blocks: Vec<BasicBlock>,
     /// Allocated local variables
@@ -210,6 +293,9 @@ impl Function {
             params: Vec::new(),
             return_type,
             is_async,
+            is_const: false,
+            visibility: Visibility::Private,
+            hints: Vec::new(),
             blocks: Vec::new(),
             locals: HashMap::new(),
             next_temp: 0,
@@ -289,15 +375,18 @@ impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // Signature
         let async_str = if self.is_async { "async " } else { "" };
-        write!(f, "define {}{}@{}(", async_str, self.return_type, self.name)?;
+        let const_str = if self.is_const { "const " } else { "" };
+        write!(f, "define {}{}{}@{}(", async_str, const_str, self.return_type, self.name)?;
         for (i, (name, ty)) in self.params.iter().enumerate() {
             if i > 0 { write!(f, ", ")?; }
             write!(f, "{} %{}", ty, name)?;
         }
         writeln!(f, ") {{")?;
 
-        // Locals
-        for (name, ty) in &self.locals {
+        // Locals, by name
+        let mut locals: Vec<_> = self.locals.iter().collect();
+        locals.sort_by_key(|(name, _)| *name);
+        for (name, ty) in locals {
             writeln!(f, "  %{} = alloca {}", name, ty)?;
         }
         if !self.locals.is_empty() {
@@ -403,6 +492,13 @@ impl WsRoute {
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Maximum request body size in bytes
+    pub max_body: Option<u64>,
+    /// TLS certificate and key paths
+    pub tls_cert: Option<String>,
+    pub tls_key: Option<String>,
+    /// Request timeout in milliseconds
+    pub timeout: Option<u64>,
 }
 
 /// Database configuration
@@ -412,6 +508,8 @@ pub struct DatabaseConfig {
     pub db_type: String, // postgres, mysql, sqlite
     pub url: String,
     pub pool_size: u32,
+    /// Connection timeout in milliseconds
+    pub timeout: Option<u64>,
 }
 
 /// Global (global variable or constant)
@@ -420,7 +518,12 @@ pub struct Global {
     pub name: String,
     pub ty: IrType,
     pub initializer: Option<Value>,
+    /// A compile-time `const` rather than a `static`
     pub is_const: bool,
+    /// A `static mut`, written at runtime
+    pub mutable: bool,
+    /// Declared visibility of a `const`
+    pub visibility: Visibility,
 }
 
 impl Global {
@@ -430,9 +533,16 @@ impl Global {
             ty,
             initializer: None,
             is_const: false,
+            mutable: false,
+            visibility: Visibility::Private,
         }
     }
 
+    pub fn mutable(mut self) -> Self {
+        self.mutable = true;
+        self
+    }
+
     pub fn with_init(mut self, value: Value) -> Self {
         self.initializer = Some(value);
         self
@@ -560,10 +670,533 @@ impl TypeAlias {
     }
 }
 
+/// Error reading back the textual form of a module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IrParseError {
+    /// Line of the text, starting at 1
+    pub line: usize,
+    /// What was expected
+    pub message: String,
+}
+
+impl fmt::Display for IrParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for IrParseError {}
+
+/// Reads back the module name, string table and functions printed by the
+/// `Display` of [`Module`] or by [`Module::to_debug_lines`]. Struct, enum,
+/// route and server lines are skipped.
+///
+/// The text does not tell a float constant with an integral value from an
+/// integer, so `1.0` reads back as `1`.
+pub fn parse_ir_debug(text: &str) -> Result<Module, IrParseError> {
+    let mut module = Module::new("");
+    let mut current: Option<Function> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| IrParseError { line: index + 1, message };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match current.as_mut() {
+            None => {
+                if let Some(name) = line.strip_prefix("; Module:") {
+                    module.name = name.trim_start().to_string();
+                } else if let Some(signature) = line.strip_prefix("define ") {
+                    current = Some(parse_signature(signature).map_err(error)?);
+                } else if let Some(entry) = line.strip_prefix("@str") {
+                    let (_, literal) = entry.split_once(" = ")
+                        .ok_or_else(|| error(format!("expected `@strN = \"...\"`, found `{}`", line)))?;
+                    let mut cursor = Cursor::new(literal);
+                    let value = cursor.quoted().and_then(|value| cursor.finish().map(|_| value)).map_err(error)?;
+                    module.string_table.push(value);
+                }
+            }
+            Some(_) if line == "}" => module.functions.extend(current.take()),
+            Some(func) => {
+                let label = line.strip_suffix(':').filter(|label| !label.contains(' ') && !label.starts_with(['%', ';']));
+                if let Some(label) = label {
+                    func.blocks.push(BasicBlock::new(label));
+                } else if func.blocks.is_empty() {
+                    // Allocas before the first label are the function's locals
+                    let (name, ty) = line.strip_prefix('%')
+                        .and_then(|local| local.split_once(" = alloca "))
+                        .ok_or_else(|| error(format!("expected a local or a block label, found `{}`", line)))?;
+                    func.add_local(name, Cursor::new(ty).whole_type().map_err(error)?);
+                } else {
+                    let inst = parse_instruction(line).map_err(error)?;
+                    if let Some(dest) = inst.dest() {
+                        func.next_temp = func.next_temp.max(dest + 1);
+                    }
+                    func.emit(inst);
+                }
+            }
+        }
+    }
+
+    match current {
+        Some(func) => Err(IrParseError {
+            line: text.lines().count(),
+            message: format!("function `{}` is not closed with `}}`", func.name),
+        }),
+        None => Ok(module),
+    }
+}
+
+/// Reads `[async ][const ]type@name(type %param, ...) {`
+fn parse_signature(signature: &str) -> Result<Function, String> {
+    let mut cursor = Cursor::new(signature);
+    let is_async = cursor.eat("async ");
+    let is_const = cursor.eat("const ");
+    let return_type = cursor.ty()?;
+    cursor.expect("@")?;
+    let name = cursor.take_while(|c| c != '(');
+    cursor.expect("(")?;
+
+    let mut func = Function::new(name, return_type, is_async);
+    func.is_const = is_const;
+    func.blocks.clear();
+    if !cursor.eat(")") {
+        loop {
+            let ty = cursor.ty()?;
+            cursor.expect(" %")?;
+            let param = cursor.take_while(|c| c != ',' && c != ')');
+            func.add_param(param, ty);
+            if cursor.eat(")") {
+                break;
+            }
+            cursor.expect(", ")?;
+        }
+    }
+    cursor.expect(" {")?;
+    cursor.finish()?;
+    Ok(func)
+}
+
+/// Reads one instruction in the form printed by its `Display`
+fn parse_instruction(line: &str) -> Result<Instruction, String> {
+    if let Some(text) = line.strip_prefix(';') {
+        return Ok(Instruction::Comment(text.strip_prefix(' ').unwrap_or(text).to_string()));
+    }
+    if let Some(rest) = line.strip_prefix("store ") {
+        let [value, ptr] = values(rest)?;
+        return Ok(Instruction::Store { value, ptr });
+    }
+    if let Some(rest) = line.strip_prefix("ret ") {
+        return Ok(Instruction::Return(parse_value(rest)?));
+    }
+    if let Some(rest) = line.strip_prefix("br ") {
+        return Ok(match rest.split(", ").collect::<Vec<_>>().as_slice() {
+            [target] => Instruction::Branch { target: target.to_string() },
+            [cond, then_label, else_label] => Instruction::CondBranch {
+                cond: parse_value(cond)?,
+                then_label: then_label.to_string(),
+                else_label: else_label.to_string(),
+            },
+            _ => return Err(format!("expected `br label` or `br cond, then, else`, found `{}`", line)),
+        });
+    }
+    if let Some(rest) = line.strip_prefix("setfield ") {
+        let (field, value) = rest.rsplit_once("), ").ok_or_else(|| format!("expected `setfield ptr %S.i (field), value`, found `{}`", line))?;
+        let (ptr, struct_name, field_index, field_name) = parse_field(&format!("{})", field))?;
+        return Ok(Instruction::SetField { ptr, struct_name, field_index, field_name, value: parse_value(value)? });
+    }
+    if let Some(rest) = line.strip_prefix("setelem ") {
+        let [ptr, index, value] = values(rest)?;
+        return Ok(Instruction::SetElement { ptr, index, value });
+    }
+    if let Some(rest) = line.strip_prefix("call @") {
+        return parse_call(None, rest);
+    }
+
+    let (dest, rest) = line.split_once(" = ").ok_or_else(|| format!("unknown instruction `{}`", line))?;
+    if let Some(ty) = rest.strip_prefix("alloca ") {
+        let dest = dest.strip_prefix('%').ok_or_else(|| format!("expected `%name`, found `{}`", dest))?;
+        return Ok(Instruction::Alloca { dest: dest.to_string(), ty: Cursor::new(ty).whole_type()? });
+    }
+    let dest: u32 = dest.strip_prefix("%t")
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| format!("expected a temporary `%tN`, found `{}`", dest))?;
+
+    let (op, operands) = rest.split_once(' ').unwrap_or((rest, ""));
+    Ok(match op {
+        "load" => {
+            let mut cursor = Cursor::new(operands);
+            let ty = cursor.ty()?;
+            cursor.expect(" ")?;
+            Instruction::Load { dest, ptr: parse_value(cursor.rest)?, ty }
+        }
+        "call" => {
+            let rest = operands.strip_prefix('@').ok_or_else(|| format!("expected `call @name(...)`, found `{}`", line))?;
+            return parse_call(Some(dest), rest);
+        }
+        "cmp" => {
+            let (op, operands) = operands.split_once(' ').ok_or_else(|| format!("expected `cmp op left, right`, found `{}`", line))?;
+            let op = compare_op(op).ok_or_else(|| format!("unknown comparison `{}`", op))?;
+            let [left, right] = values(operands)?;
+            Instruction::Compare { dest, op, left, right }
+        }
+        "not" => Instruction::Not { dest, value: parse_value(operands)? },
+        "neg" => Instruction::Neg { dest, value: parse_value(operands)? },
+        "await" => Instruction::Await { dest, future: parse_value(operands)? },
+        "phi" => {
+            let incoming = operands.strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+                .ok_or_else(|| format!("expected `phi [value, label], ...`, found `{}`", line))?
+                .split("], [")
+                .map(|pair| {
+                    let (value, label) = pair.split_once(", ").ok_or_else(|| format!("expected `[value, label]`, found `[{}]`", pair))?;
+                    Ok((parse_value(value)?, label.to_string()))
+                })
+                .collect::<Result<_, String>>()?;
+            Instruction::Phi { dest, incoming }
+        }
+        "getfield" => {
+            let (ptr, struct_name, field_index, field_name) = parse_field(operands)?;
+            Instruction::GetField { dest, ptr, struct_name, field_index, field_name }
+        }
+        "getelem" => {
+            let (in_bounds, operands) = match operands.strip_prefix("inbounds ") {
+                Some(rest) => (true, rest),
+                None => (false, operands),
+            };
+            let [ptr, index] = values(operands)?;
+            Instruction::GetElement { dest, ptr, index, in_bounds }
+        }
+        "newstruct" => {
+            let struct_name = operands.strip_prefix('%').ok_or_else(|| format!("expected `newstruct %Name`, found `{}`", line))?;
+            Instruction::NewStruct { dest, struct_name: struct_name.to_string() }
+        }
+        "newarray" => {
+            let mut cursor = Cursor::new(operands);
+            let elem_type = cursor.ty()?;
+            cursor.expect(", ")?;
+            Instruction::NewArray { dest, elem_type, size: parse_value(cursor.rest)? }
+        }
+        "cast" => {
+            let (value, ty) = operands.rsplit_once(" to ").ok_or_else(|| format!("expected `cast value to type`, found `{}`", line))?;
+            Instruction::Cast { dest, value: parse_value(value)?, to_type: Cursor::new(ty).whole_type()? }
+        }
+        "format" => {
+            let mut cursor = Cursor::new(operands);
+            let mut parts = Vec::new();
+            while !cursor.rest.is_empty() {
+                if cursor.rest.starts_with('"') {
+                    parts.push(FormatPart::Literal(cursor.quoted()?));
+                } else {
+                    parts.push(FormatPart::Value(parse_value(cursor.take_while(|c| c != ','))?));
+                }
+                if !cursor.rest.is_empty() {
+                    cursor.expect(", ")?;
+                }
+            }
+            Instruction::FormatString { dest, parts }
+        }
+        _ => {
+            let (checked, name) = match op.strip_prefix("checked_") {
+                Some(name) => (true, name),
+                None => (false, op),
+            };
+            let op = binary_op(name).ok_or_else(|| format!("unknown instruction `{}`", line))?;
+            let [left, right] = values(operands)?;
+            if checked {
+                Instruction::CheckedBinary { dest, op, left, right }
+            } else {
+                Instruction::Binary { dest, op, left, right }
+            }
+        }
+    })
+}
+
+/// Reads `name(args...)` after `call @`
+fn parse_call(dest: Option<u32>, rest: &str) -> Result<Instruction, String> {
+    // Arguments never contain parentheses, names may
+    let (func, args) = rest.strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+        .ok_or_else(|| format!("expected `call @name(args)`, found `call @{}`", rest))?;
+    let args = match args {
+        "" => Vec::new(),
+        args => args.split(", ").map(parse_value).collect::<Result<_, _>>()?,
+    };
+    Ok(Instruction::Call { dest, func: func.to_string(), args })
+}
+
+/// Reads `ptr %Struct.index (field)`
+fn parse_field(text: &str) -> Result<(Value, String, usize, String), String> {
+    let malformed = || format!("expected `ptr %Struct.index (field)`, found `{}`", text);
+    let (rest, field_name) = text.strip_suffix(')').and_then(|rest| rest.rsplit_once(" (")).ok_or_else(malformed)?;
+    let (rest, field_index) = rest.rsplit_once('.').ok_or_else(malformed)?;
+    let (ptr, struct_name) = rest.rsplit_once(" %").ok_or_else(malformed)?;
+    let field_index = field_index.parse().map_err(|_| malformed())?;
+    Ok((parse_value(ptr)?, struct_name.to_string(), field_index, field_name.to_string()))
+}
+
+/// Reads exactly `N` comma-separated values
+fn values<const N: usize>(text: &str) -> Result<[Value; N], String> {
+    let values = text.split(", ").map(parse_value).collect::<Result<Vec<_>, _>>()?;
+    let found = values.len();
+    values.try_into().map_err(|_| format!("expected {} operands, found {} in `{}`", N, found, text))
+}
+
+/// Reads a value in the form printed by its `Display`
+fn parse_value(text: &str) -> Result<Value, String> {
+    let index = |digits: &str| digits.parse::<u32>().ok().filter(|_| digits.bytes().all(|b| b.is_ascii_digit()));
+    Ok(match text {
+        "void" => Value::Void,
+        "true" => Value::ConstBool(true),
+        "false" => Value::ConstBool(false),
+        _ => {
+            if let Some(id) = text.strip_prefix("str#") {
+                Value::ConstString(id.parse().map_err(|_| format!("invalid string index `{}`", text))?)
+            } else if let Some(id) = text.strip_prefix("%arg").and_then(index) {
+                Value::Param(id as usize)
+            } else if let Some(id) = text.strip_prefix("%t").and_then(index) {
+                Value::Temp(id)
+            } else if let Some(name) = text.strip_prefix('%') {
+                Value::Local(name.to_string())
+            } else if let Some(name) = text.strip_prefix('@') {
+                Value::Global(name.to_string())
+            } else if let Ok(v) = text.parse::<i64>() {
+                Value::ConstInt(v)
+            } else if let Ok(v) = text.parse::<f64>() {
+                Value::const_float(v)
+            } else {
+                return Err(format!("invalid value `{}`", text));
+            }
+        }
+    })
+}
+
+fn binary_op(name: &str) -> Option<BinaryOp> {
+    Some(match name {
+        "add" => BinaryOp::Add,
+        "sub" => BinaryOp::Sub,
+        "mul" => BinaryOp::Mul,
+        "div" => BinaryOp::Div,
+        "mod" => BinaryOp::Mod,
+        "and" => BinaryOp::And,
+        "or" => BinaryOp::Or,
+        "xor" => BinaryOp::Xor,
+        "shl" => BinaryOp::Shl,
+        "shr" => BinaryOp::Shr,
+        _ => return None,
+    })
+}
+
+fn compare_op(name: &str) -> Option<CompareOp> {
+    Some(match name {
+        "eq" => CompareOp::Eq,
+        "ne" => CompareOp::Ne,
+        "lt" => CompareOp::Lt,
+        "le" => CompareOp::Le,
+        "gt" => CompareOp::Gt,
+        "ge" => CompareOp::Ge,
+        _ => return None,
+    })
+}
+
+/// Position in a line being read
+struct Cursor<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { rest: text }
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        match self.rest.strip_prefix(prefix) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, prefix: &str) -> Result<(), String> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}`, found `{}`", prefix, self.rest))
+        }
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        if self.rest.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unexpected `{}`", self.rest))
+        }
+    }
+
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) -> &'a str {
+        let end = self.rest.find(|c| !keep(c)).unwrap_or(self.rest.len());
+        let (taken, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        taken
+    }
+
+    /// A type that makes up the rest of the text
+    fn whole_type(&mut self) -> Result<IrType, String> {
+        let ty = self.ty()?;
+        self.finish()?;
+        Ok(ty)
+    }
+
+    /// A type in the form printed by its `Display`
+    fn ty(&mut self) -> Result<IrType, String> {
+        let simple = [
+            ("void", IrType::Void),
+            ("i64", IrType::I64),
+            ("f64", IrType::F64),
+            ("i1", IrType::Bool),
+            ("string", IrType::String),
+        ];
+        for (name, ty) in simple {
+            if self.eat(name) {
+                return Ok(ty);
+            }
+        }
+
+        if self.eat("*") {
+            Ok(IrType::Ptr(Box::new(self.ty()?)))
+        } else if self.eat("[") {
+            let size = self.take_while(|c| c.is_ascii_digit()).parse().map_err(|_| format!("expected an array size, found `{}`", self.rest))?;
+            self.expect(" x ")?;
+            let elem = self.ty()?;
+            self.expect("]")?;
+            Ok(IrType::Array(Box::new(elem), size))
+        } else if self.eat("%") {
+            Ok(IrType::Struct(self.struct_name()))
+        } else if self.eat("fn(") {
+            let params = self.types(")")?;
+            self.expect(" -> ")?;
+            Ok(IrType::Function { params, ret: Box::new(self.ty()?) })
+        } else if self.eat("future<") {
+            let inner = self.ty()?;
+            self.expect(">")?;
+            Ok(IrType::Future(Box::new(inner)))
+        } else if self.eat("range<") {
+            let inner = self.ty()?;
+            self.expect(">")?;
+            Ok(IrType::Range(Box::new(inner)))
+        } else if self.eat("generator<") {
+            let inner = self.ty()?;
+            self.expect(">")?;
+            Ok(IrType::Generator(Box::new(inner)))
+        } else if self.eat("option<") {
+            let inner = self.ty()?;
+            self.expect(">")?;
+            Ok(IrType::Option(Box::new(inner)))
+        } else if self.eat("set<") {
+            let inner = self.ty()?;
+            self.expect(">")?;
+            Ok(IrType::Set(Box::new(inner)))
+        } else if self.eat("task<") {
+            let inner = self.ty()?;
+            self.expect(">")?;
+            Ok(IrType::Task(Box::new(inner)))
+        } else if self.eat("result<") {
+            let [ok, err] = self.type_pair()?;
+            Ok(IrType::Result(Box::new(ok), Box::new(err)))
+        } else if self.eat("map<") {
+            let [key, value] = self.type_pair()?;
+            Ok(IrType::Map(Box::new(key), Box::new(value)))
+        } else if self.eat("(") {
+            Ok(IrType::Tuple(self.types(")")?))
+        } else {
+            Err(format!("expected a type, found `{}`", self.rest))
+        }
+    }
+
+    /// The two type arguments of `result<..>` or `map<..>`, after the `<`
+    fn type_pair(&mut self) -> Result<[IrType; 2], String> {
+        let first = self.ty()?;
+        self.expect(", ")?;
+        let second = self.ty()?;
+        self.expect(">")?;
+        Ok([first, second])
+    }
+
+    /// Comma-separated types up to `close`
+    fn types(&mut self, close: &str) -> Result<Vec<IrType>, String> {
+        let mut types = Vec::new();
+        if self.eat(close) {
+            return Ok(types);
+        }
+        loop {
+            types.push(self.ty()?);
+            if self.eat(close) {
+                return Ok(types);
+            }
+            self.expect(", ")?;
+        }
+    }
+
+    /// A struct name, or a trait object such as `dyn Shape`
+    fn struct_name(&mut self) -> String {
+        let end_of_name = |text: &str| text.find([')', ',', '>', ']', ' ', '@']).unwrap_or(text.len());
+        let (mut name, mut rest) = self.rest.split_at(end_of_name(self.rest));
+        if name == "dyn" {
+            if let Some(after) = rest.strip_prefix(' ') {
+                let end = end_of_name(after);
+                name = &self.rest[..end + 4];
+                rest = &after[end..];
+            }
+        }
+        self.rest = rest;
+        name.to_string()
+    }
+
+    /// A double-quoted literal with Rust escapes, as printed by `{:?}` and
+    /// `escape_default`
+    fn quoted(&mut self) -> Result<String, String> {
+        self.expect("\"")?;
+        let mut value = String::new();
+        let mut chars = self.rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[i + 1..];
+                    return Ok(value);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('0') => value.push('\0'),
+                    Some(c @ ('\\' | '\'' | '"')) => value.push(c),
+                    Some('u') => {
+                        let hex: String = chars.by_ref()
+                            .map(|(_, c)| c)
+                            .skip_while(|c| *c == '{')
+                            .take_while(|c| *c != '}')
+                            .collect();
+                        let c = u32::from_str_radix(&hex, 16).ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("invalid escape `\\u{{{}}}`", hex))?;
+                        value.push(c);
+                    }
+                    other => return Err(format!("invalid escape `\\{}`", other.map(String::from).unwrap_or_default())),
+                },
+                c => value.push(c),
+            }
+        }
+        Err(format!("unterminated string `\"{}`", self.rest))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::instruction::BinaryOp;
 
     #[test]
     fn test_function_creation() {
@@ -585,6 +1218,19 @@ mod tests {
         assert_eq!(func.blocks[0].instructions.len(), 2);
     }
 
+    #[test]
+    fn test_enum_for_variant_is_deterministic() {
+        let mut module = Module::new("test");
+        for name in ["Light", "Color", "Signal"] {
+            let mut def = EnumDef::new(name.to_string());
+            def.variants.push(("Red".to_string(), VariantData::Unit));
+            module.add_enum(def);
+        }
+        assert_eq!(module.enum_for_variant("Red").map(|def| def.name.as_str()), Some("Color"));
+        assert!(matches!(module.get_variant("Signal", "Red"), Some(VariantData::Unit)));
+        assert!(module.get_variant("Signal", "Green").is_none());
+    }
+
     #[test]
     fn test_module_display() {
         let mut module = Module::new("test");
@@ -598,4 +1244,101 @@ mod tests {
         assert!(output.contains("; Module: test"));
         assert!(output.contains("define void@main"));
     }
+    fn two_function_module() -> Module {
+        let mut module = Module::new("roundtrip");
+        module.add_string("total: \"{}\"\n".to_string());
+
+        let mut add = Function::new("add", IrType::I64, false);
+        add.add_param("a", IrType::I64);
+        add.add_param("b", IrType::I64);
+        let t0 = add.new_temp();
+        add.emit(Instruction::Binary { dest: t0, op: BinaryOp::Add, left: Value::Param(0), right: Value::Param(1) });
+        add.emit(Instruction::Return(Value::Temp(t0)));
+        module.add_function(add);
+
+        let mut main = Function::new("main", IrType::Void, true);
+        main.add_param("user", IrType::Ptr(Box::new(IrType::Struct("User".to_string()))));
+        main.add_local("total", IrType::I64);
+        main.add_local("items", IrType::Array(Box::new(IrType::F64), 4));
+        main.add_local("lookup", IrType::Map(
+            Box::new(IrType::String),
+            Box::new(IrType::Option(Box::new(IrType::Ptr(Box::new(IrType::I64))))),
+        ));
+        main.add_local("outcome", IrType::Result(Box::new(IrType::Struct("User".to_string())), Box::new(IrType::String)));
+        main.add_local("seen", IrType::Set(Box::new(IrType::Struct("User".to_string()))));
+        main.emit(Instruction::Comment("sum the age twice".to_string()));
+        let age = main.new_temp();
+        main.emit(Instruction::GetField {
+            dest: age,
+            ptr: Value::Param(0),
+            struct_name: "User".to_string(),
+            field_index: 1,
+            field_name: "age".to_string(),
+        });
+        let sum = main.new_temp();
+        main.emit(Instruction::Call { dest: Some(sum), func: "add".to_string(), args: vec![Value::Temp(age), Value::ConstInt(-2)] });
+        main.emit(Instruction::Store { value: Value::Temp(sum), ptr: Value::Local("total".to_string()) });
+        let big = main.new_temp();
+        main.emit(Instruction::Compare { dest: big, op: CompareOp::Gt, left: Value::Temp(sum), right: Value::ConstInt(10) });
+        main.emit(Instruction::CondBranch { cond: Value::Temp(big), then_label: "big".to_string(), else_label: "done".to_string() });
+        main.new_block("big");
+        let text = main.new_temp();
+        main.emit(Instruction::FormatString {
+            dest: text,
+            parts: vec![FormatPart::Literal("total = ".to_string()), FormatPart::Value(Value::Temp(sum))],
+        });
+        main.emit(Instruction::Call { dest: None, func: "println".to_string(), args: vec![Value::Temp(text)] });
+        main.emit(Instruction::Branch { target: "done".to_string() });
+        main.new_block("done");
+        let flag = main.new_temp();
+        main.emit(Instruction::Phi {
+            dest: flag,
+            incoming: vec![(Value::ConstBool(true), "big".to_string()), (Value::ConstBool(false), "entry".to_string())],
+        });
+        let ratio = main.new_temp();
+        main.emit(Instruction::Cast { dest: ratio, value: Value::Temp(sum), to_type: IrType::F64 });
+        main.emit(Instruction::SetElement { ptr: Value::Local("items".to_string()), index: Value::ConstInt(0), value: Value::Temp(ratio) });
+        main.emit(Instruction::Return(Value::Void));
+        module.add_function(main);
+
+        module
+    }
+
+    #[test]
+    fn test_debug_lines_round_trip() {
+        let module = two_function_module();
+        let lines = module.to_debug_lines();
+        assert!(lines.contains(&"define i64@add(i64 %a, i64 %b) {".to_string()));
+        assert!(lines.contains(&"%t1 = call @add(%t0, -2)".to_string()));
+        assert!(lines.contains(&"%lookup = alloca map<string, option<*i64>>".to_string()));
+
+        let parsed = parse_ir_debug(&module.to_string()).unwrap();
+        assert_eq!(parsed.name, "roundtrip");
+        assert_eq!(parsed.string_table, module.string_table);
+        assert_eq!(parsed.functions.len(), 2);
+        assert!(parsed.functions[1].is_async);
+        assert_eq!(parsed.functions[1].blocks.len(), 3);
+        assert_eq!(parsed.to_debug_lines(), lines);
+
+        // The trimmed lines read back the same way
+        let reparsed = parse_ir_debug(&lines.join("\n")).unwrap();
+        assert_eq!(reparsed.to_debug_lines(), lines);
+    }
+
+    #[test]
+    fn test_parse_ir_debug_continues_temporaries() {
+        let mut parsed = parse_ir_debug(&two_function_module().to_string()).unwrap();
+        let main = parsed.get_function_mut("main").unwrap();
+        assert_eq!(main.new_temp(), 6);
+    }
+
+    #[test]
+    fn test_parse_ir_debug_reports_line() {
+        let err = parse_ir_debug("define i64@f() {\nentry:\n  %t0 = frob 1, 2\n}").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert!(err.message.contains("frob"), "{}", err);
+
+        let err = parse_ir_debug("define i64@f() {\nentry:\n  ret 1").unwrap_err();
+        assert!(err.message.contains("not closed"), "{}", err);
+    }
 }
@@ -0,0 +1,181 @@
+//! IR optimization passes
+//!
+//! Peephole passes that clean up the control flow graph left by lowering.
+//! They run from `opt_level >= 1` and never change what a function computes.
+
+use crate::instruction::Instruction;
+use crate::module::{Function, Module};
+
+/// Label prefixes the backends use to recover structured loops; blocks
+/// carrying them are never merged away or removed
+const LOOP_PREFIXES: &[&str] = &["for_", "while_", "iter_", "whilelet_"];
+
+/// Runs the passes enabled at `opt_level` on every function of the module
+pub fn optimize(module: &mut Module, opt_level: u8) {
+    if opt_level >= 1 {
+        for func in &mut module.functions {
+            merge_blocks(func);
+        }
+    }
+}
+
+/// Removes empty blocks that only branch elsewhere and joins each block with
+/// its sole successor when that successor has no other predecessor
+pub fn merge_blocks(func: &mut Function) {
+    while remove_forwarding_block(func) || merge_into_predecessor(func) {}
+}
+
+fn is_pinned(label: &str) -> bool {
+    LOOP_PREFIXES.iter().any(|prefix| label.starts_with(prefix))
+}
+
+/// Labels that instructions anywhere in the function branch to
+fn branch_targets(func: &Function) -> impl Iterator<Item = &str> {
+    func.blocks.iter()
+        .flat_map(|block| &block.instructions)
+        .flat_map(|inst| match inst {
+            Instruction::Branch { target } => vec![target.as_str()],
+            Instruction::CondBranch { then_label, else_label, .. } => vec![then_label.as_str(), else_label.as_str()],
+            _ => Vec::new(),
+        })
+}
+
+/// Points every branch to `from` at `to` instead
+fn retarget(func: &mut Function, from: &str, to: &str) {
+    for inst in func.blocks.iter_mut().flat_map(|block| &mut block.instructions) {
+        match inst {
+            Instruction::Branch { target } if target == from => *target = to.to_string(),
+            Instruction::CondBranch { then_label, else_label, .. } => {
+                if then_label == from {
+                    *then_label = to.to_string();
+                }
+                if else_label == from {
+                    *else_label = to.to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Removes one block whose only instruction is an unconditional branch
+fn remove_forwarding_block(func: &mut Function) -> bool {
+    let found = func.blocks.iter().enumerate().skip(1).find_map(|(index, block)| match block.instructions.as_slice() {
+        [Instruction::Branch { target }] if *target != block.label && !is_pinned(&block.label) => {
+            Some((index, block.label.clone(), target.clone()))
+        }
+        _ => None,
+    });
+    let Some((index, label, target)) = found else {
+        return false;
+    };
+
+    // Phi nodes in the target name their incoming blocks
+    let target_has_phi = func.blocks.iter()
+        .filter(|block| block.label == target)
+        .flat_map(|block| &block.instructions)
+        .any(|inst| matches!(inst, Instruction::Phi { .. }));
+    if target_has_phi {
+        return false;
+    }
+
+    func.blocks.remove(index);
+    retarget(func, &label, &target);
+    true
+}
+
+/// Appends one block to its predecessor when the predecessor ends by
+/// jumping to it and nothing else jumps there
+fn merge_into_predecessor(func: &mut Function) -> bool {
+    let found = func.blocks.iter().enumerate().find_map(|(pred, block)| {
+        let Some(Instruction::Branch { target }) = block.instructions.last() else {
+            return None;
+        };
+        // Earlier terminators in the block may lead elsewhere too
+        let exits = block.instructions.iter().filter(|inst| inst.is_terminator()).count();
+        if exits != 1 || *target == block.label || is_pinned(target) {
+            return None;
+        }
+        let succ = func.blocks.iter().position(|b| b.label == *target)?;
+        let preds = branch_targets(func).filter(|label| label == target).count();
+        (succ != 0 && preds == 1).then_some((pred, succ))
+    });
+    let Some((pred, succ)) = found else {
+        return false;
+    };
+
+    let block = func.blocks.remove(succ);
+    let pred = if succ < pred { pred - 1 } else { pred };
+    let pred_label = func.blocks[pred].label.clone();
+    func.blocks[pred].instructions.pop();
+    func.blocks[pred].instructions.extend(block.instructions);
+
+    // Successors of the merged block now come from its predecessor
+    for inst in func.blocks.iter_mut().flat_map(|b| &mut b.instructions) {
+        if let Instruction::Phi { incoming, .. } = inst {
+            for (_, label) in incoming.iter_mut().filter(|(_, label)| *label == block.label) {
+                *label = pred_label.clone();
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::Value;
+    use crate::lower::lower_program;
+    use crate::types::IrType;
+    use mendes_lexer::Lexer;
+    use mendes_parser::parse;
+
+    fn lower_source(source: &str) -> Module {
+        let mut lexer = Lexer::new(source, 0);
+        let tokens = lexer.tokenize();
+        let (program, _) = parse(tokens);
+        lower_program(&program)
+    }
+
+    #[test]
+    fn test_merge_blocks_after_if_else() {
+        let source = r#"fn sign(x: int) -> int:
+    if x > 0:
+        return 1
+    else:
+        let y = x * 2
+    return 0
+"#;
+        let mut module = lower_source(source);
+        let before = module.functions[0].blocks.len();
+
+        optimize(&mut module, 0);
+        assert_eq!(module.functions[0].blocks.len(), before);
+
+        optimize(&mut module, 1);
+        let func = &module.functions[0];
+        assert!(func.blocks.len() < before);
+        // The join block only had the else branch as predecessor
+        assert!(func.blocks.iter().all(|block| !block.label.starts_with("endif")));
+        assert!(matches!(func.blocks.last().unwrap().instructions.last(), Some(Instruction::Return(_))));
+    }
+
+    #[test]
+    fn test_remove_forwarding_block() {
+        let mut func = Function::new("f", IrType::Void, false);
+        func.emit(Instruction::CondBranch {
+            cond: Value::ConstBool(true),
+            then_label: "a".to_string(),
+            else_label: "b".to_string(),
+        });
+        func.new_block("a");
+        func.emit(Instruction::Branch { target: "b".to_string() });
+        func.new_block("b");
+        func.emit(Instruction::Return(Value::Void));
+
+        merge_blocks(&mut func);
+        assert_eq!(func.blocks.len(), 2);
+        assert!(matches!(&func.blocks[0].instructions[0],
+            Instruction::CondBranch { then_label, else_label, .. } if then_label == "b" && else_label == "b"));
+    }
+}
@@ -34,6 +34,18 @@ pub enum IrType {
     Tuple(Vec<IrType>),
     /// Range type (for iterating)
     Range(Box<IrType>),
+    /// Lazy sequence produced by a generator function
+    Generator(Box<IrType>),
+    /// `Option<T>`
+    Option(Box<IrType>),
+    /// `Result<T, E>`
+    Result(Box<IrType>, Box<IrType>),
+    /// `Map<K, V>`
+    Map(Box<IrType>, Box<IrType>),
+    /// `Set<T>`
+    Set(Box<IrType>),
+    /// `Task<T>`, the handle of a spawned future
+    Task(Box<IrType>),
 }
 
 impl IrType {
@@ -52,6 +64,11 @@ impl IrType {
             IrType::Future(_) => 8, // pointer to state machine
             IrType::Tuple(elems) => elems.iter().map(|e| e.size_bytes()).sum(),
             IrType::Range(_) => 24, // start + end + inclusive flag
+            IrType::Generator(_) => 8, // pointer to the producer
+            IrType::Option(inner) => inner.size_bytes() + 1, // payload + tag
+            IrType::Result(ok, err) => ok.size_bytes().max(err.size_bytes()) + 1,
+            IrType::Map(..) | IrType::Set(_) => 8, // pointer to the table
+            IrType::Task(_) => 8, // handle to the running task
         }
     }
 
@@ -62,7 +79,7 @@ impl IrType {
 
     /// Checks if it is a pointer
     pub fn is_pointer(&self) -> bool {
-        matches!(self, IrType::Ptr(_) | IrType::String | IrType::Future(_))
+        matches!(self, IrType::Ptr(_) | IrType::String | IrType::Future(_) | IrType::Generator(_))
     }
 
     /// Converts from MendesType to IrType
@@ -74,13 +91,13 @@ impl IrType {
             mendes_parser::Type::String => IrType::String,
             mendes_parser::Type::Named(name) => IrType::Struct(name.clone()),
             mendes_parser::Type::Generic { name, args } => {
+                let arg = |i: usize| Box::new(args.get(i).map(Self::from_mendes_type).unwrap_or(IrType::Void));
                 match name.as_str() {
-                    "Result" | "Option" => {
-                        // Result<T, E> and Option<T> are represented as tagged unions
-                        // For simplicity, we use a struct
-                        IrType::Struct(format!("{}_{}", name,
-                            args.iter().map(|a| format!("{:?}", a)).collect::<Vec<_>>().join("_")))
-                    }
+                    "Option" => IrType::Option(arg(0)),
+                    "Result" => IrType::Result(arg(0), arg(1)),
+                    "Map" => IrType::Map(arg(0), arg(1)),
+                    "Set" => IrType::Set(arg(0)),
+                    "Task" => IrType::Task(arg(0)),
                     _ => IrType::Struct(name.clone()),
                 }
             }
@@ -103,6 +120,8 @@ impl IrType {
                     ret: Box::new(Self::from_mendes_type(return_type)),
                 }
             }
+            // Trait objects are boxed; encoded by name
+            mendes_parser::Type::Dyn(trait_name) => IrType::Struct(format!("dyn {}", trait_name)),
         }
     }
 }
@@ -136,6 +155,12 @@ impl fmt::Display for IrType {
                 write!(f, ")")
             }
             IrType::Range(inner) => write!(f, "range<{}>", inner),
+            IrType::Generator(inner) => write!(f, "generator<{}>", inner),
+            IrType::Option(inner) => write!(f, "option<{}>", inner),
+            IrType::Result(ok, err) => write!(f, "result<{}, {}>", ok, err),
+            IrType::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            IrType::Set(inner) => write!(f, "set<{}>", inner),
+            IrType::Task(inner) => write!(f, "task<{}>", inner),
         }
     }
 }
@@ -172,11 +197,17 @@ pub struct StructDef {
     pub fields: Vec<(String, IrType)>,
     /// Method names (the actual methods are stored as separate functions)
     pub methods: Vec<String>,
+    /// Whether the struct is hashed as a map key or set element
+    pub derive_hash: bool,
+    /// Traits requested with `@derive(...)`
+    pub derives: Vec<String>,
+    /// Declared visibility
+    pub visibility: mendes_parser::Visibility,
 }
 
 impl StructDef {
     pub fn new(name: String) -> Self {
-        Self { name, generic_params: Vec::new(), fields: Vec::new(), methods: Vec::new() }
+        Self { name, generic_params: Vec::new(), fields: Vec::new(), methods: Vec::new(), derive_hash: false, derives: Vec::new(), visibility: mendes_parser::Visibility::Private }
     }
 
     pub fn with_generics(mut self, params: Vec<GenericParam>) -> Self {
@@ -209,6 +240,43 @@ impl StructDef {
     }
 }
 
+/// Data carried by an enum variant
+#[derive(Debug, Clone)]
+pub enum VariantData {
+    Unit,
+    Tuple(Vec<IrType>),
+    Struct(Vec<(String, IrType)>),
+}
+
+/// Enum definition in IR
+#[derive(Debug, Clone)]
+pub struct EnumDef {
+    pub name: String,
+    /// Generic type parameters
+    pub generic_params: Vec<GenericParam>,
+    pub variants: Vec<(String, VariantData)>,
+    /// Integer value of each variant, when no variant carries data
+    pub discriminants: Option<Vec<i64>>,
+}
+
+impl EnumDef {
+    pub fn new(name: String) -> Self {
+        Self { name, generic_params: Vec::new(), variants: Vec::new(), discriminants: None }
+    }
+
+    pub fn add_generic_param(&mut self, param: GenericParam) {
+        self.generic_params.push(param);
+    }
+
+    pub fn add_variant(&mut self, name: String, data: VariantData) {
+        self.variants.push((name, data));
+    }
+
+    pub fn has_variant(&self, name: &str) -> bool {
+        self.variants.iter().any(|(n, _)| n == name)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -27,8 +27,8 @@ pub struct Lexer<'src> {
     file_id: u32,
     /// Stack of indentation levels
     indent_stack: Vec<u32>,
-    /// Pending DEDENT tokens to emit
-    pending_dedents: u32,
+    /// Pending DEDENT tokens to emit, located at the line that closed the blocks
+    pending_dedents: Vec<Token>,
     /// Whether we are at the start of a line (to process indentation)
     at_line_start: bool,
     /// Accumulated diagnostics
@@ -47,7 +47,7 @@ impl<'src> Lexer<'src> {
             offset: 0,
             file_id,
             indent_stack: vec![0], // Start with indentation 0
-            pending_dedents: 0,
+            pending_dedents: Vec::new(),
             at_line_start: true,
             diagnostics: Diagnostics::new(),
         }
@@ -291,6 +291,31 @@ impl<'src> Lexer<'src> {
             }
         }
 
+        // Time-unit suffix: `30s`, `500ms`, `5m`, `1h`
+        if !is_float {
+            let suffix: String = self.chars[self.pos..].iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .collect();
+            if let Some(unit_ms) = duration_unit_ms(&suffix) {
+                for _ in 0..suffix.len() {
+                    self.advance();
+                }
+                let span = self.make_span(start);
+                let millis = num_str.parse::<u64>().ok().and_then(|n| n.checked_mul(unit_ms));
+                return match millis {
+                    Some(millis) => Token::new(TokenKind::DurationLit(millis), span),
+                    None => {
+                        self.diagnostics.push(
+                            Diagnostic::error("invalid duration")
+                                .with_code(ErrorCode::INVALID_NUMBER)
+                                .with_label(span, "duration is too large"),
+                        );
+                        Token::new(TokenKind::DurationLit(0), span)
+                    }
+                };
+            }
+        }
+
         let span = self.make_span(start);
 
         if is_float {
@@ -455,6 +480,17 @@ impl<'src> Lexer<'src> {
                         None => break,
                     }
                 }
+                // `{{` and `}}` are literal braces
+                Some('{') if self.peek_next() == Some('{') => {
+                    current_lit.push('{');
+                    self.advance();
+                    self.advance();
+                }
+                Some('}') if self.peek_next() == Some('}') => {
+                    current_lit.push('}');
+                    self.advance();
+                    self.advance();
+                }
                 Some('{') => {
                     self.advance(); // Consume {
                     // Read the expression until }
@@ -507,9 +543,8 @@ impl<'src> Lexer<'src> {
     /// Reads the next token
     pub fn next_token(&mut self) -> Token {
         // First, check for pending DEDENTs
-        if self.pending_dedents > 0 {
-            self.pending_dedents -= 1;
-            return Token::new(TokenKind::Dedent, Span::point(self.current_position(), self.file_id));
+        if let Some(token) = self.pending_dedents.pop() {
+            return token;
         }
 
         // Skip comments
@@ -523,10 +558,11 @@ impl<'src> Lexer<'src> {
             }
 
             if self.peek().is_some() {
-                let indent_tokens = self.handle_indentation();
-                if let Some(token) = indent_tokens.into_iter().next() {
-                    // If there is more than one DEDENT, store the pending ones
-                    // (simplification - in practice would need a queue)
+                let mut indent_tokens = self.handle_indentation().into_iter();
+                if let Some(token) = indent_tokens.next() {
+                    // Closing several blocks at once yields more than one
+                    // DEDENT; the extra ones are emitted on the following calls
+                    self.pending_dedents.extend(indent_tokens);
                     return token;
                 }
             }
@@ -678,6 +714,7 @@ impl<'src> Lexer<'src> {
                 }
             }
             ',' => TokenKind::Comma,
+            ';' => TokenKind::Semicolon,
             '.' => {
                 if self.peek() == Some('.') {
                     self.advance();
@@ -699,6 +736,7 @@ impl<'src> Lexer<'src> {
             ']' => TokenKind::RBracket,
             '|' => TokenKind::Pipe,
             '$' => TokenKind::Dollar,
+            '@' => TokenKind::At,
             '?' => TokenKind::Question,
             _ => {
                 let span = self.make_span(start);
@@ -732,6 +770,17 @@ impl<'src> Lexer<'src> {
     }
 }
 
+/// Milliseconds in one unit of a duration literal suffix
+fn duration_unit_ms(suffix: &str) -> Option<u64> {
+    match suffix {
+        "ms" => Some(1),
+        "s" => Some(1_000),
+        "m" => Some(60_000),
+        "h" => Some(3_600_000),
+        _ => None,
+    }
+}
+
 /// Tokenizes source code and returns the tokens
 pub fn tokenize(source: &str, file_id: u32) -> (Vec<Token>, Diagnostics) {
     let mut lexer = Lexer::new(source, file_id);
@@ -769,6 +818,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_duration_literals() {
+        let tokens = lex("30s 500ms 5m 1h 2 max 3.5");
+        assert_eq!(
+            tokens,
+            vec![
+                TokenKind::DurationLit(30_000),
+                TokenKind::DurationLit(500),
+                TokenKind::DurationLit(300_000),
+                TokenKind::DurationLit(3_600_000),
+                TokenKind::IntLit(2),
+                TokenKind::Ident("max".into()),
+                TokenKind::FloatLit(3.5),
+            ]
+        );
+    }
+
     #[test]
     fn test_keywords() {
         let tokens = lex("fn async await return");
@@ -785,7 +851,7 @@ mod tests {
 
     #[test]
     fn test_http_keywords() {
-        let tokens = lex("api GET POST server middleware");
+        let tokens = lex("api GET POST server middleware group");
         assert_eq!(
             tokens,
             vec![
@@ -794,6 +860,7 @@ mod tests {
                 TokenKind::Post,
                 TokenKind::Server,
                 TokenKind::Middleware,
+                TokenKind::Group,
             ]
         );
     }
@@ -854,6 +921,43 @@ mod tests {
         assert!(tokens.contains(&TokenKind::Dedent));
     }
 
+    #[test]
+    fn test_dedent_positions() {
+        // Both blocks close at `w`, so both DEDENTs point at its line
+        let source = "if x:\n    if y:\n        z\nw";
+        let tokens = Lexer::new(source, 0).tokenize();
+        let dedents: Vec<_> = tokens.iter()
+            .filter(|t| t.kind == TokenKind::Dedent)
+            .map(|t| (t.span.start.line, t.span.start.column))
+            .collect();
+        assert_eq!(dedents, vec![(4, 1), (4, 1)]);
+    }
+
+    #[test]
+    fn test_closing_nested_blocks_emits_every_dedent() {
+        // Three blocks close at `w`: all three DEDENTs come before it
+        let source = "a:\n    b:\n        c:\n            d\nw";
+        let kinds: Vec<_> = Lexer::new(source, 0).tokenize().into_iter()
+            .map(|t| t.kind)
+            .filter(|kind| !matches!(kind, TokenKind::Newline | TokenKind::Colon))
+            .collect();
+        let w = kinds.iter().position(|kind| *kind == TokenKind::Ident("w".to_string())).unwrap();
+        assert_eq!(&kinds[w - 3..w], &[TokenKind::Dedent, TokenKind::Dedent, TokenKind::Dedent]);
+        assert_eq!(
+            kinds.iter().filter(|kind| **kind == TokenKind::Indent).count(),
+            kinds.iter().filter(|kind| **kind == TokenKind::Dedent).count()
+        );
+    }
+
+    #[test]
+    fn test_interpolation_brace_escapes() {
+        let tokens = lex(r#"f"{{x}} = {x}""#);
+        assert_eq!(
+            tokens,
+            vec![TokenKind::InterpolatedString(vec![("{x} = ".into(), "x".into())])]
+        );
+    }
+
     #[test]
     fn test_borrow() {
         let tokens = lex("&user &mut user");
@@ -6,7 +6,12 @@ use mendes_error::span::Span;
 use std::fmt;
 
 /// All token types for the Mendes language
+///
+/// With the `serde` feature a kind serializes as `{"kind": "Ident", "value": "x"}`,
+/// tagged by the variant name; kinds without data such as `Indent`, `Dedent`,
+/// `Newline` and `Eof` carry no `value`.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "kind", content = "value"))]
 pub enum TokenKind {
     // =========================================
     // Keywords - Control flow
@@ -19,6 +24,8 @@ pub enum TokenKind {
     Fn,
     /// `return` - function return
     Return,
+    /// `yield` - produces the next item of a generator
+    Yield,
     /// `if` - conditional
     If,
     /// `else` - if alternative
@@ -61,6 +68,8 @@ pub enum TokenKind {
     Server,
     /// `middleware` - middleware definition
     Middleware,
+    /// `group` - route group with a shared prefix
+    Group,
     /// `use` - apply middleware
     Use,
     /// `body` - HTTP request body
@@ -133,6 +142,8 @@ pub enum TokenKind {
     Is,
     /// `copy` - struct modifier
     Copy,
+    /// `const` - compile-time function modifier
+    Const,
 
     // =========================================
     // Primitive types (keywords)
@@ -157,6 +168,8 @@ pub enum TokenKind {
     IntLit(i64),
     /// Float literal: `3.14`, `2.5e10`
     FloatLit(f64),
+    /// Duration literal in milliseconds: `30s`, `500ms`, `5m`, `1h`
+    DurationLit(u64),
     /// String literal: `"hello"`
     StringLit(String),
     /// Interpolated string: f"hello {name}"
@@ -239,6 +252,8 @@ pub enum TokenKind {
     ColonColon,
     /// `,`
     Comma,
+    /// `;` - separates value and length in `[value; count]`
+    Semicolon,
     /// `.`
     Dot,
     /// `..`
@@ -265,6 +280,8 @@ pub enum TokenKind {
     Pipe,
     /// `$`
     Dollar,
+    /// `@`
+    At,
     /// `?`
     Question,
 
@@ -300,6 +317,7 @@ impl TokenKind {
                 | TokenKind::Mut
                 | TokenKind::Fn
                 | TokenKind::Return
+                | TokenKind::Yield
                 | TokenKind::If
                 | TokenKind::Else
                 | TokenKind::For
@@ -317,6 +335,7 @@ impl TokenKind {
                 | TokenKind::Ws
                 | TokenKind::Server
                 | TokenKind::Middleware
+                | TokenKind::Group
                 | TokenKind::Use
                 | TokenKind::Body
                 | TokenKind::Query
@@ -343,6 +362,7 @@ impl TokenKind {
                 | TokenKind::Is
                 | TokenKind::As
                 | TokenKind::Copy
+                | TokenKind::Const
                 | TokenKind::And
                 | TokenKind::Or
                 | TokenKind::Not
@@ -353,7 +373,11 @@ impl TokenKind {
     pub fn is_literal(&self) -> bool {
         matches!(
             self,
-            TokenKind::IntLit(_) | TokenKind::FloatLit(_) | TokenKind::StringLit(_) | TokenKind::InterpolatedString(_)
+            TokenKind::IntLit(_)
+                | TokenKind::FloatLit(_)
+                | TokenKind::DurationLit(_)
+                | TokenKind::StringLit(_)
+                | TokenKind::InterpolatedString(_)
         )
     }
 
@@ -365,6 +389,7 @@ impl TokenKind {
             "mut" => Some(TokenKind::Mut),
             "fn" => Some(TokenKind::Fn),
             "return" => Some(TokenKind::Return),
+            "yield" => Some(TokenKind::Yield),
             "if" => Some(TokenKind::If),
             "else" => Some(TokenKind::Else),
             "for" => Some(TokenKind::For),
@@ -386,6 +411,7 @@ impl TokenKind {
             "ws" => Some(TokenKind::Ws),
             "server" => Some(TokenKind::Server),
             "middleware" => Some(TokenKind::Middleware),
+            "group" => Some(TokenKind::Group),
             "use" => Some(TokenKind::Use),
             "body" => Some(TokenKind::Body),
             "query" => Some(TokenKind::Query),
@@ -422,6 +448,7 @@ impl TokenKind {
             "is" => Some(TokenKind::Is),
             "as" => Some(TokenKind::As),
             "copy" => Some(TokenKind::Copy),
+            "const" => Some(TokenKind::Const),
 
             // Primitive types
             "int" => Some(TokenKind::IntType),
@@ -449,6 +476,7 @@ impl fmt::Display for TokenKind {
             TokenKind::Mut => write!(f, "mut"),
             TokenKind::Fn => write!(f, "fn"),
             TokenKind::Return => write!(f, "return"),
+            TokenKind::Yield => write!(f, "yield"),
             TokenKind::If => write!(f, "if"),
             TokenKind::Else => write!(f, "else"),
             TokenKind::For => write!(f, "for"),
@@ -466,6 +494,7 @@ impl fmt::Display for TokenKind {
             TokenKind::Ws => write!(f, "ws"),
             TokenKind::Server => write!(f, "server"),
             TokenKind::Middleware => write!(f, "middleware"),
+            TokenKind::Group => write!(f, "group"),
             TokenKind::Use => write!(f, "use"),
             TokenKind::Body => write!(f, "body"),
             TokenKind::Query => write!(f, "query"),
@@ -492,6 +521,7 @@ impl fmt::Display for TokenKind {
             TokenKind::Is => write!(f, "is"),
             TokenKind::As => write!(f, "as"),
             TokenKind::Copy => write!(f, "copy"),
+            TokenKind::Const => write!(f, "const"),
             TokenKind::IntType => write!(f, "int"),
             TokenKind::FloatType => write!(f, "float"),
             TokenKind::BoolType => write!(f, "bool"),
@@ -502,6 +532,7 @@ impl fmt::Display for TokenKind {
             // Literals
             TokenKind::IntLit(n) => write!(f, "{}", n),
             TokenKind::FloatLit(n) => write!(f, "{}", n),
+            TokenKind::DurationLit(ms) => write!(f, "{}ms", ms),
             TokenKind::StringLit(s) => write!(f, "\"{}\"", s),
             TokenKind::InterpolatedString(parts) => {
                 write!(f, "f\"")?;
@@ -543,6 +574,7 @@ impl fmt::Display for TokenKind {
             TokenKind::Colon => write!(f, ":"),
             TokenKind::ColonColon => write!(f, "::"),
             TokenKind::Comma => write!(f, ","),
+            TokenKind::Semicolon => write!(f, ";"),
             TokenKind::Dot => write!(f, "."),
             TokenKind::DotDot => write!(f, ".."),
             TokenKind::DotDotEq => write!(f, "..="),
@@ -556,6 +588,7 @@ impl fmt::Display for TokenKind {
             TokenKind::RAngle => write!(f, ">"),
             TokenKind::Pipe => write!(f, "|"),
             TokenKind::Dollar => write!(f, "$"),
+            TokenKind::At => write!(f, "@"),
             TokenKind::Question => write!(f, "?"),
 
             // Special
@@ -570,8 +603,10 @@ impl fmt::Display for TokenKind {
 
 /// A token with its location in the source code
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     /// Token type
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub kind: TokenKind,
     /// Location in source code
     pub span: Span,
@@ -1,5 +1,6 @@
 //! AST - Abstract Syntax Tree for the Mendes language
 
+use crate::visit::{self, Visitor};
 use mendes_error::Span;
 
 /// Complete program (source file)
@@ -34,6 +35,33 @@ pub enum Stmt {
         span: Span,
     },
 
+    /// `let (a, b) = expr`, binding the elements of a tuple; `_` skips one
+    LetTuple {
+        names: Vec<String>,
+        value: Expr,
+        mutable: bool,
+        span: Span,
+    },
+
+    /// Module-level state shared by all functions and handlers:
+    /// `static mut counter: int = 0`
+    Static {
+        name: String,
+        ty: Type,
+        value: Expr,
+        mutable: bool,
+        span: Span,
+    },
+
+    /// Compile-time constant: `const MAX: int = 100`
+    Const {
+        name: String,
+        ty: Type,
+        value: Expr,
+        visibility: Visibility,
+        span: Span,
+    },
+
     /// Function declaration
     Fn(FnDecl),
 
@@ -62,6 +90,9 @@ pub enum Stmt {
     /// WebSocket endpoint declaration
     WebSocket(WsDecl),
 
+    /// Route group declaration
+    Group(GroupDecl),
+
     /// Server declaration
     Server(ServerDecl),
 
@@ -79,6 +110,16 @@ pub enum Stmt {
         span: Span,
     },
 
+    /// `if let Shape::Circle(r) = s: ... else: ...`; the then block runs,
+    /// with the pattern's bindings, when the value matches
+    IfLet {
+        pattern: Pattern,
+        value: Expr,
+        then_block: Vec<Stmt>,
+        else_block: Option<Vec<Stmt>>,
+        span: Span,
+    },
+
     /// `for x in items: ...`
     For {
         var: String,
@@ -94,12 +135,42 @@ pub enum Stmt {
         span: Span,
     },
 
+    /// `while let Some(x) = next(): ...`; loops while the value matches
+    WhileLet {
+        pattern: Pattern,
+        value: Expr,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+
+    /// `try: ... catch err: ...`; a failing `?` in the body runs the handler
+    TryCatch {
+        body: Vec<Stmt>,
+        error_var: String,
+        handler: Vec<Stmt>,
+        span: Span,
+    },
+
+    /// `with resource as name: ...`; `name.close()` runs when the block exits
+    With {
+        resource: Expr,
+        name: String,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+
     /// `return expr`
     Return {
         value: Option<Expr>,
         span: Span,
     },
 
+    /// `yield expr` - produces the next item of a generator function
+    Yield {
+        value: Expr,
+        span: Span,
+    },
+
     /// `break` - exit loop
     Break { span: Span },
 
@@ -108,6 +179,116 @@ pub enum Stmt {
 
     /// Expression as statement
     Expr(Expr),
+
+    /// Declaration preceded by attributes, e.g. `@cfg(debug)`
+    Attributed {
+        attrs: Vec<Attribute>,
+        item: Box<Stmt>,
+        span: Span,
+    },
+}
+
+impl Stmt {
+    /// Returns the source span of the statement
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Import { span, .. }
+            | Stmt::FromImport { span, .. }
+            | Stmt::Let { span, .. }
+            | Stmt::LetTuple { span, .. }
+            | Stmt::Static { span, .. }
+            | Stmt::Const { span, .. }
+            | Stmt::TypeAlias { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::IfLet { span, .. }
+            | Stmt::For { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::WhileLet { span, .. }
+            | Stmt::TryCatch { span, .. }
+            | Stmt::With { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Yield { span, .. }
+            | Stmt::Break { span }
+            | Stmt::Continue { span }
+            | Stmt::Attributed { span, .. } => *span,
+            Stmt::Fn(f) => f.span,
+            Stmt::Struct(s) => s.span,
+            Stmt::Enum(e) => e.span,
+            Stmt::Trait(t) => t.span,
+            Stmt::ImplTrait(i) => i.span,
+            Stmt::Api(api) => api.span,
+            Stmt::WebSocket(ws) => ws.span,
+            Stmt::Group(group) => group.span,
+            Stmt::Server(server) => server.span,
+            Stmt::Middleware(m) => m.span,
+            Stmt::Db(db) => db.span,
+            Stmt::Expr(expr) => expr.span(),
+        }
+    }
+
+    /// Returns the statement with any attributes stripped
+    pub fn unattributed(&self) -> &Stmt {
+        match self {
+            Stmt::Attributed { item, .. } => item.unattributed(),
+            other => other,
+        }
+    }
+
+    /// Returns the route declared by `@route(METHOD, "/path")` on a function,
+    /// as the equivalent `api` block
+    pub fn attributed_route(&self) -> Option<ApiDecl> {
+        let Stmt::Attributed { attrs, item, .. } = self else {
+            return None;
+        };
+        let attr = attrs.iter().find(|attr| attr.name == "route")?;
+        let (Stmt::Fn(f), [method, path]) = (item.unattributed(), attr.args.as_slice()) else {
+            return None;
+        };
+        Some(ApiDecl {
+            method: HttpMethod::from_name(method)?,
+            path: path.clone(),
+            is_async: f.is_async,
+            middlewares: Vec::new(),
+            body_type: f.params.iter().find(|p| p.name == "body").map(|p| p.ty.clone()),
+            return_type: f.return_type.clone(),
+            handler: f.body.clone(),
+            span: f.span,
+        })
+    }
+}
+
+/// Attribute on a declaration: `@name` or `@name(arg, ...)`
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<String>,
+    pub span: Span,
+}
+
+/// Traits a struct can request with `@derive(...)`
+pub const DERIVABLE_TRAITS: &[&str] = &[
+    "Debug", "Clone", "Copy", "Default", "PartialEq", "Eq", "Hash", "PartialOrd", "Ord",
+];
+
+impl Attribute {
+    /// Whether this is an optimizer hint on a function: `@inline` or `@cold`
+    pub fn is_hint(&self) -> bool {
+        self.name == "inline" || self.name == "cold"
+    }
+
+    /// The derivable traits listed by `@derive(...)`; unknown names are skipped
+    pub fn derives(&self) -> impl Iterator<Item = &str> {
+        self.args.iter()
+            .map(String::as_str)
+            .filter(|name| self.name == "derive" && DERIVABLE_TRAITS.contains(name))
+    }
+
+    /// The lint names listed by `@allow(...)`, unknown ones included
+    pub fn allowed_lints(&self) -> impl Iterator<Item = &str> {
+        self.args.iter()
+            .map(String::as_str)
+            .filter(|_| self.name == "allow")
+    }
 }
 
 /// Function declaration
@@ -118,11 +299,41 @@ pub struct FnDecl {
     pub params: Vec<Param>,
     pub return_type: Option<Type>,
     pub is_async: bool,
-    pub is_pub: bool,
+    /// `const fn`: callable in constant positions such as array lengths
+    pub is_const: bool,
+    pub visibility: Visibility,
     pub body: Vec<Stmt>,
     pub span: Span,
 }
 
+impl FnDecl {
+    /// Whether the body contains a `yield`, which makes the function a
+    /// generator; a `yield` inside a closure or `async:` block does not count
+    pub fn is_generator(&self) -> bool {
+        let mut finder = YieldFinder(false);
+        finder.visit_block(&self.body);
+        finder.0
+    }
+}
+
+struct YieldFinder(bool);
+
+impl Visitor for YieldFinder {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Yield { .. } => self.0 = true,
+            Stmt::Fn(_) => {}
+            _ => visit::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if !matches!(expr, Expr::Closure { .. } | Expr::AsyncBlock { .. }) {
+            visit::walk_expr(self, expr);
+        }
+    }
+}
+
 /// Generic type parameter: `T` or `T: Trait`
 #[derive(Debug, Clone)]
 pub struct GenericParam {
@@ -134,7 +345,10 @@ pub struct GenericParam {
 /// Function parameter
 #[derive(Debug, Clone)]
 pub struct Param {
+    /// Parameter name; `__param<N>` for a destructured parameter
     pub name: String,
+    /// Destructuring pattern: `(x, y): (int, int)`
+    pub pattern: Option<Pattern>,
     pub ty: Type,
     pub span: Span,
 }
@@ -147,6 +361,7 @@ pub struct StructDecl {
     pub fields: Vec<Field>,
     pub methods: Vec<MethodDecl>,
     pub is_copy: bool,
+    pub visibility: Visibility,
     pub span: Span,
 }
 
@@ -157,13 +372,25 @@ pub struct MethodDecl {
     pub params: Vec<Param>,
     pub return_type: Option<Type>,
     pub is_async: bool,
-    pub is_pub: bool,
+    pub visibility: Visibility,
     /// Whether this method takes &self, &mut self, or self
     pub receiver: MethodReceiver,
     pub body: Vec<Stmt>,
     pub span: Span,
 }
 
+/// Visibility of a declaration in the generated crate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Visibility {
+    /// No modifier
+    #[default]
+    Private,
+    /// `pub` - visible everywhere
+    Public,
+    /// `pub(crate)` - visible within the generated crate only
+    Crate,
+}
+
 /// Method receiver type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MethodReceiver {
@@ -180,6 +407,8 @@ pub enum MethodReceiver {
 pub struct Field {
     pub name: String,
     pub ty: Type,
+    /// Value used when a struct literal omits the field: `port: int = 8080`
+    pub default: Option<Expr>,
     pub span: Span,
 }
 
@@ -187,16 +416,41 @@ pub struct Field {
 #[derive(Debug, Clone)]
 pub struct EnumDecl {
     pub name: String,
+    pub generic_params: Vec<GenericParam>,
     pub variants: Vec<EnumVariant>,
     pub span: Span,
 }
 
+impl EnumDecl {
+    /// The integer value of each variant, for non-generic enums where no
+    /// variant carries data. Variants without an explicit `= n` follow the
+    /// previous one, starting at 0.
+    pub fn discriminants(&self) -> Option<Vec<(String, i64)>> {
+        if !self.generic_params.is_empty() {
+            return None;
+        }
+        let mut next = 0i64;
+        self.variants.iter()
+            .map(|variant| match variant.data {
+                EnumVariantData::Unit => {
+                    let value = variant.discriminant.unwrap_or(next);
+                    next = value.wrapping_add(1);
+                    Some((variant.name.clone(), value))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
 /// Enum variant
 #[derive(Debug, Clone)]
 pub struct EnumVariant {
     pub name: String,
     /// Optional associated data: Unit, Tuple, or Struct
     pub data: EnumVariantData,
+    /// Explicit integer value: `Red = 1`
+    pub discriminant: Option<i64>,
     pub span: Span,
 }
 
@@ -255,7 +509,7 @@ pub struct ApiDecl {
 }
 
 /// HTTP methods
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum HttpMethod {
     Get,
     Post,
@@ -264,11 +518,65 @@ pub enum HttpMethod {
     Patch,
 }
 
+impl HttpMethod {
+    /// Parses an upper-case method name such as `GET`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "GET" => Some(HttpMethod::Get),
+            "POST" => Some(HttpMethod::Post),
+            "PUT" => Some(HttpMethod::Put),
+            "DELETE" => Some(HttpMethod::Delete),
+            "PATCH" => Some(HttpMethod::Patch),
+            _ => None,
+        }
+    }
+}
+
+/// Route group declaration
+///
+/// Nested routes inherit the path prefix, and the group middlewares run
+/// before the route's own.
+#[derive(Debug, Clone)]
+pub struct GroupDecl {
+    pub prefix: String,
+    pub middlewares: Vec<String>,
+    pub routes: Vec<ApiDecl>,
+    pub span: Span,
+}
+
+impl GroupDecl {
+    /// Returns the nested routes with the prefix and group middlewares applied
+    pub fn expanded_routes(&self) -> Vec<ApiDecl> {
+        let prefix = self.prefix.trim_end_matches('/');
+        self.routes
+            .iter()
+            .map(|route| {
+                let mut route = route.clone();
+                route.path = if route.path == "/" && !prefix.is_empty() {
+                    prefix.to_string()
+                } else {
+                    format!("{}{}", prefix, route.path)
+                };
+                route.middlewares = self
+                    .middlewares
+                    .iter()
+                    .chain(route.middlewares.iter())
+                    .cloned()
+                    .collect();
+                route
+            })
+            .collect()
+    }
+}
+
 /// WebSocket endpoint declaration
 #[derive(Debug, Clone)]
 pub struct WsDecl {
     pub path: String,
     pub middlewares: Vec<String>,
+    /// Type of `message` in `on_message`, from a `message T` directive;
+    /// `string` when absent
+    pub message_type: Option<Type>,
     pub on_connect: Option<Vec<Stmt>>,
     pub on_message: Option<Vec<Stmt>>,
     pub on_disconnect: Option<Vec<Stmt>>,
@@ -288,6 +596,14 @@ pub enum WsEvent {
 pub struct ServerDecl {
     pub host: String,
     pub port: u16,
+    /// Maximum request body size in bytes
+    pub max_body: Option<u64>,
+    /// PEM certificate chain served over HTTPS
+    pub tls_cert: Option<String>,
+    /// PEM private key matching `tls_cert`
+    pub tls_key: Option<String>,
+    /// Request timeout in milliseconds
+    pub timeout: Option<u64>,
     pub span: Span,
 }
 
@@ -306,6 +622,8 @@ pub struct DbDecl {
     pub name: String,
     pub url: String,
     pub pool_size: u32,
+    /// Connection timeout in milliseconds
+    pub timeout: Option<u64>,
     pub span: Span,
 }
 
@@ -352,6 +670,9 @@ pub enum Type {
         params: Vec<Type>,
         return_type: Box<Type>,
     },
+
+    /// Trait object: dyn Shape
+    Dyn(std::string::String),
 }
 
 /// Expressions
@@ -363,6 +684,9 @@ pub enum Expr {
     /// Float literal
     FloatLit(f64, Span),
 
+    /// Duration literal in milliseconds: `30s`, `500ms`
+    DurationLit(u64, Span),
+
     /// String literal
     StringLit(String, Span),
 
@@ -405,6 +729,34 @@ pub enum Expr {
         span: Span,
     },
 
+    /// Named call argument: `name: value`
+    NamedArg {
+        name: String,
+        value: Box<Expr>,
+        span: Span,
+    },
+
+    /// Spread call argument: `..values` splats an array into variadic arguments
+    Spread {
+        expr: Box<Expr>,
+        span: Span,
+    },
+
+    /// Qualified enum variant: `Color::Red`
+    Path {
+        type_name: String,
+        name: String,
+        span: Span,
+    },
+
+    /// Associated function call: `Point::default()`
+    AssocCall {
+        type_name: String,
+        func: String,
+        args: Vec<Expr>,
+        span: Span,
+    },
+
     /// Field access
     FieldAccess {
         object: Box<Expr>,
@@ -412,6 +764,21 @@ pub enum Expr {
         span: Span,
     },
 
+    /// Optional chaining: `user?.name` reads the field of an `Option`'s
+    /// value, giving `None` when the option is `None`
+    OptionalFieldAccess {
+        object: Box<Expr>,
+        field: String,
+        span: Span,
+    },
+
+    /// Tuple element access: `pair.0`
+    TupleIndex {
+        object: Box<Expr>,
+        index: usize,
+        span: Span,
+    },
+
     /// Index access
     Index {
         object: Box<Expr>,
@@ -451,6 +818,26 @@ pub enum Expr {
     /// Array literal: [1, 2, 3]
     ArrayLit(Vec<Expr>, Span),
 
+    /// Map literal: `{"a": 1, "b": 2}`
+    MapLit {
+        entries: Vec<(Expr, Expr)>,
+        span: Span,
+    },
+
+    /// Repeated array literal: `[0; 16]`; the count must be a compile-time constant
+    ArrayRepeat {
+        value: Box<Expr>,
+        count: Box<Expr>,
+        span: Span,
+    },
+
+    /// Conversion to a primitive type: `color as int`
+    Cast {
+        expr: Box<Expr>,
+        ty: Type,
+        span: Span,
+    },
+
     /// Match expression: `match expr: ...`
     Match {
         expr: Box<Expr>,
@@ -491,6 +878,12 @@ pub enum Expr {
         inclusive: bool,
         span: Span,
     },
+
+    /// Async block: `async:` followed by an indented block, evaluates to a future
+    AsyncBlock {
+        body: Vec<Stmt>,
+        span: Span,
+    },
 }
 
 /// Closure parameter (can have optional type)
@@ -519,6 +912,63 @@ pub enum StringPart {
     Expr(Expr),
 }
 
+/// Part of a `"...".format(name: value)` template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatPiece {
+    /// Literal text
+    Literal(String),
+    /// `{name}` placeholder
+    Placeholder(String),
+}
+
+/// Splits a format template into literal text and `{name}` placeholders.
+/// `{{` and `}}` stand for literal braces.
+pub fn parse_format_template(template: &str) -> Result<Vec<FormatPiece>, String> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err("unclosed `{` in format string".to_string()),
+                    }
+                }
+                let name = name.trim().to_string();
+                let is_ident = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                    && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+                if !is_ident {
+                    return Err(format!("invalid placeholder `{{{}}}`, expected `{{name}}`", name));
+                }
+                if !literal.is_empty() {
+                    pieces.push(FormatPiece::Literal(std::mem::take(&mut literal)));
+                }
+                pieces.push(FormatPiece::Placeholder(name));
+            }
+            '}' => return Err("unmatched `}` in format string, use `}}` for a literal brace".to_string()),
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        pieces.push(FormatPiece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
 /// Binary operators
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinOp {
@@ -549,6 +999,65 @@ pub enum BinOp {
     DivAssign,
 }
 
+/// Built-in traits that overload operators on user types, with the
+/// method each requires: `impl Add for Vec2:` defines `fn add`
+pub const OPERATOR_TRAITS: &[(&str, &str)] = &[
+    ("Add", "add"),
+    ("Sub", "sub"),
+    ("Mul", "mul"),
+    ("Eq", "eq"),
+    ("Ord", "lt"),
+];
+
+impl BinOp {
+    /// The operator trait a user type implements to support this operator
+    pub fn operator_trait(&self) -> Option<&'static str> {
+        match self {
+            BinOp::Add => Some("Add"),
+            BinOp::Sub => Some("Sub"),
+            BinOp::Mul => Some("Mul"),
+            BinOp::Eq | BinOp::Ne => Some("Eq"),
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => Some("Ord"),
+            _ => None,
+        }
+    }
+
+    /// The arithmetic behind a compound assignment: `+=` is `+`
+    pub fn compound_base(&self) -> Option<BinOp> {
+        match self {
+            BinOp::AddAssign => Some(BinOp::Add),
+            BinOp::SubAssign => Some(BinOp::Sub),
+            BinOp::MulAssign => Some(BinOp::Mul),
+            BinOp::DivAssign => Some(BinOp::Div),
+            _ => None,
+        }
+    }
+
+    /// The operator as written in source
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Mod => "%",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::And => "and",
+            BinOp::Or => "or",
+            BinOp::Assign => "=",
+            BinOp::AddAssign => "+=",
+            BinOp::SubAssign => "-=",
+            BinOp::MulAssign => "*=",
+            BinOp::DivAssign => "/=",
+        }
+    }
+}
+
 /// Unary operators
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOp {
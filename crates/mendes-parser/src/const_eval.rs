@@ -0,0 +1,290 @@
+//! Compile-time evaluation of constant expressions
+//!
+//! Folds integer and boolean expressions, including calls to `const fn`s,
+//! for positions that need a value at compile time such as the length in
+//! `[0; square(4)]`. Concatenations of string literals are folded too.
+
+use crate::ast::*;
+use std::collections::HashMap;
+
+/// Nested `const fn` calls allowed before evaluation gives up
+const MAX_DEPTH: usize = 64;
+
+/// Statements and loop iterations executed before evaluation gives up
+const MAX_STEPS: usize = 100_000;
+
+/// A value computed at compile time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstValue {
+    Int(i64),
+    Bool(bool),
+}
+
+/// Control flow out of a `const fn` block
+enum Flow {
+    Next,
+    Return(ConstValue),
+}
+
+/// Joins `"a" + "b" + ...` when every operand of the `+` chain is a string
+/// literal; `None` when any operand is only known at runtime
+pub fn concat_string_literals(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::StringLit(s, _) => Some(s.clone()),
+        Expr::Binary { left, op: BinOp::Add, right, .. } => {
+            let mut joined = concat_string_literals(left)?;
+            joined.push_str(&concat_string_literals(right)?);
+            Some(joined)
+        }
+        _ => None,
+    }
+}
+
+/// Evaluates expressions that may call the given `const fn`s
+pub struct ConstEvaluator<'a> {
+    fns: &'a HashMap<String, FnDecl>,
+    consts: Option<&'a HashMap<String, ConstValue>>,
+    depth: usize,
+    steps: usize,
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(fns: &'a HashMap<String, FnDecl>) -> Self {
+        Self { fns, consts: None, depth: 0, steps: 0 }
+    }
+
+    /// Makes the values of module-level `const`s available by name
+    pub fn with_consts(mut self, consts: &'a HashMap<String, ConstValue>) -> Self {
+        self.consts = Some(consts);
+        self
+    }
+
+    /// Evaluates `expr`, or explains why it is not a compile-time constant
+    pub fn eval(&mut self, expr: &Expr) -> Result<ConstValue, String> {
+        self.eval_in(expr, &HashMap::new())
+    }
+
+    /// Evaluates `expr` to an integer
+    pub fn eval_int(&mut self, expr: &Expr) -> Result<i64, String> {
+        match self.eval(expr)? {
+            ConstValue::Int(n) => Ok(n),
+            ConstValue::Bool(_) => Err("expected an `int`, found a `bool`".to_string()),
+        }
+    }
+
+    fn eval_in(&mut self, expr: &Expr, locals: &HashMap<String, ConstValue>) -> Result<ConstValue, String> {
+        match expr {
+            Expr::IntLit(n, _) => Ok(ConstValue::Int(*n)),
+            Expr::BoolLit(b, _) => Ok(ConstValue::Bool(*b)),
+            Expr::Ident(name, _) => locals.get(name)
+                .or_else(|| self.consts.and_then(|consts| consts.get(name)))
+                .copied()
+                .ok_or_else(|| format!("`{}` is not a compile-time constant", name)),
+            Expr::Unary { op, expr, .. } => match (op, self.eval_in(expr, locals)?) {
+                (UnaryOp::Neg, ConstValue::Int(n)) => n.checked_neg().map(ConstValue::Int)
+                    .ok_or_else(|| "arithmetic overflow".to_string()),
+                (UnaryOp::Not, ConstValue::Bool(b)) => Ok(ConstValue::Bool(!b)),
+                _ => Err("mismatched operand type".to_string()),
+            },
+            Expr::Binary { left, op, right, .. } => {
+                let left = self.eval_in(left, locals)?;
+                let right = self.eval_in(right, locals)?;
+                eval_binary(*op, left, right)
+            }
+            Expr::Call { func, args, .. } => {
+                let name = match func.as_ref() {
+                    Expr::Ident(name, _) => name,
+                    _ => return Err("only `const fn`s can be called".to_string()),
+                };
+                let values = args.iter()
+                    .map(|arg| self.eval_in(arg, locals))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call(name, values)
+            }
+            _ => Err("expression is not a compile-time constant".to_string()),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<ConstValue>) -> Result<ConstValue, String> {
+        let f = self.fns.get(name).ok_or_else(|| format!("`{}` is not a `const fn`", name))?;
+        if args.len() != f.params.len() {
+            return Err(format!("`{}` expects {} arguments, found {}", name, f.params.len(), args.len()));
+        }
+        if self.depth == MAX_DEPTH {
+            return Err(format!("`{}` recurses too deeply", name));
+        }
+
+        let mut locals: HashMap<_, _> = f.params.iter().map(|p| p.name.clone()).zip(args).collect();
+        self.depth += 1;
+        let flow = self.exec_block(&f.body, &mut locals);
+        self.depth -= 1;
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            Flow::Next => Err(format!("`{}` does not return a value", name)),
+        }
+    }
+
+    fn exec_block(&mut self, stmts: &[Stmt], locals: &mut HashMap<String, ConstValue>) -> Result<Flow, String> {
+        for stmt in stmts {
+            self.step()?;
+            match stmt {
+                Stmt::Let { name, value, .. } => {
+                    let value = self.eval_in(value, locals)?;
+                    locals.insert(name.clone(), value);
+                }
+                Stmt::Return { value: Some(value), .. } => {
+                    return Ok(Flow::Return(self.eval_in(value, locals)?));
+                }
+                Stmt::If { condition, then_block, else_block, .. } => {
+                    let block = if self.eval_condition(condition, locals)? {
+                        Some(then_block)
+                    } else {
+                        else_block.as_ref()
+                    };
+                    if let Some(block) = block {
+                        if let Flow::Return(value) = self.exec_block(block, locals)? {
+                            return Ok(Flow::Return(value));
+                        }
+                    }
+                }
+                Stmt::While { condition, body, .. } => {
+                    while self.eval_condition(condition, locals)? {
+                        self.step()?;
+                        if let Flow::Return(value) = self.exec_block(body, locals)? {
+                            return Ok(Flow::Return(value));
+                        }
+                    }
+                }
+                Stmt::Expr(Expr::Binary { left, op, right, .. }) if is_assignment(*op) => {
+                    let name = match left.as_ref() {
+                        Expr::Ident(name, _) => name,
+                        _ => return Err("only local variables can be assigned".to_string()),
+                    };
+                    let value = self.eval_in(right, locals)?;
+                    let value = match op.compound_base() {
+                        Some(base) => eval_binary(base, self.eval_in(left, locals)?, value)?,
+                        None => value,
+                    };
+                    locals.insert(name.clone(), value);
+                }
+                _ => return Err("statement is not allowed in a `const fn`".to_string()),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn eval_condition(&mut self, condition: &Expr, locals: &HashMap<String, ConstValue>) -> Result<bool, String> {
+        match self.eval_in(condition, locals)? {
+            ConstValue::Bool(b) => Ok(b),
+            ConstValue::Int(_) => Err("condition must be a `bool`".to_string()),
+        }
+    }
+
+    fn step(&mut self) -> Result<(), String> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err("evaluation takes too many steps".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn is_assignment(op: BinOp) -> bool {
+    op == BinOp::Assign || op.compound_base().is_some()
+}
+
+fn eval_binary(op: BinOp, left: ConstValue, right: ConstValue) -> Result<ConstValue, String> {
+    use ConstValue::{Bool, Int};
+
+    let overflow = || "arithmetic overflow".to_string();
+    match (left, right) {
+        (Int(a), Int(b)) => match op {
+            BinOp::Add => a.checked_add(b).map(Int).ok_or_else(overflow),
+            BinOp::Sub => a.checked_sub(b).map(Int).ok_or_else(overflow),
+            BinOp::Mul => a.checked_mul(b).map(Int).ok_or_else(overflow),
+            BinOp::Div | BinOp::Mod if b == 0 => Err("division by zero".to_string()),
+            BinOp::Div => a.checked_div(b).map(Int).ok_or_else(overflow),
+            BinOp::Mod => a.checked_rem(b).map(Int).ok_or_else(overflow),
+            BinOp::Eq => Ok(Bool(a == b)),
+            BinOp::Ne => Ok(Bool(a != b)),
+            BinOp::Lt => Ok(Bool(a < b)),
+            BinOp::Le => Ok(Bool(a <= b)),
+            BinOp::Gt => Ok(Bool(a > b)),
+            BinOp::Ge => Ok(Bool(a >= b)),
+            _ => Err("mismatched operand type".to_string()),
+        },
+        (Bool(a), Bool(b)) => match op {
+            BinOp::And => Ok(Bool(a && b)),
+            BinOp::Or => Ok(Bool(a || b)),
+            BinOp::Eq => Ok(Bool(a == b)),
+            BinOp::Ne => Ok(Bool(a != b)),
+            _ => Err("mismatched operand type".to_string()),
+        },
+        _ => Err("mismatched operand type".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use mendes_lexer::Lexer;
+
+    fn const_fns(source: &str) -> HashMap<String, FnDecl> {
+        let tokens = Lexer::new(source, 0).tokenize();
+        let (program, diags) = parse(tokens);
+        assert!(!diags.has_errors(), "{:?}", diags);
+        program.statements.into_iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Fn(f) if f.is_const => Some((f.name.clone(), f)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn call(name: &str, args: Vec<i64>) -> Expr {
+        Expr::Call {
+            func: Box::new(Expr::Ident(name.to_string(), Default::default())),
+            args: args.into_iter().map(|n| Expr::IntLit(n, Default::default())).collect(),
+            span: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_eval_const_fn_calls() {
+        let fns = const_fns("const fn square(x: int) -> int:
+    return x * x
+
+const fn fact(n: int) -> int:
+    let mut acc = 1
+    let mut i = 2
+    while i <= n:
+        acc *= i
+        i += 1
+    return acc
+
+const fn pick(big: bool) -> int:
+    if big:
+        return square(8)
+    return 1
+");
+        let mut eval = ConstEvaluator::new(&fns);
+        assert_eq!(eval.eval_int(&call("square", vec![4])), Ok(16));
+        assert_eq!(eval.eval_int(&call("fact", vec![5])), Ok(120));
+        assert_eq!(eval.eval(&call("pick", vec![])), Err("`pick` expects 1 arguments, found 0".to_string()));
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        let fns = const_fns("const fn forever(n: int) -> int:
+    return forever(n + 1)
+
+const fn halve(n: int) -> int:
+    return n / 0
+");
+        let mut eval = ConstEvaluator::new(&fns);
+        assert_eq!(eval.eval(&call("forever", vec![0])), Err("`forever` recurses too deeply".to_string()));
+        assert_eq!(eval.eval(&call("halve", vec![4])), Err("division by zero".to_string()));
+        assert_eq!(eval.eval(&call("print", vec![1])), Err("`print` is not a `const fn`".to_string()));
+    }
+}
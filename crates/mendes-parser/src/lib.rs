@@ -18,6 +18,10 @@
 
 pub mod ast;
 pub mod parser;
+pub mod visit;
+pub mod const_eval;
+pub mod stdlib;
 
 pub use ast::*;
 pub use parser::{parse, Parser};
+pub use const_eval::{concat_string_literals, ConstEvaluator, ConstValue};
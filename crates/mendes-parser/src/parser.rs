@@ -14,6 +14,8 @@ pub struct Parser {
     pos: usize,
     /// Accumulated diagnostics
     diagnostics: Diagnostics,
+    /// Number of indented blocks enclosing the current statement
+    block_depth: usize,
 }
 
 impl Parser {
@@ -23,6 +25,7 @@ impl Parser {
             tokens,
             pos: 0,
             diagnostics: Diagnostics::new(),
+            block_depth: 0,
         }
     }
 
@@ -107,6 +110,17 @@ impl Parser {
     /// Reports an error at the current token
     fn error_at_current(&mut self, message: &str) {
         let span = self.peek().span;
+        if self.check(&TokenKind::Dedent) {
+            // A block closed before the construct was complete
+            self.diagnostics.push(
+                Diagnostic::error("unexpected end of block; check indentation")
+                    .with_code(ErrorCode::UNEXPECTED_TOKEN)
+                    .with_label(span, "indentation decreases here")
+                    .with_note(message)
+                    .with_help("indent the lines of a block further than the line that opens it")
+            );
+            return;
+        }
         self.diagnostics.push(
             Diagnostic::error(message)
                 .with_code(ErrorCode::UNEXPECTED_TOKEN)
@@ -114,31 +128,38 @@ impl Parser {
         );
     }
 
+    /// Runs `parse` for the contents of an indented block
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> T) -> T {
+        self.block_depth += 1;
+        let result = parse(self);
+        self.block_depth -= 1;
+        result
+    }
+
     /// Synchronizes after an error (panic mode recovery)
+    ///
+    /// Skips to the start of the next line at the indentation level of the
+    /// bad statement, stepping over any blocks it opened. Stops before the
+    /// DEDENT that closes the enclosing block so the caller can finish it.
     fn synchronize(&mut self) {
-        self.advance();
+        let mut depth = 0usize;
+        let mut skipped_any = false;
 
         while !self.is_at_end() {
-            // Synchronize at newline followed by a statement keyword
-            if matches!(self.previous().kind, TokenKind::Newline) {
-                match self.peek().kind {
-                    TokenKind::Let
-                    | TokenKind::Fn
-                    | TokenKind::Struct
-                    | TokenKind::Enum
-                    | TokenKind::Api
-                    | TokenKind::Ws
-                    | TokenKind::Server
-                    | TokenKind::Middleware
-                    | TokenKind::Db
-                    | TokenKind::If
-                    | TokenKind::For
-                    | TokenKind::While
-                    | TokenKind::Return => return,
-                    _ => {}
+            match self.peek().kind {
+                TokenKind::Indent => depth += 1,
+                TokenKind::Dedent if depth == 0 => return,
+                TokenKind::Dedent => depth -= 1,
+                TokenKind::Newline => {}
+                _ => {
+                    let at_line_start = matches!(self.previous().kind, TokenKind::Newline | TokenKind::Dedent);
+                    if skipped_any && depth == 0 && at_line_start {
+                        return;
+                    }
                 }
             }
             self.advance();
+            skipped_any = true;
         }
     }
 
@@ -158,6 +179,10 @@ impl Parser {
                 Err(_) => self.synchronize(),
             }
             self.skip_newlines();
+            // A dedent at the top level is left over from error recovery
+            while self.match_token(&TokenKind::Dedent) {
+                self.skip_newlines();
+            }
         }
 
         Program { statements }
@@ -167,6 +192,10 @@ impl Parser {
     fn parse_statement(&mut self) -> Result<Stmt, ()> {
         self.skip_newlines();
 
+        if self.block_depth > 0 {
+            self.reject_nested_declaration()?;
+        }
+
         match &self.peek().kind {
             TokenKind::Import => self.parse_import(),
             TokenKind::From => self.parse_from_import(),
@@ -174,6 +203,9 @@ impl Parser {
             TokenKind::Let => self.parse_let(),
             TokenKind::Fn => self.parse_fn(),
             TokenKind::Struct => self.parse_struct(),
+            TokenKind::Pub => self.parse_visible_item(),
+            TokenKind::Const if matches!(self.peek_next().kind, TokenKind::Ident(_)) => self.parse_const(),
+            TokenKind::Const => self.parse_fn(),
             TokenKind::Enum => self.parse_enum(),
             TokenKind::Trait => self.parse_trait(),
             TokenKind::Impl => self.parse_impl(),
@@ -182,17 +214,286 @@ impl Parser {
             TokenKind::Ws => self.parse_websocket(),
             TokenKind::Server => self.parse_server(),
             TokenKind::Middleware => self.parse_middleware(),
+            TokenKind::Group => self.parse_group(),
             TokenKind::Db => self.parse_db(),
             TokenKind::If => self.parse_if(),
             TokenKind::For => self.parse_for(),
             TokenKind::While => self.parse_while(),
             TokenKind::Return => self.parse_return(),
+            TokenKind::Yield => self.parse_yield(),
             TokenKind::Break => self.parse_break(),
             TokenKind::Continue => self.parse_continue(),
+            TokenKind::At => self.parse_attributed(),
+            // `try` is contextual: only `try:` opens a block
+            TokenKind::Ident(name) if name == "try" && self.peek_next().kind == TokenKind::Colon => {
+                self.parse_try_catch()
+            }
+            // `static` is contextual: only `static <name>` or `static mut` declares one
+            TokenKind::Ident(name) if name == "static"
+                && matches!(self.peek_next().kind, TokenKind::Ident(_) | TokenKind::Mut) => {
+                self.parse_static()
+            }
+            // `with` is contextual: only `with <name>...` opens a block
+            TokenKind::Ident(name) if name == "with" && matches!(self.peek_next().kind, TokenKind::Ident(_)) => {
+                self.parse_with()
+            }
             _ => self.parse_expr_stmt(),
         }
     }
 
+    /// Reports server, route and database declarations inside a block;
+    /// the caller's recovery then skips the whole declaration
+    fn reject_nested_declaration(&mut self) -> Result<(), ()> {
+        let keyword = match self.peek().kind {
+            TokenKind::Server => "server",
+            TokenKind::Api => "api",
+            TokenKind::Ws => "ws",
+            TokenKind::Db => "db",
+            TokenKind::Middleware => "middleware",
+            TokenKind::Group => "group",
+            _ => return Ok(()),
+        };
+        self.diagnostics.push(
+            Diagnostic::error(format!("`{}` must be declared at module scope", keyword))
+                .with_code(ErrorCode::INVALID_SYNTAX)
+                .with_label(self.peek().span, "not allowed inside a block")
+                .with_help("move the declaration to the top level of the file"),
+        );
+        Err(())
+    }
+
+    /// Parse: one or more `@name(args)` lines followed by a declaration
+    fn parse_attributed(&mut self) -> Result<Stmt, ()> {
+        let start_span = self.peek().span;
+        let mut attrs = Vec::new();
+
+        while self.check(&TokenKind::At) {
+            let attr_start = self.advance().span;
+            let name = self.parse_identifier()?;
+
+            let mut args = Vec::new();
+            if self.match_token(&TokenKind::LParen) {
+                while !self.check(&TokenKind::RParen) && !self.is_at_end() {
+                    args.push(self.parse_attribute_arg()?);
+                    if !self.match_token(&TokenKind::Comma) {
+                        break;
+                    }
+                }
+                self.expect(&TokenKind::RParen, "expected ')' after attribute arguments")?;
+            }
+
+            let span = attr_start.merge(self.previous().span);
+            match name.as_str() {
+                "cfg" if args.len() != 1 => {
+                    self.diagnostics.push(
+                        Diagnostic::error("`@cfg` takes exactly one flag name")
+                            .with_code(ErrorCode::INVALID_SYNTAX)
+                            .with_label(span, format!("found {} arguments", args.len()))
+                            .with_help("write it as `@cfg(debug)`"),
+                    );
+                }
+                "cfg" => {}
+                "route" => self.validate_route_attribute(&args, span),
+                "inline" | "cold" => self.validate_hint_attribute(&name, &args, span),
+                "derive" => self.validate_derive_attribute(&args, span),
+                "allow" => self.validate_allow_attribute(&args, span),
+                _ => {
+                    self.diagnostics.push(
+                        Diagnostic::warning(format!("unknown attribute `@{}`", name))
+                            .with_code(ErrorCode::UNKNOWN_ATTRIBUTE)
+                            .with_label(span, "unknown attribute, ignored")
+                            .with_note("the supported attributes are `@cfg(flag)`, `@route(METHOD, \"/path\")`, `@inline`, `@cold`, `@derive(Trait, ...)` and `@allow(lint, ...)`"),
+                    );
+                }
+            }
+
+            attrs.push(Attribute { name, args, span });
+            self.expect_newline()?;
+            self.skip_newlines();
+        }
+
+        let item = self.parse_statement()?;
+        let is_declaration = matches!(
+            item,
+            Stmt::Let { .. }
+                | Stmt::Static { .. }
+                | Stmt::Const { .. }
+                | Stmt::Fn(_)
+                | Stmt::Struct(_)
+                | Stmt::Enum(_)
+                | Stmt::Trait(_)
+                | Stmt::ImplTrait(_)
+                | Stmt::TypeAlias { .. }
+                | Stmt::Api(_)
+                | Stmt::WebSocket(_)
+                | Stmt::Group(_)
+                | Stmt::Middleware(_)
+                | Stmt::Db(_)
+                | Stmt::Attributed { .. }
+        );
+        if !is_declaration {
+            self.diagnostics.push(
+                Diagnostic::error("attributes can only be applied to declarations")
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(start_span, "attribute applied here"),
+            );
+        }
+        if let Some(route) = attrs.iter().find(|attr| attr.name == "route") {
+            if !matches!(item.unattributed(), Stmt::Fn(_)) {
+                self.diagnostics.push(
+                    Diagnostic::error("`@route` can only be applied to functions")
+                        .with_code(ErrorCode::INVALID_SYNTAX)
+                        .with_label(route.span, "attribute applied here"),
+                );
+            }
+        }
+
+        if let Some(derive) = attrs.iter().find(|attr| attr.name == "derive") {
+            if !matches!(item.unattributed(), Stmt::Struct(_)) {
+                self.diagnostics.push(
+                    Diagnostic::error("`@derive` can only be applied to structs")
+                        .with_code(ErrorCode::INVALID_SYNTAX)
+                        .with_label(derive.span, "attribute applied here"),
+                );
+            }
+        }
+
+        for hint in attrs.iter().filter(|attr| attr.is_hint()) {
+            if !matches!(item.unattributed(), Stmt::Fn(_)) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`@{}` can only be applied to functions", hint.name))
+                        .with_code(ErrorCode::INVALID_SYNTAX)
+                        .with_label(hint.span, "attribute applied here"),
+                );
+            }
+        }
+
+        let span = start_span.merge(self.previous().span);
+
+        Ok(Stmt::Attributed { attrs, item: Box::new(item), span })
+    }
+
+    /// Parse one attribute argument: a name, an HTTP method or a string
+    fn parse_attribute_arg(&mut self) -> Result<String, ()> {
+        let arg = match &self.peek().kind {
+            TokenKind::Get => "GET".to_string(),
+            TokenKind::Post => "POST".to_string(),
+            TokenKind::Put => "PUT".to_string(),
+            TokenKind::Delete => "DELETE".to_string(),
+            TokenKind::Patch => "PATCH".to_string(),
+            TokenKind::StringLit(s) => s.clone(),
+            _ => return self.parse_identifier(),
+        };
+        self.advance();
+        Ok(arg)
+    }
+
+    /// Checks the arguments of `@inline`, `@inline(always | never)` and `@cold`
+    fn validate_hint_attribute(&mut self, name: &str, args: &[String], span: Span) {
+        let valid = match (name, args) {
+            (_, []) => true,
+            ("inline", [mode]) => mode == "always" || mode == "never",
+            _ => false,
+        };
+        if !valid {
+            let help = if name == "inline" {
+                "write it as `@inline`, `@inline(always)` or `@inline(never)`"
+            } else {
+                "write it as `@cold`"
+            };
+            self.diagnostics.push(
+                Diagnostic::error(format!("invalid arguments to `@{}`", name))
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(span, format!("found `{}`", args.join(", ")))
+                    .with_help(help),
+            );
+        }
+    }
+
+    /// Checks that `@derive(...)` lists traits that can be derived
+    fn validate_derive_attribute(&mut self, args: &[String], span: Span) {
+        if args.is_empty() {
+            self.diagnostics.push(
+                Diagnostic::error("`@derive` takes at least one trait name")
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(span, "no traits listed")
+                    .with_help("write it as `@derive(Clone, PartialEq)`"),
+            );
+        }
+        for name in args.iter().filter(|name| !DERIVABLE_TRAITS.contains(&name.as_str())) {
+            self.diagnostics.push(
+                Diagnostic::warning(format!("`{}` cannot be derived", name))
+                    .with_code(ErrorCode::INVALID_DERIVE)
+                    .with_label(span, "not derivable, ignored")
+                    .with_note(format!("the derivable traits are {}", DERIVABLE_TRAITS.iter()
+                        .map(|t| format!("`{}`", t))
+                        .collect::<Vec<_>>()
+                        .join(", "))),
+            );
+        }
+    }
+
+    /// Checks that `@allow(...)` names known lints
+    fn validate_allow_attribute(&mut self, args: &[String], span: Span) {
+        if args.is_empty() {
+            self.diagnostics.push(
+                Diagnostic::error("`@allow` takes at least one lint name")
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(span, "no lints listed")
+                    .with_help("write it as `@allow(unused)`"),
+            );
+        }
+        for name in args.iter().filter(|name| ErrorCode::lint(name).is_none()) {
+            self.diagnostics.push(
+                Diagnostic::warning(format!("unknown lint `{}`", name))
+                    .with_code(ErrorCode::UNKNOWN_LINT)
+                    .with_label(span, "unknown lint, ignored")
+                    .with_note(format!("the known lints are {}", ErrorCode::LINTS.iter()
+                        .map(|(lint, _)| format!("`{}`", lint))
+                        .collect::<Vec<_>>()
+                        .join(", "))),
+            );
+        }
+    }
+
+    /// Checks the method and path of `@route(METHOD, "/path")`
+    fn validate_route_attribute(&mut self, args: &[String], span: Span) {
+        let [method, path] = args else {
+            self.diagnostics.push(
+                Diagnostic::error("`@route` takes a method and a path")
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(span, format!("found {} arguments", args.len()))
+                    .with_help("write it as `@route(GET, \"/health\")`"),
+            );
+            return;
+        };
+
+        if HttpMethod::from_name(method).is_none() {
+            self.diagnostics.push(
+                Diagnostic::error(format!("unknown HTTP method `{}` in `@route`", method))
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(span, "unknown method")
+                    .with_note("expected GET, POST, PUT, DELETE or PATCH"),
+            );
+        }
+
+        let valid_segment = |segment: &str| match segment.strip_prefix('{') {
+            Some(param) => param.strip_suffix('}').is_some_and(|param| {
+                let name = param.split(':').next().unwrap_or_default();
+                !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+            }),
+            None => !segment.contains(['{', '}', ' ']),
+        };
+        if !path.starts_with('/') || !path[1..].split('/').all(valid_segment) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("invalid route path `{}`", path))
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(span, "invalid path")
+                    .with_help("paths start with `/` and write parameters as `{name}` or `{name:type}`"),
+            );
+        }
+    }
+
     /// Parse: `import "path/to/file.ms"` or `import module_name [as alias]`
     fn parse_import(&mut self) -> Result<Stmt, ()> {
         let start_span = self.peek().span;
@@ -290,7 +591,12 @@ impl Parser {
 
         while self.match_token(&TokenKind::Dot) {
             path.push('.');
-            path.push_str(&self.parse_identifier()?);
+            // Type keywords name modules too, as in `std.string`
+            if self.match_token(&TokenKind::StringType) {
+                path.push_str("string");
+            } else {
+                path.push_str(&self.parse_identifier()?);
+            }
         }
 
         Ok(path)
@@ -318,6 +624,10 @@ impl Parser {
 
         let mutable = self.match_token(&TokenKind::Mut);
 
+        if self.match_token(&TokenKind::LParen) {
+            return self.parse_let_tuple(mutable, start_span);
+        }
+
         let name = self.parse_identifier()?;
 
         // Optional type
@@ -332,7 +642,7 @@ impl Parser {
         let value = self.parse_expression()?;
 
         let span = start_span.merge(self.previous().span);
-        self.expect_newline()?;
+        self.expect_newline_after(&value)?;
 
         Ok(Stmt::Let {
             name,
@@ -343,12 +653,145 @@ impl Parser {
         })
     }
 
-    /// Parse: `fn name<T, U>(params) -> type [async]:`
+    /// Parse: `static mut name: type = value`
+    fn parse_static(&mut self) -> Result<Stmt, ()> {
+        let start_span = self.peek().span;
+        self.advance(); // consume 'static'
+
+        if self.block_depth > 0 {
+            self.diagnostics.push(
+                Diagnostic::error("`static` must be declared at module scope")
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(start_span, "not allowed inside a block")
+                    .with_help("use `let` for a local, or move the `static` to the top level of the file"),
+            );
+            return Err(());
+        }
+
+        let mutable = self.match_token(&TokenKind::Mut);
+        let name = self.parse_identifier()?;
+        self.expect(&TokenKind::Colon, "expected ':' and a type after static name")?;
+        let ty = self.parse_type()?;
+        self.expect(&TokenKind::Eq, "expected '=' after static type")?;
+        let value = self.parse_expression()?;
+
+        let span = start_span.merge(self.previous().span);
+        self.expect_newline()?;
+
+        Ok(Stmt::Static {
+            name,
+            ty,
+            value,
+            mutable,
+            span,
+        })
+    }
+
+    /// Parse: `const NAME: type = value`
+    fn parse_const(&mut self) -> Result<Stmt, ()> {
+        let start_span = self.peek().span;
+        let visibility = self.parse_visibility()?;
+        self.advance(); // consume 'const'
+
+        if self.block_depth > 0 {
+            self.diagnostics.push(
+                Diagnostic::error("`const` must be declared at module scope")
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(start_span, "not allowed inside a block")
+                    .with_help("use `let` for a local, or move the `const` to the top level of the file"),
+            );
+            return Err(());
+        }
+
+        let name = self.parse_identifier()?;
+        self.expect(&TokenKind::Colon, "expected ':' and a type after const name")?;
+        let ty = self.parse_type()?;
+        self.expect(&TokenKind::Eq, "expected '=' after const type")?;
+        let value = self.parse_expression()?;
+
+        let span = start_span.merge(self.previous().span);
+        self.expect_newline()?;
+
+        Ok(Stmt::Const { name, ty, value, visibility, span })
+    }
+
+    /// Parse the rest of `let (a, b) = expr`, after the `(`
+    fn parse_let_tuple(&mut self, mutable: bool, start_span: Span) -> Result<Stmt, ()> {
+        let mut names = vec![self.parse_identifier()?];
+        while self.match_token(&TokenKind::Comma) {
+            names.push(self.parse_identifier()?);
+        }
+        self.expect(&TokenKind::RParen, "expected ')' after tuple names")?;
+
+        self.expect(&TokenKind::Eq, "expected '=' after tuple names")?;
+
+        let value = self.parse_expression()?;
+
+        let span = start_span.merge(self.previous().span);
+        self.expect_newline()?;
+
+        Ok(Stmt::LetTuple {
+            names,
+            value,
+            mutable,
+            span,
+        })
+    }
+
+    /// Parse a declaration with a visibility modifier: `pub fn`, `pub(crate) struct`, `pub const`
+    fn parse_visible_item(&mut self) -> Result<Stmt, ()> {
+        let start = self.pos;
+        self.parse_visibility()?;
+        let item = self.peek().clone();
+        let next = self.peek_next().kind.clone();
+        self.pos = start;
+
+        match item.kind {
+            TokenKind::Const if matches!(next, TokenKind::Ident(_)) => self.parse_const(),
+            TokenKind::Fn | TokenKind::Const => self.parse_fn(),
+            TokenKind::Struct => self.parse_struct(),
+            _ => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("expected `fn`, `struct` or `const` after a visibility modifier, found `{}`", item.kind))
+                        .with_code(ErrorCode::INVALID_SYNTAX)
+                        .with_label(item.span, "expected `fn`, `struct` or `const`")
+                );
+                Err(())
+            }
+        }
+    }
+
+    /// Parse an optional visibility modifier: `pub` or `pub(crate)`
+    fn parse_visibility(&mut self) -> Result<Visibility, ()> {
+        if !self.match_token(&TokenKind::Pub) {
+            return Ok(Visibility::Private);
+        }
+        if !self.match_token(&TokenKind::LParen) {
+            return Ok(Visibility::Public);
+        }
+
+        let scope = self.peek().clone();
+        if !matches!(&scope.kind, TokenKind::Ident(name) if name == "crate") {
+            self.diagnostics.push(
+                Diagnostic::error(format!("unknown visibility `pub({})`", scope.kind))
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(scope.span, "expected `crate`")
+                    .with_help("use `pub` for a public item or `pub(crate)` for one visible within the crate")
+            );
+            return Err(());
+        }
+        self.advance();
+        self.expect(&TokenKind::RParen, "expected ')' after `pub(crate`")?;
+        Ok(Visibility::Crate)
+    }
+
+    /// Parse: `[pub] [const] fn name<T, U>(params) -> type [async]:`
     fn parse_fn(&mut self) -> Result<Stmt, ()> {
         let start_span = self.peek().span;
-        let is_pub = false; // TODO: implement pub
+        let visibility = self.parse_visibility()?;
+        let is_const = self.match_token(&TokenKind::Const);
 
-        self.advance(); // consume 'fn'
+        self.expect(&TokenKind::Fn, "expected `fn`")?;
 
         let name = self.parse_identifier()?;
 
@@ -385,15 +828,17 @@ impl Parser {
             params,
             return_type,
             is_async,
-            is_pub,
+            is_const,
+            visibility,
             body,
             span,
         }))
     }
 
-    /// Parse: `struct Name<T, U> [copy]:`
+    /// Parse: `[pub] struct Name<T, U> [copy]:`
     fn parse_struct(&mut self) -> Result<Stmt, ()> {
         let start_span = self.peek().span;
+        let visibility = self.parse_visibility()?;
         self.advance(); // consume 'struct'
 
         let name = self.parse_identifier()?;
@@ -420,17 +865,25 @@ impl Parser {
             fields,
             methods,
             is_copy,
+            visibility,
             span,
         }))
     }
 
-    /// Parse: `enum Name:`
+    /// Parse: `enum Name:` or `enum Name<T>:`
     fn parse_enum(&mut self) -> Result<Stmt, ()> {
         let start_span = self.peek().span;
         self.advance(); // consume 'enum'
 
         let name = self.parse_identifier()?;
 
+        // Optional generic parameters
+        let generic_params = if self.check(&TokenKind::Lt) {
+            self.parse_generic_params()?
+        } else {
+            Vec::new()
+        };
+
         self.expect(&TokenKind::Colon, "expected ':' after enum name")?;
         self.expect_newline()?;
 
@@ -440,6 +893,7 @@ impl Parser {
 
         Ok(Stmt::Enum(EnumDecl {
             name,
+            generic_params,
             variants,
             span,
         }))
@@ -484,6 +938,7 @@ impl Parser {
                         fields.push(Field {
                             name: field_name,
                             ty: field_type,
+                            default: None,
                             span: field_span.merge(self.previous().span),
                         });
                         if !self.match_token(&TokenKind::Comma) {
@@ -498,9 +953,31 @@ impl Parser {
                 EnumVariantData::Unit
             };
 
+            // Explicit discriminant: `Red = 1`
+            let discriminant = if self.match_token(&TokenKind::Eq) {
+                if !matches!(data, EnumVariantData::Unit) {
+                    self.error_at_current("only variants without data can have a discriminant");
+                    return Err(());
+                }
+                let negative = self.match_token(&TokenKind::Minus);
+                match self.peek().kind {
+                    TokenKind::IntLit(n) => {
+                        self.advance();
+                        Some(if negative { -n } else { n })
+                    }
+                    _ => {
+                        self.error_at_current("expected an integer discriminant");
+                        return Err(());
+                    }
+                }
+            } else {
+                None
+            };
+
             variants.push(EnumVariant {
                 name: variant_name,
                 data,
+                discriminant,
                 span: variant_span.merge(self.previous().span),
             });
 
@@ -525,7 +1002,7 @@ impl Parser {
         self.expect_newline()?;
 
         // Parse directives and body
-        let (middlewares, body_type, return_type, handler) = self.parse_api_body()?;
+        let (middlewares, body_type, return_type, handler) = self.nested(Self::parse_api_body)?;
 
         let span = start_span.merge(self.previous().span);
 
@@ -553,6 +1030,7 @@ impl Parser {
         self.expect(&TokenKind::Indent, "expected indented block for WebSocket")?;
 
         let mut middlewares = Vec::new();
+        let mut message_type = None;
         let mut on_connect = None;
         let mut on_message = None;
         let mut on_disconnect = None;
@@ -572,6 +1050,11 @@ impl Parser {
                         middlewares.push(middleware_name);
                         self.skip_newlines();
                     }
+                    "message" if message_type.is_none() => {
+                        self.advance();
+                        message_type = Some(self.parse_type()?);
+                        self.skip_newlines();
+                    }
                     "on_connect" => {
                         self.advance();
                         self.expect(&TokenKind::Colon, "expected ':' after 'on_connect'")?;
@@ -608,6 +1091,7 @@ impl Parser {
         Ok(Stmt::WebSocket(WsDecl {
             path,
             middlewares,
+            message_type,
             on_connect,
             on_message,
             on_disconnect,
@@ -626,6 +1110,10 @@ impl Parser {
 
         let mut host = String::from("0.0.0.0");
         let mut port = 8080u16;
+        let mut max_body = None;
+        let mut tls_cert = None;
+        let mut tls_key = None;
+        let mut timeout = None;
 
         while !self.check(&TokenKind::Dedent) && !self.is_at_end() {
             self.skip_newlines();
@@ -648,6 +1136,27 @@ impl Parser {
                             self.advance();
                         }
                     }
+                    "max_body" => {
+                        if let TokenKind::IntLit(bytes) = &self.peek().kind {
+                            max_body = Some(*bytes as u64);
+                            self.advance();
+                        } else {
+                            self.error_at_current("expected body size in bytes after 'max_body'");
+                        }
+                    }
+                    "timeout" => timeout = self.parse_timeout_directive(),
+                    "tls_cert" | "tls_key" => {
+                        if let TokenKind::StringLit(path) = &self.peek().kind.clone() {
+                            if name == "tls_cert" {
+                                tls_cert = Some(path.clone());
+                            } else {
+                                tls_key = Some(path.clone());
+                            }
+                            self.advance();
+                        } else {
+                            self.error_at_current(&format!("expected file path string after '{}'", name));
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -658,7 +1167,36 @@ impl Parser {
 
         let span = start_span.merge(self.previous().span);
 
-        Ok(Stmt::Server(ServerDecl { host, port, span }))
+        // A certificate is useless without its key and vice versa
+        if tls_cert.is_some() != tls_key.is_some() {
+            let (present, missing) = if tls_cert.is_some() {
+                ("tls_cert", "tls_key")
+            } else {
+                ("tls_key", "tls_cert")
+            };
+            self.diagnostics.push(
+                Diagnostic::error(format!("`{}` is set without `{}`", present, missing))
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(span, "incomplete TLS configuration")
+                    .with_help(format!("add a `{} \"path\"` directive to the server block", missing)),
+            );
+        }
+
+        Ok(Stmt::Server(ServerDecl { host, port, max_body, tls_cert, tls_key, timeout, span }))
+    }
+
+    /// Parses the duration after a `timeout` directive
+    fn parse_timeout_directive(&mut self) -> Option<u64> {
+        if let TokenKind::DurationLit(ms) = self.peek().kind {
+            self.advance();
+            Some(ms)
+        } else {
+            self.error_at_current("expected a duration like `30s` or `500ms` after 'timeout'");
+            if !matches!(self.peek().kind, TokenKind::Newline | TokenKind::Dedent | TokenKind::Eof) {
+                self.advance();
+            }
+            None
+        }
     }
 
     /// Parse: `middleware name:`
@@ -678,6 +1216,52 @@ impl Parser {
         Ok(Stmt::Middleware(MiddlewareDecl { name, body, span }))
     }
 
+    /// Parse: `group /prefix:` followed by `use` directives and `api` routes
+    fn parse_group(&mut self) -> Result<Stmt, ()> {
+        let start_span = self.peek().span;
+        self.advance(); // consume 'group'
+
+        let prefix = self.parse_path_pattern()?;
+
+        self.expect(&TokenKind::Colon, "expected ':' after group prefix")?;
+        self.expect_newline()?;
+        self.expect(&TokenKind::Indent, "expected indented block for group")?;
+
+        let mut middlewares = Vec::new();
+        let mut routes = Vec::new();
+
+        while !self.check(&TokenKind::Dedent) && !self.is_at_end() {
+            self.skip_newlines();
+            if self.check(&TokenKind::Dedent) {
+                break;
+            }
+
+            match &self.peek().kind {
+                TokenKind::Use => {
+                    self.advance();
+                    let name = self.parse_identifier()?;
+                    middlewares.push(name);
+                    self.skip_newlines();
+                }
+                TokenKind::Api => {
+                    if let Stmt::Api(api) = self.parse_api()? {
+                        routes.push(api);
+                    }
+                }
+                _ => {
+                    self.error_at_current("expected `use` or `api` inside group");
+                    return Err(());
+                }
+            }
+        }
+
+        self.match_token(&TokenKind::Dedent);
+
+        let span = start_span.merge(self.previous().span);
+
+        Ok(Stmt::Group(GroupDecl { prefix, middlewares, routes, span }))
+    }
+
     /// Parse: `db type name:`
     fn parse_db(&mut self) -> Result<Stmt, ()> {
         let start_span = self.peek().span;
@@ -692,6 +1276,7 @@ impl Parser {
 
         let mut url = String::new();
         let mut pool_size = 10u32;
+        let mut timeout = None;
 
         while !self.check(&TokenKind::Dedent) && !self.is_at_end() {
             self.skip_newlines();
@@ -714,6 +1299,7 @@ impl Parser {
                             self.advance();
                         }
                     }
+                    "timeout" => timeout = self.parse_timeout_directive(),
                     _ => {}
                 }
             }
@@ -729,15 +1315,21 @@ impl Parser {
             name,
             url,
             pool_size,
+            timeout,
             span,
         }))
     }
 
-    /// Parse: `if expr:`
+    /// Parse: `if expr:` or `if let pattern = expr:`
     fn parse_if(&mut self) -> Result<Stmt, ()> {
         let start_span = self.peek().span;
         self.advance(); // consume 'if'
 
+        let binding = if self.match_token(&TokenKind::Let) {
+            Some(self.parse_let_pattern()?)
+        } else {
+            None
+        };
         let condition = self.parse_expression()?;
 
         self.expect(&TokenKind::Colon, "expected ':' after condition")?;
@@ -755,8 +1347,18 @@ impl Parser {
 
         let span = start_span.merge(self.previous().span);
 
-        Ok(Stmt::If {
-            condition,
+        if let Some(pattern) = binding {
+            return Ok(Stmt::IfLet {
+                pattern,
+                value: condition,
+                then_block,
+                else_block,
+                span,
+            });
+        }
+
+        Ok(Stmt::If {
+            condition,
             then_block,
             else_block,
             span,
@@ -789,11 +1391,16 @@ impl Parser {
         })
     }
 
-    /// Parse: `while expr:`
+    /// Parse: `while expr:` or `while let pattern = expr:`
     fn parse_while(&mut self) -> Result<Stmt, ()> {
         let start_span = self.peek().span;
         self.advance(); // consume 'while'
 
+        let binding = if self.match_token(&TokenKind::Let) {
+            Some(self.parse_let_pattern()?)
+        } else {
+            None
+        };
         let condition = self.parse_expression()?;
 
         self.expect(&TokenKind::Colon, "expected ':' after condition")?;
@@ -803,6 +1410,15 @@ impl Parser {
 
         let span = start_span.merge(self.previous().span);
 
+        if let Some(pattern) = binding {
+            return Ok(Stmt::WhileLet {
+                pattern,
+                value: condition,
+                body,
+                span,
+            });
+        }
+
         Ok(Stmt::While {
             condition,
             body,
@@ -810,6 +1426,66 @@ impl Parser {
         })
     }
 
+    /// Parse the `Pattern =` of `if let` and `while let`, after the `let`
+    fn parse_let_pattern(&mut self) -> Result<Pattern, ()> {
+        let pattern = self.parse_pattern()?;
+        self.expect(&TokenKind::Eq, "expected '=' after pattern")?;
+        Ok(pattern)
+    }
+
+    /// Parse: `try: ... catch err: ...`
+    fn parse_try_catch(&mut self) -> Result<Stmt, ()> {
+        let start_span = self.peek().span;
+        self.advance(); // consume 'try'
+
+        self.expect(&TokenKind::Colon, "expected ':' after 'try'")?;
+        self.expect_newline()?;
+        let body = self.parse_block()?;
+
+        self.skip_newlines();
+        if !matches!(&self.peek().kind, TokenKind::Ident(name) if name == "catch") {
+            self.error_at_current("expected 'catch' after 'try' block");
+            return Err(());
+        }
+        self.advance(); // consume 'catch'
+
+        let error_var = self.parse_identifier()?;
+        self.expect(&TokenKind::Colon, "expected ':' after catch binding")?;
+        self.expect_newline()?;
+        let handler = self.parse_block()?;
+
+        let span = start_span.merge(self.previous().span);
+
+        Ok(Stmt::TryCatch {
+            body,
+            error_var,
+            handler,
+            span,
+        })
+    }
+
+    /// Parse: `with expr as name: ...`
+    fn parse_with(&mut self) -> Result<Stmt, ()> {
+        let start_span = self.peek().span;
+        self.advance(); // consume 'with'
+
+        let resource = self.parse_expression()?;
+        self.expect(&TokenKind::As, "expected 'as' after resource in 'with'")?;
+        let name = self.parse_identifier()?;
+        self.expect(&TokenKind::Colon, "expected ':' after 'with' binding")?;
+        self.expect_newline()?;
+        let body = self.parse_block()?;
+
+        let span = start_span.merge(self.previous().span);
+
+        Ok(Stmt::With {
+            resource,
+            name,
+            body,
+            span,
+        })
+    }
+
     /// Parse: `return [expr]`
     fn parse_return(&mut self) -> Result<Stmt, ()> {
         let start_span = self.peek().span;
@@ -822,11 +1498,27 @@ impl Parser {
         };
 
         let span = start_span.merge(self.previous().span);
-        self.expect_newline()?;
+        match &value {
+            Some(value) => self.expect_newline_after(value)?,
+            None => self.expect_newline()?,
+        }
 
         Ok(Stmt::Return { value, span })
     }
 
+    /// Parse: `yield expr`
+    fn parse_yield(&mut self) -> Result<Stmt, ()> {
+        let start_span = self.peek().span;
+        self.advance(); // consume 'yield'
+
+        let value = self.parse_expression()?;
+
+        let span = start_span.merge(self.previous().span);
+        self.expect_newline()?;
+
+        Ok(Stmt::Yield { value, span })
+    }
+
     /// Parse: `break`
     fn parse_break(&mut self) -> Result<Stmt, ()> {
         let span = self.peek().span;
@@ -919,15 +1611,18 @@ impl Parser {
         self.expect(&TokenKind::LParen, "expected '(' after method name")?;
 
         // Parse receiver
+        let mut depth = 0;
+        self.skip_list_layout(&mut depth);
         let receiver = self.parse_method_receiver()?;
+        self.skip_list_layout(&mut depth);
 
         // Parse remaining parameters
         let mut params = Vec::new();
         if self.match_token(&TokenKind::Comma) {
-            if !self.check(&TokenKind::RParen) {
-                params = self.parse_param_list()?;
-            }
+            params = self.parse_wrapped_params(&mut depth)?;
         }
+        self.skip_list_layout(&mut depth);
+        self.close_list_layout(depth)?;
 
         self.expect(&TokenKind::RParen, "expected ')' after parameters")?;
 
@@ -1047,7 +1742,7 @@ impl Parser {
     /// Parse expression statement
     fn parse_expr_stmt(&mut self) -> Result<Stmt, ()> {
         let expr = self.parse_expression()?;
-        self.expect_newline()?;
+        self.expect_newline_after(&expr)?;
         Ok(Stmt::Expr(expr))
     }
 
@@ -1061,17 +1756,19 @@ impl Parser {
 
         let mut statements = Vec::new();
 
-        while !self.check(&TokenKind::Dedent) && !self.is_at_end() {
-            self.skip_newlines();
-            if self.check(&TokenKind::Dedent) {
-                break;
-            }
+        self.nested(|parser| {
+            while !parser.check(&TokenKind::Dedent) && !parser.is_at_end() {
+                parser.skip_newlines();
+                if parser.check(&TokenKind::Dedent) {
+                    break;
+                }
 
-            match self.parse_statement() {
-                Ok(stmt) => statements.push(stmt),
-                Err(_) => self.synchronize(),
+                match parser.parse_statement() {
+                    Ok(stmt) => statements.push(stmt),
+                    Err(_) => parser.synchronize(),
+                }
             }
-        }
+        });
 
         self.match_token(&TokenKind::Dedent);
 
@@ -1091,8 +1788,8 @@ impl Parser {
                 break;
             }
 
-            // Check if it's a method (starts with 'fn')
-            if self.check(&TokenKind::Fn) {
+            // Check if it's a method (starts with 'fn' or a visibility)
+            if self.check(&TokenKind::Fn) || self.check(&TokenKind::Pub) {
                 methods.push(self.parse_method()?);
             } else {
                 // Parse field
@@ -1100,10 +1797,16 @@ impl Parser {
                 let name = self.parse_identifier()?;
                 self.expect(&TokenKind::Colon, "expected ':' after field name")?;
                 let ty = self.parse_type()?;
+                let default = if self.match_token(&TokenKind::Eq) {
+                    Some(self.parse_expression()?)
+                } else {
+                    None
+                };
 
                 fields.push(Field {
                     name,
                     ty,
+                    default,
                     span: span.merge(self.previous().span),
                 });
             }
@@ -1119,27 +1822,30 @@ impl Parser {
     /// Parse method declaration inside a struct
     fn parse_method(&mut self) -> Result<MethodDecl, ()> {
         let start_span = self.peek().span;
-        let is_pub = false; // TODO: implement pub
+        let visibility = self.parse_visibility()?;
 
-        self.advance(); // consume 'fn'
+        self.expect(&TokenKind::Fn, "expected `fn` after a visibility modifier")?;
 
         let name = self.parse_identifier()?;
 
         self.expect(&TokenKind::LParen, "expected '(' after method name")?;
 
         // Parse receiver (self, &self, or &mut self)
+        let mut depth = 0;
+        self.skip_list_layout(&mut depth);
         let receiver = self.parse_method_receiver()?;
+        self.skip_list_layout(&mut depth);
 
         // Parse remaining parameters
         let mut params = Vec::new();
         if receiver != MethodReceiver::Value || self.check(&TokenKind::Comma) {
             // If we have a receiver, skip comma before additional params
             if self.match_token(&TokenKind::Comma) || receiver == MethodReceiver::Value {
-                if !self.check(&TokenKind::RParen) {
-                    params = self.parse_param_list()?;
-                }
+                params = self.parse_wrapped_params(&mut depth)?;
             }
         }
+        self.skip_list_layout(&mut depth);
+        self.close_list_layout(depth)?;
 
         self.expect(&TokenKind::RParen, "expected ')' after parameters")?;
 
@@ -1164,7 +1870,7 @@ impl Parser {
             params,
             return_type,
             is_async,
-            is_pub,
+            visibility,
             receiver,
             body,
             span,
@@ -1275,6 +1981,48 @@ impl Parser {
         self.parse_or_pattern()
     }
 
+    /// Parse an integer literal in a pattern, allowing a leading `-`
+    fn parse_int_pattern_bound(&mut self) -> Result<Expr, ()> {
+        let start_span = self.peek().span;
+        let negative = self.match_token(&TokenKind::Minus);
+        let token = self.peek().clone();
+        match token.kind {
+            TokenKind::IntLit(n) => {
+                self.advance();
+                let value = if negative { -n } else { n };
+                Ok(Expr::IntLit(value, start_span.merge(token.span)))
+            }
+            _ => {
+                self.error_at_current("expected integer in pattern");
+                Err(())
+            }
+        }
+    }
+
+    /// Parse the rest of a range pattern, starting at the `..` or `..=`
+    fn parse_range_pattern(&mut self, start: Option<Expr>, start_span: Span) -> Result<Pattern, ()> {
+        let inclusive = self.check(&TokenKind::DotDotEq);
+        self.advance();
+
+        // The upper bound may only be left out of an exclusive range
+        let end = if matches!(self.peek().kind, TokenKind::IntLit(_) | TokenKind::Minus) {
+            Some(self.parse_int_pattern_bound()?)
+        } else if inclusive || start.is_none() {
+            self.error_at_current("expected upper bound of range pattern");
+            return Err(());
+        } else {
+            None
+        };
+
+        let span = start_span.merge(self.previous().span);
+        Ok(Pattern::Range {
+            start: start.map(Box::new),
+            end: end.map(Box::new),
+            inclusive,
+            span,
+        })
+    }
+
     /// Parse or pattern: `A | B | C`
     fn parse_or_pattern(&mut self) -> Result<Pattern, ()> {
         let mut left = self.parse_primary_pattern()?;
@@ -1305,12 +2053,20 @@ impl Parser {
                 Ok(Pattern::Wildcard(token.span))
             }
 
-            // Literal patterns
-            TokenKind::IntLit(n) => {
-                let n = *n;
-                self.advance();
-                Ok(Pattern::Literal(Expr::IntLit(n, token.span)))
+            // Integer literal or range: `3`, `-1`, `1..10`, `1..=9`, `10..`
+            TokenKind::IntLit(_) | TokenKind::Minus => {
+                let start = self.parse_int_pattern_bound()?;
+                if self.check(&TokenKind::DotDot) || self.check(&TokenKind::DotDotEq) {
+                    self.parse_range_pattern(Some(start), token.span)
+                } else {
+                    Ok(Pattern::Literal(start))
+                }
             }
+
+            // Range with no lower bound: `..10`, `..=9`
+            TokenKind::DotDot | TokenKind::DotDotEq => self.parse_range_pattern(None, token.span),
+
+            // Literal patterns
             TokenKind::FloatLit(n) => {
                 let n = *n;
                 self.advance();
@@ -1830,7 +2586,7 @@ impl Parser {
 
     /// Parse multiplication/division
     fn parse_multiplicative(&mut self) -> Result<Expr, ()> {
-        let mut left = self.parse_unary()?;
+        let mut left = self.parse_cast()?;
 
         while self.check(&TokenKind::Star)
             || self.check(&TokenKind::Slash)
@@ -1844,7 +2600,7 @@ impl Parser {
                 _ => unreachable!(),
             };
 
-            let right = self.parse_unary()?;
+            let right = self.parse_cast()?;
             let span = left.span().merge(right.span());
 
             left = Expr::Binary {
@@ -1858,6 +2614,32 @@ impl Parser {
         Ok(left)
     }
 
+    /// Parse `expr as int` / `expr as float`; only a primitive type after
+    /// `as` makes a cast, so other uses of `as` (imports, `with ... as name`)
+    /// are left to the caller. `bool` and `string` targets are parsed so the
+    /// checker can reject them with a clear message
+    fn parse_cast(&mut self) -> Result<Expr, ()> {
+        let mut expr = self.parse_unary()?;
+
+        while self.check(&TokenKind::As)
+            && matches!(
+                self.peek_next().kind,
+                TokenKind::IntType | TokenKind::FloatType | TokenKind::BoolType | TokenKind::StringType
+            )
+        {
+            self.advance(); // consume 'as'
+            let ty = self.parse_type()?;
+            let span = expr.span().merge(self.previous().span);
+            expr = Expr::Cast {
+                expr: Box::new(expr),
+                ty,
+                span,
+            };
+        }
+
+        Ok(expr)
+    }
+
     /// Parse unary
     fn parse_unary(&mut self) -> Result<Expr, ()> {
         if self.match_token(&TokenKind::Minus) {
@@ -1917,6 +2699,26 @@ impl Parser {
 
         loop {
             if self.match_token(&TokenKind::Dot) {
+                if let TokenKind::IntLit(index) = self.peek().kind {
+                    self.advance();
+                    let span = expr.span().merge(self.previous().span);
+                    expr = Expr::TupleIndex {
+                        object: Box::new(expr),
+                        index: index as usize,
+                        span,
+                    };
+                    continue;
+                }
+                // `t.0.1` lexes the indices as the float `0.1`
+                if let TokenKind::FloatLit(_) = self.peek().kind {
+                    self.diagnostics.push(
+                        Diagnostic::error("nested tuple indices must be parenthesized")
+                            .with_code(ErrorCode::INVALID_SYNTAX)
+                            .with_label(self.peek().span, "read as a float literal")
+                            .with_help("write the inner access in parentheses, e.g. `(t.0).1`"),
+                    );
+                    return Err(());
+                }
                 let field = self.parse_field_name()?;
 
                 // Check if it's a method call
@@ -1960,6 +2762,34 @@ impl Parser {
                     span,
                 };
             } else if self.match_token(&TokenKind::Question) {
+                // Optional chaining: expr?.field. The checker reads it as
+                // `(expr?).field` when `expr` is a `Result`.
+                if self.check(&TokenKind::Dot) {
+                    let try_span = expr.span().merge(self.previous().span);
+                    self.advance();
+                    let field = self.parse_field_name()?;
+                    // `expr?.method()` is always a propagating `?`
+                    if self.match_token(&TokenKind::LParen) {
+                        let args = self.parse_arg_list()?;
+                        self.expect(&TokenKind::RParen, "expected ')' after arguments")?;
+                        let span = expr.span().merge(self.previous().span);
+                        expr = Expr::MethodCall {
+                            object: Box::new(Expr::Try { expr: Box::new(expr), span: try_span }),
+                            method: field,
+                            args,
+                            span,
+                        };
+                        continue;
+                    }
+                    let span = expr.span().merge(self.previous().span);
+                    expr = Expr::OptionalFieldAccess {
+                        object: Box::new(expr),
+                        field,
+                        span,
+                    };
+                    continue;
+                }
+
                 // Try operator: expr?
                 let span = expr.span().merge(self.previous().span);
                 expr = Expr::Try {
@@ -2019,6 +2849,11 @@ impl Parser {
                 self.advance();
                 Ok(Expr::FloatLit(n, token.span))
             }
+            TokenKind::DurationLit(ms) => {
+                let ms = *ms;
+                self.advance();
+                Ok(Expr::DurationLit(ms, token.span))
+            }
             TokenKind::StringLit(s) => {
                 let s = s.clone();
                 self.advance();
@@ -2141,7 +2976,18 @@ impl Parser {
                 self.advance();
                 let mut elements = Vec::new();
                 if !self.check(&TokenKind::RBracket) {
-                    elements.push(self.parse_expression()?);
+                    let first = self.parse_expression()?;
+
+                    // [value; count]
+                    if self.match_token(&TokenKind::Semicolon) {
+                        let count = self.parse_expression()?;
+                        self.expect(&TokenKind::RBracket, "expected ']' after array length")?;
+                        let span = token.span.merge(self.previous().span);
+                        return Ok(Expr::ArrayRepeat { value: Box::new(first), count: Box::new(count), span });
+                    }
+
+                    elements.push(first);
+
                     while self.match_token(&TokenKind::Comma) {
                         elements.push(self.parse_expression()?);
                     }
@@ -2150,6 +2996,23 @@ impl Parser {
                 let span = token.span.merge(self.previous().span);
                 Ok(Expr::ArrayLit(elements, span))
             }
+            // A brace without a type name before it opens a map literal
+            TokenKind::LBrace => {
+                self.advance();
+                let mut entries = Vec::new();
+                while !self.check(&TokenKind::RBrace) && !self.is_at_end() {
+                    let key = self.parse_expression()?;
+                    self.expect(&TokenKind::Colon, "expected ':' after map key")?;
+                    let value = self.parse_expression()?;
+                    entries.push((key, value));
+                    if !self.match_token(&TokenKind::Comma) {
+                        break;
+                    }
+                }
+                self.expect(&TokenKind::RBrace, "expected '}' after map entries")?;
+                let span = token.span.merge(self.previous().span);
+                Ok(Expr::MapLit { entries, span })
+            }
             TokenKind::Ident(name) => {
                 let name = name.clone();
                 self.advance();
@@ -2177,6 +3040,21 @@ impl Parser {
                     return Ok(Expr::StructLit { name, fields, span });
                 }
 
+                // Associated function call: `Type::func(args)`, or a unit
+                // enum variant: `Color::Red`
+                if self.match_token(&TokenKind::ColonColon) {
+                    let func = self.parse_identifier()?;
+                    if !self.check(&TokenKind::LParen) {
+                        let span = token.span.merge(self.previous().span);
+                        return Ok(Expr::Path { type_name: name, name: func, span });
+                    }
+                    self.expect(&TokenKind::LParen, "expected '(' after associated function name")?;
+                    let args = self.parse_arg_list()?;
+                    self.expect(&TokenKind::RParen, "expected ')' after arguments")?;
+                    let span = token.span.merge(self.previous().span);
+                    return Ok(Expr::AssocCall { type_name: name, func, args, span });
+                }
+
                 Ok(Expr::Ident(name, token.span))
             }
             // `db` can be used as an expression (db.main.query...)
@@ -2197,6 +3075,10 @@ impl Parser {
             TokenKind::Pipe => {
                 self.parse_closure()
             }
+            // Async block: async: <indented block>
+            TokenKind::Async => {
+                self.parse_async_block()
+            }
             _ => {
                 self.error_at_current("expression expected");
                 Err(())
@@ -2251,11 +3133,15 @@ impl Parser {
                 // Multi-line block
                 let block = self.parse_block()?;
                 ClosureBody::Block(block)
+            } else if self.check(&TokenKind::Return) {
+                ClosureBody::Block(vec![self.parse_closure_return()?])
             } else {
                 // Single expression after colon
                 let expr = self.parse_expression()?;
                 ClosureBody::Expr(Box::new(expr))
             }
+        } else if self.check(&TokenKind::Return) {
+            ClosureBody::Block(vec![self.parse_closure_return()?])
         } else {
             // Expression body
             let expr = self.parse_expression()?;
@@ -2272,6 +3158,36 @@ impl Parser {
         })
     }
 
+    /// Parse the one-line body `|x| return x + 1`; like a `return` in a block
+    /// body, it returns from the closure, not the enclosing function
+    fn parse_closure_return(&mut self) -> Result<Stmt, ()> {
+        let start_span = self.peek().span;
+        self.advance(); // consume 'return'
+
+        let value = if matches!(self.peek().kind, TokenKind::Newline | TokenKind::RParen | TokenKind::Comma) {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+
+        let span = start_span.merge(self.previous().span);
+        Ok(Stmt::Return { value, span })
+    }
+
+    /// Parse async block expression: `async:` followed by an indented block
+    fn parse_async_block(&mut self) -> Result<Expr, ()> {
+        let start_span = self.peek().span;
+        self.advance(); // consume 'async'
+
+        self.expect(&TokenKind::Colon, "expected ':' after 'async'")?;
+        self.expect(&TokenKind::Newline, "expected newline before async block")?;
+        let body = self.parse_block()?;
+
+        let span = start_span.merge(self.previous().span);
+
+        Ok(Expr::AsyncBlock { body, span })
+    }
+
     // =========================================
     // Parsing helpers
     // =========================================
@@ -2364,6 +3280,11 @@ impl Parser {
                 self.advance();
                 return Ok(Type::String);
             }
+            // `dyn` is contextual so it stays usable as an identifier
+            TokenKind::Ident(n) if n == "dyn" && matches!(self.peek_next().kind, TokenKind::Ident(_)) => {
+                self.advance();
+                return Ok(Type::Dyn(self.parse_identifier()?));
+            }
             TokenKind::Ident(n) => {
                 let name = n.clone();
                 self.advance();
@@ -2397,25 +3318,92 @@ impl Parser {
         Ok(Type::Named(name))
     }
 
+    /// Skips the line breaks of a parenthesized list split across lines,
+    /// tracking the indentation the lexer opened or closed inside it
+    fn skip_list_layout(&mut self, depth: &mut usize) {
+        loop {
+            match self.peek().kind {
+                TokenKind::Newline => {}
+                TokenKind::Indent => *depth += 1,
+                TokenKind::Dedent if *depth > 0 => *depth -= 1,
+                _ => return,
+            }
+            self.advance();
+        }
+    }
+
+    /// The indentation opened by wrapped lines must be closed before the
+    /// `)`, or the body that follows would lose its own indentation
+    fn close_list_layout(&mut self, depth: usize) -> Result<(), ()> {
+        if depth > 0 {
+            let span = self.peek().span;
+            self.diagnostics.push(
+                Diagnostic::error("parameter list split across lines must end with `)` on its own line")
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(span, "`)` is still inside the indented parameters")
+                    .with_help("move the `)` to a new line, at the indentation of the line that opens the list")
+            );
+            return Err(());
+        }
+        Ok(())
+    }
+
     fn parse_param_list(&mut self) -> Result<Vec<Param>, ()> {
+        // Parameters may be wrapped onto indented lines
+        let mut depth = 0;
+        let params = self.parse_wrapped_params(&mut depth)?;
+        self.close_list_layout(depth)?;
+        Ok(params)
+    }
+
+    /// Parses parameters up to the `)`, skipping line breaks; `depth` counts
+    /// the indentation opened so far, including before a method receiver
+    fn parse_wrapped_params(&mut self, depth: &mut usize) -> Result<Vec<Param>, ()> {
         let mut params = Vec::new();
+        self.skip_list_layout(depth);
 
         if !self.check(&TokenKind::RParen) {
             loop {
                 let span = self.peek().span;
-                let name = self.parse_identifier()?;
+                let mut pattern = None;
+                // `body` names the request body of a `@route` function
+                let name = if self.match_token(&TokenKind::Body) {
+                    "body".to_string()
+                } else if self.check(&TokenKind::LParen) {
+                    let destructure = self.parse_pattern()?;
+                    if destructure.irrefutable_text().is_none() {
+                        self.diagnostics.push(
+                            Diagnostic::error("refutable pattern in function parameter")
+                                .with_code(ErrorCode::INVALID_SYNTAX)
+                                .with_label(destructure.span(), "this pattern may not match every argument")
+                                .with_help("bind the parameter to a name and `match` on it in the body"),
+                        );
+                    }
+                    pattern = Some(destructure);
+                    // Lowering unpacks the argument from this name into the pattern's variables
+                    format!("__param{}", params.len())
+                } else {
+                    self.parse_identifier()?
+                };
                 self.expect(&TokenKind::Colon, "expected ':' after parameter name")?;
                 let ty = self.parse_type()?;
 
                 params.push(Param {
                     name,
+                    pattern,
                     ty,
                     span: span.merge(self.previous().span),
                 });
 
+                self.skip_list_layout(depth);
                 if !self.match_token(&TokenKind::Comma) {
                     break;
                 }
+                // A trailing comma may precede the `)`
+                self.skip_list_layout(depth);
+                if self.check(&TokenKind::RParen) {
+                    break;
+                }
             }
         }
 
@@ -2426,15 +3414,40 @@ impl Parser {
         let mut args = Vec::new();
 
         if !self.check(&TokenKind::RParen) {
-            args.push(self.parse_expression()?);
+            args.push(self.parse_arg()?);
             while self.match_token(&TokenKind::Comma) {
-                args.push(self.parse_expression()?);
+                args.push(self.parse_arg()?);
             }
         }
 
         Ok(args)
     }
 
+    /// Parse a call argument: `expr`, `name: expr` or `..expr`
+    fn parse_arg(&mut self) -> Result<Expr, ()> {
+        // `..values` spreads an array; a bare `..` or `..=end` stays a range
+        if self.check(&TokenKind::DotDot)
+            && !matches!(self.peek_next().kind, TokenKind::RParen | TokenKind::Comma)
+        {
+            let start_span = self.advance().span;
+            let expr = self.parse_or()?;
+            let span = start_span.merge(expr.span());
+            return Ok(Expr::Spread { expr: Box::new(expr), span });
+        }
+
+        if let TokenKind::Ident(name) = &self.peek().kind {
+            if self.peek_next().kind == TokenKind::Colon {
+                let name = name.clone();
+                let start_span = self.advance().span;
+                self.advance(); // consume ':'
+                let value = self.parse_expression()?;
+                let span = start_span.merge(value.span());
+                return Ok(Expr::NamedArg { name, value: Box::new(value), span });
+            }
+        }
+        self.parse_expression()
+    }
+
     fn parse_http_method(&mut self) -> Result<HttpMethod, ()> {
         let method = match &self.peek().kind {
             TokenKind::Get => HttpMethod::Get,
@@ -2468,6 +3481,12 @@ impl Parser {
                     path.push_str(segment);
                     self.advance();
                 }
+                // Keywords are plain segments right after a '/', as in `/api/v1`;
+                // `async` stays the route modifier so `api GET / async:` works
+                kind if kind.is_keyword() && *kind != TokenKind::Async && path.ends_with('/') => {
+                    path.push_str(&kind.to_string());
+                    self.advance();
+                }
                 TokenKind::LBrace => {
                     self.advance();
                     path.push('{');
@@ -2530,6 +3549,15 @@ impl Parser {
         }
     }
 
+    /// Ends a statement whose value may end with an indented block, which
+    /// already consumed the line ending along with its closing dedent
+    fn expect_newline_after(&mut self, value: &Expr) -> Result<(), ()> {
+        if ends_with_block(value) && self.previous().kind == TokenKind::Dedent {
+            return Ok(());
+        }
+        self.expect_newline()
+    }
+
     fn expect_newline(&mut self) -> Result<(), ()> {
         if self.check(&TokenKind::Newline) || self.check(&TokenKind::Eof) || self.check(&TokenKind::Dedent) {
             self.match_token(&TokenKind::Newline);
@@ -2541,6 +3569,18 @@ impl Parser {
     }
 }
 
+/// Whether `expr` ends with an indented block: an `async:` block, a `match`
+/// or a closure with a block body, possibly awaited or assigned
+fn ends_with_block(expr: &Expr) -> bool {
+    match expr {
+        Expr::AsyncBlock { .. } | Expr::Match { .. } => true,
+        Expr::Closure { body, .. } => matches!(body, ClosureBody::Block(_)),
+        Expr::Await { expr, .. } => ends_with_block(expr),
+        Expr::Binary { right, .. } => ends_with_block(right),
+        _ => false,
+    }
+}
+
 // =========================================
 // Trait to get span from Expr
 // =========================================
@@ -2550,6 +3590,7 @@ impl Expr {
         match self {
             Expr::IntLit(_, span) => *span,
             Expr::FloatLit(_, span) => *span,
+            Expr::DurationLit(_, span) => *span,
             Expr::StringLit(_, span) => *span,
             Expr::BoolLit(_, span) => *span,
             Expr::None(span) => *span,
@@ -2558,7 +3599,11 @@ impl Expr {
             Expr::Unary { span, .. } => *span,
             Expr::Call { span, .. } => *span,
             Expr::MethodCall { span, .. } => *span,
+            Expr::NamedArg { span, .. } => *span,
+            Expr::Spread { span, .. } => *span,
             Expr::FieldAccess { span, .. } => *span,
+            Expr::TupleIndex { span, .. } => *span,
+            Expr::OptionalFieldAccess { span, .. } => *span,
             Expr::Index { span, .. } => *span,
             Expr::Await { span, .. } => *span,
             Expr::Borrow { span, .. } => *span,
@@ -2567,12 +3612,30 @@ impl Expr {
             Expr::Some(_, span) => *span,
             Expr::StructLit { span, .. } => *span,
             Expr::ArrayLit(_, span) => *span,
+            Expr::MapLit { span, .. } => *span,
+            Expr::ArrayRepeat { span, .. } => *span,
+            Expr::Cast { span, .. } => *span,
             Expr::Match { span, .. } => *span,
             Expr::Try { span, .. } => *span,
             Expr::Closure { span, .. } => *span,
             Expr::StringInterpolation { span, .. } => *span,
             Expr::Tuple { span, .. } => *span,
             Expr::Range { span, .. } => *span,
+            Expr::AsyncBlock { span, .. } => *span,
+            Expr::AssocCall { span, .. } => *span,
+            Expr::Path { span, .. } => *span,
+        }
+    }
+
+    /// The value of a negative integer constant such as `-1`
+    pub fn negative_constant(&self) -> Option<i64> {
+        match self {
+            Expr::Unary { op: UnaryOp::Neg, expr, .. } => match expr.as_ref() {
+                Expr::IntLit(n, _) if *n > 0 => Some(-n),
+                _ => None,
+            },
+            Expr::IntLit(n, _) if *n < 0 => Some(*n),
+            _ => None,
         }
     }
 }
@@ -2590,6 +3653,33 @@ impl Pattern {
             Pattern::Range { span, .. } => *span,
         }
     }
+
+    /// Source text of a pattern that matches every value of its type
+    /// (tuples, struct fields, bindings and `_`), or `None` if it can fail
+    pub fn irrefutable_text(&self) -> Option<String> {
+        match self {
+            Pattern::Wildcard(_) => Some("_".to_string()),
+            Pattern::Ident { name, mutable, .. } => {
+                Some(if *mutable { format!("mut {}", name) } else { name.clone() })
+            }
+            Pattern::Tuple(patterns, _) => {
+                let parts = patterns.iter().map(Pattern::irrefutable_text).collect::<Option<Vec<_>>>()?;
+                Some(format!("({})", parts.join(", ")))
+            }
+            Pattern::Struct { name, fields, .. } => {
+                let mut parts = Vec::new();
+                for (field, pattern) in fields {
+                    match pattern {
+                        Some(pattern) => parts.push(format!("{}: {}", field, pattern.irrefutable_text()?)),
+                        None => parts.push(field.clone()),
+                    }
+                }
+                parts.push("..".to_string());
+                Some(format!("{} {{ {} }}", name, parts.join(", ")))
+            }
+            Pattern::Literal(_) | Pattern::Variant { .. } | Pattern::Or(..) | Pattern::Range { .. } => None,
+        }
+    }
 }
 
 /// Helper function to parse
@@ -2634,6 +3724,20 @@ mod tests {
         assert!(matches!(program.statements[0], Stmt::Struct(_)));
     }
 
+    #[test]
+    fn test_parse_generic_enum() {
+        let (program, diags) = parse_source("enum Tree<T>:\n    Leaf(T)\n    Empty\n");
+        assert!(!diags.has_errors());
+        match &program.statements[0] {
+            Stmt::Enum(e) => {
+                assert_eq!(e.generic_params.len(), 1);
+                assert_eq!(e.generic_params[0].name, "T");
+                assert_eq!(e.variants.len(), 2);
+            }
+            other => panic!("expected enum, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_parse_api() {
         let (program, diags) = parse_source("api GET /health:\n    return string\n\n    return \"ok\"\n");
@@ -2642,6 +3746,587 @@ mod tests {
         assert!(matches!(program.statements[0], Stmt::Api(_)));
     }
 
+    #[test]
+    fn test_recovery_reports_every_bad_statement_in_block() {
+        let source = "fn f(x: int) -> int:\n    let a = (x +\n    print(a)\n    let b = x * * 2\n    return a\n\nfn g() -> int:\n    return 1\n";
+        let (program, diags) = parse_source(source);
+        let lines: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.labels[0].span.start.line)
+            .collect();
+        assert_eq!(lines, vec![2, 4]);
+
+        // The rest of the block and the following function survive
+        assert_eq!(program.statements.len(), 2);
+        match &program.statements[0] {
+            Stmt::Fn(f) => assert_eq!(f.body.len(), 2),
+            other => panic!("expected fn, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_cfg_attribute() {
+        let (program, diags) = parse_source("@cfg(debug)\nfn trace() -> int:\n    return 1\n");
+        assert!(!diags.has_errors());
+        match &program.statements[0] {
+            Stmt::Attributed { attrs, item, .. } => {
+                assert_eq!(attrs[0].name, "cfg");
+                assert_eq!(attrs[0].args, vec!["debug".to_string()]);
+                assert!(matches!(item.as_ref(), Stmt::Fn(f) if f.name == "trace"));
+            }
+            other => panic!("expected attributed item, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("@hot\nfn f() -> int:\n    return 1\n");
+        assert!(!diags.has_errors());
+        assert!(diags.iter().any(|d| d.message.contains("unknown attribute `@hot`")));
+
+        let (_, diags) = parse_source("@allow(unused, shadowing)\nfn f() -> int:\n    return 1\n");
+        assert!(diags.is_empty());
+
+        let (_, diags) = parse_source("@allow(unusd)\nfn f() -> int:\n    return 1\n");
+        assert!(!diags.has_errors());
+        assert!(diags.iter().any(|d| d.code == Some(ErrorCode::UNKNOWN_LINT) && d.message == "unknown lint `unusd`"));
+    }
+
+    #[test]
+    fn test_parse_hint_attributes() {
+        let (program, diags) = parse_source("@inline(always)\n@cold\nfn f() -> int:\n    return 1\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::Attributed { attrs, .. } => {
+                assert!(attrs.iter().all(Attribute::is_hint));
+                assert_eq!(attrs[0].args, vec!["always".to_string()]);
+            }
+            other => panic!("expected attributed item, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("@inline\nstruct Point:\n    x: int\n");
+        assert!(diags.iter().any(|d| d.message == "`@inline` can only be applied to functions"));
+
+        let (_, diags) = parse_source("@inline(sometimes)\nfn f() -> int:\n    return 1\n");
+        assert!(diags.iter().any(|d| d.message == "invalid arguments to `@inline`"));
+    }
+
+    #[test]
+    fn test_parse_field_default() {
+        let (program, diags) = parse_source("struct Config:\n    port: int = 8080\n    debug: bool\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::Struct(s) => {
+                assert!(matches!(s.fields[0].default, Some(Expr::IntLit(8080, _))));
+                assert!(s.fields[1].default.is_none());
+            }
+            other => panic!("expected struct, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_map_literal() {
+        let (program, diags) = parse_source("let m = {\"a\": 1, \"b\": 2,}\nlet e = {}\nlet p = Point { x: 1 }\n");
+        assert!(diags.is_empty());
+        assert!(matches!(&program.statements[0], Stmt::Let { value: Expr::MapLit { entries, .. }, .. } if entries.len() == 2));
+        assert!(matches!(&program.statements[1], Stmt::Let { value: Expr::MapLit { entries, .. }, .. } if entries.is_empty()));
+        assert!(matches!(&program.statements[2], Stmt::Let { value: Expr::StructLit { .. }, .. }));
+    }
+
+    #[test]
+    fn test_parse_tuple_index() {
+        let (program, diags) = parse_source("let x = (p.0).1 + p.1\n");
+        assert!(diags.is_empty());
+        let Stmt::Let { value: Expr::Binary { left, right, .. }, .. } = &program.statements[0] else {
+            panic!("expected let with binary value");
+        };
+        assert!(matches!(right.as_ref(), Expr::TupleIndex { index: 1, .. }));
+        match left.as_ref() {
+            Expr::TupleIndex { object, index: 1, .. } => {
+                assert!(matches!(object.as_ref(), Expr::TupleIndex { index: 0, .. }));
+            }
+            other => panic!("expected tuple index, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("let x = p.0.1\n");
+        assert!(diags.iter().any(|d| d.message == "nested tuple indices must be parenthesized"));
+    }
+
+    #[test]
+    fn test_parse_wrapped_param_list() {
+        let source = "fn add3(\n    a: int,\n    b: int,\n    c: int,\n) -> int:\n    return a + b + c\n\nfn next() -> int:\n    return add3(1, 2, 3)\n";
+        let (program, diags) = parse_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags);
+        assert_eq!(program.statements.len(), 2);
+        match &program.statements[0] {
+            Stmt::Fn(f) => {
+                let names: Vec<_> = f.params.iter().map(|p| p.name.as_str()).collect();
+                assert_eq!(names, vec!["a", "b", "c"]);
+                assert_eq!(f.return_type, Some(Type::Int));
+                assert_eq!(f.body.len(), 1);
+            }
+            other => panic!("expected function, got {:?}", other),
+        }
+
+        let (program, diags) = parse_source("struct P:\n    x: int\n\n    fn scale(\n        self,\n        k: int,\n    ) -> int:\n        return self.x * k\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::Struct(s) => assert_eq!(s.methods[0].params.len(), 1),
+            other => panic!("expected struct, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("fn add(\n    a: int,\n    b: int) -> int:\n    return a + b\n");
+        assert!(diags.iter().any(|d| d.message == "parameter list split across lines must end with `)` on its own line"));
+    }
+
+    #[test]
+    fn test_parse_derive_attribute() {
+        let (program, diags) = parse_source("@derive(Copy, PartialEq, Serialize)\nstruct Point:\n    x: int\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::Attributed { attrs, .. } => assert_eq!(attrs[0].derives().collect::<Vec<_>>(), vec!["Copy", "PartialEq"]),
+            other => panic!("expected attributed item, got {:?}", other),
+        }
+        assert!(diags.iter().any(|d| d.message == "`Serialize` cannot be derived"));
+
+        let errors = |source: &str| {
+            let (_, diags) = parse_source(source);
+            diags.iter().map(|d| d.message.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(errors("@derive()\nstruct S:\n    a: int\n"), vec!["`@derive` takes at least one trait name"]);
+        assert_eq!(errors("@derive(Clone)\nfn f() -> int:\n    return 1\n"), vec!["`@derive` can only be applied to structs"]);
+    }
+
+    #[test]
+    fn test_parse_route_attribute() {
+        let (program, diags) = parse_source("@route(GET, \"/users/{id:int}\")\nfn get_user(id: int) -> int:\n    return id\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        let api = program.statements[0].attributed_route().expect("expected a route");
+        assert_eq!(api.method, HttpMethod::Get);
+        assert_eq!(api.path, "/users/{id:int}");
+        assert_eq!(api.handler.len(), 1);
+
+        let errors = |source: &str| {
+            let (_, diags) = parse_source(source);
+            diags.iter().map(|d| d.message.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(errors("@route(GET)\nfn f() -> int:\n    return 1\n"), vec!["`@route` takes a method and a path"]);
+        assert_eq!(errors("@route(FETCH, \"/x\")\nfn f() -> int:\n    return 1\n"), vec!["unknown HTTP method `FETCH` in `@route`"]);
+        assert_eq!(errors("@route(GET, \"x/{id\")\nfn f() -> int:\n    return 1\n"), vec!["invalid route path `x/{id`"]);
+        assert_eq!(errors("@route(GET, \"/x\")\nstruct S:\n    a: int\n"), vec!["`@route` can only be applied to functions"]);
+    }
+
+    #[test]
+    fn test_parse_visibility() {
+        let source = "pub fn a() -> int:\n    return 1\n\npub(crate) struct B:\n    x: int\n\n    pub(crate) fn get(&self) -> int:\n        return self.x\n\nfn c():\n    pass\n";
+        let (program, diags) = parse_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags);
+        assert!(matches!(&program.statements[0], Stmt::Fn(f) if f.visibility == Visibility::Public));
+        match &program.statements[1] {
+            Stmt::Struct(s) => {
+                assert_eq!(s.visibility, Visibility::Crate);
+                assert_eq!(s.methods[0].visibility, Visibility::Crate);
+            }
+            other => panic!("expected struct, got {:?}", other),
+        }
+        assert!(matches!(&program.statements[2], Stmt::Fn(f) if f.visibility == Visibility::Private));
+
+        let (program, diags) = parse_source("pub const MAX: int = 10
+const MIN: int = 0
+");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        assert!(matches!(&program.statements[0], Stmt::Const { visibility: Visibility::Public, .. }));
+        assert!(matches!(&program.statements[1], Stmt::Const { visibility: Visibility::Private, .. }));
+
+        let errors = |source: &str| {
+            let (_, diags) = parse_source(source);
+            diags.iter().map(|d| d.message.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(errors("pub(super) fn f():\n    pass\n"), vec!["unknown visibility `pub(super)`"]);
+        assert_eq!(errors("pub let x = 1\n"), vec!["expected `fn`, `struct` or `const` after a visibility modifier, found `let`"]);
+    }
+
+    #[test]
+    fn test_parse_spread_argument() {
+        let (program, diags) = parse_source("sum(..nums, ..=3, ..)\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::Expr(Expr::Call { args, .. }) => {
+                assert!(matches!(&args[0], Expr::Spread { expr, .. } if matches!(expr.as_ref(), Expr::Ident(name, _) if name == "nums")));
+                assert!(matches!(&args[1], Expr::Range { start: None, inclusive: true, .. }));
+                assert!(matches!(&args[2], Expr::Range { start: None, end: None, .. }));
+            }
+            other => panic!("expected call, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_const_fn_and_array_repeat() {
+        let (program, diags) = parse_source("pub const fn square(x: int) -> int:\n    return x * x\n\nlet grid = [0; square(4)]\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        assert!(matches!(&program.statements[0], Stmt::Fn(f) if f.is_const && f.visibility == Visibility::Public));
+        match &program.statements[1] {
+            Stmt::Let { value: Expr::ArrayRepeat { value, count, .. }, .. } => {
+                assert!(matches!(value.as_ref(), Expr::IntLit(0, _)));
+                assert!(matches!(count.as_ref(), Expr::Call { .. }));
+            }
+            other => panic!("expected array repeat, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_with_block() {
+        let (program, diags) = parse_source("with open_file(\"log.txt\") as file:\n    file.write(\"hi\")\n\nwith(1)\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::With { resource, name, body, .. } => {
+                assert!(matches!(resource, Expr::Call { .. }));
+                assert_eq!(name, "file");
+                assert_eq!(body.len(), 1);
+            }
+            other => panic!("expected with block, got {:?}", other),
+        }
+        // `with` stays an ordinary identifier elsewhere
+        assert!(matches!(&program.statements[1], Stmt::Expr(Expr::Call { .. })));
+
+        let (_, diags) = parse_source("with conn:\n    pass\n");
+        assert_eq!(diags.iter().next().map(|d| d.message.as_str()), Some("expected 'as' after resource in 'with'"));
+    }
+
+    #[test]
+    fn test_parse_enum_discriminants_and_casts() {
+        let source = "enum Status:\n    Active = 1\n    Paused\n    Gone = -1\n\nlet code = status as int * 2\n";
+        let (program, diags) = parse_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::Enum(e) => {
+                let values = e.discriminants().expect("enum without data");
+                assert_eq!(values, vec![("Active".to_string(), 1), ("Paused".to_string(), 2), ("Gone".to_string(), -1)]);
+            }
+            other => panic!("expected enum, got {:?}", other),
+        }
+        match &program.statements[1] {
+            Stmt::Let { value: Expr::Binary { left, op: BinOp::Mul, .. }, .. } => {
+                assert!(matches!(left.as_ref(), Expr::Cast { ty: Type::Int, .. }));
+            }
+            other => panic!("expected cast, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("enum Shape:\n    Circle(float) = 1\n");
+        assert_eq!(diags.iter().next().map(|d| d.message.as_str()), Some("only variants without data can have a discriminant"));
+    }
+
+    #[test]
+    fn test_parse_dyn_type() {
+        let (program, diags) = parse_source("fn f(shapes: [dyn Shape], dyn: int) -> int:\n    return dyn\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::Fn(f) => {
+                assert_eq!(f.params[0].ty, Type::Array(Box::new(Type::Dyn("Shape".to_string()))));
+                assert_eq!(f.params[1].ty, Type::Int);
+            }
+            other => panic!("expected fn, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_range_patterns() {
+        let source = "fn f(n: int) -> int:\n    let b = match n:\n        -5..0:\n            0\n        0..=9:\n            1\n        ..-5 | 100..:\n            2\n        _:\n            3\n    return b\n";
+        let (program, diags) = parse_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags);
+        let Stmt::Fn(f) = &program.statements[0] else { panic!("expected fn") };
+        let Stmt::Let { value: Expr::Match { arms, .. }, .. } = &f.body[0] else { panic!("expected match") };
+
+        let bounds = |pattern: &Pattern| match pattern {
+            Pattern::Range { start, end, inclusive, .. } => {
+                let int = |e: &Option<Box<Expr>>| e.as_deref().map(|e| match e {
+                    Expr::IntLit(n, _) => *n,
+                    other => panic!("expected int, got {:?}", other),
+                });
+                (int(start), int(end), *inclusive)
+            }
+            other => panic!("expected range, got {:?}", other),
+        };
+        assert_eq!(bounds(&arms[0].pattern), (Some(-5), Some(0), false));
+        assert_eq!(bounds(&arms[1].pattern), (Some(0), Some(9), true));
+        match &arms[2].pattern {
+            Pattern::Or(alternatives, _) => {
+                assert_eq!(bounds(&alternatives[0]), (None, Some(-5), false));
+                assert_eq!(bounds(&alternatives[1]), (Some(100), None, false));
+            }
+            other => panic!("expected or-pattern, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("fn f(n: int) -> int:\n    let b = match n:\n        1..=:\n            0\n    return b\n");
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_parse_try_catch() {
+        let source = "fn f(s: string) -> int:\n    try:\n        let n: int = parse(s)?\n        return n\n    catch err:\n        print(err)\n    let try = 1\n    return try\n";
+        let (program, diags) = parse_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags);
+        let Stmt::Fn(f) = &program.statements[0] else { panic!("expected fn") };
+        match &f.body[0] {
+            Stmt::TryCatch { body, error_var, handler, .. } => {
+                assert_eq!(body.len(), 2);
+                assert_eq!(error_var, "err");
+                assert_eq!(handler.len(), 1);
+            }
+            other => panic!("expected try/catch, got {:?}", other),
+        }
+        // `try` stays usable as a name
+        assert!(matches!(&f.body[1], Stmt::Let { name, .. } if name == "try"));
+
+        let (_, diags) = parse_source("fn f() -> int:\n    try:\n        return 1\n    return 2\n");
+        assert!(diags.iter().any(|d| d.message.contains("expected 'catch'")));
+    }
+
+    #[test]
+    fn test_route_declarations_rejected_inside_functions() {
+        let source = "fn setup() -> int:\n    api GET /health:\n        return string\n        return \"ok\"\n    return 1\n\napi GET /ready:\n    return string\n    return \"ok\"\n";
+        let (program, diags) = parse_source(source);
+        let errors: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec!["`api` must be declared at module scope"]);
+
+        // The nested route is skipped; the function and the top-level route survive
+        assert_eq!(program.statements.len(), 2);
+        let Stmt::Fn(f) = &program.statements[0] else { panic!("expected fn") };
+        assert!(matches!(f.body.as_slice(), [Stmt::Return { .. }]));
+        assert!(matches!(&program.statements[1], Stmt::Api(_)));
+
+        let (_, diags) = parse_source("fn f():\n    if true:\n        server:\n            port 80\n");
+        assert!(diags.iter().any(|d| d.message == "`server` must be declared at module scope"));
+
+        let (_, diags) = parse_source("api GET /x:\n    return string\n    ws /chat:\n        on_connect:\n            print(1)\n    return \"ok\"\n");
+        assert!(diags.iter().any(|d| d.message == "`ws` must be declared at module scope"));
+    }
+
+    #[test]
+    fn test_parse_let_tuple() {
+        let (program, diags) = parse_source("let (lo, _, hi) = bounds(xs)
+let mut (a, b) = (1, 2)
+");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::LetTuple { names, mutable, .. } => {
+                assert_eq!(names, &["lo", "_", "hi"]);
+                assert!(!mutable);
+            }
+            other => panic!("expected tuple let, got {:?}", other),
+        }
+        assert!(matches!(&program.statements[1], Stmt::LetTuple { mutable: true, .. }));
+
+        let (_, diags) = parse_source("let (a, b = pair()
+");
+        assert!(diags.iter().any(|d| d.message.contains("expected ')' after tuple names")));
+    }
+
+    #[test]
+    fn test_parse_server_tls() {
+        let source = "server:\n    port 8443\n    tls_cert \"certs/server.pem\"\n    tls_key \"certs/server.key\"\n";
+        let (program, diags) = parse_source(source);
+        assert!(!diags.has_errors());
+        match &program.statements[0] {
+            Stmt::Server(server) => {
+                assert_eq!(server.port, 8443);
+                assert_eq!(server.tls_cert.as_deref(), Some("certs/server.pem"));
+                assert_eq!(server.tls_key.as_deref(), Some("certs/server.key"));
+            }
+            other => panic!("expected server, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("server:\n    tls_cert \"certs/server.pem\"\n");
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_parse_destructured_param() {
+        let (program, diags) = parse_source("fn dist((x, mut y): (int, int)) -> int:\n    return x\n");
+        assert!(!diags.has_errors());
+        match &program.statements[0] {
+            Stmt::Fn(f) => {
+                assert_eq!(f.params[0].name, "__param0");
+                assert!(matches!(f.params[0].pattern, Some(Pattern::Tuple(ref parts, _)) if parts.len() == 2));
+            }
+            other => panic!("expected fn, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("fn f((1, y): (int, int)) -> int:\n    return y\n");
+        assert!(diags.iter().any(|d| d.message == "refutable pattern in function parameter"));
+    }
+
+    #[test]
+    fn test_parse_duration_literals() {
+        let (program, diags) = parse_source("let t = 500ms\n");
+        assert!(!diags.has_errors());
+        match &program.statements[0] {
+            Stmt::Let { value: Expr::DurationLit(ms, _), .. } => assert_eq!(*ms, 500),
+            other => panic!("expected duration literal, got {:?}", other),
+        }
+
+        let source = "server:\n    port 8080\n    timeout 30s\n\ndb postgres main:\n    url \"postgres://localhost/app\"\n    timeout 5s\n";
+        let (program, diags) = parse_source(source);
+        assert!(!diags.has_errors());
+        match (&program.statements[0], &program.statements[1]) {
+            (Stmt::Server(server), Stmt::Db(db)) => {
+                assert_eq!(server.timeout, Some(30_000));
+                assert_eq!(db.timeout, Some(5_000));
+            }
+            other => panic!("expected server and db, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("server:\n    timeout 30\n");
+        assert!(diags.iter().any(|d| d.message.contains("expected a duration like `30s`")));
+    }
+
+    #[test]
+    fn test_short_indented_block() {
+        let (_, diags) = parse_source("fn f(x: int) -> int:\n    match x:\n        1:\n    return 0\n");
+        let diag = diags.iter().next().expect("an error");
+        assert_eq!(diag.message, "unexpected end of block; check indentation");
+        // Points at the line whose indentation closed the block
+        assert_eq!(diag.labels[0].span.start.line, 4);
+        assert_eq!(diag.notes, vec!["expected indented block".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_optional_chaining() {
+        let (program, diags) = parse_source("let city = user?.address?.city\nlet n = load()?.count\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::Let { value: Expr::OptionalFieldAccess { object, field, .. }, .. } => {
+                assert_eq!(field, "city");
+                assert!(matches!(object.as_ref(), Expr::OptionalFieldAccess { field, .. } if field == "address"));
+            }
+            other => panic!("expected optional field access, got {:?}", other),
+        }
+        assert!(matches!(&program.statements[1],
+            Stmt::Let { value: Expr::OptionalFieldAccess { object, .. }, .. } if matches!(object.as_ref(), Expr::Call { .. })));
+
+        let (program, diags) = parse_source("let n = load()?.greet()\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        assert!(matches!(&program.statements[0],
+            Stmt::Let { value: Expr::MethodCall { object, .. }, .. } if matches!(object.as_ref(), Expr::Try { .. })));
+    }
+
+    #[test]
+    fn test_parse_websocket_message_type() {
+        let (program, diags) = parse_source("ws /stream:\n    message [int]\n    on_message:\n        print(len(message))\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::WebSocket(ws) => {
+                assert!(matches!(&ws.message_type, Some(Type::Array(inner)) if **inner == Type::Int));
+                assert!(ws.on_message.is_some());
+            }
+            other => panic!("expected a WebSocket, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_static() {
+        let (program, diags) = parse_source("static mut hits: int = 0\nstatic NAME: string = \"api\"\n");
+        assert!(!diags.has_errors());
+        match (&program.statements[0], &program.statements[1]) {
+            (Stmt::Static { name, mutable: true, .. }, Stmt::Static { name: other, mutable: false, .. }) => {
+                assert_eq!(name, "hits");
+                assert_eq!(other, "NAME");
+            }
+            other => panic!("expected two statics, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("fn f():\n    static mut n: int = 0\n");
+        assert!(diags.iter().any(|d| d.message.contains("`static` must be declared at module scope")));
+    }
+
+    #[test]
+    fn test_parse_const() {
+        let (program, diags) = parse_source("const MAX: int = 100\nconst fn square(x: int) -> int:\n    return x * x\n");
+        assert!(!diags.has_errors());
+        match (&program.statements[0], &program.statements[1]) {
+            (Stmt::Const { name, ty: Type::Int, value: Expr::IntLit(100, _), .. }, Stmt::Fn(f)) => {
+                assert_eq!(name, "MAX");
+                assert!(f.is_const);
+            }
+            other => panic!("expected a const and a const fn, got {:?}", other),
+        }
+
+        let (_, diags) = parse_source("fn f():\n    const N: int = 1\n");
+        assert!(diags.iter().any(|d| d.message.contains("`const` must be declared at module scope")));
+    }
+
+    #[test]
+    fn test_parse_closure_return() {
+        let (program, diags) = parse_source("let f = |x: int| return x + 1\n");
+        assert!(!diags.has_errors());
+        match &program.statements[0] {
+            Stmt::Let { value: Expr::Closure { body: ClosureBody::Block(stmts), .. }, .. } => {
+                assert!(matches!(stmts.as_slice(), [Stmt::Return { value: Some(_), .. }]));
+            }
+            other => panic!("expected closure with a return body, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_if_let_and_while_let() {
+        let (program, diags) = parse_source("fn main():\n    if let Shape::Rect(w, h) = s:\n        print(w)\n    else:\n        print(0)\n    while let Some(x) = next():\n        print(x)\n");
+        assert!(!diags.has_errors(), "{:?}", diags);
+        match &program.statements[0] {
+            Stmt::Fn(f) => {
+                match &f.body[0] {
+                    Stmt::IfLet { pattern: Pattern::Variant { variant, data: VariantPatternData::Tuple(fields), .. }, else_block: Some(_), .. } => {
+                        assert_eq!(variant, "Rect");
+                        assert_eq!(fields.len(), 2);
+                    }
+                    other => panic!("expected if let, got {:?}", other),
+                }
+                assert!(matches!(&f.body[1], Stmt::WhileLet { pattern: Pattern::Variant { .. }, .. }));
+            }
+            other => panic!("expected function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_yield() {
+        let (program, diags) = parse_source("fn counter(n: int) -> [int]:\n    for i in 0..n:\n        yield i\n\nfn main():\n    let f = ||:\n        yield 1\n");
+        assert!(!diags.has_errors());
+        match &program.statements[0] {
+            Stmt::Fn(f) => {
+                assert!(f.is_generator());
+                match &f.body[0] {
+                    Stmt::For { body, .. } => assert!(matches!(body.as_slice(), [Stmt::Yield { .. }])),
+                    other => panic!("expected for loop, got {:?}", other),
+                }
+            }
+            other => panic!("expected function, got {:?}", other),
+        }
+        // A `yield` inside a closure does not make the enclosing function a generator
+        match &program.statements[1] {
+            Stmt::Fn(f) => assert!(!f.is_generator()),
+            other => panic!("expected function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_group() {
+        let source = "group /api/v1:\n    use auth\n\n    api GET /users:\n        return int\n        return 1\n\n    api GET /users/{id:int}:\n        return int\n        return id\n\napi GET /health:\n    return int\n    return 0\n";
+        let (program, diags) = parse_source(source);
+        assert!(!diags.has_errors());
+        assert_eq!(program.statements.len(), 2);
+        match &program.statements[0] {
+            Stmt::Group(group) => {
+                assert_eq!(group.prefix, "/api/v1");
+                assert_eq!(group.middlewares, vec!["auth".to_string()]);
+                assert_eq!(group.routes.len(), 2);
+            }
+            other => panic!("expected group, got {:?}", other),
+        }
+        assert!(matches!(program.statements[1], Stmt::Api(_)));
+    }
+
+    #[test]
+    fn test_parse_group_rejects_other_statements() {
+        let (_, diags) = parse_source("group /api:\n    let x = 1\n");
+        assert!(diags.has_errors());
+    }
+
     #[test]
     fn test_parse_server() {
         let (program, diags) = parse_source("server:\n    host \"0.0.0.0\"\n    port 8080\n");
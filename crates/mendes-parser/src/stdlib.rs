@@ -0,0 +1,84 @@
+//! Standard modules bundled with the compiler
+//!
+//! `from std.math import sqrt` resolves against this registry rather than a
+//! file on disk. Each item is lowered to a call to `__std_{module}_{item}`,
+//! which the backends implement directly.
+
+use crate::ast::Type;
+
+/// Modules available under `std`
+pub const STD_MODULES: &[&str] = &["std.math", "std.string", "std.collections"];
+
+/// A function or constant exported by a standard module
+#[derive(Debug, Clone)]
+pub struct StdItem {
+    pub name: &'static str,
+    /// Parameters of a function; `None` for a constant
+    pub params: Option<Vec<(&'static str, Type)>>,
+    /// Return type of a function, or the type of a constant
+    pub ty: Type,
+}
+
+impl StdItem {
+    fn function(name: &'static str, params: Vec<(&'static str, Type)>, ret: Type) -> Self {
+        Self { name, params: Some(params), ty: ret }
+    }
+
+    fn constant(name: &'static str, ty: Type) -> Self {
+        Self { name, params: None, ty }
+    }
+
+    /// Name of the call this item is lowered to, e.g. `__std_math_sqrt`
+    pub fn lowered_name(&self, module: &str) -> String {
+        format!("__{}_{}", module.replace('.', "_"), self.name)
+    }
+}
+
+/// Whether an import path refers to the standard library
+pub fn is_std_path(path: &str) -> bool {
+    path == "std" || path.starts_with("std.")
+}
+
+/// Items exported by a standard module, or `None` if there is no such module
+pub fn std_module(path: &str) -> Option<Vec<StdItem>> {
+    let items = match path {
+        "std.math" => vec![
+            StdItem::function("sqrt", vec![("x", Type::Float)], Type::Float),
+            StdItem::function("pow", vec![("base", Type::Float), ("exp", Type::Float)], Type::Float),
+            StdItem::function("abs", vec![("x", Type::Float)], Type::Float),
+            StdItem::function("floor", vec![("x", Type::Float)], Type::Float),
+            StdItem::function("ceil", vec![("x", Type::Float)], Type::Float),
+            StdItem::constant("pi", Type::Float),
+            StdItem::constant("e", Type::Float),
+        ],
+        "std.string" => vec![
+            StdItem::function("upper", vec![("s", Type::String)], Type::String),
+            StdItem::function("lower", vec![("s", Type::String)], Type::String),
+            StdItem::function("trim", vec![("s", Type::String)], Type::String),
+            StdItem::function("contains", vec![("s", Type::String), ("pattern", Type::String)], Type::Bool),
+        ],
+        "std.collections" => vec![
+            StdItem::function("range", vec![("start", Type::Int), ("end", Type::Int)], Type::Array(Box::new(Type::Int))),
+        ],
+        _ => return None,
+    };
+    Some(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_std_modules_resolve() {
+        for module in STD_MODULES {
+            assert!(std_module(module).is_some(), "{}", module);
+        }
+        assert!(std_module("std.nope").is_none());
+
+        let math = std_module("std.math").unwrap();
+        let sqrt = math.iter().find(|item| item.name == "sqrt").unwrap();
+        assert_eq!(sqrt.lowered_name("std.math"), "__std_math_sqrt");
+        assert!(math.iter().any(|item| item.name == "pi" && item.params.is_none()));
+    }
+}
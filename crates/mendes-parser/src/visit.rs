@@ -0,0 +1,354 @@
+//! AST traversal
+//!
+//! `Visitor` has one method per node category, each defaulting to the
+//! matching `walk_*` function, which visits the node's children. An analysis
+//! overrides only the nodes it cares about and calls `walk_*` from the
+//! override to keep descending.
+//!
+//! # Example
+//!
+//! ```rust
+//! use mendes_parser::{Expr, visit::{self, Visitor}};
+//!
+//! struct CallCounter(usize);
+//!
+//! impl Visitor for CallCounter {
+//!     fn visit_expr(&mut self, expr: &Expr) {
+//!         if matches!(expr, Expr::Call { .. }) {
+//!             self.0 += 1;
+//!         }
+//!         visit::walk_expr(self, expr);
+//!     }
+//! }
+//! ```
+
+use crate::ast::*;
+
+/// AST visitor with default depth-first traversal
+pub trait Visitor: Sized {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern);
+    }
+
+    fn visit_block(&mut self, stmts: &[Stmt]) {
+        walk_block(self, stmts);
+    }
+}
+
+/// Visits every top-level statement
+pub fn walk_program<V: Visitor>(visitor: &mut V, program: &Program) {
+    for stmt in &program.statements {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+/// Visits every statement of a block
+pub fn walk_block<V: Visitor>(visitor: &mut V, stmts: &[Stmt]) {
+    for stmt in stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+/// Visits the expressions and nested blocks of a statement
+pub fn walk_stmt<V: Visitor>(visitor: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::Let { value, .. } | Stmt::LetTuple { value, .. } | Stmt::Static { value, .. } | Stmt::Const { value, .. } => {
+            visitor.visit_expr(value)
+        }
+        Stmt::Fn(f) => visitor.visit_block(&f.body),
+        Stmt::Struct(s) => {
+            for default in s.fields.iter().filter_map(|f| f.default.as_ref()) {
+                visitor.visit_expr(default);
+            }
+            for method in &s.methods {
+                visitor.visit_block(&method.body);
+            }
+        }
+        Stmt::ImplTrait(impl_decl) => {
+            for method in &impl_decl.methods {
+                visitor.visit_block(&method.body);
+            }
+        }
+        Stmt::Api(api) => visitor.visit_block(&api.handler),
+        Stmt::Group(group) => {
+            for api in &group.routes {
+                visitor.visit_block(&api.handler);
+            }
+        }
+        Stmt::WebSocket(ws) => {
+            for handler in [&ws.on_connect, &ws.on_message, &ws.on_disconnect].into_iter().flatten() {
+                visitor.visit_block(handler);
+            }
+        }
+        Stmt::Middleware(m) => visitor.visit_block(&m.body),
+        Stmt::If { condition, then_block, else_block, .. } => {
+            visitor.visit_expr(condition);
+            visitor.visit_block(then_block);
+            if let Some(else_block) = else_block {
+                visitor.visit_block(else_block);
+            }
+        }
+        Stmt::IfLet { value, then_block, else_block, .. } => {
+            visitor.visit_expr(value);
+            visitor.visit_block(then_block);
+            if let Some(else_block) = else_block {
+                visitor.visit_block(else_block);
+            }
+        }
+        Stmt::For { iter, body, .. } => {
+            visitor.visit_expr(iter);
+            visitor.visit_block(body);
+        }
+        Stmt::While { condition, body, .. } => {
+            visitor.visit_expr(condition);
+            visitor.visit_block(body);
+        }
+        Stmt::WhileLet { value, body, .. } => {
+            visitor.visit_expr(value);
+            visitor.visit_block(body);
+        }
+        Stmt::TryCatch { body, handler, .. } => {
+            visitor.visit_block(body);
+            visitor.visit_block(handler);
+        }
+        Stmt::With { resource, body, .. } => {
+            visitor.visit_expr(resource);
+            visitor.visit_block(body);
+        }
+        Stmt::Return { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        Stmt::Yield { value, .. } => visitor.visit_expr(value),
+        Stmt::Expr(expr) => visitor.visit_expr(expr),
+        Stmt::Attributed { item, .. } => visitor.visit_stmt(item),
+        Stmt::Import { .. }
+        | Stmt::FromImport { .. }
+        | Stmt::Enum(_)
+        | Stmt::Trait(_)
+        | Stmt::TypeAlias { .. }
+        | Stmt::Server(_)
+        | Stmt::Db(_)
+        | Stmt::Break { .. }
+        | Stmt::Continue { .. } => {}
+    }
+}
+
+/// Visits the sub-expressions, patterns and nested blocks of an expression
+pub fn walk_expr<V: Visitor>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Binary { left, right, .. } => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        Expr::Unary { expr, .. }
+        | Expr::FieldAccess { object: expr, .. }
+        | Expr::TupleIndex { object: expr, .. }
+        | Expr::OptionalFieldAccess { object: expr, .. }
+        | Expr::Await { expr, .. }
+        | Expr::Borrow { expr, .. }
+        | Expr::Try { expr, .. }
+        | Expr::Ok(expr, _)
+        | Expr::Err(expr, _)
+        | Expr::Some(expr, _)
+        | Expr::NamedArg { value: expr, .. }
+        | Expr::Spread { expr, .. } => visitor.visit_expr(expr),
+        Expr::Call { func, args, .. } => {
+            visitor.visit_expr(func);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        Expr::AssocCall { args, .. } => {
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        Expr::MethodCall { object, args, .. } => {
+            visitor.visit_expr(object);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        Expr::Index { object, index, .. } => {
+            visitor.visit_expr(object);
+            visitor.visit_expr(index);
+        }
+        Expr::StructLit { fields, .. } => {
+            for (_, value) in fields {
+                visitor.visit_expr(value);
+            }
+        }
+        Expr::ArrayRepeat { value, count, .. } => {
+            visitor.visit_expr(value);
+            visitor.visit_expr(count);
+        }
+        Expr::Cast { expr, .. } => visitor.visit_expr(expr),
+        Expr::ArrayLit(elements, _) | Expr::Tuple { elements, .. } => {
+            for element in elements {
+                visitor.visit_expr(element);
+            }
+        }
+        Expr::MapLit { entries, .. } => {
+            for (key, value) in entries {
+                visitor.visit_expr(key);
+                visitor.visit_expr(value);
+            }
+        }
+        Expr::Match { expr, arms, .. } => {
+            visitor.visit_expr(expr);
+            for arm in arms {
+                visitor.visit_pattern(&arm.pattern);
+                if let Some(guard) = &arm.guard {
+                    visitor.visit_expr(guard);
+                }
+                visitor.visit_block(&arm.body);
+            }
+        }
+        Expr::Closure { body, .. } => match body {
+            ClosureBody::Expr(expr) => visitor.visit_expr(expr),
+            ClosureBody::Block(stmts) => visitor.visit_block(stmts),
+        },
+        Expr::StringInterpolation { parts, .. } => {
+            for part in parts {
+                if let StringPart::Expr(expr) = part {
+                    visitor.visit_expr(expr);
+                }
+            }
+        }
+        Expr::Range { start, end, .. } => {
+            if let Some(start) = start {
+                visitor.visit_expr(start);
+            }
+            if let Some(end) = end {
+                visitor.visit_expr(end);
+            }
+        }
+        Expr::AsyncBlock { body, .. } => visitor.visit_block(body),
+        Expr::IntLit(..)
+        | Expr::FloatLit(..)
+        | Expr::DurationLit(..)
+        | Expr::StringLit(..)
+        | Expr::BoolLit(..)
+        | Expr::None(_)
+        | Expr::Ident(..)
+        | Expr::Path { .. } => {}
+    }
+}
+
+/// Visits the sub-patterns and embedded expressions of a pattern
+pub fn walk_pattern<V: Visitor>(visitor: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Literal(expr) => visitor.visit_expr(expr),
+        Pattern::Tuple(patterns, _) | Pattern::Or(patterns, _) => {
+            for pattern in patterns {
+                visitor.visit_pattern(pattern);
+            }
+        }
+        Pattern::Struct { fields, .. }
+        | Pattern::Variant { data: VariantPatternData::Struct(fields), .. } => {
+            for pattern in fields.iter().filter_map(|(_, p)| p.as_ref()) {
+                visitor.visit_pattern(pattern);
+            }
+        }
+        Pattern::Variant { data: VariantPatternData::Tuple(patterns), .. } => {
+            for pattern in patterns {
+                visitor.visit_pattern(pattern);
+            }
+        }
+        Pattern::Range { start, end, .. } => {
+            if let Some(start) = start {
+                visitor.visit_expr(start);
+            }
+            if let Some(end) = end {
+                visitor.visit_expr(end);
+            }
+        }
+        Pattern::Variant { data: VariantPatternData::Unit, .. }
+        | Pattern::Wildcard(_)
+        | Pattern::Ident { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mendes_lexer::Lexer;
+
+    #[derive(Default)]
+    struct Counter {
+        stmts: usize,
+        exprs: usize,
+        idents: usize,
+    }
+
+    impl Visitor for Counter {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            self.stmts += 1;
+            walk_stmt(self, stmt);
+        }
+
+        fn visit_expr(&mut self, expr: &Expr) {
+            self.exprs += 1;
+            if matches!(expr, Expr::Ident(..)) {
+                self.idents += 1;
+            }
+            walk_expr(self, expr);
+        }
+    }
+
+    fn count(source: &str) -> Counter {
+        let mut lexer = Lexer::new(source, 0);
+        let (program, diags) = crate::parse(lexer.tokenize());
+        assert!(!diags.has_errors());
+        let mut counter = Counter::default();
+        counter.visit_program(&program);
+        counter
+    }
+
+    #[test]
+    fn test_counts_nested_nodes() {
+        let counter = count(r#"
+fn add(a: int, b: int) -> int:
+    let sum = a + b
+    if sum > 10:
+        return sum
+    return add(sum, 1)
+"#);
+        // fn, let, if, return (then), return
+        assert_eq!(counter.stmts, 5);
+        // a + b, a, b, sum > 10, sum, 10, sum, add(sum, 1), add, sum, 1
+        assert_eq!(counter.exprs, 11);
+        assert_eq!(counter.idents, 6);
+    }
+
+    #[test]
+    fn test_override_stops_descent() {
+        struct TopLevelOnly(usize);
+
+        impl Visitor for TopLevelOnly {
+            fn visit_stmt(&mut self, _stmt: &Stmt) {
+                self.0 += 1;
+            }
+        }
+
+        let mut lexer = Lexer::new("fn f() -> int:\n    return 1\n\nfn g() -> int:\n    return 2\n", 0);
+        let (program, _) = crate::parse(lexer.tokenize());
+        let mut visitor = TopLevelOnly(0);
+        visitor.visit_program(&program);
+        assert_eq!(visitor.0, 2);
+    }
+}
@@ -7,6 +7,10 @@ use crate::error::{MendesError, Result};
 use async_trait::async_trait;
 use serde::de::DeserializeOwned;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Pool size used when a database does not declare `pool`
+pub const DEFAULT_POOL_SIZE: u32 = 10;
 
 /// Trait for database pools
 #[async_trait]
@@ -26,6 +30,7 @@ pub trait DatabasePool: Send + Sync {
 
 /// PostgreSQL Pool
 #[cfg(feature = "postgres")]
+#[derive(Clone)]
 pub struct PostgresPool {
     name: String,
     pool: sqlx::PgPool,
@@ -33,10 +38,14 @@ pub struct PostgresPool {
 
 #[cfg(feature = "postgres")]
 impl PostgresPool {
-    /// Connects to PostgreSQL
-    pub async fn connect(name: impl Into<String>, url: &str, pool_size: u32) -> Result<Self> {
-        let pool = sqlx::postgres::PgPoolOptions::new()
-            .max_connections(pool_size)
+    /// Connects to PostgreSQL; `timeout` bounds how long getting a connection
+    /// from the pool may take
+    pub async fn connect(name: impl Into<String>, url: &str, pool_size: u32, timeout: Option<Duration>) -> Result<Self> {
+        let mut options = sqlx::postgres::PgPoolOptions::new().max_connections(pool_size);
+        if let Some(timeout) = timeout {
+            options = options.acquire_timeout(timeout);
+        }
+        let pool = options
             .connect(url)
             .await
             .map_err(|e| MendesError::Database(e.to_string()))?;
@@ -112,6 +121,7 @@ impl DatabasePool for PostgresPool {
 
 /// MySQL Pool
 #[cfg(feature = "mysql")]
+#[derive(Clone)]
 pub struct MysqlPool {
     name: String,
     pool: sqlx::MySqlPool,
@@ -119,10 +129,14 @@ pub struct MysqlPool {
 
 #[cfg(feature = "mysql")]
 impl MysqlPool {
-    /// Connects to MySQL
-    pub async fn connect(name: impl Into<String>, url: &str, pool_size: u32) -> Result<Self> {
-        let pool = sqlx::mysql::MySqlPoolOptions::new()
-            .max_connections(pool_size)
+    /// Connects to MySQL; `timeout` bounds how long getting a connection
+    /// from the pool may take
+    pub async fn connect(name: impl Into<String>, url: &str, pool_size: u32, timeout: Option<Duration>) -> Result<Self> {
+        let mut options = sqlx::mysql::MySqlPoolOptions::new().max_connections(pool_size);
+        if let Some(timeout) = timeout {
+            options = options.acquire_timeout(timeout);
+        }
+        let pool = options
             .connect(url)
             .await
             .map_err(|e| MendesError::Database(e.to_string()))?;
@@ -172,6 +186,7 @@ impl DatabasePool for MysqlPool {
 
 /// SQLite Pool
 #[cfg(feature = "sqlite")]
+#[derive(Clone)]
 pub struct SqlitePool {
     name: String,
     pool: sqlx::SqlitePool,
@@ -179,10 +194,14 @@ pub struct SqlitePool {
 
 #[cfg(feature = "sqlite")]
 impl SqlitePool {
-    /// Connects to SQLite
-    pub async fn connect(name: impl Into<String>, url: &str, pool_size: u32) -> Result<Self> {
-        let pool = sqlx::sqlite::SqlitePoolOptions::new()
-            .max_connections(pool_size)
+    /// Connects to SQLite; `timeout` bounds how long getting a connection
+    /// from the pool may take
+    pub async fn connect(name: impl Into<String>, url: &str, pool_size: u32, timeout: Option<Duration>) -> Result<Self> {
+        let mut options = sqlx::sqlite::SqlitePoolOptions::new().max_connections(pool_size);
+        if let Some(timeout) = timeout {
+            options = options.acquire_timeout(timeout);
+        }
+        let pool = options
             .connect(url)
             .await
             .map_err(|e| MendesError::Database(e.to_string()))?;
@@ -193,6 +212,29 @@ impl SqlitePool {
         })
     }
 
+    /// Connects to SQLite as the `main` database with the default pool size
+    pub async fn from_url(url: &str) -> Result<Self> {
+        Self::connect("main", url, DEFAULT_POOL_SIZE, None).await
+    }
+
+    /// Opens an empty in-memory database named `main`, for tests
+    pub async fn in_memory() -> Result<Self> {
+        // Each connection to `sqlite::memory:` gets a database of its own, so
+        // the pool keeps exactly one connection open for its whole life
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .map_err(|e| MendesError::Database(e.to_string()))?;
+
+        Ok(Self {
+            name: "main".to_string(),
+            pool,
+        })
+    }
+
     /// Executes typed query
     pub async fn query<T>(&self, sql: &str) -> Result<Vec<T>>
     where
@@ -204,6 +246,28 @@ impl SqlitePool {
             .map_err(|e| MendesError::Database(e.to_string()))
     }
 
+    /// Executes query and returns one row
+    pub async fn query_one<T>(&self, sql: &str) -> Result<T>
+    where
+        T: for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> + Send + Unpin,
+    {
+        sqlx::query_as::<_, T>(sql)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| MendesError::Database(e.to_string()))
+    }
+
+    /// Executes optional query
+    pub async fn query_optional<T>(&self, sql: &str) -> Result<Option<T>>
+    where
+        T: for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> + Send + Unpin,
+    {
+        sqlx::query_as::<_, T>(sql)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| MendesError::Database(e.to_string()))
+    }
+
     /// Creates table if it does not exist
     pub async fn ensure_table(&self, sql: &str) -> Result<()> {
         sqlx::query(sql)
@@ -225,8 +289,11 @@ impl DatabasePool for SqlitePool {
         "sqlite"
     }
 
-    async fn execute(&self, sql: &str, _params: &[&str]) -> Result<u64> {
-        sqlx::query(sql)
+    async fn execute(&self, sql: &str, params: &[&str]) -> Result<u64> {
+        // `?` placeholders are bound in order; SQLite converts the text to
+        // the column's type
+        params.iter()
+            .fold(sqlx::query(sql), |query, param| query.bind(*param))
             .execute(&self.pool)
             .await
             .map(|r| r.rows_affected())
@@ -328,4 +395,22 @@ mod tests {
 
         assert!(manager.get("nonexistent").is_none());
     }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_sqlite_in_memory() {
+        let pool = SqlitePool::in_memory().await.unwrap();
+        assert_eq!(pool.name(), "main");
+
+        pool.ensure_table("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL)").await.unwrap();
+        let inserted = pool.execute("INSERT INTO users (id, name) VALUES (?, ?)", &["1", "Ana"]).await.unwrap();
+        assert_eq!(inserted, 1);
+
+        let (id, name): (i64, String) = pool.query_one("SELECT id, name FROM users").await.unwrap();
+        assert_eq!((id, name.as_str()), (1, "Ana"));
+
+        let missing: Option<(i64,)> = pool.query_optional("SELECT id FROM users WHERE id = 2").await.unwrap();
+        assert!(missing.is_none());
+        assert!(pool.query_one::<(i64,)>("SELECT id FROM nowhere").await.is_err());
+    }
 }
@@ -6,8 +6,9 @@ use crate::error::{HttpError, MendesError, Result};
 use crate::router::Router;
 use crate::types::MendesString;
 
-use http_body_util::{BodyExt, Full};
-use hyper::body::{Bytes, Incoming};
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited, StreamBody};
+use hyper::body::{Body, Bytes, Frame};
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper_util::rt::TokioIo;
@@ -15,8 +16,16 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::convert::Infallible;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
 use tokio::net::TcpListener;
+use tokio_rustls::rustls;
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::WebSocketStream;
 
 pub use hyper::StatusCode;
 
@@ -58,8 +67,13 @@ impl Request {
         self
     }
 
-    /// Creates request from hyper request
-    pub(crate) async fn from_hyper(req: hyper::Request<Incoming>) -> Result<Self> {
+    /// Creates request from hyper request, rejecting bodies larger than
+    /// `max_body_size` with 413
+    pub(crate) async fn from_hyper<B>(req: hyper::Request<B>, max_body_size: Option<usize>) -> Result<Self>
+    where
+        B: Body,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
         let method = req.method().to_string();
         let uri = req.uri();
         let path = uri.path().to_string();
@@ -82,8 +96,15 @@ impl Request {
             .collect();
 
         // Read body
-        let body = req.collect().await
-            .map_err(|e| MendesError::Http(HttpError::new(400, e.to_string())))?
+        let limit = max_body_size.unwrap_or(usize::MAX);
+        let body = Limited::new(req.into_body(), limit).collect().await
+            .map_err(|e| {
+                if e.is::<LengthLimitError>() {
+                    MendesError::Http(HttpError::new(413, "Payload Too Large"))
+                } else {
+                    MendesError::Http(HttpError::new(400, e.to_string()))
+                }
+            })?
             .to_bytes()
             .to_vec();
 
@@ -140,6 +161,72 @@ impl Request {
     }
 }
 
+/// Body sent to hyper: buffered or streamed
+pub(crate) type HyperBody = UnsyncBoxBody<Bytes, std::io::Error>;
+
+/// Size of the chunks read from a streamed body
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Where a streamed body is read from
+enum StreamSource {
+    Reader(Pin<Box<dyn AsyncRead + Send>>),
+    /// File opened with `tokio::fs` once the response is sent
+    File(String),
+}
+
+/// Async byte source of a streamed response
+///
+/// Clones share the source, so only the first of them to be sent reads it.
+#[derive(Clone)]
+pub struct BodyStream(Arc<Mutex<Option<StreamSource>>>);
+
+impl BodyStream {
+    fn new(source: StreamSource) -> Self {
+        Self(Arc::new(Mutex::new(Some(source))))
+    }
+
+    /// Path of the file to stream, while it has not been opened yet
+    fn file_path(&self) -> Option<String> {
+        match &*self.0.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(StreamSource::File(path)) => Some(path.clone()),
+            _ => None,
+        }
+    }
+
+    /// Takes the source, leaving nothing for the other clones
+    fn take(&self) -> Option<StreamSource> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+impl std::fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BodyStream")
+    }
+}
+
+/// Status codes accepted by [`Response::with_status`]: a [`StatusCode`] or
+/// a Mendes `int`
+pub trait IntoStatus {
+    fn into_status(self) -> u16;
+}
+
+impl IntoStatus for StatusCode {
+    fn into_status(self) -> u16 {
+        self.as_u16()
+    }
+}
+
+/// An `int` outside 100-599 is not a status code and becomes a 500
+impl IntoStatus for i64 {
+    fn into_status(self) -> u16 {
+        match u16::try_from(self) {
+            Ok(code) if (100..=599).contains(&code) => code,
+            _ => StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        }
+    }
+}
+
 /// Mendes HTTP Response
 #[derive(Debug, Clone)]
 pub struct Response {
@@ -149,6 +236,8 @@ pub struct Response {
     pub headers: HashMap<String, String>,
     /// Body
     pub body: Vec<u8>,
+    /// Streamed body; sent instead of `body` when present
+    stream: Option<BodyStream>,
 }
 
 impl Response {
@@ -158,9 +247,51 @@ impl Response {
             status,
             headers: HashMap::new(),
             body: body.into(),
+            stream: None,
         }
     }
 
+    /// Response whose body is read from `reader` while it is sent
+    ///
+    /// The length is not known up front, so the body goes out with chunked
+    /// transfer encoding and is never buffered whole in memory.
+    pub fn stream(status: u16, reader: impl AsyncRead + Send + 'static) -> Self {
+        let mut resp = Self::new(status, Vec::new());
+        resp.stream = Some(BodyStream::new(StreamSource::Reader(Box::pin(reader))));
+        resp.headers.insert("content-type".to_string(), "application/octet-stream".to_string());
+        resp
+    }
+
+    /// Streams the file at `path`, or answers 404 if it cannot be opened
+    ///
+    /// The file is opened without blocking by [`Response::open_stream`] once
+    /// the handler has returned.
+    pub fn stream_file(path: &str) -> Self {
+        let mut resp = Self::new(200, Vec::new());
+        resp.stream = Some(BodyStream::new(StreamSource::File(path.to_string())));
+        resp.headers.insert("content-type".to_string(), "application/octet-stream".to_string());
+        resp
+    }
+
+    /// Opens a streamed file, turning the response into a 404 if it cannot be
+    pub(crate) async fn open_stream(mut self) -> Self {
+        let Some(path) = self.stream.as_ref().and_then(BodyStream::file_path) else {
+            return self;
+        };
+        match tokio::fs::File::open(&path).await {
+            Ok(file) => {
+                self.stream = Some(BodyStream::new(StreamSource::Reader(Box::pin(file))));
+                self
+            }
+            Err(_) => Self::not_found(format!("File not found: {}", path)),
+        }
+    }
+
+    /// Whether the body is streamed rather than buffered
+    pub fn is_streamed(&self) -> bool {
+        self.stream.is_some()
+    }
+
     /// Response 200 OK with body
     pub fn ok(body: impl Into<String>) -> Self {
         let body_str = body.into();
@@ -196,6 +327,16 @@ impl Response {
         Self::error(401, message)
     }
 
+    /// Response 413 Payload Too Large
+    pub fn payload_too_large(message: impl Into<String>) -> Self {
+        Self::error(413, message)
+    }
+
+    /// Response 408 Request Timeout
+    pub fn request_timeout(message: impl Into<String>) -> Self {
+        Self::error(408, message)
+    }
+
     /// Response 404 Not Found
     pub fn not_found(message: impl Into<String>) -> Self {
         Self::error(404, message)
@@ -206,31 +347,83 @@ impl Response {
         Self::error(500, message)
     }
 
+    /// Response 302 Found pointing the client at `location`
+    pub fn redirect(location: impl Into<String>) -> Self {
+        Self::new(302, Vec::new()).with_header("location", location)
+    }
+
+    /// Response 301 Moved Permanently pointing the client at `location`
+    pub fn redirect_permanent(location: impl Into<String>) -> Self {
+        Self::new(301, Vec::new()).with_header("location", location)
+    }
+
     /// Adds header
     pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
         self.headers.insert(name.into().to_lowercase(), value.into());
         self
     }
 
+    /// Replaces the status code
+    pub fn with_status(mut self, status: impl IntoStatus) -> Self {
+        self.status = status.into_status();
+        self
+    }
+
     /// Converts to hyper response
-    pub(crate) fn into_hyper(self) -> hyper::Response<Full<Bytes>> {
+    pub(crate) fn into_hyper(self) -> hyper::Response<HyperBody> {
         let mut builder = hyper::Response::builder().status(self.status);
 
         for (name, value) in &self.headers {
             builder = builder.header(name.as_str(), value.as_str());
         }
 
+        let body = match self.stream.as_ref().and_then(BodyStream::take) {
+            Some(source) => StreamBody::new(read_frames(source)).boxed_unsync(),
+            None => full_body(self.body),
+        };
+
         builder
-            .body(Full::new(Bytes::from(self.body)))
+            .body(body)
             .unwrap_or_else(|_| {
                 hyper::Response::builder()
                     .status(500)
-                    .body(Full::new(Bytes::from("Internal Server Error")))
+                    .body(full_body("Internal Server Error"))
                     .unwrap()
             })
     }
 }
 
+/// Buffered hyper body
+fn full_body(bytes: impl Into<Bytes>) -> HyperBody {
+    Full::new(bytes.into()).map_err(|never| match never {}).boxed_unsync()
+}
+
+/// Reads `source` in chunks until it is exhausted or fails; a file not yet
+/// opened is opened on the first read
+fn read_frames(
+    source: StreamSource,
+) -> impl futures_util::Stream<Item = std::io::Result<Frame<Bytes>>> + Send {
+    futures_util::stream::unfold(Some(source), |source| async move {
+        let mut reader = match source? {
+            StreamSource::Reader(reader) => reader,
+            StreamSource::File(path) => match tokio::fs::File::open(path).await {
+                Ok(file) => Box::pin(file),
+                Err(e) => return Some((Err(e), None)),
+            },
+        };
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(Frame::data(Bytes::from(buf))), Some(StreamSource::Reader(reader))))
+            }
+            // End the stream after reporting the error
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
+
 impl From<HttpError> for Response {
     fn from(e: HttpError) -> Self {
         Response::error(e.status, e.message)
@@ -277,6 +470,9 @@ impl From<bool> for Response {
 pub struct Server {
     addr: String,
     router: Option<Router>,
+    max_body_size: Option<usize>,
+    request_timeout: Option<Duration>,
+    tls: Option<(String, String)>,
 }
 
 impl Server {
@@ -285,6 +481,9 @@ impl Server {
         Self {
             addr: addr.into(),
             router: None,
+            max_body_size: None,
+            request_timeout: None,
+            tls: None,
         }
     }
 
@@ -294,49 +493,146 @@ impl Server {
         self
     }
 
+    /// Sets the maximum request body size in bytes
+    ///
+    /// Larger requests are answered with `413 Payload Too Large` before
+    /// routing.
+    pub fn max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = Some(bytes);
+        self
+    }
+
+    /// Sets how long a request may take, body included
+    ///
+    /// Slower requests are answered with `408 Request Timeout`.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Serves HTTPS with the given PEM certificate chain and private key
+    pub fn tls(mut self, cert_path: impl Into<String>, key_path: impl Into<String>) -> Self {
+        self.tls = Some((cert_path.into(), key_path.into()));
+        self
+    }
+
     /// Starts the server
     pub async fn run(self) -> Result<()> {
         let addr: SocketAddr = self.addr.parse()
             .map_err(|e| MendesError::Internal(format!("Invalid address: {}", e)))?;
 
+        // Fail before binding if the certificate or key is unusable
+        let acceptor = match &self.tls {
+            Some((cert_path, key_path)) => Some(TlsAcceptor::from(load_tls_config(cert_path, key_path)?)),
+            None => None,
+        };
+
         let router = Arc::new(self.router.unwrap_or_else(Router::new));
+        let max_body_size = self.max_body_size;
+        let request_timeout = self.request_timeout;
 
         let listener = TcpListener::bind(addr).await?;
-        tracing::info!("Mendes server listening on http://{}", addr);
+        let scheme = if acceptor.is_some() { "https" } else { "http" };
+        tracing::info!("Mendes server listening on {}://{}", scheme, addr);
 
         loop {
             let (stream, remote_addr) = listener.accept().await?;
-            let io = TokioIo::new(stream);
             let router = router.clone();
+            let acceptor = acceptor.clone();
 
             tokio::spawn(async move {
-                let service = service_fn(move |req| {
-                    let router = router.clone();
-                    async move {
-                        let response = handle_request(req, router).await;
-                        Ok::<_, Infallible>(response)
-                    }
-                });
-
-                if let Err(err) = http1::Builder::new()
-                    .serve_connection(io, service)
-                    .await
-                {
-                    tracing::error!("Error serving connection from {}: {:?}", remote_addr, err);
+                match acceptor {
+                    Some(acceptor) => match acceptor.accept(stream).await {
+                        Ok(stream) => serve_connection(stream, router, max_body_size, request_timeout, remote_addr).await,
+                        Err(err) => tracing::error!("TLS handshake with {} failed: {:?}", remote_addr, err),
+                    },
+                    None => serve_connection(stream, router, max_body_size, request_timeout, remote_addr).await,
                 }
             });
         }
     }
 }
 
+/// Loads the TLS certificate chain and private key from PEM files
+fn load_tls_config(cert_path: &str, key_path: &str) -> Result<Arc<rustls::ServerConfig>> {
+    let open = |path: &str| {
+        std::fs::File::open(path)
+            .map(std::io::BufReader::new)
+            .map_err(|e| MendesError::Internal(format!("Cannot open TLS file '{}': {}", path, e)))
+    };
+
+    let certs = rustls_pemfile::certs(&mut open(cert_path)?)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| MendesError::Internal(format!("Invalid TLS certificate '{}': {}", cert_path, e)))?;
+    if certs.is_empty() {
+        return Err(MendesError::Internal(format!("No certificate found in '{}'", cert_path)));
+    }
+
+    let key = rustls_pemfile::private_key(&mut open(key_path)?)
+        .map_err(|e| MendesError::Internal(format!("Invalid TLS key '{}': {}", key_path, e)))?
+        .ok_or_else(|| MendesError::Internal(format!("No private key found in '{}'", key_path)))?;
+
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| MendesError::Internal(format!("Invalid TLS configuration: {}", e)))?;
+
+    Ok(Arc::new(config))
+}
+
+/// Serves HTTP/1 on an accepted connection
+async fn serve_connection<S>(
+    stream: S,
+    router: Arc<Router>,
+    max_body_size: Option<usize>,
+    request_timeout: Option<Duration>,
+    remote_addr: SocketAddr,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(move |req| {
+        let router = router.clone();
+        async move {
+            let handled = handle_request(req, router, max_body_size);
+            let response = match request_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, handled).await
+                    .unwrap_or_else(|_| Response::request_timeout("Request Timeout").into_hyper()),
+                None => handled.await,
+            };
+            Ok::<_, Infallible>(response)
+        }
+    });
+
+    if let Err(err) = http1::Builder::new()
+        .serve_connection(TokioIo::new(stream), service)
+        .with_upgrades()
+        .await
+    {
+        tracing::error!("Error serving connection from {}: {:?}", remote_addr, err);
+    }
+}
+
 /// Request handler
-async fn handle_request(
-    req: hyper::Request<Incoming>,
+async fn handle_request<B>(
+    mut req: hyper::Request<B>,
     router: Arc<Router>,
-) -> hyper::Response<Full<Bytes>> {
+    max_body_size: Option<usize>,
+) -> hyper::Response<HyperBody>
+where
+    B: Body,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    // A `ws` route takes the connection over once the handshake is answered
+    if let Some(response) = upgrade_websocket(&mut req, &router) {
+        return response.into_hyper();
+    }
+
     // Parse request
-    let request = match Request::from_hyper(req).await {
+    let request = match Request::from_hyper(req, max_body_size).await {
         Ok(r) => r,
+        Err(MendesError::Http(e)) if e.status == 413 => {
+            return Response::payload_too_large(e.message).into_hyper();
+        }
         Err(e) => {
             return Response::bad_request(e.to_string()).into_hyper();
         }
@@ -344,11 +640,40 @@ async fn handle_request(
 
     // Route request
     match router.handle(&request).await {
-        Some(response) => response.into_hyper(),
+        Some(response) => response.open_stream().await.into_hyper(),
         None => Response::not_found("Not Found").into_hyper(),
     }
 }
 
+/// Answers the handshake of an upgrade request to a `ws` route and hands the
+/// upgraded connection to the route's handler
+fn upgrade_websocket<B>(req: &mut hyper::Request<B>, router: &Router) -> Option<Response> {
+    let is_upgrade = req.headers().get(hyper::header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    if !is_upgrade {
+        return None;
+    }
+    let handler = router.match_ws(req.uri().path())?.clone();
+    let accept = derive_accept_key(req.headers().get(hyper::header::SEC_WEBSOCKET_KEY)?.as_bytes());
+
+    let upgrade = hyper::upgrade::on(req);
+    tokio::spawn(async move {
+        match upgrade.await {
+            Ok(upgraded) => {
+                let socket = WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None).await;
+                crate::websocket::serve_socket(socket, |conn| handler(conn)).await;
+            }
+            Err(err) => tracing::error!("WebSocket upgrade failed: {:?}", err),
+        }
+    });
+
+    Some(Response::new(101, Vec::new())
+        .with_header("upgrade", "websocket")
+        .with_header("connection", "Upgrade")
+        .with_header("sec-websocket-accept", accept))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -367,6 +692,37 @@ mod tests {
         assert!(String::from_utf8(resp.body).unwrap().contains("not found"));
     }
 
+    #[test]
+    fn test_response_with_header_and_status() {
+        let resp = Response::ok("ok")
+            .with_header("X-Foo", "bar")
+            .with_status(StatusCode::CREATED)
+            .into_hyper();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(resp.headers().get("x-foo").unwrap(), "bar");
+
+        let resp = Response::ok("ok").with_status(202_i64);
+        assert_eq!(resp.status, 202);
+
+        // 65736 would wrap to 200 as a u16
+        let resp = Response::ok("ok").with_status(65736_i64);
+        assert_eq!(resp.status, 500);
+        let resp = Response::ok("ok").with_status(-1_i64);
+        assert_eq!(resp.status, 500);
+    }
+
+    #[test]
+    fn test_response_redirect() {
+        let resp = Response::redirect("/login").into_hyper();
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(resp.headers().get("location").unwrap(), "/login");
+
+        let resp = Response::redirect_permanent("https://example.com/new");
+        assert_eq!(resp.status, 301);
+        assert_eq!(resp.headers.get("location").unwrap(), "https://example.com/new");
+        assert!(resp.body.is_empty());
+    }
+
     #[test]
     fn test_response_json() {
         #[derive(Serialize)]
@@ -377,4 +733,133 @@ mod tests {
         assert_eq!(resp.status, 200);
         assert!(resp.headers.get("content-type").unwrap().contains("application/json"));
     }
+
+    #[tokio::test]
+    async fn test_missing_tls_files_fail_at_startup() {
+        let result = Server::new("127.0.0.1:0")
+            .tls("/nonexistent/cert.pem", "/nonexistent/key.pem")
+            .run()
+            .await;
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("/nonexistent/cert.pem"), "{}", err);
+    }
+
+    fn post(body: &str) -> hyper::Request<Full<Bytes>> {
+        hyper::Request::post("/upload")
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap()
+    }
+
+    fn echo_router() -> Arc<Router> {
+        let mut router = Router::new();
+        router.post("/upload", |req: Request| async move {
+            Response::ok(req.body_string().unwrap())
+        });
+        Arc::new(router)
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected() {
+        let resp = handle_request(post("0123456789abcdef"), echo_router(), Some(8)).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_reaches_handler() {
+        let resp = handle_request(post("01234567"), echo_router(), Some(8)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"01234567");
+    }
+
+    /// Decodes a chunked HTTP/1.1 body
+    fn decode_chunked(mut body: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let line_end = body.windows(2).position(|w| w == b"\r\n").unwrap();
+            let size = usize::from_str_radix(std::str::from_utf8(&body[..line_end]).unwrap(), 16).unwrap();
+            body = &body[line_end + 2..];
+            if size == 0 {
+                return out;
+            }
+            out.extend_from_slice(&body[..size]);
+            body = &body[size + 2..];
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamed_body_arrives_complete() {
+        use tokio::io::AsyncWriteExt;
+
+        let payload: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let mut router = Router::new();
+        let served = payload.clone();
+        router.get("/download", move |_req: Request| {
+            let served = served.clone();
+            async move { Response::stream(200, std::io::Cursor::new(served)) }
+        });
+
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        tokio::spawn(serve_connection(server, Arc::new(router), None, None, addr));
+
+        client
+            .write_all(b"GET /download HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut raw = Vec::new();
+        client.read_to_end(&mut raw).await.unwrap();
+
+        let head_end = raw.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8_lossy(&raw[..head_end]).to_lowercase();
+        assert!(head.starts_with("http/1.1 200"), "{}", head);
+        assert!(head.contains("transfer-encoding: chunked"), "{}", head);
+        assert!(!head.contains("content-length"), "{}", head);
+
+        let body = decode_chunked(&raw[head_end + 4..]);
+        assert_eq!(body.len(), payload.len());
+        assert!(body == payload);
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_times_out() {
+        use tokio::io::AsyncWriteExt;
+
+        let mut router = Router::new();
+        router.get("/slow", |_req: Request| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Response::ok("done")
+        });
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        tokio::spawn(serve_connection(server, Arc::new(router), None, Some(Duration::from_millis(50)), addr));
+
+        client
+            .write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut raw = Vec::new();
+        client.read_to_end(&mut raw).await.unwrap();
+        assert!(String::from_utf8_lossy(&raw).starts_with("HTTP/1.1 408"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_file_missing_is_not_found() {
+        let resp = Response::stream_file("/nonexistent/download.bin").open_stream().await;
+        assert_eq!(resp.status, 404);
+        assert!(!resp.is_streamed());
+    }
+
+    #[tokio::test]
+    async fn test_cloned_stream_is_read_once() {
+        let resp = Response::stream(200, &b"payload"[..]);
+        let copy = resp.clone();
+        assert!(copy.is_streamed());
+
+        let body = resp.into_hyper().into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"payload");
+        let body = copy.into_hyper().into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+    }
 }
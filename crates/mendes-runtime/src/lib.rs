@@ -33,11 +33,11 @@ pub mod error;
 pub mod middleware;
 pub mod websocket;
 
-pub use http::{Server, Request, Response, StatusCode};
+pub use http::{Server, Request, Response, StatusCode, IntoStatus};
 pub use router::Router;
-pub use types::{MendesString, MendesArray, MendesResult, MendesOption};
-pub use websocket::WsConnection;
-pub use error::{MendesError, Result};
+pub use types::{MendesString, MendesArray, MendesResult, MendesOption, Generator, Yielder};
+pub use websocket::{WsConnection, FromWsMessage};
+pub use error::{MendesError, HttpError, Result};
 
 #[cfg(feature = "postgres")]
 pub use database::PostgresPool;
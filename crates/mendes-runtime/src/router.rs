@@ -107,6 +107,11 @@ impl RoutePattern {
                                     return None;
                                 }
                             }
+                            "uuid" => {
+                                if uuid::Uuid::parse_str(path_seg).is_err() {
+                                    return None;
+                                }
+                            }
                             _ => {}
                         }
                     }
@@ -338,6 +343,13 @@ mod tests {
         assert!(params.is_none());
     }
 
+    #[test]
+    fn test_route_pattern_uuid_param() {
+        let pattern = RoutePattern::parse("/orders/{id:uuid}");
+        assert!(pattern.match_path("/orders/67e55044-10b1-426f-9247-bb680e5fe0c8").is_some());
+        assert!(pattern.match_path("/orders/123").is_none());
+    }
+
     #[test]
     fn test_route_pattern_multiple_params() {
         let pattern = RoutePattern::parse("/users/{user_id}/posts/{post_id}");
@@ -3,9 +3,12 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::ops::{Add, Deref};
+use std::panic;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
 
 /// Mendes String - wrapper over String with additional methods
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct MendesString(pub String);
 
 impl MendesString {
@@ -13,8 +16,9 @@ impl MendesString {
         Self(s.into())
     }
 
+    /// Number of characters, the unit strings are indexed and sliced by; O(n)
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.0.chars().count()
     }
 
     pub fn is_empty(&self) -> bool {
@@ -28,6 +32,59 @@ impl MendesString {
     pub fn concat(&self, other: &MendesString) -> MendesString {
         MendesString(format!("{}{}", self.0, other.0))
     }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn trim(&self) -> MendesString {
+        MendesString(self.0.trim().to_string())
+    }
+
+    pub fn to_upper(&self) -> MendesString {
+        MendesString(self.0.to_uppercase())
+    }
+
+    pub fn to_lower(&self) -> MendesString {
+        MendesString(self.0.to_lowercase())
+    }
+
+    /// Pieces between occurrences of `separator`
+    pub fn split(&self, separator: impl AsRef<str>) -> MendesArray<MendesString> {
+        self.0.split(separator.as_ref()).map(MendesString::from).collect()
+    }
+
+    /// Lines of the string, without their `\n` or `\r\n` endings
+    pub fn lines(&self) -> MendesArray<MendesString> {
+        self.0.lines().map(MendesString::from).collect()
+    }
+
+    /// Number of characters (Unicode scalar values), in O(n)
+    pub fn char_count(&self) -> i64 {
+        self.0.chars().count() as i64
+    }
+
+    /// Character at `index`, negative indices counting from the end.
+    /// Walks the string, so it costs O(n) rather than O(1)
+    pub fn char_at(&self, index: i64) -> MendesString {
+        let count = self.char_count();
+        let position = if index < 0 { count + index } else { index };
+        match usize::try_from(position).ok().and_then(|p| self.0.chars().nth(p)) {
+            Some(c) => MendesString(c.to_string()),
+            None => panic!("string index {} out of range for a string of {} characters", index, count),
+        }
+    }
+
+    /// Characters from `start` up to `end` (exclusive), or to the end of the
+    /// string. Bounds count characters, never splitting one; O(n)
+    pub fn slice(&self, start: i64, end: Option<i64>) -> MendesString {
+        let count = self.char_count();
+        let end = end.unwrap_or(count);
+        if start < 0 || start > end || end > count {
+            panic!("string slice {}..{} out of range for a string of {} characters", start, end, count);
+        }
+        MendesString(self.0.chars().skip(start as usize).take((end - start) as usize).collect())
+    }
 }
 
 impl Add for MendesString {
@@ -62,6 +119,12 @@ impl Deref for MendesString {
     }
 }
 
+impl AsRef<str> for MendesString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
 impl fmt::Display for MendesString {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -80,6 +143,12 @@ impl From<String> for MendesString {
     }
 }
 
+impl From<MendesString> for String {
+    fn from(s: MendesString) -> Self {
+        s.0
+    }
+}
+
 /// Mendes dynamic array
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MendesArray<T>(pub Vec<T>);
@@ -258,6 +327,20 @@ impl<T> MendesOption<T> {
             MendesOption::None => MendesOption::None,
         }
     }
+
+    pub fn and_then<U, F: FnOnce(T) -> MendesOption<U>>(self, f: F) -> MendesOption<U> {
+        match self {
+            MendesOption::Some(v) => f(v),
+            MendesOption::None => MendesOption::None,
+        }
+    }
+
+    pub fn as_ref(&self) -> MendesOption<&T> {
+        match self {
+            MendesOption::Some(v) => MendesOption::Some(v),
+            MendesOption::None => MendesOption::None,
+        }
+    }
 }
 
 impl<T> Default for MendesOption<T> {
@@ -284,6 +367,75 @@ impl<T> From<MendesOption<T>> for Option<T> {
     }
 }
 
+/// Mendes generator - lazy sequence produced by a function with `yield`
+///
+/// The body runs on its own thread and only advances when the consumer asks
+/// for the next item, so infinite generators are fine.
+pub struct Generator<T> {
+    resume: Option<SyncSender<()>>,
+    items: Receiver<T>,
+    producer: Option<JoinHandle<()>>,
+}
+
+/// Handle the generator body uses to hand items to its consumer
+pub struct Yielder<T> {
+    resume: Receiver<()>,
+    items: SyncSender<T>,
+}
+
+/// Unwinds the producer thread once the consumer is gone
+struct Stopped;
+
+impl<T: Send + 'static> Generator<T> {
+    pub fn new<F>(body: F) -> Self
+    where
+        F: FnOnce(Yielder<T>) + Send + 'static,
+    {
+        let (resume_tx, resume_rx) = mpsc::sync_channel(0);
+        let (items_tx, items_rx) = mpsc::sync_channel(0);
+        let producer = thread::spawn(move || {
+            let yielder = Yielder { resume: resume_rx, items: items_tx };
+            // Nothing runs until the first item is requested
+            if yielder.resume.recv().is_ok() {
+                body(yielder);
+            }
+        });
+        Generator {
+            resume: Some(resume_tx),
+            items: items_rx,
+            producer: Some(producer),
+        }
+    }
+}
+
+impl<T> Yielder<T> {
+    /// Hands `value` to the consumer and waits until the next item is requested
+    pub fn send(&self, value: T) {
+        if self.items.send(value).is_err() || self.resume.recv().is_err() {
+            panic::resume_unwind(Box::new(Stopped));
+        }
+    }
+}
+
+impl<T> Iterator for Generator<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let resume = self.resume.as_ref()?;
+        if resume.send(()).is_ok() {
+            if let Ok(item) = self.items.recv() {
+                return Some(item);
+            }
+        }
+        // The body finished: surface its panic, if any, to the consumer
+        self.resume = None;
+        if let Some(Err(payload)) = self.producer.take().map(JoinHandle::join) {
+            panic::resume_unwind(payload);
+        }
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,6 +451,36 @@ mod tests {
         assert_eq!(concat.0, "hello world");
     }
 
+    #[test]
+    fn test_mendes_string_chars() {
+        let s = MendesString::new("pão de ló");
+        assert_eq!(s.char_count(), 9);
+        // `len` agrees with indexing, not with the 11 UTF-8 bytes
+        assert_eq!(s.len(), 9);
+        assert_eq!(s.char_at(1).0, "ã");
+        assert_eq!(s.char_at(-1).0, "ó");
+        assert_eq!(s.slice(1, Some(3)).0, "ão");
+        assert_eq!(s.slice(7, None).0, "ló");
+        assert_eq!(s.slice(2, Some(2)).0, "");
+    }
+
+    #[test]
+    fn test_mendes_string_split_lines() {
+        let text = MendesString::new("1, 2,3\r\n4,5\n");
+        let lines = text.lines();
+        assert_eq!(lines.0, vec![MendesString::new("1, 2,3"), MendesString::new("4,5")]);
+
+        let fields = lines.0[0].split(MendesString::new(","));
+        let numbers: Vec<i64> = fields.0.iter().map(|f| f.trim().as_str().parse().unwrap()).collect();
+        assert_eq!(numbers, vec![1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "string slice 2..12 out of range for a string of 9 characters")]
+    fn test_mendes_string_slice_out_of_range() {
+        MendesString::new("pão de ló").slice(2, Some(12));
+    }
+
     #[test]
     fn test_mendes_array() {
         let mut arr: MendesArray<i64> = MendesArray::new();
@@ -331,4 +513,23 @@ mod tests {
         assert!(none.is_none());
         assert_eq!(none.unwrap_or(0), 0);
     }
+
+    #[test]
+    fn test_generator() {
+        let counter = Generator::new(|y| {
+            for i in 0..3 {
+                y.send(i);
+            }
+        });
+        assert_eq!(counter.collect::<Vec<i64>>(), vec![0, 1, 2]);
+
+        let naturals = Generator::new(|y| {
+            let mut n = 0;
+            loop {
+                y.send(n);
+                n += 1;
+            }
+        });
+        assert_eq!(naturals.skip(5).take(2).collect::<Vec<i64>>(), vec![5, 6]);
+    }
 }
@@ -2,16 +2,51 @@
 //!
 //! Provides WebSocket connections for real-time communication.
 
+use std::future::Future;
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, Mutex};
 use futures_util::{StreamExt, SinkExt};
 use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use crate::types::{MendesArray, MendesString};
+
+/// Conversion from a received frame to the type of `message` declared by a
+/// `ws` block
+pub trait FromWsMessage: Sized {
+    /// Converts the frame, or returns `None` when it does not hold this type
+    fn from_ws_message(message: Message) -> Option<Self>;
+}
+
+/// Text frames, and binary frames holding UTF-8
+impl FromWsMessage for MendesString {
+    fn from_ws_message(message: Message) -> Option<Self> {
+        match message {
+            Message::Text(text) => Some(MendesString::new(text)),
+            Message::Binary(bytes) => String::from_utf8(bytes).ok().map(MendesString::new),
+            _ => None,
+        }
+    }
+}
+
+/// Frames as bytes, one `int` per byte
+impl FromWsMessage for MendesArray<i64> {
+    fn from_ws_message(message: Message) -> Option<Self> {
+        match message {
+            Message::Binary(bytes) => Some(bytes.into_iter().map(i64::from).collect()),
+            Message::Text(text) => Some(text.bytes().map(i64::from).collect()),
+            _ => None,
+        }
+    }
+}
 
 /// WebSocket connection handle
 #[derive(Clone)]
 pub struct WsConnection {
     /// Sender for outgoing messages
     sender: mpsc::UnboundedSender<String>,
+    /// Frames received from the client
+    incoming: Option<Arc<Mutex<mpsc::UnboundedReceiver<Message>>>>,
     /// Connection ID
     pub id: String,
 }
@@ -21,10 +56,37 @@ impl WsConnection {
     pub fn new(sender: mpsc::UnboundedSender<String>) -> Self {
         Self {
             sender,
+            incoming: None,
             id: uuid::Uuid::new_v4().to_string(),
         }
     }
 
+    /// Attaches the channel that delivers the frames received from the client
+    pub fn with_incoming(mut self, incoming: mpsc::UnboundedReceiver<Message>) -> Self {
+        self.incoming = Some(Arc::new(Mutex::new(incoming)));
+        self
+    }
+
+    /// Receives the next text message; `None` once the connection is closed
+    pub async fn recv(&self) -> Option<MendesString> {
+        self.recv_as().await
+    }
+
+    /// Receives the next message converted to `T`, skipping frames that do
+    /// not convert, such as pings; `None` once the connection is closed
+    pub async fn recv_as<T: FromWsMessage>(&self) -> Option<T> {
+        let mut incoming = self.incoming.as_ref()?.lock().await;
+        while let Some(frame) = incoming.recv().await {
+            if let Message::Close(_) = frame {
+                return None;
+            }
+            if let Some(message) = T::from_ws_message(frame) {
+                return Some(message);
+            }
+        }
+        None
+    }
+
     /// Sends a message to the client
     pub async fn send(&self, message: &str) {
         let _ = self.sender.send(message.to_string());
@@ -53,6 +115,41 @@ impl WsConnection {
     }
 }
 
+/// Runs `handler` on an open socket: frames from the client feed `recv` and
+/// `recv_as`, and messages sent on the connection go out as text frames
+pub async fn serve_socket<S, F, Fut>(socket: WebSocketStream<S>, handler: F)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    F: FnOnce(WsConnection) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let (mut sink, mut stream) = socket.split();
+    let (outgoing_tx, mut outgoing_rx) = mpsc::unbounded_channel::<String>();
+    let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+
+    let writer = tokio::spawn(async move {
+        while let Some(text) = outgoing_rx.recv().await {
+            if sink.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    });
+    let reader = tokio::spawn(async move {
+        while let Some(Ok(frame)) = stream.next().await {
+            if incoming_tx.send(frame).is_err() {
+                break;
+            }
+        }
+    });
+
+    handler(WsConnection::new(outgoing_tx).with_incoming(incoming_rx)).await;
+
+    // The writer drains what the handler sent once every handle is dropped
+    reader.abort();
+    let _ = writer.await;
+}
+
 /// WebSocket handler trait
 pub trait WsHandler: Send + Sync + 'static {
     /// Called when a new connection is established
@@ -125,4 +222,40 @@ mod tests {
         let msg = rx.recv().await;
         assert_eq!(msg, Some("Hello".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_ws_connection_recv_as() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        let conn = WsConnection::new(tx).with_incoming(incoming_rx);
+
+        incoming_tx.send(Message::Binary(vec![1, 255])).unwrap();
+        incoming_tx.send(Message::Ping(vec![])).unwrap();
+        incoming_tx.send(Message::Text("hi".to_string())).unwrap();
+        incoming_tx.send(Message::Close(None)).unwrap();
+
+        let bytes: Option<MendesArray<i64>> = conn.recv_as().await;
+        assert_eq!(bytes.map(|b| b.0), Some(vec![1, 255]));
+        assert_eq!(conn.recv().await.map(|s| s.0), Some("hi".to_string()));
+        assert_eq!(conn.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_serve_socket_delivers_client_frames() {
+        use tokio_tungstenite::tungstenite::protocol::Role;
+
+        let (server_io, client_io) = tokio::io::duplex(1024);
+        let server = WebSocketStream::from_raw_socket(server_io, Role::Server, None).await;
+        let mut client = WebSocketStream::from_raw_socket(client_io, Role::Client, None).await;
+
+        client.send(Message::Text("ping".to_string())).await.unwrap();
+        let served = tokio::spawn(serve_socket(server, |conn| async move {
+            let message: Option<MendesString> = conn.recv_as().await;
+            conn.send(&format!("got {}", message.unwrap().0)).await;
+        }));
+
+        let reply = client.next().await.unwrap().unwrap();
+        assert_eq!(reply, Message::Text("got ping".to_string()));
+        served.await.unwrap();
+    }
 }
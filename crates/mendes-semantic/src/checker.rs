@@ -1,11 +1,17 @@
 //! Type Checker for the Mendes language
 
 use crate::ownership::OwnershipChecker;
+use crate::suggest;
 use crate::symbols::{Symbol, SymbolKind};
-use crate::types::{MendesType, StructDef};
+use crate::types::{MendesType, StructDef, TraitDef};
 use crate::SemanticContext;
 use mendes_error::{Diagnostic, Diagnostics, ErrorCode, Span};
 use mendes_parser::*;
+use mendes_parser::visit::{self, Visitor};
+use std::collections::{HashMap, HashSet};
+
+/// Generic parameters, parameters and return type of a callee
+type CallSignature = (Vec<String>, Vec<(String, MendesType)>, MendesType);
 
 /// Main Type Checker
 pub struct TypeChecker<'ctx> {
@@ -17,8 +23,42 @@ pub struct TypeChecker<'ctx> {
     diagnostics: Diagnostics,
     /// Return type of the current function
     current_return_type: Option<MendesType>,
+    /// Type the next checked expression is expected to have, used to infer
+    /// generic parameters that only appear in a return type
+    expected_type: Option<MendesType>,
     /// Whether we are in an async context
     in_async: bool,
+    /// Number of loops enclosing the current statement
+    loop_depth: usize,
+    /// Error types propagated by `?` in each enclosing `try` block
+    try_errors: Vec<Vec<(MendesType, Span)>>,
+    /// Path parameters of the route handler being checked
+    path_params: Vec<String>,
+    /// Lengths of immutable locals bound to array literals, by declaration span
+    array_lengths: HashMap<Span, usize>,
+    /// Declaration spans of immutable locals bound to ranges without an end
+    unbounded_ranges: HashSet<Span>,
+    /// `const fn`s by name, evaluated in constant positions
+    const_fns: HashMap<String, FnDecl>,
+    /// Declaration spans of constants and immutable statics, which cannot be
+    /// assigned, with what they are called in diagnostics
+    immutable_globals: HashMap<Span, &'static str>,
+    /// Values of the `const`s checked so far, by name
+    const_values: HashMap<String, ConstValue>,
+    /// Types returned inside each enclosing closure whose return type is inferred
+    closure_returns: Vec<Vec<(MendesType, Span)>>,
+    /// Item type of the generator function being checked, which `yield`
+    /// values must match
+    generator_item: Option<MendesType>,
+    /// Warnings silenced by the `@allow` attributes around the current item
+    allowed_lints: Vec<ErrorCode>,
+    /// Enums declaring each unit variant, with the variant's declaration span
+    variant_owners: HashMap<String, Vec<(String, Span)>>,
+    /// Enums declaring each tuple variant, in declaration order, with the
+    /// variant's declaration span
+    constructor_owners: HashMap<String, Vec<(String, Span)>>,
+    /// Flags enabled with `--cfg`; `@cfg(flag)` items without one are skipped
+    cfg: HashSet<String>,
 }
 
 impl<'ctx> TypeChecker<'ctx> {
@@ -28,7 +68,22 @@ impl<'ctx> TypeChecker<'ctx> {
             ownership: OwnershipChecker::new(),
             diagnostics: Diagnostics::new(),
             current_return_type: None,
+            expected_type: None,
             in_async: false,
+            loop_depth: 0,
+            try_errors: Vec::new(),
+            path_params: Vec::new(),
+            array_lengths: HashMap::new(),
+            unbounded_ranges: HashSet::new(),
+            const_fns: HashMap::new(),
+            immutable_globals: HashMap::new(),
+            const_values: HashMap::new(),
+            closure_returns: Vec::new(),
+            generator_item: None,
+            allowed_lints: Vec::new(),
+            variant_owners: HashMap::new(),
+            constructor_owners: HashMap::new(),
+            cfg: HashSet::new(),
         }
     }
 
@@ -43,6 +98,23 @@ impl<'ctx> TypeChecker<'ctx> {
 
     /// Analyzes the complete program
     pub fn check_program(&mut self, program: &Program) {
+        // Items whose `@cfg` is disabled are skipped, as lowering drops them
+        let program = &Program {
+            statements: program.statements.iter()
+                .filter(|stmt| self.is_enabled(stmt))
+                .cloned()
+                .collect(),
+        };
+
+        // Imported names must not collide with each other or with local items
+        self.check_imports(program);
+
+        // Two routes must not answer the same method and path
+        self.check_routes(program);
+
+        // Items imported from bundled `std` modules
+        self.register_std_imports(program);
+
         // First pass: register structs and functions
         for stmt in &program.statements {
             self.register_declarations(stmt);
@@ -54,10 +126,249 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
+    /// Whether every `@cfg` flag on the statement is enabled
+    fn is_enabled(&self, stmt: &Stmt) -> bool {
+        let Stmt::Attributed { attrs, .. } = stmt else {
+            return true;
+        };
+        attrs.iter()
+            .filter(|attr| attr.name == "cfg")
+            .flat_map(|attr| &attr.args)
+            .all(|flag| self.cfg.contains(flag))
+    }
+
+    /// Detects name collisions introduced by imports
+    fn check_imports(&mut self, program: &Program) {
+        let mut imported: HashMap<String, Span> = HashMap::new();
+
+        for stmt in &program.statements {
+            let bindings: Vec<(String, Span)> = match stmt {
+                // `import math` binds `math`; `import "utils.ms"` only binds its alias
+                Stmt::Import { path, alias, span } => {
+                    let is_file = path.ends_with(".ms") || path.contains('/');
+                    match alias {
+                        Some(alias) => vec![(alias.clone(), *span)],
+                        None if !is_file => vec![(path.clone(), *span)],
+                        None => Vec::new(),
+                    }
+                }
+                Stmt::FromImport { items: ImportItems::Names(items), .. } => items.iter()
+                    .map(|item| (item.alias.clone().unwrap_or_else(|| item.name.clone()), item.span))
+                    .collect(),
+                _ => Vec::new(),
+            };
+
+            for (name, span) in bindings {
+                // `as _` imports an item without binding it
+                if name == "_" {
+                    continue;
+                }
+                if let Some(previous) = imported.get(&name) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("the name `{}` is imported more than once", name))
+                            .with_code(ErrorCode::DUPLICATE_DEFINITION)
+                            .with_label(span, format!("`{}` imported again here", name))
+                            .with_secondary_label(*previous, "first imported here")
+                            .with_help(format!("use `as` to import it under a different name, e.g. `{} as other_{}`", name, name))
+                    );
+                } else {
+                    imported.insert(name, span);
+                }
+            }
+        }
+
+        for stmt in &program.statements {
+            let local = match stmt.unattributed() {
+                Stmt::Fn(f) => Some((&f.name, f.span)),
+                Stmt::Struct(s) => Some((&s.name, s.span)),
+                Stmt::Enum(e) => Some((&e.name, e.span)),
+                Stmt::Trait(t) => Some((&t.name, t.span)),
+                Stmt::Middleware(m) => Some((&m.name, m.span)),
+                Stmt::Db(db) => Some((&db.name, db.span)),
+                Stmt::TypeAlias { name, span, .. } => Some((name, *span)),
+                _ => None,
+            };
+
+            if let Some((name, span)) = local {
+                if let Some(import_span) = imported.get(name) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("the name `{}` is defined here but also imported", name))
+                            .with_code(ErrorCode::DUPLICATE_DEFINITION)
+                            .with_label(span, format!("`{}` redefined here", name))
+                            .with_secondary_label(*import_span, "previous import of the name here")
+                            .with_help("use `as` to import it under a different name")
+                    );
+                }
+            }
+        }
+    }
+
+    /// Defines the items imported from bundled `std` modules; user-file
+    /// imports are resolved by the CLI instead
+    fn register_std_imports(&mut self, program: &Program) {
+        for stmt in &program.statements {
+            let (module, items, span) = match stmt {
+                Stmt::Import { path, span, .. } => (path, None, *span),
+                Stmt::FromImport { module, items, span } => (module, Some(items), *span),
+                _ => continue,
+            };
+            if !stdlib::is_std_path(module) {
+                continue;
+            }
+
+            let Some(exports) = stdlib::std_module(module) else {
+                let help = match suggest::closest_match(module, stdlib::STD_MODULES.iter().copied()) {
+                    Some(closest) => format!("did you mean `{}`?", closest),
+                    None => format!("available modules: {}", stdlib::STD_MODULES.join(", ")),
+                };
+                self.diagnostics.push(
+                    Diagnostic::error(format!("unknown standard module `{}`", module))
+                        .with_code(ErrorCode::UNKNOWN_MODULE)
+                        .with_label(span, "not bundled with the compiler")
+                        .with_help(help)
+                );
+                continue;
+            };
+
+            let imported: Vec<(&str, &str, Span)> = match items {
+                Some(ImportItems::Names(names)) => names.iter()
+                    .map(|item| (item.name.as_str(), item.alias.as_deref().unwrap_or(&item.name), item.span))
+                    .collect(),
+                Some(ImportItems::All) => exports.iter().map(|item| (item.name, item.name, span)).collect(),
+                None => Vec::new(),
+            };
+
+            for (name, binding, item_span) in imported {
+                let Some(export) = exports.iter().find(|item| item.name == name) else {
+                    let mut diag = Diagnostic::error(format!("module `{}` has no item `{}`", module, name))
+                        .with_code(ErrorCode::UNKNOWN_MODULE)
+                        .with_label(item_span, format!("not found in `{}`", module));
+                    if let Some(closest) = suggest::closest_match(name, exports.iter().map(|item| item.name)) {
+                        diag = diag.with_help(format!("did you mean `{}`?", closest));
+                    }
+                    self.diagnostics.push(diag);
+                    continue;
+                };
+                // `as _` imports an item without binding it
+                if binding == "_" {
+                    continue;
+                }
+
+                let ty = MendesType::from_ast(&export.ty);
+                let symbol = match &export.params {
+                    Some(params) => {
+                        let params: Vec<(String, MendesType)> = params.iter()
+                            .map(|(name, ty)| (name.to_string(), MendesType::from_ast(ty)))
+                            .collect();
+                        let fn_ty = MendesType::Function {
+                            params: params.iter().map(|(_, ty)| ty.clone()).collect(),
+                            ret: Box::new(ty.clone()),
+                        };
+                        Symbol::new(binding.to_string(), fn_ty, SymbolKind::Function {
+                            generic_params: vec![],
+                            params,
+                            return_type: ty,
+                            is_async: false,
+                        }, item_span)
+                    }
+                    None => Symbol::variable(binding.to_string(), ty, false, item_span),
+                };
+                self.ctx.symbols.define(symbol.with_visibility(Visibility::Public));
+            }
+        }
+    }
+
+    /// Detects routes declared more than once, including group routes after
+    /// the prefix is applied
+    fn check_routes(&mut self, program: &Program) {
+        let mut seen: HashMap<(HttpMethod, String), Span> = HashMap::new();
+
+        let routes = program.statements.iter().flat_map(|stmt| match stmt.unattributed() {
+            Stmt::Api(api) => vec![api.clone()],
+            Stmt::Group(group) => group.expanded_routes(),
+            Stmt::Fn(_) => stmt.attributed_route().into_iter().collect(),
+            _ => Vec::new(),
+        });
+
+        for api in routes {
+            // `/users/{id}` and `/users/{user_id:int}` match the same requests
+            let key = api.path.split('/')
+                .map(|segment| if segment.starts_with('{') { "{}" } else { segment })
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if let Some(previous) = seen.get(&(api.method, key.clone())) {
+                let route = format!("{} {}", format!("{:?}", api.method).to_uppercase(), api.path);
+                self.diagnostics.push(
+                    Diagnostic::error(format!("route `{}` is defined more than once", route))
+                        .with_code(ErrorCode::DUPLICATE_DEFINITION)
+                        .with_label(api.span, "route redefined here")
+                        .with_secondary_label(*previous, "previous definition of the route here")
+                );
+            } else {
+                seen.insert((api.method, key), api.span);
+            }
+        }
+    }
+
+    /// Reports names declared more than once in the same list of fields,
+    /// variants or parameters, labeling the repeated occurrence
+    fn check_duplicate_names<'a>(&mut self, kind: &str, names: impl Iterator<Item = (&'a str, Span)>) {
+        let mut seen: HashMap<&str, Span> = HashMap::new();
+        for (name, span) in names {
+            if let Some(previous) = seen.get(name) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("{} `{}` is defined more than once", kind, name))
+                        .with_code(ErrorCode::DUPLICATE_DEFINITION)
+                        .with_label(span, format!("`{}` redefined here", name))
+                        .with_secondary_label(*previous, format!("previous definition of `{}` here", name))
+                );
+            } else {
+                seen.insert(name, span);
+            }
+        }
+    }
+
+    /// Warns when a user declaration replaces a builtin symbol
+    fn warn_if_shadows_builtin(&mut self, name: &str, span: Span) {
+        if self.is_builtin(name) {
+            self.lint(
+                Diagnostic::warning(format!("declaration of `{}` shadows a builtin", name))
+                    .with_code(ErrorCode::SHADOWED_BUILTIN)
+                    .with_label(span, format!("this declaration replaces the builtin `{}`", name))
+                    .with_help(format!("consider renaming it, e.g. `my_{}`", name))
+            );
+        }
+    }
+
+    /// Warns when a `let` directly in a route handler redeclares one of
+    /// its path parameters
+    fn warn_if_shadows_path_param(&mut self, name: &str, span: Span) {
+        if !self.path_params.iter().any(|param| param == name) {
+            return;
+        }
+        let Some(param) = self.ctx.symbols.lookup_current_scope(name) else {
+            return;
+        };
+        if !matches!(param.kind, SymbolKind::Parameter) {
+            return;
+        }
+        let mut diag = Diagnostic::warning(format!("`{}` shadows the path parameter of the same name", name))
+            .with_code(ErrorCode::SHADOWED_PATH_PARAM)
+            .with_label(span, format!("this `let` hides the path parameter `{}`", name))
+            .with_help(format!("use a different name, e.g. `{}_value`", name));
+        if let Some(route_span) = param.defined_at {
+            diag = diag.with_secondary_label(route_span, "path parameter declared in this route");
+        }
+        self.lint(diag);
+    }
+
     /// First pass: register declarations
     fn register_declarations(&mut self, stmt: &Stmt) {
         match stmt {
             Stmt::Struct(s) => {
+                self.warn_if_shadows_builtin(&s.name, s.span);
+
                 let fields: Vec<_> = s.fields.iter()
                     .map(|f| (f.name.clone(), MendesType::from_ast(&f.ty)))
                     .collect();
@@ -77,7 +388,12 @@ impl<'ctx> TypeChecker<'ctx> {
 
                 self.ctx.types.register_struct(StructDef {
                     name: s.name.clone(),
+                    generic_params: s.generic_params.iter().map(|gp| gp.name.clone()).collect(),
                     fields: fields.clone(),
+                    defaults: s.fields.iter()
+                        .filter(|f| f.default.is_some())
+                        .map(|f| f.name.clone())
+                        .collect(),
                     methods: methods.clone(),
                     is_copy: s.is_copy,
                 });
@@ -92,9 +408,12 @@ impl<'ctx> TypeChecker<'ctx> {
                     ty: MendesType::Named(s.name.clone()),
                     mutable: false,
                     defined_at: Some(s.span),
+                    visibility: s.visibility,
                 });
             }
             Stmt::Enum(e) => {
+                self.warn_if_shadows_builtin(&e.name, e.span);
+
                 // Collect variants
                 let variants: Vec<_> = e.variants.iter()
                     .map(|v| {
@@ -111,17 +430,84 @@ impl<'ctx> TypeChecker<'ctx> {
                     })
                     .collect();
 
+                let generic_params: Vec<_> = e.generic_params.iter()
+                    .map(|gp| gp.name.clone())
+                    .collect();
+                self.ctx.types.register_enum(&e.name, generic_params.clone());
+                if let Some(values) = e.discriminants() {
+                    self.ctx.types.register_discriminants(&e.name, values);
+                }
+                for variant in &e.variants {
+                    if let EnumVariantData::Struct(fields) = &variant.data {
+                        let names = fields.iter().map(|f| f.name.clone()).collect();
+                        self.ctx.types.register_variant_fields(&e.name, &variant.name, names);
+                    }
+                }
+
                 self.ctx.symbols.define(Symbol {
                     name: e.name.clone(),
                     kind: SymbolKind::Enum {
+                        generic_params: generic_params.clone(),
                         variants: variants.clone(),
                     },
                     ty: MendesType::Named(e.name.clone()),
                     mutable: false,
                     defined_at: Some(e.span),
+                    visibility: Visibility::Private,
                 });
+
+                // Variants are usable unqualified: tuple variants as constructor
+                // functions, unit variants as values of the enum type
+                let enum_type = |args: Vec<MendesType>| if args.is_empty() {
+                    MendesType::Named(e.name.clone())
+                } else {
+                    MendesType::Generic { name: e.name.clone(), args }
+                };
+                for (variant, (_, types)) in e.variants.iter().zip(&variants) {
+                    match &variant.data {
+                        EnumVariantData::Tuple(_) => {
+                            let return_type = enum_type(generic_params.iter()
+                                .map(|gp| MendesType::Named(gp.clone()))
+                                .collect());
+                            self.ctx.symbols.define(Symbol {
+                                name: variant.name.clone(),
+                                kind: SymbolKind::Function {
+                                    generic_params: generic_params.clone(),
+                                    params: types.iter().enumerate()
+                                        .map(|(i, t)| (format!("__arg{}", i), t.clone()))
+                                        .collect(),
+                                    return_type: return_type.clone(),
+                                    is_async: false,
+                                },
+                                ty: MendesType::Function {
+                                    params: types.clone(),
+                                    ret: Box::new(return_type),
+                                },
+                                mutable: false,
+                                defined_at: Some(variant.span),
+                                visibility: Visibility::Private,
+                            });
+                            self.constructor_owners.entry(variant.name.clone())
+                                .or_default()
+                                .push((e.name.clone(), variant.span));
+                        }
+                        EnumVariantData::Unit => {
+                            let ty = enum_type(vec![MendesType::Unknown; generic_params.len()]);
+                            self.ctx.symbols.define(Symbol::variable(variant.name.clone(), ty, false, variant.span));
+                            self.variant_owners.entry(variant.name.clone())
+                                .or_default()
+                                .push((e.name.clone(), variant.span));
+                        }
+                        EnumVariantData::Struct(_) => {}
+                    }
+                }
             }
             Stmt::Fn(f) => {
+                self.warn_if_shadows_builtin(&f.name, f.span);
+                if f.is_const {
+                    self.const_fns.insert(f.name.clone(), f.clone());
+                }
+
                 // Collect generic parameter names
                 let generic_params: Vec<_> = f.generic_params.iter()
                     .map(|gp| gp.name.clone())
@@ -130,9 +516,13 @@ impl<'ctx> TypeChecker<'ctx> {
                 let params: Vec<_> = f.params.iter()
                     .map(|p| (p.name.clone(), MendesType::from_ast(&p.ty)))
                     .collect();
-                let return_type = f.return_type.as_ref()
+                let mut return_type = f.return_type.as_ref()
                     .map(MendesType::from_ast)
                     .unwrap_or(MendesType::Unit);
+                // Calling a generator produces its items lazily
+                if let (true, MendesType::Array(item)) = (f.is_generator(), &return_type) {
+                    return_type = MendesType::Generic { name: "Generator".to_string(), args: vec![(**item).clone()] };
+                }
 
                 self.ctx.symbols.define(Symbol {
                     name: f.name.clone(),
@@ -148,9 +538,34 @@ impl<'ctx> TypeChecker<'ctx> {
                     },
                     mutable: false,
                     defined_at: Some(f.span),
+                    visibility: f.visibility,
                 });
             }
+            Stmt::Static { name, ty, mutable, span, .. } => {
+                self.warn_if_shadows_builtin(name, *span);
+                if !*mutable {
+                    self.immutable_globals.insert(*span, "immutable static");
+                }
+                self.ctx.symbols.define(Symbol::variable(
+                    name.clone(),
+                    MendesType::from_ast(ty),
+                    *mutable,
+                    *span,
+                ));
+            }
+            Stmt::Const { name, ty, visibility, span, .. } => {
+                self.warn_if_shadows_builtin(name, *span);
+                self.immutable_globals.insert(*span, "constant");
+                self.ctx.symbols.define(Symbol::variable(
+                    name.clone(),
+                    MendesType::from_ast(ty),
+                    false,
+                    *span,
+                ).with_visibility(*visibility));
+            }
             Stmt::Db(db) => {
+                self.warn_if_shadows_builtin(&db.name, db.span);
+
                 self.ctx.symbols.define(Symbol {
                     name: db.name.clone(),
                     kind: SymbolKind::Database {
@@ -160,9 +575,12 @@ impl<'ctx> TypeChecker<'ctx> {
                     ty: MendesType::Named(format!("Database<{:?}>", db.db_type)),
                     mutable: false,
                     defined_at: Some(db.span),
+                    visibility: Visibility::Private,
                 });
             }
             Stmt::Middleware(m) => {
+                self.warn_if_shadows_builtin(&m.name, m.span);
+
                 self.ctx.symbols.define(Symbol {
                     name: m.name.clone(),
                     kind: SymbolKind::Middleware,
@@ -172,8 +590,40 @@ impl<'ctx> TypeChecker<'ctx> {
                     },
                     mutable: false,
                     defined_at: Some(m.span),
+                    visibility: Visibility::Private,
+                });
+            }
+            Stmt::Trait(t) => {
+                let methods = t.methods.iter()
+                    .map(|m| {
+                        let params: Vec<_> = m.params.iter()
+                            .map(|p| (p.name.clone(), MendesType::from_ast(&p.ty)))
+                            .collect();
+                        let return_type = m.return_type.as_ref()
+                            .map(MendesType::from_ast)
+                            .unwrap_or(MendesType::Unit);
+                        (m.name.clone(), params, return_type, m.is_async)
+                    })
+                    .collect();
+
+                self.ctx.types.register_trait(TraitDef {
+                    name: t.name.clone(),
+                    methods,
                 });
             }
+            Stmt::ImplTrait(i) => {
+                self.ctx.types.register_impl(&i.type_name, &i.trait_name);
+                let operator = OPERATOR_TRAITS.iter().find(|(name, _)| *name == i.trait_name);
+                if let Some((_, method)) = operator {
+                    let result = i.methods.iter()
+                        .find(|m| m.name == *method)
+                        .and_then(|m| m.return_type.as_ref())
+                        .map(MendesType::from_ast)
+                        .unwrap_or(MendesType::Unknown);
+                    self.ctx.types.register_operator(&i.type_name, &i.trait_name, result);
+                }
+            }
+            Stmt::Attributed { item, .. } => self.register_declarations(item),
             _ => {}
         }
     }
@@ -181,6 +631,25 @@ impl<'ctx> TypeChecker<'ctx> {
     /// Checks a statement
     fn check_statement(&mut self, stmt: &Stmt) {
         match stmt {
+            // Nested items behind a disabled `@cfg` are dropped by lowering too
+            Stmt::Attributed { .. } if !self.is_enabled(stmt) => {}
+            Stmt::Attributed { attrs, item, .. } => {
+                let outer_lints = self.allowed_lints.len();
+                let allowed = attrs.iter().flat_map(|attr| attr.allowed_lints()).filter_map(ErrorCode::lint);
+                self.allowed_lints.extend(allowed.flatten());
+                if let Stmt::Struct(s) = item.unattributed() {
+                    for attr in attrs.iter().filter(|attr| attr.name == "derive") {
+                        self.check_derives(s, attr);
+                    }
+                }
+                if let (Some(api), Stmt::Fn(f)) = (stmt.attributed_route(), item.unattributed()) {
+                    self.check_route_params(f, &api);
+                    self.check_api(&api);
+                } else {
+                    self.check_statement(item);
+                }
+                self.allowed_lints.truncate(outer_lints);
+            }
             Stmt::Import { .. } => {
                 // Imports are processed during the module loading phase
                 // Here we just ignore them - the CLI is responsible for resolving imports
@@ -192,6 +661,15 @@ impl<'ctx> TypeChecker<'ctx> {
             Stmt::Let { name, ty, value, mutable, span } => {
                 self.check_let(name, ty.as_ref(), value, *mutable, *span);
             }
+            Stmt::LetTuple { names, value, mutable, span } => {
+                self.check_let_tuple(names, value, *mutable, *span);
+            }
+            Stmt::Static { name, ty, value, mutable, span } => {
+                self.check_static(name, ty, value, *mutable, *span);
+            }
+            Stmt::Const { name, ty, value, span, .. } => {
+                self.check_const(name, ty, value, *span);
+            }
             Stmt::Fn(f) => {
                 self.check_fn(f);
             }
@@ -204,6 +682,11 @@ impl<'ctx> TypeChecker<'ctx> {
             Stmt::Api(api) => {
                 self.check_api(api);
             }
+            Stmt::Group(group) => {
+                for api in group.expanded_routes() {
+                    self.check_api(&api);
+                }
+            }
             Stmt::WebSocket(ws) => {
                 self.check_websocket(ws);
             }
@@ -219,15 +702,30 @@ impl<'ctx> TypeChecker<'ctx> {
             Stmt::If { condition, then_block, else_block, span } => {
                 self.check_if(condition, then_block, else_block.as_deref(), *span);
             }
+            Stmt::IfLet { pattern, value, then_block, else_block, .. } => {
+                self.check_if_let(pattern, value, then_block, else_block.as_deref());
+            }
             Stmt::For { var, iter, body, span } => {
                 self.check_for(var, iter, body, *span);
             }
             Stmt::While { condition, body, span } => {
                 self.check_while(condition, body, *span);
             }
+            Stmt::WhileLet { pattern, value, body, .. } => {
+                self.check_while_let(pattern, value, body);
+            }
+            Stmt::TryCatch { body, error_var, handler, span } => {
+                self.check_try_catch(body, error_var, handler, *span);
+            }
+            Stmt::With { resource, name, body, span } => {
+                self.check_with(resource, name, body, *span);
+            }
             Stmt::Return { value, span } => {
                 self.check_return(value.as_ref(), *span);
             }
+            Stmt::Yield { value, span } => {
+                self.check_yield(value, *span);
+            }
             Stmt::Expr(expr) => {
                 self.check_expr(expr);
             }
@@ -241,13 +739,10 @@ impl<'ctx> TypeChecker<'ctx> {
                 self.check_type_alias(name, ty, *span);
             }
             Stmt::Break { span } => {
-                // Break is valid inside loops - no type checking needed
-                // Loop context checking would be done here if implemented
-                let _ = span;
+                self.check_loop_control("break", *span);
             }
             Stmt::Continue { span } => {
-                // Continue is valid inside loops - no type checking needed
-                let _ = span;
+                self.check_loop_control("continue", *span);
             }
         }
     }
@@ -286,8 +781,17 @@ impl<'ctx> TypeChecker<'ctx> {
             );
         }
 
-        // Check that the trait exists
-        if self.ctx.symbols.lookup(&i.trait_name).is_none() {
+        // Check that the trait exists; operator traits are built in
+        let operator = OPERATOR_TRAITS.iter().find(|(name, _)| *name == i.trait_name);
+        if let Some((trait_name, method)) = operator {
+            if !i.methods.iter().any(|m| m.name == *method) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`impl {} for {}` is missing `fn {}`", trait_name, i.type_name, method))
+                        .with_code(ErrorCode::UNKNOWN_VARIABLE)
+                        .with_label(i.span, format!("`{}` overloads an operator through `{}`", trait_name, method))
+                );
+            }
+        } else if self.ctx.symbols.lookup(&i.trait_name).is_none() {
             self.diagnostics.push(
                 Diagnostic::error(format!("trait `{}` not found", i.trait_name))
                     .with_code(ErrorCode::UNKNOWN_TYPE)
@@ -295,6 +799,8 @@ impl<'ctx> TypeChecker<'ctx> {
             );
         }
 
+        self.check_impl_asyncness(i);
+
         // Check each method implementation
         for method in &i.methods {
             self.ctx.symbols.push_scope();
@@ -332,23 +838,70 @@ impl<'ctx> TypeChecker<'ctx> {
             // Register parameters
             for param in &method.params {
                 let ty = MendesType::from_ast(&param.ty);
-                self.ctx.symbols.define(Symbol::parameter(
-                    param.name.clone(),
-                    ty.clone(),
-                    param.span,
-                ));
+                match &param.pattern {
+                    Some(pattern) => self.check_pattern(pattern, &ty),
+                    None => {
+                        self.ctx.symbols.define(Symbol::parameter(
+                            param.name.clone(),
+                            ty.clone(),
+                            param.span,
+                        ));
+                    }
+                }
             }
 
             // Check method body
+            self.warn_if_empty_body(&format!("method `{}::{}`", i.type_name, method.name), &method.name, &method.body, method.span);
+            if method.is_async {
+                self.in_async = true;
+                self.ownership.enter_async();
+            }
             for stmt in &method.body {
                 self.check_statement(stmt);
             }
+            if method.is_async {
+                self.in_async = false;
+                self.ownership.exit_async();
+            }
 
             self.ownership.pop_scope();
             self.ctx.symbols.pop_scope();
         }
     }
 
+    /// Checks that each implemented method is `async` exactly when the trait
+    /// declares it `async`, since the two generate different Rust signatures
+    fn check_impl_asyncness(&mut self, i: &ImplTraitDecl) {
+        let trait_methods = match self.ctx.types.get_trait(&i.trait_name) {
+            Some(def) => def.methods.clone(),
+            None => return,
+        };
+
+        for method in &i.methods {
+            let trait_async = match trait_methods.iter().find(|(name, ..)| *name == method.name) {
+                Some((_, _, _, is_async)) => *is_async,
+                None => continue,
+            };
+            if method.is_async == trait_async {
+                continue;
+            }
+
+            let impl_name = format!("`impl {} for {}`", i.trait_name, i.type_name);
+            let trait_name = format!("trait `{}`", i.trait_name);
+            let (async_in, sync_in, help) = if trait_async {
+                (trait_name, impl_name, "add `async` after the return type, as in the trait")
+            } else {
+                (impl_name, trait_name, "remove `async`: the trait declares this method synchronous")
+            };
+            self.diagnostics.push(
+                Diagnostic::error(format!("method `{}` is `async` in {} but not in {}", method.name, async_in, sync_in))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(method.span, "async-ness does not match the trait")
+                    .with_help(help)
+            );
+        }
+    }
+
     /// Checks type alias
     fn check_type_alias(&mut self, name: &str, ty: &Type, span: Span) {
         let mendes_type = MendesType::from_ast(ty);
@@ -364,12 +917,25 @@ impl<'ctx> TypeChecker<'ctx> {
 
     /// Checks let declaration
     fn check_let(&mut self, name: &str, ty: Option<&Type>, value: &Expr, mutable: bool, span: Span) {
+        let declared_type = ty.map(MendesType::from_ast);
+        self.expected_type = declared_type.clone();
         let value_type = self.check_expr(value);
 
-        let declared_type = ty.map(MendesType::from_ast);
+        if let Some(declared) = &declared_type {
+            self.check_type_arity(declared, span);
+        }
+
+        self.warn_if_shadows_path_param(name, span);
+
+        if let (Expr::ArrayLit(elements, _), false) = (value, mutable) {
+            self.array_lengths.insert(span, elements.len());
+        }
+        if let (Expr::Range { end: None, .. }, false) = (value, mutable) {
+            self.unbounded_ranges.insert(span);
+        }
 
         let final_type = if let Some(declared) = &declared_type {
-            if !declared.is_compatible_with(&value_type) {
+            if !self.ctx.types.coerces_to(&value_type, declared) {
                 self.diagnostics.push(
                     Diagnostic::error(format!(
                         "incompatible type: expected `{}`, found `{}`",
@@ -396,6 +962,155 @@ impl<'ctx> TypeChecker<'ctx> {
         self.ownership.define(name.to_string(), final_type, mutable, span);
     }
 
+    /// Checks `static [mut] name: T = value`; the symbol itself was defined
+    /// while registering declarations
+    fn check_static(&mut self, name: &str, ty: &Type, value: &Expr, mutable: bool, span: Span) {
+        let declared = MendesType::from_ast(ty);
+        self.expected_type = Some(declared.clone());
+        let value_type = self.check_expr(value);
+
+        if !matches!(declared, MendesType::Int | MendesType::Float | MendesType::Bool | MendesType::String) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("static `{}` has unsupported type `{}`", name, declared))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, "declared here")
+                    .with_help("statics can be `int`, `float`, `bool` or `string`")
+            );
+            return;
+        }
+        if !self.ctx.types.coerces_to(&value_type, &declared) {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "incompatible type: expected `{}`, found `{}`",
+                    declared, value_type
+                ))
+                .with_code(ErrorCode::TYPE_MISMATCH)
+                .with_label(value.span(), "incompatible types here")
+            );
+            return;
+        }
+
+        if let Err(reason) = self.eval_global_initializer(&declared, value) {
+            self.diagnostics.push(
+                Diagnostic::error("static initializer must be a compile-time constant")
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(value.span(), "not a constant")
+                    .with_note(reason)
+                    .with_help("use a literal, or for `int` and `bool` constants and calls to `const fn`s")
+            );
+        }
+
+        if mutable && !matches!(declared, MendesType::Int | MendesType::Bool) {
+            self.lint(
+                Diagnostic::warning(format!("mutable static `{}` of type `{}` is not atomic", name, declared))
+                    .with_code(ErrorCode::STATIC_DATA_RACE)
+                    .with_label(span, "shared by every handler through a lock")
+                    .with_note("each read and each write takes the lock separately, so `x = x + 1` in concurrent handlers can lose updates")
+                    .with_help("use an `int` or `bool` static for counters and flags")
+            );
+        }
+    }
+
+    /// Checks `const NAME: T = value` and records its value for later
+    /// constant positions; the symbol was defined while registering declarations
+    fn check_const(&mut self, name: &str, ty: &Type, value: &Expr, span: Span) {
+        let declared = MendesType::from_ast(ty);
+        self.expected_type = Some(declared.clone());
+        let value_type = self.check_expr(value);
+
+        if !matches!(declared, MendesType::Int | MendesType::Float | MendesType::Bool) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("constant `{}` has unsupported type `{}`", name, declared))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, "declared here")
+                    .with_help("constants can be `int`, `float` or `bool`; use `static` for a `string`")
+            );
+            return;
+        }
+        if !self.ctx.types.coerces_to(&value_type, &declared) {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "incompatible type: expected `{}`, found `{}`",
+                    declared, value_type
+                ))
+                .with_code(ErrorCode::TYPE_MISMATCH)
+                .with_label(value.span(), "incompatible types here")
+            );
+            return;
+        }
+
+        match self.eval_global_initializer(&declared, value) {
+            Ok(Some(folded)) => {
+                self.const_values.insert(name.to_string(), folded);
+            }
+            Ok(None) => {}
+            Err(reason) => {
+                self.diagnostics.push(
+                    Diagnostic::error("constant initializer must be a compile-time constant")
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(value.span(), "not a constant")
+                        .with_note(reason)
+                        .with_help("use literals, other constants and calls to `const fn`s")
+                );
+            }
+        }
+    }
+
+    /// Evaluates the initializer of a `static` or `const`: `int` and `bool`
+    /// fold through the const evaluator, `float` and `string` must be literals
+    fn eval_global_initializer(&self, ty: &MendesType, value: &Expr) -> Result<Option<ConstValue>, String> {
+        match (ty, value) {
+            (MendesType::Int | MendesType::Bool, _) => ConstEvaluator::new(&self.const_fns)
+                .with_consts(&self.const_values)
+                .eval(value)
+                .map(Some),
+            (MendesType::Float, Expr::FloatLit(..)) | (MendesType::String, Expr::StringLit(..)) => Ok(None),
+            (MendesType::String, _) if concat_string_literals(value).is_some() => Ok(None),
+            (MendesType::Float, Expr::Unary { op: UnaryOp::Neg, expr, .. }) if matches!(**expr, Expr::FloatLit(..)) => Ok(None),
+            _ => Err("only literals are allowed here".to_string()),
+        }
+    }
+
+    /// Checks `let (a, b) = expr`, binding each name to its tuple element
+    fn check_let_tuple(&mut self, names: &[String], value: &Expr, mutable: bool, span: Span) {
+        self.expected_type = None;
+        let value_type = self.check_expr(value);
+
+        let element_types = match value_type {
+            MendesType::Tuple(types) if types.len() == names.len() => types,
+            MendesType::Tuple(types) => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "cannot destructure a tuple of {} elements into {} names",
+                        types.len(),
+                        names.len()
+                    ))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, "wrong number of names here")
+                );
+                vec![MendesType::Unknown; names.len()]
+            }
+            MendesType::Unknown => vec![MendesType::Unknown; names.len()],
+            other => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("cannot destructure `{}`: expected a tuple", other))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(span, "not a tuple")
+                );
+                vec![MendesType::Unknown; names.len()]
+            }
+        };
+
+        for (name, ty) in names.iter().zip(element_types) {
+            if name == "_" {
+                continue;
+            }
+            self.warn_if_shadows_path_param(name, span);
+            self.ctx.symbols.define(Symbol::variable(name.clone(), ty.clone(), mutable, span));
+            self.ownership.define(name.clone(), ty, mutable, span);
+        }
+    }
+
     /// Checks function
     fn check_fn(&mut self, f: &FnDecl) {
         self.ctx.symbols.push_scope();
@@ -406,9 +1121,20 @@ impl<'ctx> TypeChecker<'ctx> {
             self.ctx.types.register_generic_param(&gp.name);
         }
 
+        self.check_duplicate_names(
+            "parameter",
+            f.params.iter().filter(|p| p.pattern.is_none()).map(|p| (p.name.as_str(), p.span)),
+        );
+
         // Register parameters
         for param in &f.params {
             let ty = MendesType::from_ast(&param.ty);
+            self.check_type_arity(&ty, param.span);
+            // A destructured parameter binds the variables of its pattern
+            if let Some(pattern) = &param.pattern {
+                self.check_pattern(pattern, &ty);
+                continue;
+            }
             self.ctx.symbols.define(Symbol::parameter(
                 param.name.clone(),
                 ty.clone(),
@@ -421,7 +1147,21 @@ impl<'ctx> TypeChecker<'ctx> {
         let return_type = f.return_type.as_ref()
             .map(MendesType::from_ast)
             .unwrap_or(MendesType::Unit);
-        self.current_return_type = Some(return_type);
+        self.check_type_arity(&return_type, f.span);
+        self.warn_if_empty_body(&format!("function `{}`", f.name), &f.name, &f.body, f.span);
+        if f.name == "main" {
+            self.check_main_signature(f, &return_type);
+        }
+        if f.is_generator() {
+            self.generator_item = Some(self.generator_item_type(f, &return_type));
+            self.current_return_type = Some(MendesType::Unit);
+        } else {
+            self.current_return_type = Some(return_type);
+        }
+
+        if f.is_const {
+            self.check_const_fn_body(f);
+        }
 
         // Async context
         if f.is_async {
@@ -440,6 +1180,8 @@ impl<'ctx> TypeChecker<'ctx> {
         }
 
         self.current_return_type = None;
+        self.generator_item = None;
+        self.warn_unused_locals(&f.body);
 
         // Unregister generic type parameters
         for gp in &f.generic_params {
@@ -450,32 +1192,155 @@ impl<'ctx> TypeChecker<'ctx> {
         self.ctx.symbols.pop_scope();
     }
 
-    /// Checks struct
-    fn check_struct(&mut self, s: &StructDecl) {
-        // Register generic type parameters temporarily
-        for gp in &s.generic_params {
-            self.ctx.types.register_generic_param(&gp.name);
-        }
-
-        for field in &s.fields {
-            let ty = MendesType::from_ast(&field.ty);
-            if !self.ctx.types.type_exists(&ty) {
+    /// The item type of a generator, declared as its return type `[T]`
+    fn generator_item_type(&mut self, f: &FnDecl, return_type: &MendesType) -> MendesType {
+        match return_type {
+            MendesType::Array(item) => (**item).clone(),
+            MendesType::Unknown => MendesType::Unknown,
+            other => {
                 self.diagnostics.push(
-                    Diagnostic::error(format!("unknown type: `{}`", ty))
-                        .with_code(ErrorCode::UNKNOWN_TYPE)
-                        .with_label(field.span, "type not found")
+                    Diagnostic::error(format!("generator `{}` must return an array of its items, found `{}`", f.name, other))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(f.span, "contains `yield`")
+                        .with_help("declare the item type as `-> [T]`")
                 );
+                MendesType::Unknown
             }
         }
+    }
 
-        // Unregister generic type parameters
-        for gp in &s.generic_params {
-            self.ctx.types.unregister_generic_param(&gp.name);
+    /// Reports operations the const evaluator cannot perform at compile time
+    fn check_const_fn_body(&mut self, f: &FnDecl) {
+        if f.is_async {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`{}` cannot be both `const` and `async`", f.name))
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(f.span, "declared `const async fn`")
+            );
         }
-    }
+
+        let mut violations = ConstViolations { const_fns: &self.const_fns, found: Vec::new() };
+        violations.visit_block(&f.body);
+        for (what, span) in violations.found {
+            self.diagnostics.push(
+                Diagnostic::error(format!("{} is not allowed in a `const fn`", what))
+                    .with_code(ErrorCode::INVALID_SYNTAX)
+                    .with_label(span, "not evaluable at compile time")
+                    .with_help(format!("remove `const` from `{}` or compute this value outside it", f.name))
+            );
+        }
+    }
+
+    /// Warns about a body with no statements, which usually means unfinished
+    /// code. Names starting with `_` mark the emptiness as intentional.
+    fn warn_if_empty_body(&mut self, what: &str, name: &str, body: &[Stmt], span: Span) {
+        if !body.is_empty() || name.starts_with('_') {
+            return;
+        }
+        let mut diag = Diagnostic::warning(format!("{} has an empty body", what))
+            .with_code(ErrorCode::EMPTY_BODY)
+            .with_label(span, "body contains no statements");
+        if !name.is_empty() {
+            diag = diag.with_help(format!("if this is intentional, rename it to `_{}`", name));
+        }
+        self.lint(diag);
+    }
+
+    /// Checks struct
+    fn check_struct(&mut self, s: &StructDecl) {
+        // Register generic type parameters temporarily
+        for gp in &s.generic_params {
+            self.ctx.types.register_generic_param(&gp.name);
+        }
+
+        self.check_duplicate_names("field", s.fields.iter().map(|f| (f.name.as_str(), f.span)));
+
+        for field in &s.fields {
+            let ty = MendesType::from_ast(&field.ty);
+            self.check_type_arity(&ty, field.span);
+            if !self.ctx.types.type_exists(&ty) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("unknown type: `{}`", ty))
+                        .with_code(ErrorCode::UNKNOWN_TYPE)
+                        .with_label(field.span, "type not found")
+                );
+            }
+            if let Some(default) = &field.default {
+                let default_ty = self.check_expr(default);
+                if !ty.is_compatible_with(&default_ty) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "default value for field `{}` has type `{}`, expected `{}`",
+                            field.name, default_ty, ty
+                        ))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(default.span(), "incompatible type")
+                    );
+                }
+            }
+        }
+
+        // Unregister generic type parameters
+        for gp in &s.generic_params {
+            self.ctx.types.unregister_generic_param(&gp.name);
+        }
+    }
+
+    /// Checks if values of `ty` are copied rather than moved, including
+    /// structs declared with `copy`
+    fn is_copy_type(&self, ty: &MendesType) -> bool {
+        match ty {
+            MendesType::Named(name) => self.ctx.types.get_struct(name).is_some_and(|def| def.is_copy),
+            other => other.is_copy(),
+        }
+    }
+
+    /// Warns about traits in `@derive(...)` that the struct cannot derive:
+    /// `Copy` needs `Copy` fields, `Eq`, `Hash` and `Ord` rule out floats,
+    /// and a user `impl Eq` or `impl Ord` already provides the comparison
+    fn check_derives(&mut self, s: &StructDecl, attr: &Attribute) {
+        for name in attr.derives() {
+            let blocking_field = s.fields.iter().find(|field| {
+                let ty = MendesType::from_ast(&field.ty);
+                match name {
+                    "Copy" => !self.is_copy_type(&ty),
+                    "Eq" | "Hash" | "Ord" => ty == MendesType::Float,
+                    _ => false,
+                }
+            });
+            if let Some(field) = blocking_field {
+                self.diagnostics.push(
+                    Diagnostic::warning(format!("`{}` cannot be derived for `{}`", name, s.name))
+                        .with_code(ErrorCode::INVALID_DERIVE)
+                        .with_label(attr.span, "ignored")
+                        .with_secondary_label(field.span, format!("field `{}` is `{}`", field.name, MendesType::from_ast(&field.ty)))
+                );
+            }
+
+            let user_impl = match name {
+                "PartialEq" => "Eq",
+                "PartialOrd" => "Ord",
+                _ => continue,
+            };
+            if self.ctx.types.implements(&s.name, user_impl) {
+                self.diagnostics.push(
+                    Diagnostic::warning(format!("`{}` is already implemented by `impl {} for {}`", name, user_impl, s.name))
+                        .with_code(ErrorCode::INVALID_DERIVE)
+                        .with_label(attr.span, "ignored")
+                );
+            }
+        }
+    }
 
     /// Checks enum
     fn check_enum(&mut self, e: &EnumDecl) {
+        // Register generic type parameters temporarily
+        for gp in &e.generic_params {
+            self.ctx.types.register_generic_param(&gp.name);
+        }
+
+        self.check_duplicate_names("variant", e.variants.iter().map(|v| (v.name.as_str(), v.span)));
+
         // Check that all variant types exist
         for variant in &e.variants {
             match &variant.data {
@@ -485,6 +1350,7 @@ impl<'ctx> TypeChecker<'ctx> {
                 EnumVariantData::Tuple(types) => {
                     for ty in types {
                         let mendes_ty = MendesType::from_ast(ty);
+                        self.check_type_arity(&mendes_ty, variant.span);
                         if !self.ctx.types.type_exists(&mendes_ty) {
                             self.diagnostics.push(
                                 Diagnostic::error(format!("unknown type: `{}`", mendes_ty))
@@ -497,6 +1363,7 @@ impl<'ctx> TypeChecker<'ctx> {
                 EnumVariantData::Struct(fields) => {
                     for field in fields {
                         let mendes_ty = MendesType::from_ast(&field.ty);
+                        self.check_type_arity(&mendes_ty, field.span);
                         if !self.ctx.types.type_exists(&mendes_ty) {
                             self.diagnostics.push(
                                 Diagnostic::error(format!("unknown type: `{}`", mendes_ty))
@@ -508,6 +1375,107 @@ impl<'ctx> TypeChecker<'ctx> {
                 }
             }
         }
+
+        // Two variants cannot convert to the same `int`
+        if let Some(values) = e.discriminants() {
+            let mut seen: HashMap<i64, &str> = HashMap::new();
+            for ((name, value), variant) in values.iter().zip(&e.variants) {
+                if let Some(previous) = seen.insert(*value, name) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("discriminant {} is used by both `{}` and `{}`", value, previous, name))
+                            .with_code(ErrorCode::DUPLICATE_DEFINITION)
+                            .with_label(variant.span, format!("`{}` = {}", name, value))
+                            .with_help("give each variant a distinct value")
+                    );
+                }
+            }
+        }
+
+        // Unregister generic type parameters
+        for gp in &e.generic_params {
+            self.ctx.types.unregister_generic_param(&gp.name);
+        }
+    }
+
+    /// Checks that generic types are given as many type arguments as they declare
+    fn check_type_arity(&mut self, ty: &MendesType, span: Span) {
+        let (name, args): (&str, &[MendesType]) = match ty {
+            MendesType::Named(name) if !self.ctx.types.is_generic_param(name) => (name, &[]),
+            MendesType::Generic { name, args } => (name, args),
+            MendesType::Ref(inner) | MendesType::MutRef(inner) | MendesType::Array(inner)
+            | MendesType::Future(inner) | MendesType::Range(inner) => {
+                return self.check_type_arity(inner, span);
+            }
+            MendesType::Tuple(types) => {
+                for t in types {
+                    self.check_type_arity(t, span);
+                }
+                return;
+            }
+            MendesType::Function { params, ret } => {
+                for p in params {
+                    self.check_type_arity(p, span);
+                }
+                return self.check_type_arity(ret, span);
+            }
+            MendesType::Dyn(trait_name) => {
+                if self.ctx.types.get_trait(trait_name).is_none() {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("trait `{}` not found", trait_name))
+                            .with_code(ErrorCode::UNKNOWN_TYPE)
+                            .with_label(span, "`dyn` requires a trait")
+                    );
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        if let Some(expected) = self.ctx.types.generic_arity(name) {
+            if expected != args.len() {
+                let diag = if expected == 0 {
+                    Diagnostic::error(format!("type `{}` is not generic", name))
+                        .with_label(span, format!("`{}` takes no type arguments", name))
+                        .with_help(format!("remove the type arguments from `{}`", name))
+                } else {
+                    Diagnostic::error(format!(
+                        "type `{}` expects {} type argument{}, found {}",
+                        name,
+                        expected,
+                        if expected == 1 { "" } else { "s" },
+                        args.len()
+                    ))
+                    .with_label(span, "wrong number of type arguments")
+                };
+                self.diagnostics.push(diag.with_code(ErrorCode::WRONG_TYPE_ARG_COUNT));
+            }
+        }
+
+        // Map keys and set elements are hashed
+        let role = match name {
+            "Map" => Some("a map key"),
+            "Set" => Some("a set element"),
+            _ => None,
+        };
+        if let (Some(role), Some(key)) = (role, args.first()) {
+            if let Some(bad) = self.ctx.types.unhashable_part(key) {
+                let reason = if &bad == key {
+                    format!("`{}` cannot be hashed", key)
+                } else {
+                    format!("it contains `{}`, which cannot be hashed", bad)
+                };
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` cannot be used as {}: {}", key, role, reason))
+                        .with_code(ErrorCode::UNHASHABLE_KEY)
+                        .with_label(span, format!("`{}` used as {} here", key, role))
+                        .with_note("keys may contain `int`, `bool`, `string`, and tuples, arrays, options or structs of those")
+                );
+            }
+        }
+
+        for arg in args {
+            self.check_type_arity(arg, span);
+        }
     }
 
     /// Checks API
@@ -517,6 +1485,16 @@ impl<'ctx> TypeChecker<'ctx> {
 
         // Register `body` if declared
         if let Some(body_type) = &api.body_type {
+            // GET and DELETE requests carry no body by convention
+            if matches!(api.method, HttpMethod::Get | HttpMethod::Delete) {
+                let method = format!("{:?}", api.method).to_uppercase();
+                self.lint(
+                    Diagnostic::warning(format!("`{}` route `{}` declares a request body", method, api.path))
+                        .with_code(ErrorCode::BODY_ON_BODYLESS_METHOD)
+                        .with_label(api.span, format!("`body {}` declared here", MendesType::from_ast(body_type)))
+                        .with_help("remove the `body` directive, or use `POST`, `PUT` or `PATCH`")
+                );
+            }
             let ty = MendesType::from_ast(body_type);
             self.ctx.symbols.define(Symbol::parameter(
                 "body".to_string(),
@@ -528,7 +1506,8 @@ impl<'ctx> TypeChecker<'ctx> {
 
         // Register path parameters
         // E.g.: /users/{id:int} -> registers `id` as int
-        self.register_path_params(&api.path, api.span);
+        let path_params = self.register_path_params(&api.path, api.span);
+        self.path_params = path_params.clone();
 
         // Define return type
         let return_type = api.return_type.as_ref()
@@ -546,13 +1525,45 @@ impl<'ctx> TypeChecker<'ctx> {
         for stmt in &api.handler {
             self.check_statement(stmt);
         }
+        self.path_params.clear();
 
         if api.is_async {
             self.in_async = false;
             self.ownership.exit_async();
         }
 
-        self.current_return_type = None;
+        // A declared return type must be produced on every path
+        if let Some(expected) = self.current_return_type.take() {
+            if expected == MendesType::Unit {
+                self.warn_if_empty_body(&format!("handler for `{}`", api.path), "", &api.handler, api.span);
+            }
+            if expected != MendesType::Unit && !self.block_always_returns(&api.handler) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "API handler does not return a value of type `{}` on all paths",
+                        expected
+                    ))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(api.span, format!("expected `{}` to be returned", expected))
+                    .with_help("add a `return` statement at the end of the handler")
+                );
+            }
+        }
+
+        let mut reads = IdentReads::default();
+        reads.visit_block(&api.handler);
+        for param in path_params {
+            if param.starts_with('_') || reads.names.contains(&param) {
+                continue;
+            }
+            self.lint(
+                Diagnostic::warning(format!("path parameter `{}` is never used", param))
+                    .with_code(ErrorCode::UNUSED_PATH_PARAM)
+                    .with_label(api.span, format!("`{}` is declared in the route but not read by the handler", param))
+                    .with_help(format!("if this is intentional, rename it to `_{}`", param))
+            );
+        }
+
         self.ownership.pop_scope();
         self.ctx.symbols.pop_scope();
     }
@@ -586,10 +1597,22 @@ impl<'ctx> TypeChecker<'ctx> {
         // Check on_message handler
         if let Some(handler) = &ws.on_message {
             self.ctx.symbols.push_scope();
-            // Register `message` variable
+            // Register `message` variable, typed by the `message` directive
+            let message_type = ws.message_type.as_ref()
+                .map(MendesType::from_ast)
+                .unwrap_or(MendesType::String);
+            let bytes = MendesType::Array(Box::new(MendesType::Int));
+            if message_type != MendesType::String && message_type != bytes {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("WebSocket messages cannot be received as `{}`", message_type))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(ws.span, "in this WebSocket")
+                        .with_help("use `message string` for text or `message [int]` for bytes")
+                );
+            }
             self.ctx.symbols.define(Symbol::variable(
                 "message".to_string(),
-                MendesType::String,
+                message_type,
                 false,
                 ws.span,
             ));
@@ -612,9 +1635,56 @@ impl<'ctx> TypeChecker<'ctx> {
         self.ctx.symbols.pop_scope();
     }
 
-    /// Registers path parameters
-    fn register_path_params(&mut self, path: &str, span: Span) {
+    /// Checks that each parameter of a `@route` function is a path
+    /// parameter of the same type, or the request `body`
+    fn check_route_params(&mut self, f: &FnDecl, api: &ApiDecl) {
+        for param in &f.params {
+            if param.name == "body" {
+                continue;
+            }
+            let declared = api.path.split('/')
+                .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+                .map(|segment| segment.split_once(':').unwrap_or((segment, "string")))
+                .find(|(name, _)| *name == param.name);
+
+            match declared {
+                Some((_, ty)) => {
+                    // Unsupported types are reported with the route itself
+                    let path_type = path_param_type(ty).unwrap_or(MendesType::Unknown);
+                    let param_type = MendesType::from_ast(&param.ty);
+                    if !param_type.is_compatible_with(&path_type) {
+                        self.diagnostics.push(
+                            Diagnostic::error(format!(
+                                "parameter `{}` has type `{}`, but the route declares it as `{}`",
+                                param.name, param_type, path_type
+                            ))
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(param.span, format!("expected `{}`", path_type))
+                        );
+                    }
+                }
+                None => {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "parameter `{}` of `{}` is not a parameter of route `{}`",
+                            param.name, f.name, api.path
+                        ))
+                        .with_code(ErrorCode::UNKNOWN_VARIABLE)
+                        .with_label(param.span, "not in the route path")
+                        .with_help(format!(
+                            "add `{{{}}}` to the path, or name the request body parameter `body`",
+                            param.name
+                        ))
+                    );
+                }
+            }
+        }
+    }
+
+    /// Registers path parameters, returning their names
+    fn register_path_params(&mut self, path: &str, span: Span) -> Vec<String> {
         // Simple parsing of {param:type}
+        let mut names = Vec::new();
         let mut chars = path.chars().peekable();
         while let Some(c) = chars.next() {
             if c == '{' {
@@ -636,17 +1706,24 @@ impl<'ctx> TypeChecker<'ctx> {
                 }
 
                 if !param.is_empty() {
-                    let ty = match ty_str.as_str() {
-                        "int" => MendesType::Int,
-                        "string" => MendesType::String,
-                        _ => MendesType::String,
-                    };
+                    let ty = path_param_type(&ty_str).unwrap_or_else(|| {
+                        self.diagnostics.push(
+                            Diagnostic::error(format!("unsupported type `{}` for path parameter `{}`", ty_str, param))
+                                .with_code(ErrorCode::UNKNOWN_TYPE)
+                                .with_label(span, format!("`{{{}:{}}}` in the route path", param, ty_str))
+                                .with_help("path parameters can be `int`, `string` or `uuid`")
+                        );
+                        MendesType::Unknown
+                    });
 
                     self.ctx.symbols.define(Symbol::parameter(param.clone(), ty.clone(), span));
-                    self.ownership.define(param, ty, false, span);
+                    self.ownership.define(param.clone(), ty, false, span);
+                    names.push(param);
                 }
             }
         }
+
+        names
     }
 
     /// Checks middleware
@@ -699,6 +1776,31 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
+    /// Checks `if let`: the pattern's bindings are typed from the value and
+    /// visible only in the then block
+    fn check_if_let(&mut self, pattern: &Pattern, value: &Expr, then_block: &[Stmt], else_block: Option<&[Stmt]>) {
+        let value_type = self.check_expr(value);
+
+        self.ctx.symbols.push_scope();
+        self.ownership.push_scope();
+        self.check_pattern(pattern, &value_type);
+        for stmt in then_block {
+            self.check_statement(stmt);
+        }
+        self.ownership.pop_scope();
+        self.ctx.symbols.pop_scope();
+
+        if let Some(else_stmts) = else_block {
+            self.ctx.symbols.push_scope();
+            self.ownership.push_scope();
+            for stmt in else_stmts {
+                self.check_statement(stmt);
+            }
+            self.ownership.pop_scope();
+            self.ctx.symbols.pop_scope();
+        }
+    }
+
     /// Checks for
     fn check_for(&mut self, var: &str, iter: &Expr, body: &[Stmt], span: Span) {
         let iter_type = self.check_expr(iter);
@@ -708,6 +1810,11 @@ impl<'ctx> TypeChecker<'ctx> {
             MendesType::Array(inner) => (**inner).clone(),
             MendesType::Range(inner) => (**inner).clone(),
             MendesType::String => MendesType::String, // Iterate over chars as strings
+            // User types iterate through their `next() -> Option<T>` method
+            MendesType::Named(name) if self.ctx.types.iterator_item(name).is_some() => {
+                self.ctx.types.iterator_item(name).unwrap_or(MendesType::Unknown)
+            }
+            MendesType::Generic { name, args } if name == "Generator" && args.len() == 1 => args[0].clone(),
             _ => {
                 self.diagnostics.push(
                     Diagnostic::error(format!("expected iterable type, found `{}`", iter_type))
@@ -724,9 +1831,11 @@ impl<'ctx> TypeChecker<'ctx> {
         self.ctx.symbols.define(Symbol::variable(var.to_string(), elem_type.clone(), false, span));
         self.ownership.define(var.to_string(), elem_type, false, span);
 
+        self.loop_depth += 1;
         for stmt in body {
             self.check_statement(stmt);
         }
+        self.loop_depth -= 1;
 
         self.ownership.pop_scope();
         self.ctx.symbols.pop_scope();
@@ -745,6 +1854,97 @@ impl<'ctx> TypeChecker<'ctx> {
 
         self.ctx.symbols.push_scope();
         self.ownership.push_scope();
+        self.loop_depth += 1;
+        for stmt in body {
+            self.check_statement(stmt);
+        }
+        self.loop_depth -= 1;
+        self.ownership.pop_scope();
+        self.ctx.symbols.pop_scope();
+    }
+
+    /// Checks `while let`, binding the pattern afresh on each iteration
+    fn check_while_let(&mut self, pattern: &Pattern, value: &Expr, body: &[Stmt]) {
+        let value_type = self.check_expr(value);
+
+        self.ctx.symbols.push_scope();
+        self.ownership.push_scope();
+        self.check_pattern(pattern, &value_type);
+        self.loop_depth += 1;
+        for stmt in body {
+            self.check_statement(stmt);
+        }
+        self.loop_depth -= 1;
+        self.ownership.pop_scope();
+        self.ctx.symbols.pop_scope();
+    }
+
+    /// Checks `try: ... catch err: ...`
+    ///
+    /// Every `?` directly in the body must propagate the same error type,
+    /// which becomes the type of the catch binding.
+    fn check_try_catch(&mut self, body: &[Stmt], error_var: &str, handler: &[Stmt], span: Span) {
+        self.try_errors.push(Vec::new());
+        self.ctx.symbols.push_scope();
+        self.ownership.push_scope();
+        for stmt in body {
+            self.check_statement(stmt);
+        }
+        self.ownership.pop_scope();
+        self.ctx.symbols.pop_scope();
+        let errors = self.try_errors.pop().unwrap_or_default();
+
+        let mut error_type: Option<(MendesType, Span)> = None;
+        for (ty, at) in errors {
+            if ty == MendesType::Unknown {
+                continue;
+            }
+            match &error_type {
+                None => error_type = Some((ty, at)),
+                Some((expected, first)) if !expected.is_compatible_with(&ty) => {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "mismatched error types in `try` block: expected `{}`, found `{}`",
+                            expected, ty
+                        ))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(at, format!("propagates `{}`", ty))
+                        .with_secondary_label(*first, format!("`{}` is bound to `{}` here", expected, error_var))
+                    );
+                }
+                Some(_) => {}
+            }
+        }
+        let error_type = error_type.map(|(ty, _)| ty).unwrap_or(MendesType::Unknown);
+
+        self.ctx.symbols.push_scope();
+        self.ownership.push_scope();
+        self.ctx.symbols.define(Symbol::variable(error_var.to_string(), error_type.clone(), false, span));
+        self.ownership.define(error_var.to_string(), error_type, false, span);
+        for stmt in handler {
+            self.check_statement(stmt);
+        }
+        self.ownership.pop_scope();
+        self.ctx.symbols.pop_scope();
+    }
+
+    /// Checks a `with` block: the resource must have a `close()` method,
+    /// called when the block exits
+    fn check_with(&mut self, resource: &Expr, name: &str, body: &[Stmt], span: Span) {
+        let resource_type = self.check_expr(resource);
+        if resource_type != MendesType::Unknown && !self.is_closeable(&resource_type) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`{}` cannot be used in `with`: it has no `close()` method", resource_type))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(resource.span(), "not a closeable resource")
+                    .with_help("add `fn close(&mut self)` to the type, directly or through a trait impl")
+            );
+        }
+
+        self.ctx.symbols.push_scope();
+        self.ownership.push_scope();
+        self.ctx.symbols.define(Symbol::variable(name.to_string(), resource_type.clone(), true, span));
+        self.ownership.define(name.to_string(), resource_type, true, span);
         for stmt in body {
             self.check_statement(stmt);
         }
@@ -752,12 +1952,49 @@ impl<'ctx> TypeChecker<'ctx> {
         self.ctx.symbols.pop_scope();
     }
 
+    /// Whether `ty` is a struct with a parameterless `close` method
+    fn is_closeable(&self, ty: &MendesType) -> bool {
+        let name = match ty {
+            MendesType::Named(name) => name,
+            _ => return false,
+        };
+        let inherent = self.ctx.types.get_struct(name)
+            .and_then(|def| def.methods.iter().find(|(method, ..)| method == "close"));
+        inherent.or_else(|| self.ctx.types.impl_method(name, "close"))
+            .is_some_and(|(_, params, ..)| params.is_empty())
+    }
+
+    /// Checks that `break`/`continue` appear inside a loop of the current function
+    fn check_loop_control(&mut self, keyword: &str, span: Span) {
+        if self.loop_depth == 0 {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`{}` outside of a loop", keyword))
+                    .with_code(ErrorCode::BREAK_OUTSIDE_LOOP)
+                    .with_label(span, format!("cannot `{}` outside of a loop", keyword))
+            );
+        }
+    }
+
     /// Checks return
     fn check_return(&mut self, value: Option<&Expr>, span: Span) {
-        let return_type = value.map(|e| self.check_expr(e)).unwrap_or(MendesType::Unit);
+        if let (Some(value), Some(_)) = (value, &self.generator_item) {
+            self.check_expr(value);
+            self.diagnostics.push(
+                Diagnostic::error("a generator cannot return a value")
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, "returns a value")
+                    .with_help("use `yield` to produce an item, or a bare `return` to stop")
+            );
+            return;
+        }
+
+        let return_type = value.map(|e| {
+            self.expected_type = self.current_return_type.clone();
+            self.check_expr(e)
+        }).unwrap_or(MendesType::Unit);
 
         if let Some(expected) = &self.current_return_type {
-            if !expected.is_compatible_with(&return_type) {
+            if !self.ctx.types.coerces_to(&return_type, expected) {
                 self.diagnostics.push(
                     Diagnostic::error(format!(
                         "incompatible return type: expected `{}`, found `{}`",
@@ -767,14 +2004,81 @@ impl<'ctx> TypeChecker<'ctx> {
                     .with_label(span, "incompatible type")
                 );
             }
+        } else if let Some(returns) = self.closure_returns.last_mut() {
+            returns.push((return_type, span));
+        }
+    }
+
+    /// Checks `yield`: the value must match the generator's item type
+    fn check_yield(&mut self, value: &Expr, span: Span) {
+        self.expected_type = self.generator_item.clone();
+        let value_type = self.check_expr(value);
+        match &self.generator_item {
+            Some(item) => {
+                if !self.ctx.types.coerces_to(&value_type, item) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("`yield` produces `{}`, but the generator yields `{}`", value_type, item))
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(value.span(), format!("expected `{}`", item))
+                    );
+                }
+            }
+            None => {
+                self.diagnostics.push(
+                    Diagnostic::error("`yield` outside of a generator function")
+                        .with_code(ErrorCode::INVALID_SYNTAX)
+                        .with_label(span, "cannot yield here")
+                        .with_help("`yield` must appear directly in a function body, not in a closure or `async:` block")
+                );
+            }
+        }
+    }
+
+    /// Return-path analysis: whether every path through the block ends in a `return`
+    fn block_always_returns(&self, stmts: &[Stmt]) -> bool {
+        stmts.iter().any(|stmt| match stmt {
+            Stmt::Return { .. } => true,
+            Stmt::If { then_block, else_block: Some(else_stmts), .. }
+            | Stmt::IfLet { then_block, else_block: Some(else_stmts), .. } => {
+                self.block_always_returns(then_block) && self.block_always_returns(else_stmts)
+            }
+            Stmt::With { body, .. } => self.block_always_returns(body),
+            Stmt::Expr(Expr::Match { expr, arms, .. }) => {
+                self.match_is_exhaustive(expr, arms)
+                    && arms.iter().all(|arm| self.block_always_returns(&arm.body))
+            }
+            // Loops may run zero times
+            _ => false,
+        })
+    }
+
+    /// Whether some arm of an already checked match always runs. Matches
+    /// over enums, `bool` and `int` are checked for exhaustiveness; any
+    /// other match needs a catch-all arm
+    fn match_is_exhaustive(&self, scrutinee: &Expr, arms: &[MatchArm]) -> bool {
+        let mut ty = self.ctx.expr_types.get(&scrutinee.span()).unwrap_or(&MendesType::Unknown);
+        while let MendesType::Ref(inner) | MendesType::MutRef(inner) = ty {
+            ty = inner;
         }
+        matches!(ty, MendesType::Bool | MendesType::Int)
+            || self.match_variants(ty).is_some()
+            || arms.iter().any(|arm| arm.guard.is_none() && Self::pattern_is_catch_all(&arm.pattern))
     }
 
     /// Checks expression and returns its type
     fn check_expr(&mut self, expr: &Expr) -> MendesType {
+        let ty = self.check_expr_kind(expr);
+        self.ctx.expr_types.insert(expr.span(), ty.clone());
+        ty
+    }
+
+    fn check_expr_kind(&mut self, expr: &Expr) -> MendesType {
+        // The expected type only applies to the outermost expression
+        let expected = self.expected_type.take();
         match expr {
             Expr::IntLit(_, _) => MendesType::Int,
             Expr::FloatLit(_, _) => MendesType::Float,
+            Expr::DurationLit(_, _) => MendesType::Duration,
             Expr::StringLit(_, _) => MendesType::String,
             Expr::BoolLit(_, _) => MendesType::Bool,
             Expr::None(_) => MendesType::Named("None".to_string()),
@@ -786,7 +2090,14 @@ impl<'ctx> TypeChecker<'ctx> {
                 }
 
                 if let Some(symbol) = self.ctx.symbols.lookup(name) {
-                    symbol.ty.clone()
+                    let (ty, defined_at) = (symbol.ty.clone(), symbol.defined_at);
+                    match self.variant_owners.get(name) {
+                        // A unit variant, unless a local of the same name hides it
+                        Some(owners) if owners.iter().any(|(_, at)| Some(*at) == defined_at) => {
+                            self.unit_variant_type(name, ty, expected.as_ref(), *span)
+                        }
+                        _ => ty,
+                    }
                 } else {
                     self.diagnostics.push(
                         Diagnostic::error(format!("variable not found: `{}`", name))
@@ -806,21 +2117,84 @@ impl<'ctx> TypeChecker<'ctx> {
             }
 
             Expr::Call { func, args, span } => {
-                self.check_call(func, args, *span)
+                self.check_call(func, args, *span, expected)
             }
 
             Expr::MethodCall { object, method, args, span } => {
                 self.check_method_call(object, method, args, *span)
             }
 
+            Expr::NamedArg { name, value, span } => {
+                self.check_expr(value);
+                self.diagnostics.push(
+                    Diagnostic::error(format!("named argument `{}` is only allowed in `format` calls", name))
+                        .with_code(ErrorCode::INVALID_SYNTAX)
+                        .with_label(*span, "named argument")
+                        .with_help("pass the value positionally")
+                );
+                MendesType::Unknown
+            }
+
+            Expr::Spread { expr, span } => {
+                let spread_type = self.check_expr(expr);
+                if !matches!(spread_type, MendesType::Array(_) | MendesType::Unknown) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("cannot spread `{}`: expected an array", spread_type))
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(expr.span(), "not an array")
+                    );
+                }
+                self.diagnostics.push(
+                    Diagnostic::error("cannot spread an array into a non-variadic parameter")
+                        .with_code(ErrorCode::INVALID_SYNTAX)
+                        .with_label(*span, "spread argument")
+                        .with_help("pass the array itself, or its elements one by one")
+                );
+                MendesType::Unknown
+            }
+
             Expr::FieldAccess { object, field, span } => {
                 self.check_field_access(object, field, *span)
             }
 
+            Expr::TupleIndex { object, index, span } => {
+                self.check_tuple_index(object, *index, *span)
+            }
+
+            Expr::OptionalFieldAccess { object, field, span } => {
+                self.check_optional_field_access(object, field, *span)
+            }
+
             Expr::Index { object, index, span } => {
                 self.check_index(object, index, *span)
             }
 
+            Expr::AssocCall { type_name, func, args, span } => {
+                self.check_assoc_call(type_name, func, args, *span)
+            }
+
+            Expr::Path { type_name, name, span } => {
+                match self.enum_variants(type_name) {
+                    Some(variants) if variants.iter().any(|v| v == name) => {
+                        // The path settles variants declared by several enums
+                        self.expected_type = Some(MendesType::Named(type_name.clone()));
+                        self.check_expr(&Expr::Ident(name.clone(), *span))
+                    }
+                    Some(variants) => {
+                        self.diagnostics.push(self.unknown_variant(type_name, name, &variants, *span));
+                        MendesType::Unknown
+                    }
+                    None => {
+                        self.diagnostics.push(
+                            Diagnostic::error(format!("enum `{}` not found", type_name))
+                                .with_code(ErrorCode::UNKNOWN_TYPE)
+                                .with_label(*span, "type not declared")
+                        );
+                        MendesType::Unknown
+                    }
+                }
+            }
+
             Expr::Await { expr, span } => {
                 if !self.in_async {
                     self.diagnostics.push(
@@ -892,6 +2266,13 @@ impl<'ctx> TypeChecker<'ctx> {
             }
 
             Expr::ArrayLit(elements, _) => {
+                // Elements of a `[dyn Trait]` may have different implementing types
+                if let Some(MendesType::Array(elem)) = &expected {
+                    if matches!(**elem, MendesType::Dyn(_)) {
+                        return self.check_dyn_array_lit(elements, elem);
+                    }
+                }
+
                 if elements.is_empty() {
                     MendesType::Array(Box::new(MendesType::Unknown))
                 } else {
@@ -910,16 +2291,84 @@ impl<'ctx> TypeChecker<'ctx> {
                 }
             }
 
+            Expr::MapLit { entries, span } => self.check_map_lit(entries, expected, *span),
+
+            Expr::ArrayRepeat { value, count, span: _ } => {
+                let elem_type = self.check_expr(value);
+                let count_type = self.check_expr(count);
+                if !count_type.is_compatible_with(&MendesType::Int) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("array length must be an `int`, found `{}`", count_type))
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(count.span(), "array length")
+                    );
+                } else {
+                    match ConstEvaluator::new(&self.const_fns).with_consts(&self.const_values).eval_int(count) {
+                        Ok(n) if n < 0 => {
+                            self.diagnostics.push(
+                                Diagnostic::error(format!("array length cannot be negative, found {}", n))
+                                    .with_code(ErrorCode::TYPE_MISMATCH)
+                                    .with_label(count.span(), "array length")
+                            );
+                        }
+                        Ok(_) => {}
+                        Err(reason) => {
+                            self.diagnostics.push(
+                                Diagnostic::error("array length must be a compile-time constant")
+                                    .with_code(ErrorCode::TYPE_MISMATCH)
+                                    .with_label(count.span(), "not a constant")
+                                    .with_note(reason)
+                                    .with_help("use an integer literal or a call to a `const fn`")
+                            );
+                        }
+                    }
+                }
+                MendesType::Array(Box::new(elem_type))
+            }
+
+            Expr::Cast { expr, ty, span } => {
+                let from = self.check_expr(expr);
+                let to = MendesType::from_ast(ty);
+                let error = match &from {
+                    _ if !matches!(to, MendesType::Int | MendesType::Float) => {
+                        Some(format!("cannot cast `{}` as `{}`: only `int` and `float` are valid cast targets", from, to))
+                    }
+                    MendesType::Int | MendesType::Float | MendesType::Unknown => None,
+                    MendesType::Named(name) if self.ctx.types.is_enum(name) => {
+                        if self.ctx.types.discriminants(name).is_none() {
+                            Some(format!("cannot cast `{}` as `{}`: only enums without data have integer values", from, to))
+                        } else if to != MendesType::Int {
+                            Some(format!("cannot cast `{}` as `{}`: enums can only be cast to `int`", from, to))
+                        } else {
+                            None
+                        }
+                    }
+                    _ => Some(format!("cannot cast `{}` as `{}`", from, to)),
+                };
+                if let Some(message) = error {
+                    self.diagnostics.push(
+                        Diagnostic::error(message)
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(*span, "invalid cast")
+                    );
+                }
+                to
+            }
+
             Expr::Match { expr, arms, span } => {
                 self.check_match(expr, arms, *span)
             }
 
             Expr::Try { expr, span } => {
-                self.check_try(expr, *span)
+                self.check_try(expr, *span, expected)
             }
 
             Expr::Closure { params, return_type, body, span } => {
-                self.check_closure(params, return_type, body, *span)
+                self.check_closure(params, return_type, body, *span, expected.as_ref())
+            }
+
+            Expr::AsyncBlock { body, span: _ } => {
+                self.check_async_block(body)
             }
 
             Expr::StringInterpolation { parts, span: _ } => {
@@ -969,16 +2418,23 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
-    /// Checks closure expression
-    fn check_closure(&mut self, params: &[ClosureParam], return_type: &Option<Type>, body: &ClosureBody, _span: Span) -> MendesType {
+    /// Checks closure expression; parameters without a type take it from
+    /// the expected `Fn` type when the closure is passed to a known method
+    fn check_closure(&mut self, params: &[ClosureParam], return_type: &Option<Type>, body: &ClosureBody, _span: Span, expected: Option<&MendesType>) -> MendesType {
+        let expected_params = match expected {
+            Some(MendesType::Generic { name, args }) if name == "Fn" => &args[..args.len().saturating_sub(1)],
+            _ => &[],
+        };
+
         // Enter new scope for closure body
         self.ctx.symbols.push_scope();
         self.ownership.push_scope();
 
         // Add parameters to scope
-        let param_types: Vec<MendesType> = params.iter().map(|p| {
+        let param_types: Vec<MendesType> = params.iter().enumerate().map(|(i, p)| {
             let ty = p.ty.as_ref()
                 .map(MendesType::from_ast)
+                .or_else(|| expected_params.get(i).cloned())
                 .unwrap_or(MendesType::Unknown);
 
             self.ctx.symbols.define(Symbol::variable(
@@ -991,35 +2447,56 @@ impl<'ctx> TypeChecker<'ctx> {
             ty
         }).collect();
 
+        // `return` anywhere in the body returns from the closure, not the
+        // enclosing function; loops and `try` blocks around the closure
+        // cannot be reached from inside it
+        let declared_type = return_type.as_ref().map(MendesType::from_ast);
+        let outer_return_type = std::mem::replace(&mut self.current_return_type, declared_type.clone());
+        let outer_generator_item = self.generator_item.take();
+        let outer_loop_depth = std::mem::take(&mut self.loop_depth);
+        let outer_try_errors = std::mem::take(&mut self.try_errors);
+
         // Check body
-        let body_type = match body {
+        let ret_type = match body {
             ClosureBody::Expr(expr) => {
-                self.check_expr(expr)
+                self.expected_type = declared_type.clone();
+                let body_type = self.check_expr(expr);
+                match declared_type {
+                    Some(declared) => {
+                        if !self.ctx.types.coerces_to(&body_type, &declared) {
+                            self.diagnostics.push(
+                                Diagnostic::error(format!(
+                                    "incompatible return type: expected `{}`, found `{}`",
+                                    declared, body_type
+                                ))
+                                .with_code(ErrorCode::TYPE_MISMATCH)
+                                .with_label(expr.span(), "incompatible type")
+                            );
+                        }
+                        declared
+                    }
+                    None => body_type,
+                }
             }
             ClosureBody::Block(stmts) => {
-                let mut result_type = MendesType::Unit;
+                self.closure_returns.push(Vec::new());
                 for stmt in stmts {
-                    if let Stmt::Return { value, .. } = stmt {
-                        if let Some(val) = value {
-                            result_type = self.check_expr(val);
-                        }
-                    } else {
-                        self.check_statement(stmt);
-                    }
+                    self.check_statement(stmt);
                 }
-                result_type
+                let returns = self.closure_returns.pop().unwrap_or_default();
+                declared_type.unwrap_or_else(|| self.unify_closure_returns(returns, "closure"))
             }
         };
 
+        self.current_return_type = outer_return_type;
+        self.generator_item = outer_generator_item;
+        self.loop_depth = outer_loop_depth;
+        self.try_errors = outer_try_errors;
+
         // Pop closure scope
         self.ownership.pop_scope();
         self.ctx.symbols.pop_scope();
 
-        // Determine return type
-        let ret_type = return_type.as_ref()
-            .map(MendesType::from_ast)
-            .unwrap_or(body_type);
-
         // Return a function type
         MendesType::Generic {
             name: "Fn".to_string(),
@@ -1031,18 +2508,109 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
+    /// Infers a closure's or `async:` block's return type from its `return`s:
+    /// the first one fixes the type and every later one must agree with it
+    fn unify_closure_returns(&mut self, returns: Vec<(MendesType, Span)>, owner: &str) -> MendesType {
+        let mut returns = returns.into_iter();
+        let Some((inferred, first_span)) = returns.next() else {
+            return MendesType::Unit;
+        };
+        for (ty, span) in returns {
+            if !self.ctx.types.coerces_to(&ty, &inferred) {
+                let mut diag = Diagnostic::error(format!(
+                    "incompatible return type: expected `{}`, found `{}`",
+                    inferred, ty
+                ))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, "incompatible type")
+                    .with_secondary_label(first_span, format!("the {} returns `{}` here", owner, inferred));
+                if owner == "closure" {
+                    diag = diag.with_help("annotate the closure's return type, e.g. `|x| -> int: ...`");
+                }
+                self.diagnostics.push(diag);
+            }
+        }
+        inferred
+    }
+
+    /// Checks async block: the body runs in an async context and the block yields a Future
+    fn check_async_block(&mut self, body: &[Stmt]) -> MendesType {
+        self.ctx.symbols.push_scope();
+        self.ownership.push_scope();
+
+        let was_async = self.in_async;
+        self.in_async = true;
+        self.ownership.enter_async();
+
+        // `return` inside the block produces the future's value, not the function's,
+        // and loops and `try` blocks around it cannot be reached from inside it
+        let outer_return_type = self.current_return_type.take();
+        let outer_generator_item = self.generator_item.take();
+        let outer_closure_returns = std::mem::take(&mut self.closure_returns);
+        let outer_loop_depth = std::mem::take(&mut self.loop_depth);
+        let outer_try_errors = std::mem::take(&mut self.try_errors);
+
+        // Every `return`, however deeply nested, contributes to the future's type
+        self.closure_returns.push(Vec::new());
+        for stmt in body {
+            self.check_statement(stmt);
+        }
+        let returns = self.closure_returns.pop().unwrap_or_default();
+        let result_type = self.unify_closure_returns(returns, "async block");
+
+        self.current_return_type = outer_return_type;
+        self.generator_item = outer_generator_item;
+        self.closure_returns = outer_closure_returns;
+        self.loop_depth = outer_loop_depth;
+        self.try_errors = outer_try_errors;
+
+        self.in_async = was_async;
+        if was_async {
+            self.ownership.enter_async();
+        } else {
+            self.ownership.exit_async();
+        }
+
+        self.ownership.pop_scope();
+        self.ctx.symbols.pop_scope();
+
+        MendesType::Future(Box::new(result_type))
+    }
+
     /// Checks try expression (?)
-    fn check_try(&mut self, expr: &Expr, span: Span) -> MendesType {
+    fn check_try(&mut self, expr: &Expr, span: Span, expected: Option<MendesType>) -> MendesType {
+        // `e?` producing `T` means `e` is a `Result<T, _>`
+        self.expected_type = expected.map(|ok| MendesType::Generic {
+            name: "Result".to_string(),
+            args: vec![ok, MendesType::Unknown],
+        });
         let expr_type = self.check_expr(expr);
+        self.try_value(&expr_type, span)
+    }
 
+    /// The value `?` unwraps from `expr_type`, recording what it propagates
+    fn try_value(&mut self, expr_type: &MendesType, span: Span) -> MendesType {
         // The ? operator works on Result<T, E> and Option<T>
-        match &expr_type {
+        match expr_type {
             MendesType::Generic { name, args } if name == "Result" => {
-                // Result<T, E>? returns T, propagates E
+                // Result<T, E>? returns T, propagates E (to the catch handler inside `try`)
+                let error = args.get(1).cloned().unwrap_or(MendesType::Unknown);
+                match self.try_errors.last_mut() {
+                    Some(errors) => errors.push((error, span)),
+                    None => self.check_propagated_error(&error, span),
+                }
                 args.first().cloned().unwrap_or(MendesType::Unknown)
             }
             MendesType::Generic { name, args } if name == "Option" => {
                 // Option<T>? returns T, propagates None
+                if !self.try_errors.is_empty() {
+                    self.diagnostics.push(
+                        Diagnostic::error("`?` on an `Option` has no error value for `catch` to bind")
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(span, "`None` would reach the catch handler")
+                            .with_help("convert it to a `Result` first so the handler receives an error")
+                    );
+                }
                 args.first().cloned().unwrap_or(MendesType::Unknown)
             }
             MendesType::Unknown => MendesType::Unknown,
@@ -1060,6 +2628,85 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
+    /// `main` takes no parameters and returns nothing or `Result<(), E>`,
+    /// whose error ends the program with a nonzero exit code
+    fn check_main_signature(&mut self, f: &FnDecl, return_type: &MendesType) {
+        if !f.params.is_empty() {
+            self.diagnostics.push(
+                Diagnostic::error("`main` cannot take parameters")
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(f.span, format!("declared with {} parameter(s)", f.params.len()))
+            );
+        }
+        let valid = match return_type {
+            MendesType::Unit => true,
+            MendesType::Generic { name, args } if name == "Result" => {
+                match args.first() {
+                    Some(MendesType::Tuple(elements)) => elements.is_empty(),
+                    Some(ok) => matches!(ok, MendesType::Unit | MendesType::Unknown),
+                    None => true,
+                }
+            }
+            _ => false,
+        };
+        if !valid {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`main` must return nothing or `Result<(), E>`, found `{}`", return_type))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(f.span, "invalid return type for `main`")
+            );
+        }
+    }
+
+    /// `?` outside a `try` block returns the error from the enclosing
+    /// function, which must return a `Result` with a compatible error type
+    fn check_propagated_error(&mut self, error: &MendesType, span: Span) {
+        let expected = match &self.current_return_type {
+            Some(MendesType::Generic { name, args }) if name == "Result" => {
+                args.get(1).cloned().unwrap_or(MendesType::Unknown)
+            }
+            Some(MendesType::Unknown) | None => return,
+            Some(other) => {
+                self.diagnostics.push(
+                    Diagnostic::error("the `?` operator can only be used in a function that returns `Result`")
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(span, format!("the function returns `{}`", other))
+                        .with_help(format!("change the return type to `Result<{}, {}>`", other, error))
+                );
+                return;
+            }
+        };
+        // User error enums convert into `HttpError` (status 500) when propagated
+        let converts_to_http = matches!(
+            (error, &expected),
+            (MendesType::Named(name), MendesType::Named(target)) if target == "HttpError" && self.ctx.types.is_enum(name)
+        );
+        if *error != MendesType::Unknown && expected != MendesType::Unknown
+            && !error.is_compatible_with(&expected) && !converts_to_http
+        {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`?` propagates `{}`, but the function returns errors of type `{}`", error, expected))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, format!("propagates `{}`", error))
+            );
+        }
+    }
+
+    /// Checks an array literal whose elements must all coerce to `elem`, a `dyn Trait`
+    fn check_dyn_array_lit(&mut self, elements: &[Expr], elem: &MendesType) -> MendesType {
+        for element in elements {
+            let element_type = self.check_expr(element);
+            if !self.ctx.types.coerces_to(&element_type, elem) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` cannot be used as `{}`", element_type, elem))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(element.span(), format!("`{}` does not implement the trait", element_type))
+                );
+            }
+        }
+        MendesType::Array(Box::new(elem.clone()))
+    }
+
     /// Checks match expression
     fn check_match(&mut self, expr: &Expr, arms: &[MatchArm], span: Span) -> MendesType {
         let scrutinee_type = self.check_expr(expr);
@@ -1086,25 +2733,18 @@ impl<'ctx> TypeChecker<'ctx> {
                 }
             }
 
-            // Check arm body
+            // Check arm body; the arm's value comes from its last statement
             let mut arm_type = MendesType::Unit;
             for stmt in &arm.body {
-                match stmt {
-                    Stmt::Return { value, .. } => {
-                        if let Some(val) = value.as_ref() {
-                            arm_type = self.check_expr(val);
-                        }
-                    }
-                    Stmt::Expr(Expr::Ok(val, _)) | Stmt::Expr(Expr::Err(val, _)) => {
-                        arm_type = self.check_expr(val);
-                    }
-                    Stmt::Expr(e) => {
-                        arm_type = self.check_expr(e);
-                    }
+                arm_type = match stmt {
+                    Stmt::Return { value: Some(val), .. } => self.check_expr(val),
+                    Stmt::Expr(Expr::Ok(val, _)) | Stmt::Expr(Expr::Err(val, _)) => self.check_expr(val),
+                    Stmt::Expr(e) => self.check_expr(e),
                     _ => {
                         self.check_statement(stmt);
+                        MendesType::Unit
                     }
-                }
+                };
             }
 
             arm_types.push(arm_type);
@@ -1113,21 +2753,37 @@ impl<'ctx> TypeChecker<'ctx> {
             self.ctx.symbols.pop_scope();
         }
 
-        // All arms should have compatible types
-        if let Some(first_type) = arm_types.first() {
-            for (i, arm_type) in arm_types.iter().enumerate().skip(1) {
-                if !first_type.is_compatible_with(arm_type) {
+        self.check_match_exhaustive(&scrutinee_type, arms, span);
+
+        // All arms should have compatible types; the first arm producing a
+        // value decides the type of the match
+        if !arm_types.is_empty() {
+            let match_type = arm_types.iter()
+                .find(|ty| **ty != MendesType::Unit)
+                .unwrap_or(&arm_types[0])
+                .clone();
+
+            for (arm, arm_type) in arms.iter().zip(&arm_types) {
+                if *arm_type == MendesType::Unit && match_type != MendesType::Unit {
+                    let end = arm.body.last().map(Stmt::span).unwrap_or(arm.span);
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("match arm does not produce a value of type `{}`", match_type))
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(end, "the arm ends here without a value")
+                            .with_help(format!("end the arm with an expression of type `{}`", match_type))
+                    );
+                } else if !match_type.is_compatible_with(arm_type) {
                     self.diagnostics.push(
                         Diagnostic::error(format!(
                             "match arms have incompatible types: expected `{}`, found `{}`",
-                            first_type, arm_type
+                            match_type, arm_type
                         ))
                         .with_code(ErrorCode::TYPE_MISMATCH)
-                        .with_label(arms[i].span, "incompatible type in this arm")
+                        .with_label(arm.span, "incompatible type in this arm")
                     );
                 }
             }
-            first_type.clone()
+            match_type
         } else {
             self.diagnostics.push(
                 Diagnostic::error("match expression must have at least one arm")
@@ -1138,6 +2794,127 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
+    /// Reports a match whose arms leave some values of the scrutinee unhandled.
+    /// Guarded arms never count towards coverage.
+    fn check_match_exhaustive(&mut self, scrutinee_type: &MendesType, arms: &[MatchArm], span: Span) {
+        let mut scrutinee_type = scrutinee_type;
+        while let MendesType::Ref(inner) | MendesType::MutRef(inner) = scrutinee_type {
+            scrutinee_type = inner;
+        }
+        let variants = self.match_variants(scrutinee_type);
+
+        let patterns: Vec<&Pattern> = arms.iter()
+            .filter(|arm| arm.guard.is_none())
+            .map(|arm| &arm.pattern)
+            .collect();
+        self.warn_unreachable_arms(arms, variants.as_deref());
+        if patterns.iter().any(|p| Self::pattern_is_catch_all_in(p, variants.as_deref())) {
+            return;
+        }
+
+        let missing: Vec<String> = match (scrutinee_type, &variants) {
+            (_, Some(variants)) => variants.iter()
+                .filter(|variant| !patterns.iter().any(|p| Self::pattern_covers_variant(p, variant, variants)))
+                .map(|variant| format!("`{}`", variant))
+                .collect(),
+            (MendesType::Bool, _) => [true, false].into_iter()
+                .filter(|value| !patterns.iter().any(|p| Self::pattern_covers_bool(p, *value)))
+                .map(|value| format!("`{}`", value))
+                .collect(),
+            // The domain is unbounded, so only a catch-all arm covers it
+            (MendesType::Int, _) => vec!["`_`".to_string()],
+            _ => Vec::new(),
+        };
+
+        if !missing.is_empty() {
+            let listed = match missing.split_last() {
+                Some((last, [])) => last.clone(),
+                Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+                None => String::new(),
+            };
+            self.diagnostics.push(
+                Diagnostic::error(format!("non-exhaustive match: {} not covered", listed))
+                    .with_code(ErrorCode::NON_EXHAUSTIVE_MATCH)
+                    .with_label(span, format!("`{}` values are not all handled", scrutinee_type))
+                    .with_help("add the missing arms or a `_:` arm for the remaining values")
+            );
+        }
+    }
+
+    /// Variants a match over `ty` must cover: those of a user enum, or the
+    /// two of `Option` and `Result`
+    fn match_variants(&self, ty: &MendesType) -> Option<Vec<String>> {
+        match ty {
+            MendesType::Named(name) => self.enum_variants(name),
+            MendesType::Generic { name, .. } if name == "Option" => Some(vec!["Some".to_string(), "None".to_string()]),
+            MendesType::Generic { name, .. } if name == "Result" => Some(vec!["Ok".to_string(), "Err".to_string()]),
+            _ => None,
+        }
+    }
+
+    /// Warns about arms that follow an unguarded catch-all arm, which
+    /// always matches first
+    fn warn_unreachable_arms(&mut self, arms: &[MatchArm], variants: Option<&[String]>) {
+        let Some(first) = arms.iter()
+            .position(|arm| arm.guard.is_none() && Self::pattern_is_catch_all_in(&arm.pattern, variants))
+        else {
+            return;
+        };
+        for arm in &arms[first + 1..] {
+            self.lint(
+                Diagnostic::warning("unreachable match arm")
+                    .with_code(ErrorCode::UNREACHABLE_ARM)
+                    .with_label(arm.pattern.span(), "never matched")
+                    .with_secondary_label(arms[first].pattern.span(), "this arm matches every value first")
+                    .with_help("remove the arm or move it before the catch-all")
+            );
+        }
+    }
+
+    /// Whether a pattern matches every value of its type
+    fn pattern_is_catch_all(pattern: &Pattern) -> bool {
+        Self::pattern_is_catch_all_in(pattern, None)
+    }
+
+    /// Like `pattern_is_catch_all`, except that a bare name is a unit
+    /// variant rather than a binding when it is one of `variants`
+    fn pattern_is_catch_all_in(pattern: &Pattern, variants: Option<&[String]>) -> bool {
+        match pattern {
+            Pattern::Wildcard(_) => true,
+            Pattern::Ident { name, .. } => !variants.is_some_and(|vs| vs.contains(name)),
+            Pattern::Range { start: None, end: None, .. } => true,
+            Pattern::Or(patterns, _) => patterns.iter().any(|p| Self::pattern_is_catch_all_in(p, variants)),
+            _ => false,
+        }
+    }
+
+    /// Whether a pattern matches every value of `variant`: its payload
+    /// patterns, if any, must all be catch-alls
+    fn pattern_covers_variant(pattern: &Pattern, variant: &str, variants: &[String]) -> bool {
+        match pattern {
+            Pattern::Ident { name, .. } if variants.contains(name) => name == variant,
+            // `Rect { w, h }` parses as a struct pattern
+            Pattern::Struct { name, fields, .. } if variants.contains(name) => name == variant
+                && fields.iter().all(|(_, p)| p.as_ref().is_none_or(Self::pattern_is_catch_all)),
+            Pattern::Variant { variant: name, data, .. } => name == variant && match data {
+                VariantPatternData::Unit => true,
+                VariantPatternData::Tuple(patterns) => patterns.iter().all(Self::pattern_is_catch_all),
+                VariantPatternData::Struct(fields) => fields.iter()
+                    .all(|(_, p)| p.as_ref().is_none_or(Self::pattern_is_catch_all)),
+            },
+            Pattern::Or(patterns, _) => patterns.iter().any(|p| Self::pattern_covers_variant(p, variant, variants)),
+            _ => Self::pattern_is_catch_all(pattern),
+        }
+    }
+
+    fn pattern_covers_bool(pattern: &Pattern, value: bool) -> bool {
+        match pattern {
+            Pattern::Literal(Expr::BoolLit(b, _)) => *b == value,
+            Pattern::Or(patterns, _) => patterns.iter().any(|p| Self::pattern_covers_bool(p, value)),
+            _ => Self::pattern_is_catch_all(pattern),
+        }
+    }
+
     /// Checks a pattern and binds variables
     fn check_pattern(&mut self, pattern: &Pattern, expected_type: &MendesType) {
         match pattern {
@@ -1159,6 +2936,10 @@ impl<'ctx> TypeChecker<'ctx> {
                 }
             }
 
+            Pattern::Ident { name, .. } if self.is_unit_variant_of(expected_type, name) => {
+                // A unit variant of the matched enum, not a binding
+            }
+
             Pattern::Ident { name, mutable, span } => {
                 // Bind the variable
                 self.ctx.symbols.define(Symbol::variable(
@@ -1171,12 +2952,45 @@ impl<'ctx> TypeChecker<'ctx> {
             }
 
             Pattern::Tuple(patterns, span) => {
-                // For now, just bind each pattern as Unknown
-                // Full tuple type support would require tuple types in MendesType
-                for pat in patterns {
-                    self.check_pattern(pat, &MendesType::Unknown);
+                match expected_type {
+                    MendesType::Tuple(types) if types.len() == patterns.len() => {
+                        for (pat, ty) in patterns.iter().zip(types) {
+                            self.check_pattern(pat, ty);
+                        }
+                    }
+                    MendesType::Tuple(types) => {
+                        self.diagnostics.push(
+                            Diagnostic::error(format!(
+                                "tuple pattern has {} elements but `{}` has {}",
+                                patterns.len(), expected_type, types.len()
+                            ))
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(*span, format!("expected {} elements", types.len()))
+                        );
+                        for pat in patterns {
+                            self.check_pattern(pat, &MendesType::Unknown);
+                        }
+                    }
+                    _ => {
+                        for pat in patterns {
+                            self.check_pattern(pat, &MendesType::Unknown);
+                        }
+                    }
                 }
-                let _ = span; // suppress unused warning
+            }
+
+            // `Rect { w, h }` parses as a struct pattern but names a struct
+            // variant of the matched enum
+            Pattern::Struct { name, fields, span }
+                if self.ctx.types.get_struct(name).is_none() && self.is_variant_of(expected_type, name) =>
+            {
+                let variant = Pattern::Variant {
+                    enum_name: None,
+                    variant: name.clone(),
+                    data: VariantPatternData::Struct(fields.clone()),
+                    span: *span,
+                };
+                self.check_pattern(&variant, expected_type);
             }
 
             Pattern::Struct { name, fields, span } => {
@@ -1238,77 +3052,29 @@ impl<'ctx> TypeChecker<'ctx> {
                             self.check_pattern(pat, &inner_type);
                         }
                     }
-                } else if let Some(enum_name_str) = enum_name {
-                    // User-defined enum
-                    // Clone to avoid borrow conflict
-                    let variant_types_opt = self.ctx.symbols.lookup(enum_name_str)
-                        .and_then(|symbol| {
-                            if let SymbolKind::Enum { variants } = &symbol.kind {
-                                variants.iter().find(|(n, _)| n == variant)
-                                    .map(|(_, types)| types.clone())
-                            } else {
-                                None
-                            }
-                        });
-
-                    if let Some(variant_types) = variant_types_opt {
-                        match data {
-                            VariantPatternData::Unit => {
-                                // No bindings needed
-                            }
-                            VariantPatternData::Tuple(patterns) => {
-                                for (pat, ty) in patterns.iter().zip(variant_types.iter()) {
-                                    self.check_pattern(pat, ty);
-                                }
-                            }
-                            VariantPatternData::Struct(fields) => {
-                                // For struct variants, we'd need field names stored
-                                for (field_name, field_pattern) in fields {
-                                    if let Some(pat) = field_pattern {
-                                        self.check_pattern(pat, &MendesType::Unknown);
-                                    } else {
-                                        self.ctx.symbols.define(Symbol::variable(
-                                            field_name.clone(),
-                                            MendesType::Unknown,
-                                            false,
-                                            *span,
-                                        ));
-                                    }
-                                }
-                            }
-                        }
-                    } else {
-                        // Check if enum exists but variant doesn't
-                        let enum_exists = self.ctx.symbols.lookup(enum_name_str).is_some();
-                        if enum_exists {
-                            self.diagnostics.push(
-                                Diagnostic::error(format!("variant `{}` not found in enum `{}`", variant, enum_name_str))
-                                    .with_code(ErrorCode::UNKNOWN_VARIABLE)
-                                    .with_label(*span, "unknown variant")
-                            );
-                        }
-                    }
                 } else {
-                    // Unqualified variant - try to infer from expected type
-                    if let MendesType::Named(type_name) = expected_type {
-                        // Clone to avoid borrow conflict
-                        let variant_types_opt = self.ctx.symbols.lookup(type_name)
-                            .and_then(|symbol| {
-                                if let SymbolKind::Enum { variants } = &symbol.kind {
-                                    variants.iter().find(|(n, _)| n == variant)
-                                        .map(|(_, types)| types.clone())
-                                } else {
-                                    None
-                                }
-                            });
+                    // User-defined enum, named in the pattern or taken from
+                    // the type of the value being matched
+                    let enum_name_str = match (enum_name, expected_type) {
+                        (Some(name), _) => Some(name.clone()),
+                        (None, MendesType::Named(name) | MendesType::Generic { name, .. }) => Some(name.clone()),
+                        _ => None,
+                    };
+                    let Some(enum_name_str) = enum_name_str else {
+                        return;
+                    };
 
-                        if let Some(variant_types) = variant_types_opt {
-                            if let VariantPatternData::Tuple(patterns) = data {
-                                for (pat, ty) in patterns.iter().zip(variant_types.iter()) {
-                                    self.check_pattern(pat, ty);
-                                }
+                    match self.enum_variant_types(&enum_name_str, variant, expected_type) {
+                        Some(variant_types) => {
+                            self.check_variant_bindings(&enum_name_str, variant, data, &variant_types, *span);
+                        }
+                        // An unqualified name may be a binding rather than a variant
+                        None if enum_name.is_some() => {
+                            if let Some(variants) = self.enum_variants(&enum_name_str) {
+                                self.diagnostics.push(self.unknown_variant(&enum_name_str, variant, &variants, *span));
                             }
                         }
+                        None => {}
                     }
                 }
             }
@@ -1352,15 +3118,108 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
+    /// Checks the sub-patterns of a tuple or struct variant against the
+    /// variant's associated types
+    fn check_variant_bindings(
+        &mut self,
+        enum_name: &str,
+        variant: &str,
+        data: &VariantPatternData,
+        variant_types: &[MendesType],
+        span: Span,
+    ) {
+        match data {
+            VariantPatternData::Unit => {}
+            VariantPatternData::Tuple(patterns) => {
+                for (pat, ty) in patterns.iter().zip(variant_types) {
+                    self.check_pattern(pat, ty);
+                }
+            }
+            VariantPatternData::Struct(fields) => {
+                let names = self.ctx.types.variant_fields(enum_name, variant).unwrap_or_default().to_vec();
+                for (field_name, field_pattern) in fields {
+                    let ty = match names.iter().position(|n| n == field_name) {
+                        Some(index) => variant_types.get(index).cloned().unwrap_or(MendesType::Unknown),
+                        None => {
+                            self.diagnostics.push(
+                                Diagnostic::error(format!("variant `{}::{}` has no field `{}`", enum_name, variant, field_name))
+                                    .with_code(ErrorCode::TYPE_MISMATCH)
+                                    .with_label(span, "unknown field")
+                            );
+                            MendesType::Unknown
+                        }
+                    };
+                    match field_pattern {
+                        Some(pat) => self.check_pattern(pat, &ty),
+                        None => {
+                            self.ctx.symbols.define(Symbol::variable(field_name.clone(), ty.clone(), false, span));
+                            self.ownership.define(field_name.clone(), ty, false, span);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Emits a lint warning unless an enclosing `@allow` silences its code
+    fn lint(&mut self, diag: Diagnostic) {
+        if diag.code.is_some_and(|code| self.allowed_lints.contains(&code)) {
+            return;
+        }
+        self.diagnostics.push(diag);
+    }
+
+    /// Warns about `let` bindings in a function body that are never read
+    fn warn_unused_locals(&mut self, body: &[Stmt]) {
+        let mut locals = LocalDecls::default();
+        locals.visit_block(body);
+        let mut reads = IdentReads::default();
+        reads.visit_block(body);
+        for (name, span) in locals.found {
+            if name.starts_with('_') || reads.names.contains(&name) {
+                continue;
+            }
+            self.lint(
+                Diagnostic::warning(format!("variable `{}` is never used", name))
+                    .with_code(ErrorCode::UNUSED_VARIABLE)
+                    .with_label(span, "declared here but never read")
+                    .with_help(format!("if this is intentional, rename it to `_{}`", name))
+            );
+        }
+    }
+
     /// Checks binary operation
     fn check_binary(&mut self, left: &Expr, op: BinOp, right: &Expr, span: Span) -> MendesType {
         let left_type = self.check_expr(left);
         let right_type = self.check_expr(right);
 
-        match op {
-            // Arithmetic
-            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => {
-                // String + String is concatenation
+        if let Expr::Ident(name, target_span) = left {
+            let is_write = op == BinOp::Assign || op.compound_base().is_some();
+            let declared = self.ctx.symbols.lookup(name).and_then(|s| s.defined_at);
+            if let (true, Some(declared)) = (is_write, declared) {
+                if let Some(kind) = self.immutable_globals.get(&declared) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("cannot assign to {} `{}`", kind, name))
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(*target_span, format!("assignment to {}", kind))
+                            .with_secondary_label(declared, "declared here")
+                            .with_help(format!("declare it as `static mut {}` to change it at runtime", name))
+                    );
+                }
+            }
+        }
+
+        // Operators on structs dispatch to an operator trait impl
+        if let (Some(trait_name), MendesType::Named(type_name)) = (op.operator_trait(), &left_type) {
+            if self.ctx.types.get_struct(type_name).is_some() {
+                return self.check_operator_impl(op, trait_name, type_name, &right_type, span);
+            }
+        }
+
+        match op {
+            // Arithmetic
+            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => {
+                // String + String is concatenation
                 if matches!(op, BinOp::Add) && matches!((&left_type, &right_type), (MendesType::String, MendesType::String)) {
                     return MendesType::String;
                 }
@@ -1384,6 +3243,21 @@ impl<'ctx> TypeChecker<'ctx> {
                 }
             }
 
+            // Tuples and arrays compare element by element
+            BinOp::Eq | BinOp::Ne if matches!(
+                (&left_type, &right_type),
+                (MendesType::Tuple(_), MendesType::Tuple(_)) | (MendesType::Array(_), MendesType::Array(_))
+            ) => {
+                if let Some(reason) = self.equality_mismatch(&left_type, &right_type) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("cannot compare `{}` with `{}`", left_type, right_type))
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(span, reason)
+                    );
+                }
+                MendesType::Bool
+            }
+
             // Comparison
             BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
                 if !left_type.is_compatible_with(&right_type) {
@@ -1411,9 +3285,21 @@ impl<'ctx> TypeChecker<'ctx> {
                 MendesType::Bool
             }
 
+            // Compound assignment follows the rules of its arithmetic operator
+            BinOp::AddAssign | BinOp::SubAssign | BinOp::MulAssign | BinOp::DivAssign => {
+                let base = op.compound_base().and_then(|base| base.operator_trait().map(|t| (base, t)));
+                match (base, &left_type) {
+                    (Some((base, trait_name)), MendesType::Named(type_name)) if self.ctx.types.get_struct(type_name).is_some() => {
+                        self.check_operator_impl(base, trait_name, type_name, &right_type, span);
+                    }
+                    _ => self.check_compound_assign(op, &left_type, &right_type, right.span()),
+                }
+                MendesType::Unit
+            }
+
             // Assignment
-            BinOp::Assign | BinOp::AddAssign | BinOp::SubAssign | BinOp::MulAssign | BinOp::DivAssign => {
-                if !left_type.is_compatible_with(&right_type) {
+            BinOp::Assign => {
+                if !self.ctx.types.coerces_to(&right_type, &left_type) {
                     self.diagnostics.push(
                         Diagnostic::error(format!("cannot assign `{}` to `{}`", right_type, left_type))
                             .with_code(ErrorCode::TYPE_MISMATCH)
@@ -1425,6 +3311,98 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
+    /// Why values of `left` and `right` cannot be compared with `==`, if they
+    /// cannot: tuples need the same length, and every element pair must be
+    /// comparable
+    fn equality_mismatch(&self, left: &MendesType, right: &MendesType) -> Option<String> {
+        match (left, right) {
+            (MendesType::Unknown, _) | (_, MendesType::Unknown) => None,
+            (MendesType::Tuple(a), MendesType::Tuple(b)) if a.len() != b.len() => {
+                Some(format!("tuples of {} and {} elements", a.len(), b.len()))
+            }
+            (MendesType::Tuple(a), MendesType::Tuple(b)) => a.iter().zip(b).enumerate()
+                .find_map(|(i, (x, y))| self.equality_mismatch(x, y).map(|reason| format!("element {}: {}", i, reason))),
+            (MendesType::Array(a), MendesType::Array(b)) => self.equality_mismatch(a, b)
+                .map(|reason| format!("array elements: {}", reason)),
+            (MendesType::Function { .. } | MendesType::Future(_), _) => {
+                Some(format!("`{}` values cannot be compared", left))
+            }
+            (MendesType::Generic { name, .. }, _) if name == "Fn" => {
+                Some(format!("`{}` values cannot be compared", left))
+            }
+            (MendesType::Named(a), MendesType::Named(b)) if a == b
+                && self.ctx.types.get_struct(a).is_some()
+                && self.ctx.types.operator_result(a, "Eq").is_none() =>
+            {
+                Some(format!("`{}` does not implement `Eq`", a))
+            }
+            _ if !left.is_compatible_with(right) => Some(format!("`{}` is not `{}`", left, right)),
+            _ => None,
+        }
+    }
+
+    /// Checks the operands of `+=`, `-=`, `*=` and `/=`: numbers of the same
+    /// type, or two strings for `+=`
+    fn check_compound_assign(&mut self, op: BinOp, target: &MendesType, value: &MendesType, value_span: Span) {
+        let valid = match (target, value) {
+            (MendesType::Unknown, _) | (_, MendesType::Unknown) => true,
+            (MendesType::Int, MendesType::Int) | (MendesType::Float, MendesType::Float) => true,
+            (MendesType::String, MendesType::String) => op == BinOp::AddAssign,
+            _ => false,
+        };
+        if valid {
+            return;
+        }
+
+        let mut diag = Diagnostic::error(format!("cannot apply `{}` to `{}` and `{}`", op.symbol(), target, value))
+            .with_code(ErrorCode::TYPE_MISMATCH)
+            .with_label(value_span, format!("`{}`", value));
+        diag = match (target, value) {
+            (MendesType::String, MendesType::String) => {
+                diag.with_note(format!("`{}` requires numbers; strings only support `+=`", op.symbol()))
+            }
+            (MendesType::String, _) if op == BinOp::AddAssign => {
+                diag.with_help("convert the value first, e.g. `str(value)`")
+            }
+            (MendesType::Int | MendesType::Float, MendesType::Int | MendesType::Float) => {
+                diag.with_help(format!("convert the value with `as {}`", target))
+            }
+            _ => diag.with_note(format!("`{}` requires two numbers of the same type", op.symbol())),
+        };
+        self.diagnostics.push(diag);
+    }
+
+    /// Checks an operator on a struct against its operator trait impl
+    fn check_operator_impl(&mut self, op: BinOp, trait_name: &str, type_name: &str, right_type: &MendesType, span: Span) -> MendesType {
+        let comparison = matches!(trait_name, "Eq" | "Ord");
+        let result = match self.ctx.types.operator_result(type_name, trait_name) {
+            Some(result) => result.clone(),
+            None => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("operation `{:?}` not supported between `{}` and `{}`", op, type_name, right_type))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(span, format!("`{}` does not implement `{}`", type_name, trait_name))
+                        .with_help(format!("add `impl {} for {}:` to overload this operator", trait_name, type_name))
+                );
+                return if comparison { MendesType::Bool } else { MendesType::Unknown };
+            }
+        };
+
+        let right_name = match right_type {
+            MendesType::Ref(inner) | MendesType::MutRef(inner) => inner.as_ref(),
+            other => other,
+        };
+        if !matches!(right_name, MendesType::Unknown) && *right_name != MendesType::Named(type_name.to_string()) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("operation `{:?}` not supported between `{}` and `{}`", op, type_name, right_type))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, format!("`impl {} for {}` takes another `{}`", trait_name, type_name, type_name))
+            );
+        }
+
+        if comparison { MendesType::Bool } else { result }
+    }
+
     /// Checks unary operation
     fn check_unary(&mut self, op: UnaryOp, expr: &Expr, span: Span) -> MendesType {
         let expr_type = self.check_expr(expr);
@@ -1454,15 +3432,39 @@ impl<'ctx> TypeChecker<'ctx> {
     }
 
     /// Checks function call
-    fn check_call(&mut self, func: &Expr, args: &[Expr], span: Span) -> MendesType {
+    fn check_call(&mut self, func: &Expr, args: &[Expr], span: Span, expected: Option<MendesType>) -> MendesType {
         // First, get the argument types
         let arg_types: Vec<MendesType> = args.iter().map(|a| self.check_expr(a)).collect();
 
         // Try to get generic params if this is a named function
-        let (generic_params, func_params, func_ret) = if let Expr::Ident(func_name, _) = func {
+        let constructor = match func {
+            Expr::Ident(name, _) => self.constructor_owner(name, expected.as_ref(), span).map(|owner| (name, owner)),
+            _ => None,
+        };
+        let (generic_params, func_params, func_ret) = if let Some((variant, owner)) = constructor {
+            match owner.and_then(|owner| self.constructor_signature(&owner, variant)) {
+                Some(signature) => signature,
+                None => return MendesType::Unknown,
+            }
+        } else if let Expr::Ident(func_name, _) = func {
             if let Some(symbol) = self.ctx.symbols.lookup(func_name) {
-                if let SymbolKind::Function { generic_params, params, return_type, .. } = &symbol.kind {
-                    (generic_params.clone(), params.clone(), return_type.clone())
+                // The builtin `join` awaits the task
+                if func_name == "join" && symbol.defined_at.is_none() && !self.in_async {
+                    self.diagnostics.push(
+                        Diagnostic::error("join can only be used in async context")
+                            .with_code(ErrorCode::INVALID_SYNTAX)
+                            .with_label(span, "join outside of async function")
+                            .with_help("mark the function `async`")
+                    );
+                }
+                if let SymbolKind::Function { generic_params, params, return_type, is_async } = &symbol.kind {
+                    // Calling an async function yields a future of its return type
+                    let ret = if *is_async {
+                        MendesType::Future(Box::new(return_type.clone()))
+                    } else {
+                        return_type.clone()
+                    };
+                    (generic_params.clone(), params.clone(), ret)
                 } else {
                     (vec![], vec![], MendesType::Unknown)
                 }
@@ -1483,6 +3485,13 @@ impl<'ctx> TypeChecker<'ctx> {
             }
         };
 
+        // `str(x, precision)` formats a float with a fixed number of decimals
+        if let Expr::Ident(func_name, _) = func {
+            if func_name == "str" && args.len() == 2 && self.is_builtin(func_name) {
+                return self.check_str_precision(args, &arg_types);
+            }
+        }
+
         // Check argument count
         if !func_params.is_empty() && args.len() != func_params.len() {
             self.diagnostics.push(
@@ -1494,12 +3503,25 @@ impl<'ctx> TypeChecker<'ctx> {
         }
 
         // Infer generic type parameters from arguments
-        let type_substitutions = self.infer_generic_types(&generic_params, &func_params, &arg_types);
+        let mut type_substitutions = self.infer_generic_types(&generic_params, &func_params, &arg_types);
+
+        // Parameters that only appear in the return type come from the expected type
+        if let Some(expected) = &expected {
+            self.collect_type_substitutions(&func_ret, expected, &generic_params, &mut type_substitutions);
+        }
+
+        if let Expr::Ident(func_name, _) = func {
+            if func_name == "parse" && self.is_builtin(func_name) {
+                self.check_parse_target(type_substitutions.get("T"), span);
+            }
+        }
 
         // Check argument types with substitutions applied
         for (i, ((_, expected_type), arg_type)) in func_params.iter().zip(arg_types.iter()).enumerate() {
             let substituted = self.substitute_generics(expected_type, &type_substitutions);
-            if !substituted.is_compatible_with(arg_type) {
+            if let Some(diag) = self.closure_signature_mismatch(arg_type, &substituted, args[i].span()) {
+                self.diagnostics.push(diag);
+            } else if !self.ctx.types.coerces_to(arg_type, &substituted) {
                 self.diagnostics.push(
                     Diagnostic::error(format!("incompatible argument: expected `{}`, found `{}`", substituted, arg_type))
                         .with_code(ErrorCode::TYPE_MISMATCH)
@@ -1509,16 +3531,256 @@ impl<'ctx> TypeChecker<'ctx> {
 
             // Check argument move
             if let Expr::Ident(name, arg_span) = &args[i] {
-                if !expected_type.is_ref() && !arg_type.is_copy() {
+                if !expected_type.is_ref() && !self.is_copy_type(arg_type) {
                     self.ownership.mark_moved(name, *arg_span);
                 }
             }
         }
 
-        // Apply substitutions to return type
+        // Apply substitutions to return type; parameters that could not be
+        // inferred are left unconstrained
+        for gp in &generic_params {
+            type_substitutions.entry(gp.clone()).or_insert(MendesType::Unknown);
+        }
         self.substitute_generics(&func_ret, &type_substitutions)
     }
 
+    /// The enum a call of an unqualified tuple variant constructs: the
+    /// expected enum when it declares the variant, otherwise the only enum
+    /// that does. Returns `None` when `name` is not a variant constructor, and
+    /// `Some(None)` after reporting a variant shared by several enums.
+    fn constructor_owner(&mut self, name: &str, expected: Option<&MendesType>, span: Span) -> Option<Option<String>> {
+        let defined_at = self.ctx.symbols.lookup(name)?.defined_at;
+        let owners = self.constructor_owners.get(name)?;
+        // A function or local of the same name hides the variants
+        if !owners.iter().any(|(_, at)| Some(*at) == defined_at) {
+            return None;
+        }
+
+        if let Some(MendesType::Named(enum_name) | MendesType::Generic { name: enum_name, .. }) = expected {
+            if owners.iter().any(|(owner, _)| owner == enum_name) {
+                return Some(Some(enum_name.clone()));
+            }
+        }
+        if let [(owner, _)] = owners.as_slice() {
+            return Some(Some(owner.clone()));
+        }
+        let owners = owners.clone();
+        self.diagnostics.push(self.ambiguous_variant(name, &owners, span));
+        Some(None)
+    }
+
+    /// Generic parameters, parameters and return type of the constructor of
+    /// the tuple variant `variant` of `enum_name`
+    fn constructor_signature(&self, enum_name: &str, variant: &str) -> Option<CallSignature> {
+        let SymbolKind::Enum { generic_params, variants } = &self.ctx.symbols.lookup(enum_name)?.kind else {
+            return None;
+        };
+        let (_, types) = variants.iter().find(|(name, _)| name == variant)?;
+        let params = types.iter().enumerate()
+            .map(|(i, t)| (format!("__arg{}", i), t.clone()))
+            .collect();
+        let ret = if generic_params.is_empty() {
+            MendesType::Named(enum_name.to_string())
+        } else {
+            MendesType::Generic {
+                name: enum_name.to_string(),
+                args: generic_params.iter().map(|gp| MendesType::Named(gp.clone())).collect(),
+            }
+        };
+        Some((generic_params.clone(), params, ret))
+    }
+
+    /// Explains why a closure does not fit the function type `expected`:
+    /// it takes a different number of parameters, or a parameter whose type
+    /// is incompatible with what the caller passes
+    fn closure_signature_mismatch(&self, closure: &MendesType, expected: &MendesType, span: Span) -> Option<Diagnostic> {
+        let (MendesType::Generic { name, args }, MendesType::Function { params, .. }) = (closure, expected) else {
+            return None;
+        };
+        if name != "Fn" {
+            return None;
+        }
+
+        let closure_params = &args[..args.len().saturating_sub(1)];
+        if closure_params.len() != params.len() {
+            return Some(
+                Diagnostic::error(format!(
+                    "closure takes {} parameter{}, but `{}` expects {}",
+                    closure_params.len(),
+                    if closure_params.len() == 1 { "" } else { "s" },
+                    expected,
+                    params.len()
+                ))
+                .with_code(ErrorCode::TYPE_MISMATCH)
+                .with_label(span, "wrong number of parameters")
+            );
+        }
+
+        let (index, (found, passed)) = closure_params.iter().zip(params).enumerate()
+            .find(|(_, (found, passed))| !found.is_compatible_with(passed))?;
+        Some(
+            Diagnostic::error(format!(
+                "closure parameter {} takes `{}`, but `{}` passes `{}`",
+                index + 1, found, expected, passed
+            ))
+            .with_code(ErrorCode::TYPE_MISMATCH)
+            .with_label(span, "incompatible parameter")
+        )
+    }
+
+    /// Checks `str(value: float, precision: int) -> string`
+    fn check_str_precision(&mut self, args: &[Expr], arg_types: &[MendesType]) -> MendesType {
+        if !arg_types[0].is_compatible_with(&MendesType::Float) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`str` with a precision expects a `float`, found `{}`", arg_types[0]))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(args[0].span(), "expected float")
+                    .with_help("call `str(value)` without a precision for other types")
+            );
+        }
+        if !arg_types[1].is_compatible_with(&MendesType::Int) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("precision must be `int`, found `{}`", arg_types[1]))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(args[1].span(), "expected int")
+            );
+        }
+        MendesType::String
+    }
+
+    /// Whether `name` resolves to a builtin rather than a user definition
+    fn is_builtin(&self, name: &str) -> bool {
+        self.ctx.symbols.lookup(name).is_some_and(|symbol| symbol.defined_at.is_none())
+    }
+
+    /// Checks the type `parse` was asked to produce
+    fn check_parse_target(&mut self, target: Option<&MendesType>, span: Span) {
+        match target {
+            Some(MendesType::Int | MendesType::Float | MendesType::Bool) => {}
+            None | Some(MendesType::Unknown) => {
+                self.diagnostics.push(
+                    Diagnostic::error("cannot infer the type to parse")
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(span, "type must be known here")
+                        .with_help("annotate the binding, e.g. `let n: int = parse(s)?`")
+                );
+            }
+            Some(other) => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("cannot parse a string into `{}`", other))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(span, "unsupported parse target")
+                        .with_note("`parse` supports `int`, `float` and `bool`")
+                );
+            }
+        }
+    }
+
+    /// The variant names of `enum_name`, if it is a declared enum
+    fn enum_variants(&self, enum_name: &str) -> Option<Vec<String>> {
+        match &self.ctx.symbols.lookup(enum_name)?.kind {
+            SymbolKind::Enum { variants, .. } => Some(variants.iter().map(|(name, _)| name.clone()).collect()),
+            _ => None,
+        }
+    }
+
+    /// Type of an unqualified unit variant: the expected enum when it declares
+    /// the variant, otherwise the only enum that does. A variant shared by
+    /// several enums without an expected type is ambiguous.
+    fn unit_variant_type(&mut self, variant: &str, declared: MendesType, expected: Option<&MendesType>, span: Span) -> MendesType {
+        let owners = self.variant_owners.get(variant).cloned().unwrap_or_default();
+        if let Some(expected @ (MendesType::Named(enum_name) | MendesType::Generic { name: enum_name, .. })) = expected {
+            if owners.iter().any(|(owner, _)| owner == enum_name) {
+                return match (expected, self.ctx.types.generic_arity(enum_name)) {
+                    (MendesType::Named(_), Some(arity)) if arity > 0 => MendesType::Generic {
+                        name: enum_name.clone(),
+                        args: vec![MendesType::Unknown; arity],
+                    },
+                    _ => expected.clone(),
+                };
+            }
+        }
+        if owners.len() < 2 {
+            return declared;
+        }
+        self.diagnostics.push(self.ambiguous_variant(variant, &owners, span));
+        MendesType::Unknown
+    }
+
+    /// Reports an unqualified variant declared by each of `owners`
+    fn ambiguous_variant(&self, variant: &str, owners: &[(String, Span)], span: Span) -> Diagnostic {
+        let qualified: Vec<_> = owners.iter().map(|(owner, _)| format!("`{}::{}`", owner, variant)).collect();
+        let mut diag = Diagnostic::error(format!("ambiguous variant `{}`", variant))
+            .with_code(ErrorCode::AMBIGUOUS_VARIANT)
+            .with_label(span, "declared by more than one enum")
+            .with_help(format!("write {}", qualified.join(" or ")));
+        for (owner, at) in owners {
+            diag = diag.with_secondary_label(*at, format!("variant of `{}`", owner));
+        }
+        diag
+    }
+
+    /// Whether `name` is a unit variant of the enum `ty`
+    fn is_unit_variant_of(&self, ty: &MendesType, name: &str) -> bool {
+        let (MendesType::Named(enum_name) | MendesType::Generic { name: enum_name, .. }) = ty else {
+            return false;
+        };
+        self.variant_owners.get(name).is_some_and(|owners| owners.iter().any(|(owner, _)| owner == enum_name))
+    }
+
+    /// Whether `name` is a variant of the enum `ty`
+    fn is_variant_of(&self, ty: &MendesType, name: &str) -> bool {
+        let (MendesType::Named(enum_name) | MendesType::Generic { name: enum_name, .. }) = ty else {
+            return false;
+        };
+        self.enum_variants(enum_name).is_some_and(|variants| variants.iter().any(|v| v == name))
+    }
+
+    /// Reports a variant missing from `enum_name`, suggesting the closest one
+    fn unknown_variant(&self, enum_name: &str, variant: &str, variants: &[String], span: Span) -> Diagnostic {
+        let listed: Vec<_> = variants.iter().map(|v| format!("`{}`", v)).collect();
+        let mut diag = Diagnostic::error(format!("variant `{}` not found in enum `{}`", variant, enum_name))
+            .with_code(ErrorCode::UNKNOWN_VARIABLE)
+            .with_label(span, "unknown variant")
+            .with_note(format!("`{}` has variants {}", enum_name, listed.join(", ")));
+        if let Some(closest) = suggest::closest_match(variant, variants.iter().map(String::as_str)) {
+            diag = diag.with_help(format!("did you mean `{}::{}`?", enum_name, closest));
+        }
+        diag
+    }
+
+    /// Looks up the associated types of an enum variant, substituting the
+    /// enum's generic parameters with the type arguments of `expected_type`
+    fn enum_variant_types(
+        &self,
+        enum_name: &str,
+        variant: &str,
+        expected_type: &MendesType,
+    ) -> Option<Vec<MendesType>> {
+        let (generic_params, types) = match &self.ctx.symbols.lookup(enum_name)?.kind {
+            SymbolKind::Enum { generic_params, variants } => {
+                let types = variants.iter().find(|(n, _)| n == variant)?.1.clone();
+                (generic_params.clone(), types)
+            }
+            _ => return None,
+        };
+
+        let mut substitutions = HashMap::new();
+        if let MendesType::Generic { name, args } = expected_type {
+            if name == enum_name {
+                for (gp, arg) in generic_params.iter().zip(args) {
+                    substitutions.insert(gp.clone(), arg.clone());
+                }
+            }
+        }
+        for gp in generic_params {
+            substitutions.entry(gp).or_insert(MendesType::Unknown);
+        }
+
+        Some(types.iter().map(|t| self.substitute_generics(t, &substitutions)).collect())
+    }
+
     /// Infer generic type parameters from argument types
     fn infer_generic_types(
         &self,
@@ -1565,6 +3827,11 @@ impl<'ctx> TypeChecker<'ctx> {
                     self.collect_type_substitutions(inner, arg_inner, generic_params, substitutions);
                 }
             }
+            MendesType::Future(inner) => {
+                if let MendesType::Future(arg_inner) = arg_type {
+                    self.collect_type_substitutions(inner, arg_inner, generic_params, substitutions);
+                }
+            }
             MendesType::Generic { name: _, args } => {
                 if let MendesType::Generic { name: _, args: arg_args } = arg_type {
                     for (param_arg, arg_arg) in args.iter().zip(arg_args.iter()) {
@@ -1634,8 +3901,12 @@ impl<'ctx> TypeChecker<'ctx> {
     /// Checks field access
     fn check_field_access(&mut self, object: &Expr, field: &str, _span: Span) -> MendesType {
         let object_type = self.check_expr(object);
+        self.field_type(&object_type, field)
+    }
 
-        match &object_type {
+    /// The type of `field` on a struct value, or `Unknown`
+    fn field_type(&self, object_type: &MendesType, field: &str) -> MendesType {
+        match object_type {
             MendesType::Named(name) => {
                 if let Some(struct_def) = self.ctx.types.get_struct(name) {
                     for (field_name, field_type) in &struct_def.fields {
@@ -1664,60 +3935,179 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
+    /// Checks `{k: v, ...}`: keys share one type and values another. An
+    /// empty literal takes its types from the annotation it is assigned to.
+    fn check_map_lit(&mut self, entries: &[(Expr, Expr)], expected: Option<MendesType>, span: Span) -> MendesType {
+        let Some(((first_key, first_value), rest)) = entries.split_first() else {
+            return match expected {
+                Some(MendesType::Generic { name, args }) if name == "Map" => MendesType::Generic { name, args },
+                _ => MendesType::Generic {
+                    name: "Map".to_string(),
+                    args: vec![MendesType::Unknown, MendesType::Unknown],
+                },
+            };
+        };
+
+        let key_type = self.check_expr(first_key);
+        let value_type = self.check_expr(first_value);
+        for (key, value) in rest {
+            for (what, expr, first) in [("keys", key, &key_type), ("values", value, &value_type)] {
+                let ty = self.check_expr(expr);
+                if !first.is_compatible_with(&ty) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("map {} have different types", what))
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(expr.span(), format!("expected `{}`, found `{}`", first, ty))
+                    );
+                }
+            }
+        }
+
+        let map = MendesType::Generic { name: "Map".to_string(), args: vec![key_type, value_type] };
+        self.check_type_arity(&map, span);
+        map
+    }
+
+    /// Checks `t.0`: the object must be a tuple with more than `index` elements
+    fn check_tuple_index(&mut self, object: &Expr, index: usize, span: Span) -> MendesType {
+        let object_type = self.check_expr(object);
+        let elements = match &object_type {
+            MendesType::Tuple(elements) => elements,
+            MendesType::Ref(inner) | MendesType::MutRef(inner) => match inner.as_ref() {
+                MendesType::Tuple(elements) => elements,
+                _ => return self.tuple_index_on_non_tuple(&object_type, span),
+            },
+            MendesType::Unknown => return MendesType::Unknown,
+            _ => return self.tuple_index_on_non_tuple(&object_type, span),
+        };
+        match elements.get(index) {
+            Some(element) => element.clone(),
+            None => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("tuple index `{}` is out of range for a tuple of {} elements", index, elements.len()))
+                        .with_code(ErrorCode::INVALID_INDEX)
+                        .with_label(span, format!("`{}` has no element {}", object_type, index))
+                );
+                MendesType::Unknown
+            }
+        }
+    }
+
+    fn tuple_index_on_non_tuple(&mut self, object_type: &MendesType, span: Span) -> MendesType {
+        self.diagnostics.push(
+            Diagnostic::error(format!("type `{}` cannot be indexed with `.N`", object_type))
+                .with_code(ErrorCode::TYPE_MISMATCH)
+                .with_label(span, "only tuples have numbered elements")
+        );
+        MendesType::Unknown
+    }
+
+    /// Checks `object?.field`: the object must be an `Option` of a struct,
+    /// and the result is an `Option` of the field's type. A field that is
+    /// already an `Option` is not wrapped again, so accesses can be chained.
+    fn check_optional_field_access(&mut self, object: &Expr, field: &str, span: Span) -> MendesType {
+        let object_type = self.check_expr(object);
+        let inner = match &object_type {
+            MendesType::Generic { name, args } if name == "Option" => args.first().cloned().unwrap_or(MendesType::Unknown),
+            // `load()?.count` on a `Result` propagates the error, then reads the field
+            MendesType::Generic { name, .. } if name == "Result" => {
+                let ok = self.try_value(&object_type, span);
+                return self.field_type(&ok, field);
+            }
+            MendesType::Unknown => return MendesType::Unknown,
+            _ => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("optional chaining needs an `Option`, found `{}`", object_type))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(object.span(), "not an `Option`")
+                        .with_help("use `.` to access fields of a value that is always present")
+                );
+                return MendesType::Unknown;
+            }
+        };
+
+        let struct_name = match &inner {
+            MendesType::Named(name) => name.clone(),
+            MendesType::Ref(inner) | MendesType::MutRef(inner) => match inner.as_ref() {
+                MendesType::Named(name) => name.clone(),
+                _ => String::new(),
+            },
+            MendesType::Unknown => return MendesType::Unknown,
+            _ => String::new(),
+        };
+        let field_type = self.ctx.types.get_struct(&struct_name)
+            .map(|def| def.fields.iter().find(|(name, _)| name == field).map(|(_, ty)| ty.clone()));
+        match field_type {
+            Some(Some(ty)) => match ty {
+                MendesType::Generic { ref name, .. } if name == "Option" => ty,
+                _ => MendesType::Generic { name: "Option".to_string(), args: vec![ty] },
+            },
+            Some(None) => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("no field `{}` on type `{}`", field, inner))
+                        .with_code(ErrorCode::UNKNOWN_VARIABLE)
+                        .with_label(span, "unknown field")
+                );
+                MendesType::Unknown
+            }
+            None => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("type `{}` has no fields", inner))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(span, "field access on a value without fields")
+                );
+                MendesType::Unknown
+            }
+        }
+    }
+
     /// Checks method call
     fn check_method_call(&mut self, object: &Expr, method: &str, args: &[Expr], span: Span) -> MendesType {
         let object_type = self.check_expr(object);
 
-        // Get the struct name (handle references)
-        let struct_name = match &object_type {
-            MendesType::Named(name) => Some(name.clone()),
-            MendesType::Ref(inner) | MendesType::MutRef(inner) => {
-                if let MendesType::Named(name) = inner.as_ref() {
-                    Some(name.clone())
-                } else {
-                    None
+        if method == "format" && object_type == MendesType::String {
+            return self.check_format(object, args, span);
+        }
+
+        // Look through references to the receiver
+        let receiver = match &object_type {
+            MendesType::Ref(inner) | MendesType::MutRef(inner) => inner.as_ref(),
+            other => other,
+        };
+
+        // Calls on a trait object resolve against the trait's signatures
+        if let MendesType::Dyn(trait_name) = receiver {
+            let signature = self.ctx.types.get_trait(trait_name)
+                .and_then(|t| t.methods.iter().find(|(name, ..)| name == method))
+                .cloned();
+            if let Some((_, params, return_type, _)) = signature {
+                self.check_method_args(method, &params, args, span);
+                return return_type;
+            }
+            return self.check_builtin_method(&object_type, method, args, span);
+        }
+
+        if let MendesType::Named(name) = receiver {
+            if name == "Response" && method == "with_status" {
+                if let Some(code) = args.first() {
+                    self.check_status_code(code);
                 }
             }
-            _ => None,
-        };
 
-        if let Some(name) = struct_name {
             // Clone to avoid borrow conflict
-            let struct_def = self.ctx.types.get_struct(&name).cloned();
+            let struct_def = self.ctx.types.get_struct(name).cloned();
 
             if let Some(def) = struct_def {
-                // Look for the method
-                for (method_name, params, return_type, _is_async) in &def.methods {
-                    if method_name == method {
-                        // Check argument count
-                        if args.len() != params.len() {
-                            self.diagnostics.push(
-                                Diagnostic::error(format!(
-                                    "method `{}` expects {} arguments, found {}",
-                                    method, params.len(), args.len()
-                                ))
-                                .with_code(ErrorCode::TYPE_MISMATCH)
-                                .with_label(span, "incorrect number of arguments")
-                            );
-                        }
-
-                        // Check argument types
-                        for (arg, (_, expected_type)) in args.iter().zip(params.iter()) {
-                            let arg_type = self.check_expr(arg);
-                            if !expected_type.is_compatible_with(&arg_type) {
-                                self.diagnostics.push(
-                                    Diagnostic::error(format!(
-                                        "incompatible argument: expected `{}`, found `{}`",
-                                        expected_type, arg_type
-                                    ))
-                                    .with_code(ErrorCode::TYPE_MISMATCH)
-                                    .with_label(arg.span(), "incompatible type")
-                                );
-                            }
-                        }
-
-                        return return_type.clone();
-                    }
+                // Look for the method, then for a trait method implemented for the struct
+                let signature = def.methods.iter()
+                    .find(|(method_name, ..)| method_name == method)
+                    .map(|(_, params, return_type, _)| (params.clone(), return_type.clone()))
+                    .or_else(|| self.ctx.types.impl_method(name, method)
+                        .map(|(_, params, return_type, _)| (params.clone(), return_type.clone())));
+
+                if let Some((params, return_type)) = signature {
+                    self.check_method_args(method, &params, args, span);
+                    return return_type;
                 }
 
                 // Method not found - check if it's a builtin method
@@ -1725,36 +4115,202 @@ impl<'ctx> TypeChecker<'ctx> {
             }
         }
 
+        if matches!(object_type, MendesType::Range(_))
+            && matches!(method, "to_array" | "collect")
+            && self.is_unbounded_range(object)
+        {
+            self.diagnostics.push(
+                Diagnostic::error(format!("cannot `{}` a range without an end", method))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, "this range never ends")
+                    .with_help("give the range an end, as in `0..n`")
+            );
+        }
+
         // Check builtin methods for primitive types
         self.check_builtin_method(&object_type, method, args, span)
     }
 
-    /// Checks builtin methods for types
-    fn check_builtin_method(&mut self, object_type: &MendesType, method: &str, args: &[Expr], span: Span) -> MendesType {
-        // Check args anyway
-        for arg in args {
-            self.check_expr(arg);
+    /// Whether `expr` is a range without an end, or an immutable local bound to one
+    fn is_unbounded_range(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Range { end, .. } => end.is_none(),
+            Expr::Ident(name, _) => self.ctx.symbols.lookup(name)
+                .and_then(|symbol| symbol.defined_at)
+                .is_some_and(|span| self.unbounded_ranges.contains(&span)),
+            _ => false,
         }
+    }
 
-        match object_type {
-            MendesType::String => {
-                match method {
-                    "len" => MendesType::Int,
-                    "contains" => MendesType::Bool,
-                    "concat" => MendesType::String,
-                    "to_upper" | "to_lower" | "trim" => MendesType::String,
-                    "split" => MendesType::Array(Box::new(MendesType::String)),
-                    _ => MendesType::Unknown,
-                }
-            }
-            MendesType::Array(_) => {
-                match method {
+    /// Checks the arguments of a call to a method with the given parameters
+    fn check_method_args(&mut self, method: &str, params: &[(String, MendesType)], args: &[Expr], span: Span) {
+        // Check argument count
+        if args.len() != params.len() {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "method `{}` expects {} arguments, found {}",
+                    method, params.len(), args.len()
+                ))
+                .with_code(ErrorCode::TYPE_MISMATCH)
+                .with_label(span, "incorrect number of arguments")
+            );
+        }
+
+        // Check argument types
+        for (arg, (_, expected_type)) in args.iter().zip(params.iter()) {
+            let arg_type = self.check_expr(arg);
+            if !self.ctx.types.coerces_to(&arg_type, expected_type) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "incompatible argument: expected `{}`, found `{}`",
+                        expected_type, arg_type
+                    ))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(arg.span(), "incompatible type")
+                );
+            }
+        }
+    }
+
+    /// Checks `"...".format(name: value)`, matching the named arguments
+    /// against the placeholders when the template is a literal
+    fn check_format(&mut self, template: &Expr, args: &[Expr], span: Span) -> MendesType {
+        let mut named: Vec<(&str, Span)> = Vec::new();
+        for arg in args {
+            if let Expr::NamedArg { name, value, span: arg_span } = arg {
+                self.check_expr(value);
+                if named.iter().any(|(n, _)| n == name) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("argument `{}` is given more than once", name))
+                            .with_code(ErrorCode::DUPLICATE_DEFINITION)
+                            .with_label(*arg_span, "duplicate argument")
+                    );
+                }
+                named.push((name, *arg_span));
+            } else {
+                self.check_expr(arg);
+                self.diagnostics.push(
+                    Diagnostic::error("`format` takes named arguments")
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(arg.span(), "positional argument")
+                        .with_help("name the placeholder it fills, e.g. `name: value`")
+                );
+            }
+        }
+
+        let Expr::StringLit(text, literal_span) = template else {
+            return MendesType::String;
+        };
+
+        let pieces = match parse_format_template(text) {
+            Ok(pieces) => pieces,
+            Err(message) => {
+                self.diagnostics.push(
+                    Diagnostic::error(message)
+                        .with_code(ErrorCode::INVALID_SYNTAX)
+                        .with_label(*literal_span, "invalid format string")
+                );
+                return MendesType::String;
+            }
+        };
+        let placeholders: Vec<&str> = pieces.iter()
+            .filter_map(|piece| match piece {
+                FormatPiece::Placeholder(name) => Some(name.as_str()),
+                FormatPiece::Literal(_) => None,
+            })
+            .collect();
+
+        let mut missing: Vec<&str> = Vec::new();
+        for placeholder in placeholders.iter().copied() {
+            if !named.iter().any(|(n, _)| *n == placeholder) && !missing.contains(&placeholder) {
+                missing.push(placeholder);
+            }
+        }
+        for placeholder in missing {
+            self.diagnostics.push(
+                Diagnostic::error(format!("no argument for placeholder `{{{}}}`", placeholder))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, format!("`{}` is not given", placeholder))
+                    .with_help(format!("add `{}: value` to the call", placeholder))
+            );
+        }
+
+        for (name, arg_span) in named {
+            if !placeholders.contains(&name) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("argument `{}` is not used by the format string", name))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(arg_span, "unused argument")
+                        .with_secondary_label(*literal_span, format!("no `{{{}}}` placeholder here", name))
+                );
+            }
+        }
+
+        MendesType::String
+    }
+
+    /// Checks builtin methods for types
+    fn check_builtin_method(&mut self, object_type: &MendesType, method: &str, args: &[Expr], span: Span) -> MendesType {
+        if let MendesType::Generic { name, args: type_args } = object_type {
+            if name == "Map" {
+                return self.check_map_method(object_type, type_args, method, args, span);
+            }
+        }
+
+        if let MendesType::Array(element) = object_type {
+            if matches!(method, "fold" | "reduce" | "min_by" | "max_by") {
+                return self.check_array_reduction(element, method, args, span);
+            }
+        }
+
+        // `split` needs its separator
+        if *object_type == MendesType::String && method == "split" && args.len() != 1 {
+            self.diagnostics.push(
+                Diagnostic::error(format!("method `split` expects 1 argument, found {}", args.len()))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, "incorrect number of arguments")
+                    .with_help("pass the separator, as in `line.split(\",\")`")
+            );
+        }
+
+        // Check args anyway
+        for arg in args {
+            self.check_expr(arg);
+        }
+
+        match object_type {
+            MendesType::String => {
+                match method {
+                    "len" => MendesType::Int,
+                    "contains" => MendesType::Bool,
+                    "concat" => MendesType::String,
+                    "to_upper" | "to_lower" | "trim" => MendesType::String,
+                    "split" | "lines" => MendesType::Array(Box::new(MendesType::String)),
+                    _ => MendesType::Unknown,
+                }
+            }
+            MendesType::Array(_) => {
+                match method {
                     "len" => MendesType::Int,
                     "push" | "pop" => MendesType::Unit,
                     "is_empty" => MendesType::Bool,
                     _ => MendesType::Unknown,
                 }
             }
+            MendesType::Range(element) => {
+                match method {
+                    "to_array" | "collect" => MendesType::Array(element.clone()),
+                    _ => {
+                        self.diagnostics.push(
+                            Diagnostic::error(format!("method `{}` not found on type `{}`", method, object_type))
+                                .with_code(ErrorCode::UNKNOWN_VARIABLE)
+                                .with_label(span, "method not found")
+                                .with_help("use `to_array()` to materialize the range")
+                        );
+                        MendesType::Unknown
+                    }
+                }
+            }
             MendesType::Generic { name, args: type_args } => {
                 match name.as_str() {
                     "Result" => {
@@ -1791,9 +4347,141 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
+    /// Checks `fold`, `reduce`, `min_by` and `max_by` on an array. The
+    /// closure's parameters are typed from the element type and, for `fold`,
+    /// from the initial value, which also fixes the type of the result.
+    /// `min_by` and `max_by` take a comparator returning a negative, zero or
+    /// positive `int`; they and `reduce` return `None` for an empty array.
+    fn check_array_reduction(&mut self, element: &MendesType, method: &str, args: &[Expr], span: Span) -> MendesType {
+        let arity = if method == "fold" { 2 } else { 1 };
+        if args.len() != arity {
+            self.diagnostics.push(
+                Diagnostic::error(format!("method `{}` expects {} arguments, found {}", method, arity, args.len()))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(span, "incorrect number of arguments")
+            );
+            for arg in args {
+                self.check_expr(arg);
+            }
+            return MendesType::Unknown;
+        }
+
+        let optional = MendesType::Generic { name: "Option".to_string(), args: vec![element.clone()] };
+        let (closure, params, ret, result) = match method {
+            "fold" => {
+                let acc = self.check_expr(&args[0]);
+                (&args[1], vec![acc.clone(), element.clone()], acc.clone(), acc)
+            }
+            "reduce" => (&args[0], vec![element.clone(), element.clone()], element.clone(), optional),
+            _ => (&args[0], vec![element.clone(), element.clone()], MendesType::Int, optional),
+        };
+
+        let mut expected = params.clone();
+        expected.push(MendesType::Unknown);
+        self.expected_type = Some(MendesType::Generic { name: "Fn".to_string(), args: expected });
+        let closure_type = self.check_expr(closure);
+        let (closure_params, closure_ret) = match closure_type {
+            MendesType::Generic { name, mut args } if name == "Fn" && !args.is_empty() => {
+                let ret = args.pop().unwrap_or(MendesType::Unknown);
+                (args, ret)
+            }
+            MendesType::Function { params, ret } => (params, *ret),
+            MendesType::Unknown => return result,
+            other => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` expects a closure, found `{}`", method, other))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(closure.span(), "not a closure")
+                );
+                return result;
+            }
+        };
+
+        if closure_params.len() != params.len() {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "the closure passed to `{}` must take {} parameters, found {}",
+                    method, params.len(), closure_params.len()
+                ))
+                .with_code(ErrorCode::TYPE_MISMATCH)
+                .with_label(closure.span(), "wrong number of parameters")
+            );
+            return result;
+        }
+        for (found, expected) in closure_params.iter().zip(&params) {
+            if !found.is_compatible_with(expected) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "the closure passed to `{}` takes `{}`, but is given `{}`",
+                        method, found, expected
+                    ))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(closure.span(), "incompatible parameter")
+                );
+            }
+        }
+        if !self.ctx.types.coerces_to(&closure_ret, &ret) {
+            let help = if method == "fold" {
+                "the closure returns the next accumulator, which must have the type of the initial value"
+            } else if method == "reduce" {
+                "the closure combines two elements into one of the same type"
+            } else {
+                "return a negative `int` when the first element is smaller, zero when equal and a positive `int` otherwise"
+            };
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "the closure passed to `{}` must return `{}`, found `{}`",
+                    method, ret, closure_ret
+                ))
+                .with_code(ErrorCode::TYPE_MISMATCH)
+                .with_label(closure.span(), "incompatible return type")
+                .with_help(help)
+            );
+        }
+        result
+    }
+
+    /// Checks a method on `Map<K, V>`, matching keys and values against
+    /// the map's type parameters
+    fn check_map_method(&mut self, map_type: &MendesType, type_args: &[MendesType], method: &str, args: &[Expr], span: Span) -> MendesType {
+        let key = type_args.first().cloned().unwrap_or(MendesType::Unknown);
+        let value = type_args.get(1).cloned().unwrap_or(MendesType::Unknown);
+        let key_param = ("key".to_string(), key.clone());
+        let value_param = ("value".to_string(), value.clone());
+        let optional_value = MendesType::Generic { name: "Option".to_string(), args: vec![value.clone()] };
+
+        let (params, return_type) = match method {
+            "get" | "remove" => (vec![key_param], optional_value),
+            "contains_key" => (vec![key_param], MendesType::Bool),
+            "insert" => (vec![key_param, value_param], optional_value),
+            "keys" => (vec![], MendesType::Array(Box::new(key))),
+            "values" => (vec![], MendesType::Array(Box::new(value))),
+            "len" => (vec![], MendesType::Int),
+            "is_empty" => (vec![], MendesType::Bool),
+            _ => {
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                if method != "to_string" && method != "clone" {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("method `{}` not found on type `{}`", method, map_type))
+                            .with_code(ErrorCode::UNKNOWN_VARIABLE)
+                            .with_label(span, "method not found")
+                    );
+                }
+                return MendesType::Unknown;
+            }
+        };
+        self.check_method_args(method, &params, args, span);
+        return_type
+    }
+
     /// Checks index access
     fn check_index(&mut self, object: &Expr, index: &Expr, span: Span) -> MendesType {
         let object_type = self.check_expr(object);
+        if let Expr::Range { start, end, inclusive, .. } = index {
+            return self.check_slice(object_type, start.as_deref(), end.as_deref(), *inclusive, index.span());
+        }
         let index_type = self.check_expr(index);
 
         if !matches!(index_type, MendesType::Int | MendesType::Unknown) {
@@ -1804,6 +4492,8 @@ impl<'ctx> TypeChecker<'ctx> {
             );
         }
 
+        self.check_negative_index(object, index);
+
         match object_type {
             MendesType::Array(inner) => *inner,
             MendesType::String => MendesType::String, // string[i] returns string (char)
@@ -1819,12 +4509,221 @@ impl<'ctx> TypeChecker<'ctx> {
         }
     }
 
+    /// Checks `s[start..end]`: only strings are sliced, by character, and
+    /// constant bounds must be non-negative and in order
+    fn check_slice(&mut self, object_type: MendesType, start: Option<&Expr>, end: Option<&Expr>, inclusive: bool, span: Span) -> MendesType {
+        for bound in start.iter().chain(end.iter()) {
+            let bound_type = self.check_expr(bound);
+            if !matches!(bound_type, MendesType::Int | MendesType::Unknown) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("slice bounds must be `int`, found `{}`", bound_type))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(bound.span(), "expected int")
+                );
+            } else if let Some(offset) = bound.negative_constant() {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("slice bound `{}` is negative", offset))
+                        .with_code(ErrorCode::INVALID_INDEX)
+                        .with_label(bound.span(), "slices count characters from the start")
+                );
+            }
+        }
+
+        let constant = |bound: Option<&Expr>| match bound {
+            Some(Expr::IntLit(n, _)) => Some(*n),
+            _ => None,
+        };
+        if let (Some(low), Some(high)) = (constant(start), constant(end)) {
+            let high = if inclusive { high + 1 } else { high };
+            if low > high {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("slice starts at {} but ends at {}", low, high))
+                        .with_code(ErrorCode::INVALID_INDEX)
+                        .with_label(span, "start is after end")
+                );
+            }
+        }
+
+        match object_type {
+            MendesType::String => MendesType::String,
+            MendesType::Unknown => MendesType::Unknown,
+            _ => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("type `{}` does not support slicing", object_type))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(span, "only strings can be sliced")
+                );
+                MendesType::Unknown
+            }
+        }
+    }
+
+    /// A constant status code must be a valid HTTP status, 100 to 599
+    fn check_status_code(&mut self, code: &Expr) {
+        let value = match code {
+            Expr::IntLit(n, _) => *n,
+            _ => match code.negative_constant() {
+                Some(n) => n,
+                None => return,
+            },
+        };
+        if !(100..=599).contains(&value) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`{}` is not an HTTP status code", value))
+                    .with_code(ErrorCode::TYPE_MISMATCH)
+                    .with_label(code.span(), "status codes range from 100 to 599")
+            );
+        }
+    }
+
+    /// Negative indices count from the end and must be constants; one
+    /// past the start of an array of known length is rejected
+    fn check_negative_index(&mut self, object: &Expr, index: &Expr) {
+        let Some(offset) = index.negative_constant() else {
+            if matches!(index, Expr::Unary { op: UnaryOp::Neg, .. }) {
+                self.diagnostics.push(
+                    Diagnostic::error("negative indices must be integer constants")
+                        .with_code(ErrorCode::INVALID_INDEX)
+                        .with_label(index.span(), "computed negative index")
+                        .with_help("index from the end with `arr[arr.len() - n]`")
+                );
+            }
+            return;
+        };
+
+        let length = match object {
+            Expr::ArrayLit(elements, _) => Some(elements.len()),
+            Expr::Ident(name, _) => self.ctx.symbols.lookup(name)
+                .and_then(|symbol| symbol.defined_at)
+                .and_then(|span| self.array_lengths.get(&span).copied()),
+            _ => None,
+        };
+        if let Some(length) = length {
+            if offset.unsigned_abs() as usize > length {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("index `{}` is out of range for an array of length {}", offset, length))
+                        .with_code(ErrorCode::INVALID_INDEX)
+                        .with_label(index.span(), format!("the first element is `-{}`", length))
+                );
+            }
+        }
+    }
+
+    /// Checks an associated function call: `Type::func(args)`
+    fn check_assoc_call(&mut self, type_name: &str, func: &str, args: &[Expr], span: Span) -> MendesType {
+        // `Color::Circle(r)` constructs a tuple variant like the unqualified `Circle(r)`
+        if let Some(variants) = self.enum_variants(type_name) {
+            if variants.iter().any(|v| v == func) {
+                let constructor = Expr::Call {
+                    func: Box::new(Expr::Ident(func.to_string(), span)),
+                    args: args.to_vec(),
+                    span,
+                };
+                return self.check_expr(&constructor);
+            }
+            if func == "from_int" && self.ctx.types.discriminants(type_name).is_some() {
+                self.check_method_args(func, &[("value".to_string(), MendesType::Int)], args, span);
+                return MendesType::Generic {
+                    name: "Option".to_string(),
+                    args: vec![MendesType::Named(type_name.to_string())],
+                };
+            }
+            for arg in args {
+                self.check_expr(arg);
+            }
+            self.diagnostics.push(self.unknown_variant(type_name, func, &variants, span));
+            return MendesType::Unknown;
+        }
+
+        let arg_types: Vec<MendesType> = args.iter().map(|a| self.check_expr(a)).collect();
+
+        let def = match self.ctx.types.get_struct(type_name) {
+            Some(def) => def.clone(),
+            None => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("struct `{}` not found", type_name))
+                        .with_code(ErrorCode::UNKNOWN_TYPE)
+                        .with_label(span, "type not declared")
+                );
+                return MendesType::Unknown;
+            }
+        };
+
+        if let Some((_, params, return_type, _)) = def.methods.iter().find(|(name, ..)| name == func) {
+            if params.len() != args.len() {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("expected {} arguments, found {}", params.len(), args.len()))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(span, "incorrect number of arguments")
+                );
+            }
+            for (i, ((_, expected), found)) in params.iter().zip(&arg_types).enumerate() {
+                if !expected.is_compatible_with(found) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("incompatible argument: expected `{}`, found `{}`", expected, found))
+                            .with_code(ErrorCode::TYPE_MISMATCH)
+                            .with_label(args[i].span(), "incompatible type")
+                    );
+                }
+            }
+            return return_type.clone();
+        }
+
+        if func == "default" {
+            if !args.is_empty() {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("expected 0 arguments, found {}", args.len()))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(span, "`default` takes no arguments")
+                );
+            }
+            if let Some((field, ty)) = self.non_defaultable_field(type_name, &mut Vec::new()) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` has no default value", type_name))
+                        .with_code(ErrorCode::TYPE_MISMATCH)
+                        .with_label(span, "cannot create a default instance")
+                        .with_note(format!("field `{}` has type `{}`, which has no default value", field, ty))
+                );
+            }
+            return MendesType::Named(type_name.to_string());
+        }
+
+        self.diagnostics.push(
+            Diagnostic::error(format!("no associated function `{}` found for `{}`", func, type_name))
+                .with_code(ErrorCode::UNKNOWN_VARIABLE)
+                .with_label(span, "associated function not found")
+        );
+        MendesType::Unknown
+    }
+
+    /// Finds the first field of a struct (searching nested structs) whose type
+    /// has no default value: only numbers, bools, strings and structs made of
+    /// those can be defaulted
+    fn non_defaultable_field(&self, struct_name: &str, visiting: &mut Vec<String>) -> Option<(String, MendesType)> {
+        let def = self.ctx.types.get_struct(struct_name)?;
+        visiting.push(struct_name.to_string());
+
+        let blocker = def.fields.iter().find_map(|(field, ty)| {
+            let defaultable = match ty {
+                MendesType::Int | MendesType::Float | MendesType::Bool | MendesType::String => true,
+                MendesType::Named(name) if self.ctx.types.get_struct(name).is_some() => {
+                    !visiting.contains(name) && self.non_defaultable_field(name, visiting).is_none()
+                }
+                _ => false,
+            };
+            (!defaultable).then(|| (field.clone(), ty.clone()))
+        });
+
+        visiting.pop();
+        blocker
+    }
+
     /// Checks struct literal
     fn check_struct_lit(&mut self, name: &str, fields: &[(String, Expr)], span: Span) -> MendesType {
         // Clone struct fields to avoid borrow conflict
-        let struct_fields = self.ctx.types.get_struct(name).map(|s| s.fields.clone());
+        let struct_fields = self.ctx.types.get_struct(name).map(|s| (s.fields.clone(), s.defaults.clone()));
 
-        if let Some(expected_fields_vec) = struct_fields {
+        if let Some((expected_fields_vec, defaults)) = struct_fields {
             let expected_fields: std::collections::HashSet<_> = expected_fields_vec.iter()
                 .map(|(n, _)| n.as_str())
                 .collect();
@@ -1833,8 +4732,11 @@ impl<'ctx> TypeChecker<'ctx> {
                 .map(|(n, _)| n.as_str())
                 .collect();
 
-            // Check missing fields
+            // Check missing fields; defaulted ones are filled in by lowering
             for field_name in expected_fields.difference(&provided_fields) {
+                if defaults.iter().any(|d| d == field_name) {
+                    continue;
+                }
                 self.diagnostics.push(
                     Diagnostic::error(format!("field `{}` missing in `{}`", field_name, name))
                         .with_code(ErrorCode::TYPE_MISMATCH)
@@ -1880,6 +4782,102 @@ impl<'ctx> TypeChecker<'ctx> {
     }
 }
 
+/// Collects the names of all identifiers read in a block
+#[derive(Default)]
+struct IdentReads {
+    names: std::collections::HashSet<String>,
+}
+
+impl Visitor for IdentReads {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Ident(name, _) = expr {
+            self.names.insert(name.clone());
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+/// Collects the `let` bindings of a function body, leaving out nested
+/// functions and bindings under `@allow(unused)`
+#[derive(Default)]
+struct LocalDecls {
+    found: Vec<(String, Span)>,
+}
+
+impl Visitor for LocalDecls {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { name, span, .. } => self.found.push((name.clone(), *span)),
+            Stmt::LetTuple { names, span, .. } => {
+                self.found.extend(names.iter().map(|name| (name.clone(), *span)));
+            }
+            Stmt::Fn(_) => return,
+            Stmt::Attributed { attrs, .. }
+                if attrs.iter().flat_map(|attr| attr.allowed_lints()).any(|lint| lint == "unused") => return,
+            _ => {}
+        }
+        visit::walk_stmt(self, stmt);
+    }
+}
+
+/// Collects the operations in a `const fn` body that cannot be evaluated
+/// at compile time
+struct ConstViolations<'a> {
+    const_fns: &'a HashMap<String, FnDecl>,
+    found: Vec<(String, Span)>,
+}
+
+impl Visitor for ConstViolations<'_> {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        let what = match stmt {
+            Stmt::Let { .. } | Stmt::Return { value: Some(_), .. }
+            | Stmt::If { .. } | Stmt::While { .. } | Stmt::Expr(_) => None,
+            Stmt::For { .. } => Some("a `for` loop"),
+            Stmt::TryCatch { .. } => Some("a `try` block"),
+            Stmt::Break { .. } | Stmt::Continue { .. } => Some("`break` or `continue`"),
+            _ => Some("this statement"),
+        };
+        match what {
+            Some(what) => self.found.push((what.to_string(), stmt.span())),
+            None => visit::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        let what = match expr {
+            Expr::IntLit(..) | Expr::BoolLit(..) | Expr::Ident(..)
+            | Expr::Unary { .. } | Expr::Binary { .. } => None,
+            Expr::Call { func, .. } => match func.as_ref() {
+                Expr::Ident(name, _) if self.const_fns.contains_key(name) => None,
+                Expr::Ident(name, _) => Some(format!("calling non-const fn `{}`", name)),
+                _ => Some("calling a function value".to_string()),
+            },
+            Expr::MethodCall { method, .. } => Some(format!("calling method `{}`", method)),
+            Expr::Await { .. } => Some("`.await`".to_string()),
+            Expr::Closure { .. } => Some("a closure".to_string()),
+            Expr::AsyncBlock { .. } => Some("an `async` block".to_string()),
+            Expr::FloatLit(..) => Some("a `float` value".to_string()),
+            Expr::StringLit(..) | Expr::StringInterpolation { .. } => Some("a `string` value".to_string()),
+            _ => Some("this expression".to_string()),
+        };
+        match what {
+            Some(what) => self.found.push((what, expr.span())),
+            None => visit::walk_expr(self, expr),
+        }
+    }
+}
+
+/// Type of a path parameter annotated `{name:ty}`, `string` when there is no
+/// annotation, or `None` for types a route cannot declare
+fn path_param_type(ty: &str) -> Option<MendesType> {
+    match ty {
+        "int" => Some(MendesType::Int),
+        "string" | "" => Some(MendesType::String),
+        "uuid" => Some(MendesType::Named("Uuid".to_string())),
+        _ => None,
+    }
+}
+
 /// Helper function for analysis
 pub fn analyze(program: &Program, ctx: &mut SemanticContext) -> Diagnostics {
     let mut checker = TypeChecker::new(ctx);
@@ -1887,10 +4885,18 @@ pub fn analyze(program: &Program, ctx: &mut SemanticContext) -> Diagnostics {
     checker.take_diagnostics()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use mendes_lexer::Lexer;
+/// Like `analyze`, also checking the `@cfg(flag)` items whose flag is in `cfg`
+pub fn analyze_with_cfg(program: &Program, ctx: &mut SemanticContext, cfg: &HashSet<String>) -> Diagnostics {
+    let mut checker = TypeChecker::new(ctx);
+    checker.cfg = cfg.clone();
+    checker.check_program(program);
+    checker.take_diagnostics()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mendes_lexer::Lexer;
     use mendes_parser::parse;
 
     fn analyze_source(source: &str) -> Diagnostics {
@@ -1954,4 +4960,2618 @@ fn test() -> int:
         let diags = analyze_source(source);
         assert!(!diags.has_errors());
     }
+
+    #[test]
+    fn test_closure_arity_mismatch() {
+        let source = r#"
+fn apply(f: fn(int) -> int, x: int) -> int:
+    return f(x)
+
+fn test() -> int:
+    let wrong = apply(|a: int, b: int| a + b, 5)
+    let typed = apply(|s: string| 1, 5)
+    return apply(|n| n * 2, 5)
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!(errors[0].message, "closure takes 2 parameters, but `fn(int) -> int` expects 1");
+        assert_eq!(errors[1].message, "closure parameter 1 takes `string`, but `fn(int) -> int` passes `int`");
+    }
+
+    #[test]
+    fn test_hover_expression_type() {
+        let source = "let total = 1 + 2\nlet name = \"mendes\"\n";
+        let mut lexer = Lexer::new(source, 0);
+        let (program, _) = parse(lexer.tokenize());
+        let mut ctx = SemanticContext::new();
+        analyze(&program, &mut ctx);
+
+        // Cursor on `2` (line 1, column 17)
+        let (ty, span) = ctx.hover(mendes_error::Position::new(1, 17, 16)).unwrap();
+        assert_eq!(ty, MendesType::Int);
+        assert_eq!(span.len(), 1);
+
+        // Cursor on the string literal
+        let (ty, _) = ctx.hover(mendes_error::Position::new(2, 13, 30)).unwrap();
+        assert_eq!(ty, MendesType::String);
+
+        // Cursor on the `let` keyword: no expression there
+        assert!(ctx.hover(mendes_error::Position::new(1, 1, 0)).is_none());
+    }
+
+    #[test]
+    fn test_symbol_visibility() {
+        let source = "pub fn a():\n    pass\n\nfn b():\n    pass\n\npub(crate) struct C:\n    x: int\n\npub const D: int = 1\n";
+        let mut lexer = Lexer::new(source, 0);
+        let (program, _) = parse(lexer.tokenize());
+        let mut ctx = SemanticContext::new();
+        analyze(&program, &mut ctx);
+
+        let visibility = |name: &str| ctx.symbols.lookup(name).unwrap().visibility;
+        assert_eq!(visibility("a"), Visibility::Public);
+        assert_eq!(visibility("b"), Visibility::Private);
+        assert_eq!(visibility("C"), Visibility::Crate);
+        assert_eq!(visibility("D"), Visibility::Public);
+    }
+
+    #[test]
+    fn test_type_dump() {
+        let source = "let ratio = 1.5 * 2.0\nlet label = \"n\"\n";
+        let mut lexer = Lexer::new(source, 0);
+        let (program, _) = parse(lexer.tokenize());
+        let mut ctx = SemanticContext::new();
+        analyze(&program, &mut ctx);
+
+        assert_eq!(ctx.type_dump(), vec![
+            "1:13 float",
+            "1:13 float",
+            "1:19 float",
+            "2:13 string",
+        ]);
+    }
+
+    #[test]
+    fn test_shadowed_builtin_warning() {
+        let source = r#"
+fn len(items: [int]) -> int:
+    return 0
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+        assert!(diags.iter().any(|d| d.level == mendes_error::Level::Warning
+            && d.code == Some(ErrorCode::SHADOWED_BUILTIN)
+            && d.message.contains("len")));
+    }
+
+    #[test]
+    fn test_await_async_block() {
+        let source = r#"
+fn compute() -> int async:
+    let task = async:
+        let x = 20
+        return x * 2
+    let value: int = await task
+    return value
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+
+        let source = r#"
+fn compute() -> string async:
+    let task = async:
+        return 42
+    let value: string = await task
+    return value
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.has_errors());
+
+        // A `return` nested in an `if` is checked against the other returns
+        let source = r#"
+fn compute(flag: bool) -> int async:
+    let task = async:
+        if flag:
+            return "early"
+        return 42
+    let value: int = await task
+    return value
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message == "incompatible return type: expected `string`, found `int`"));
+
+        let source = r#"
+fn compute(flag: bool) -> int async:
+    let task = async:
+        if flag:
+            return 1
+        return 2
+    let value: int = await task
+    return value
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+    }
+
+    #[test]
+    fn test_spawn_and_join() {
+        let source = r#"
+fn fetch() -> int async:
+    return 1
+
+fn compute() -> int async:
+    let a = spawn(fetch())
+    let b = spawn(async:
+        return 2
+    )
+    let total: int = join(a) + join(b)
+    return total
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+
+        let source = r#"
+fn compute() -> int async:
+    let task = spawn(42)
+    return join(task)
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message.contains("expected `Future<T>`, found `int`")));
+
+        let source = r#"
+fn fetch() -> int async:
+    return 1
+
+fn compute() -> int:
+    let task = spawn(fetch())
+    return join(task)
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message == "join can only be used in async context"));
+    }
+
+    #[test]
+    fn test_unused_path_param_warning() {
+        let source = r#"
+api GET /users/{id:int}/posts/{post_id:int}:
+    return int
+    return id
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+        let unused: Vec<_> = diags.iter()
+            .filter(|d| d.code == Some(ErrorCode::UNUSED_PATH_PARAM))
+            .collect();
+        assert_eq!(unused.len(), 1);
+        assert!(unused[0].message.contains("post_id"));
+    }
+
+    #[test]
+    fn test_path_param_types() {
+        let source = r#"
+api GET /users/{id:foo}:
+    return int
+    return id
+
+api GET /orders/{id:uuid}/items/{n:int}:
+    return int
+    return 1
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.clone())
+            .collect();
+        assert_eq!(errors, vec!["unsupported type `foo` for path parameter `id`"]);
+
+        // Supported but unread parameters still warn
+        let unused: Vec<_> = diags.iter()
+            .filter(|d| d.code == Some(ErrorCode::UNUSED_PATH_PARAM))
+            .map(|d| d.message.clone())
+            .collect();
+        assert_eq!(unused, vec![
+            "path parameter `id` is never used",
+            "path parameter `n` is never used",
+        ]);
+    }
+
+    #[test]
+    fn test_uuid_path_param_is_a_string() {
+        let source = r#"
+fn lookup(key: string) -> string:
+    return key
+
+api GET /orders/{id:uuid}:
+    return string
+    return lookup(id)
+
+api GET /carts/{id:uuid}:
+    return string
+    let key: string = id
+    return key
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_allow_unused_silences_warning() {
+        let unused = |source: &str| -> Vec<String> {
+            analyze_source(source).iter()
+                .filter(|d| d.code == Some(ErrorCode::UNUSED_VARIABLE))
+                .map(|d| d.message.clone())
+                .collect()
+        };
+
+        let source = "fn f() -> int:\n    let scratch = 1\n    let _ignored = 2\n    let x = 3\n    return x\n";
+        assert_eq!(unused(source), vec!["variable `scratch` is never used"]);
+
+        let allowed = format!("@allow(unused)\n{}", source);
+        assert!(unused(&allowed).is_empty());
+
+        // The suppression ends with the item it is attached to
+        let sibling = format!("{}\nfn g() -> int:\n    let other = 1\n    return 0\n", allowed);
+        assert_eq!(unused(&sibling), vec!["variable `other` is never used"]);
+
+        let on_let = "fn f() -> int:\n    @allow(unused)\n    let scratch = 1\n    return 0\n";
+        assert!(unused(on_let).is_empty());
+    }
+
+    #[test]
+    fn test_response_builder_methods() {
+        let source = r#"
+api GET /items:
+    return Response
+    return response("ok").with_header("X-Foo", "bar").with_status(201)
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+api GET /items:
+    return Response
+    return response("ok").with_status("created")
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(errors, vec!["incompatible argument: expected `int`, found `string`"]);
+
+        let source = r#"
+api GET /items:
+    return Response
+    return response("ok").with_status(70000)
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message == "`70000` is not an HTTP status code"));
+    }
+
+    #[test]
+    fn test_redirect_builtins() {
+        let source = r#"
+api GET /old:
+    return Response
+    return redirect_permanent("/new")
+
+api GET /account:
+    return Response
+    return redirect("/login").with_header("Cache-Control", "no-store")
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+api GET /old:
+    return string
+    return redirect(301)
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(errors, vec![
+            "incompatible argument: expected `string`, found `int`",
+            "incompatible return type: expected `string`, found `Response`",
+        ]);
+    }
+
+    #[test]
+    fn test_body_on_get_route_warning() {
+        let source = r#"
+struct Filter:
+    name: string
+
+api GET /users:
+    body Filter
+    return string
+    return body.name
+
+api POST /users:
+    body Filter
+    return string
+    return body.name
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+        let warnings: Vec<_> = diags.iter()
+            .filter(|d| d.code == Some(ErrorCode::BODY_ON_BODYLESS_METHOD))
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(warnings, vec!["`GET` route `/users` declares a request body"]);
+    }
+
+    #[test]
+    fn test_let_shadowing_path_param_warning() {
+        let source = r#"
+api GET /users/{id:int}:
+    return int
+    let id = id + 1
+    if id > 10:
+        let id = 0
+        return id
+    return id
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+        let shadows: Vec<_> = diags.iter()
+            .filter(|d| d.code == Some(ErrorCode::SHADOWED_PATH_PARAM))
+            .collect();
+        // Only the `let` in the handler's own scope is reported
+        assert_eq!(shadows.len(), 1);
+        assert_eq!(shadows[0].message, "`id` shadows the path parameter of the same name");
+    }
+
+    #[test]
+    fn test_str_with_precision() {
+        let source = r#"
+fn show(x: float) -> string:
+    let plain: string = str(42)
+    return str(x, 2)
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn show(n: int, x: float) -> string:
+    let a = str(n, 2)
+    let b = str(x, "2")
+    return str(x, 1, 2)
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "`str` with a precision expects a `float`, found `int`",
+            "precision must be `int`, found `string`",
+            "expected 1 arguments, found 3",
+        ]);
+    }
+
+    #[test]
+    fn test_negative_index() {
+        let source = r#"
+fn ends(xs: [int]) -> int:
+    let ys = [1, 2, 3]
+    return xs[-1] + ys[-3]
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn bad(xs: [int], n: int) -> int:
+    let ys = [1, 2, 3]
+    let a = ys[-4]
+    let b = [1, 2][-3]
+    return xs[-n]
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "index `-4` is out of range for an array of length 3",
+            "index `-3` is out of range for an array of length 2",
+            "negative indices must be integer constants",
+        ]);
+        assert!(diags.iter().filter(|d| d.level == mendes_error::Level::Error).all(|d| d.code == Some(ErrorCode::INVALID_INDEX)));
+    }
+
+    #[test]
+    fn test_unhashable_set_element() {
+        let source = r#"
+struct Point:
+    x: int
+    y: int
+
+fn unique(points: Set<Point>, names: Map<string, int>) -> int:
+    return 0
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+struct Sample:
+    id: int
+    value: float
+
+fn unique(samples: Set<Sample>, by_weight: Map<float, string>) -> int:
+    return 0
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "`Sample` cannot be used as a set element: it contains `float`, which cannot be hashed",
+            "`float` cannot be used as a map key: `float` cannot be hashed",
+        ]);
+        assert!(diags.iter().all(|d| d.code == Some(ErrorCode::UNHASHABLE_KEY)));
+    }
+
+    #[test]
+    fn test_operator_trait_impls() {
+        let source = r#"
+struct Vec2:
+    x: float
+    y: float
+
+impl Add for Vec2:
+    fn add(self, other: Vec2) -> Vec2:
+        return Vec2 { x: self.x + other.x, y: self.y + other.y }
+
+impl Ord for Vec2:
+    fn lt(&self, other: &Vec2) -> bool:
+        return self.x < other.x
+
+fn sum(a: Vec2, b: Vec2) -> Vec2:
+    let c: Vec2 = a + b
+    let smaller: bool = a < b
+    return c
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+struct Vec2:
+    x: float
+
+impl Mul for Vec2:
+    fn times(self, other: Vec2) -> Vec2:
+        return self
+
+fn diff(a: Vec2, b: Vec2) -> Vec2:
+    let same = a == b
+    return a - b
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "`impl Mul for Vec2` is missing `fn mul`",
+            "operation `Eq` not supported between `Vec2` and `Vec2`",
+            "operation `Sub` not supported between `Vec2` and `Vec2`",
+        ]);
+    }
+
+    #[test]
+    fn test_destructured_tuple_param() {
+        let source = r#"
+fn dist((x, y): (int, int)) -> int:
+    return x * x + y * y
+
+fn label((name, (a, _)): (string, (int, bool))) -> string:
+    return name
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn first((x, y, z): (int, int)) -> int:
+    return x
+
+fn name((n, count): (string, int)) -> int:
+    return n
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert!(errors.contains(&"tuple pattern has 3 elements but `(int, int)` has 2"), "{:?}", errors);
+        assert!(errors.contains(&"incompatible return type: expected `int`, found `string`"), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_compound_assignment_operands() {
+        let source = r#"
+fn update(name: string) -> int:
+    let mut s = "id-"
+    s += name
+    let mut n = 1
+    n += 2
+    n *= 3
+    let mut f = 1.5
+    f /= 2.0
+    return n
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn update() -> int:
+    let mut s = "id-"
+    s += 1
+    s -= "x"
+    let mut n = 1
+    n += 2.5
+    n -= true
+    return n
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "cannot apply `+=` to `string` and `int`",
+            "cannot apply `-=` to `string` and `string`",
+            "cannot apply `+=` to `int` and `float`",
+            "cannot apply `-=` to `int` and `bool`",
+        ]);
+        assert!(diags.iter().all(|d| d.code == Some(ErrorCode::TYPE_MISMATCH)));
+    }
+
+    #[test]
+    fn test_static_declarations() {
+        let source = r#"
+const fn limit() -> int:
+    return 10
+
+static mut hits: int = 0
+static MAX: int = limit() * 2
+static mut label: string = "none"
+
+fn bump() -> int:
+    hits += 1
+    label = "bumped"
+    return hits + MAX
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+        let warnings: Vec<_> = diags.iter()
+            .filter(|d| d.code == Some(ErrorCode::STATIC_DATA_RACE))
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(warnings, vec!["mutable static `label` of type `string` is not atomic"]);
+
+        let source = r#"
+fn now() -> int:
+    return 1
+
+static START: int = now()
+static NAME: string = 5
+static ITEMS: [int] = [1]
+static LIMIT: int = 3
+
+fn reset():
+    LIMIT = 0
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert_eq!(errors[0], "static initializer must be a compile-time constant");
+        assert_eq!(errors[1], "incompatible type: expected `string`, found `int`");
+        assert_eq!(errors[2], "static `ITEMS` has unsupported type `[int]`");
+        assert_eq!(errors[3], "cannot assign to immutable static `LIMIT`");
+    }
+
+    #[test]
+    fn test_result_main() {
+        let source = r#"
+fn parse_port(s: string) -> Result<int, string>:
+    return Ok(8080)
+
+fn main() -> Result<(), string>:
+    let port = parse_port("8080")?
+    println(port)
+    return Ok(())
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn code() -> Result<int, int>:
+    return Err(1)
+
+fn run() -> int:
+    return code()?
+
+fn main() -> Result<(), string>:
+    let n = code()?
+    return Ok(())
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "the `?` operator can only be used in a function that returns `Result`",
+            "`?` propagates `int`, but the function returns errors of type `string`",
+        ]);
+
+        let diags = analyze_source("fn main(args: [string]) -> int:\n    return 0\n");
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "`main` cannot take parameters",
+            "`main` must return nothing or `Result<(), E>`, found `int`",
+        ]);
+    }
+
+    #[test]
+    fn test_map_literal() {
+        let source = r#"
+fn ages() -> Map<string, int>:
+    let empty: Map<string, int> = {}
+    return {"a": 1, "b": 2}
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn names() -> Map<string, string>:
+    return {"a": 1}
+
+fn mixed() -> int:
+    let m = {"a": 1, "b": "two", 3: 4}
+    return m.len()
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(errors, vec![
+            "incompatible return type: expected `Map<string, string>`, found `Map<string, int>`",
+            "map values have different types",
+            "map keys have different types",
+        ]);
+    }
+
+    #[test]
+    fn test_tuple_index() {
+        let source = r#"
+fn second() -> string:
+    let p = (1, "a")
+    return p.1
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn bad(n: int) -> int:
+    let p = (1, "a")
+    let x = p.5
+    return n.0
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(errors, vec![
+            "tuple index `5` is out of range for a tuple of 2 elements",
+            "type `int` cannot be indexed with `.N`",
+        ]);
+    }
+
+    #[test]
+    fn test_tuple_and_array_equality() {
+        let source = r#"
+fn main():
+    let a = (1, "x")
+    let b = (2, "y")
+    let same = a == b
+    let differ = [1, 2] != [3]
+    let nested = [(1, true)] == [(2, false)]
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+struct Point:
+    x: int
+
+fn main():
+    let p = Point { x: 1 }
+    let short = (1, 2) == (1, 2, 3)
+    let swapped = (1, "x") == ("x", 1)
+    let words = [1, 2] == ["a"]
+    let points = (p, 1) == (p, 1)
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "cannot compare `(int, int)` with `(int, int, int)`",
+            "cannot compare `(int, string)` with `(string, int)`",
+            "cannot compare `[int]` with `[string]`",
+            "cannot compare `(Point, int)` with `(Point, int)`",
+        ]);
+        let labels: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).filter_map(|d| d.labels.first()).map(|l| l.message.as_str()).collect();
+        assert_eq!(labels, vec![
+            "tuples of 2 and 3 elements",
+            "element 0: `int` is not `string`",
+            "array elements: `int` is not `string`",
+            "element 0: `Point` does not implement `Eq`",
+        ]);
+    }
+
+    #[test]
+    fn test_custom_error_enum() {
+        let source = r#"
+enum AppError:
+    NotFound
+    Invalid(string)
+
+fn find(id: int) -> Result<int, AppError>:
+    if id < 0:
+        return Err(AppError::Invalid("negative id"))
+    if id == 0:
+        return Err(AppError::NotFound)
+    return Ok(id)
+
+fn twice(id: int) -> Result<int, AppError>:
+    let n = find(id)?
+    return Ok(n * 2)
+
+fn lookup(id: int) -> Result<int, HttpError>:
+    let n = find(id)?
+    return Ok(n)
+
+fn main() -> Result<(), AppError>:
+    let n = twice(3)?
+    return Ok(())
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+enum AppError:
+    NotFound
+
+enum DbError:
+    Closed
+
+fn find(id: int) -> Result<int, AppError>:
+    return Err(DbError::Closed)
+
+fn fetch() -> Result<int, DbError>:
+    return Err(DbError::Closed)
+
+fn load() -> Result<int, AppError>:
+    let n = fetch()?
+    return Ok(n)
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "incompatible return type: expected `Result<int, AppError>`, found `Result<?, DbError>`",
+            "`?` propagates `DbError`, but the function returns errors of type `AppError`",
+        ]);
+    }
+
+    #[test]
+    fn test_derive_conflicts() {
+        let source = r#"
+@derive(Copy, Eq, Ord)
+struct Point:
+    x: int
+    y: int
+
+@derive(Copy, Hash)
+struct User:
+    name: string
+    score: float
+
+struct Version:
+    major: int
+
+@derive(PartialEq, Clone)
+struct Release:
+    version: Version
+
+impl Eq for Release:
+    fn eq(self, other: Release) -> bool:
+        return true
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+        let warnings: Vec<_> = diags.iter()
+            .filter(|d| d.code == Some(ErrorCode::INVALID_DERIVE))
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(warnings, vec![
+            "`Copy` cannot be derived for `User`",
+            "`Hash` cannot be derived for `User`",
+            "`PartialEq` is already implemented by `impl Eq for Release`",
+        ]);
+    }
+
+    #[test]
+    fn test_string_lines_and_split() {
+        let source = r#"
+fn total(text: string) -> Result<int, string>:
+    let rows: [string] = text.lines()
+    let mut sum = 0
+    for line in rows:
+        for field in line.split(","):
+            let n: int = parse(field.trim())?
+            sum = sum + n
+    return Ok(sum)
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let diags = analyze_source("fn f(s: string) -> int:\n    let fields: [int] = s.lines()\n    return s.split().len()\n");
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "incompatible type: expected `[int]`, found `[string]`",
+            "method `split` expects 1 argument, found 0",
+        ]);
+    }
+
+    #[test]
+    fn test_literal_inference_through_let() {
+        let source = r#"
+fn main():
+    let n = 2
+    let m: int = n * 3 + 1
+    let r = 1.5
+    let s: float = r * 2.0
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn main():
+    let x = 3.14
+    let y: int = x
+    let z: int = 3.14 + 1
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "incompatible type: expected `int`, found `float`",
+            "operation `Add` not supported between `float` and `int`",
+            "incompatible type: expected `int`, found `float`",
+        ]);
+    }
+
+    #[test]
+    fn test_unit_return() {
+        let source = r#"
+fn nothing():
+    return ()
+
+fn pair() -> ((), int):
+    return ((), 1)
+
+fn main():
+    let u: () = nothing()
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let diags = analyze_source("fn nothing():\n    return (1,)\n");
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec!["incompatible return type: expected `()`, found `(int)`"]);
+    }
+
+    #[test]
+    fn test_generator() {
+        let source = r#"
+fn counter(n: int) -> [int]:
+    for i in 0..n:
+        if i > 100:
+            return
+        yield i
+
+fn main():
+    let mut total = 0
+    for x in counter(5):
+        total = total + x
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn names() -> [string]:
+    yield 1
+    return ["a"]
+
+fn single() -> int:
+    yield 1
+
+fn helper():
+    let f = ||:
+        yield 1
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "`yield` produces `int`, but the generator yields `string`",
+            "a generator cannot return a value",
+            "generator `single` must return an array of its items, found `int`",
+            "`yield` outside of a generator function",
+        ]);
+    }
+
+    #[test]
+    fn test_string_slicing() {
+        let source = r#"
+fn initials(name: string, n: int) -> string:
+    let first = name[0]
+    let last = name[-1]
+    return first + name[1..n] + name[..2] + name[n..] + name[0..=1] + last
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn bad(name: string, items: [int]) -> string:
+    let a = name[3..1]
+    let b = name[-2..]
+    let c = items[0..1]
+    return name["a"..]
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "slice starts at 3 but ends at 1",
+            "slice bound `-2` is negative",
+            "type `[int]` does not support slicing",
+            "slice bounds must be `int`, found `string`",
+        ]);
+    }
+
+    #[test]
+    fn test_optional_chaining() {
+        let source = r#"
+struct Address:
+    city: string
+
+struct User:
+    name: string
+    address: Option<Address>
+    home: Address
+
+fn lookup(user: Option<User>) -> Option<string>:
+    let name: Option<string> = user?.name
+    let street: Option<string> = user?.home?.city
+    return user?.address?.city
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+struct User:
+    name: string
+
+fn bad(user: User, maybe: Option<User>, n: Option<int>) -> string:
+    let a = user?.name
+    let b = maybe?.email
+    let c = n?.value
+    return maybe?.name
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "optional chaining needs an `Option`, found `User`",
+            "no field `email` on type `User`",
+            "type `int` has no fields",
+            "incompatible return type: expected `string`, found `Option<string>`",
+        ]);
+    }
+
+    #[test]
+    fn test_optional_chaining_on_result_propagates() {
+        let source = r#"
+struct Config:
+    port: int
+
+fn load() -> Result<Config, string>:
+    return Ok(Config { port: 8080 })
+
+fn port() -> Result<int, string>:
+    let p: int = load()?.port
+    return Ok(p)
+
+fn bad() -> int:
+    return load()?.port
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).collect();
+        // Only `bad`, which cannot propagate the error, is rejected
+        assert_eq!(errors.len(), 1, "{:?}", errors.iter().map(|d| &d.message).collect::<Vec<_>>());
+        assert_eq!(errors[0].labels[0].span.start.line, 13);
+    }
+
+    #[test]
+    fn test_websocket_message_type() {
+        let source = r#"
+ws /stream:
+    message [int]
+    on_message:
+        let first: int = message[0]
+        let size = len(message)
+
+ws /chat:
+    on_message:
+        let text: string = message
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+ws /stream:
+    message [int]
+    on_message:
+        let text: string = message
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message == "incompatible type: expected `string`, found `[int]`"),
+            "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let diags = analyze_source("ws /n:\n    message float\n    on_message:\n        print(message)\n");
+        assert!(diags.iter().any(|d| d.message == "WebSocket messages cannot be received as `float`"));
+    }
+
+    #[test]
+    fn test_array_reductions() {
+        let source = r#"
+fn summary(nums: [int], words: [string]) -> string:
+    let total: int = nums.fold(0, |acc, x| acc + x)
+    let text: string = nums.fold("", |acc, x| acc + words[x])
+    let longest: Option<string> = words.max_by(|a, b| a.len() - b.len())
+    let product: Option<int> = nums.reduce(|a, b| a * b)
+    let low = nums.min_by(|a: int, b: int| a - b)
+    return text
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        // The result of `fold` has the accumulator's type, not the element's
+        let source = r#"
+fn bad(nums: [int]) -> int:
+    let name: string = nums.fold(0, |acc, x| acc + x)
+    let last = nums.fold(0.0, |acc, x| x)
+    let first = nums.max_by(|a, b| a < b)
+    let same = nums.reduce(|a: string, b| a)
+    return nums.fold(0)
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "incompatible type: expected `string`, found `int`",
+            "the closure passed to `fold` must return `float`, found `int`",
+            "the closure passed to `max_by` must return `int`, found `bool`",
+            "the closure passed to `reduce` takes `string`, but is given `int`",
+            "the closure passed to `reduce` must return `int`, found `string`",
+            "method `fold` expects 2 arguments, found 1",
+        ]);
+    }
+
+    #[test]
+    fn test_const_declarations() {
+        let source = r#"
+const MAX: int = 100
+const DOUBLE: int = MAX * 2
+const RATE: float = 0.5
+const DEBUG: bool = false
+
+fn scaled(n: int) -> int:
+    let buf = [0; DOUBLE]
+    if DEBUG:
+        return 0
+    return n * MAX + len(buf)
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn now() -> int:
+    return 1
+
+const START: int = now()
+const NAME: string = "api"
+const LIMIT: int = 3
+
+fn reset():
+    LIMIT = 0
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert_eq!(errors[0], "constant initializer must be a compile-time constant");
+        assert_eq!(errors[1], "constant `NAME` has unsupported type `string`");
+        assert_eq!(errors[2], "cannot assign to constant `LIMIT`");
+    }
+
+    #[test]
+    fn test_return_inside_closure_returns_from_closure() {
+        // The closures return strings while the function returns an int
+        let source = r#"
+fn describe(n: int) -> int:
+    let sign = |x: int|:
+        if x < 0:
+            return "negative"
+        return "positive"
+    let first = || return "first"
+    let label: string = sign(n)
+    return n
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn describe(n: int) -> string:
+    let sign = |x: int|:
+        if x < 0:
+            return "negative"
+        return 1
+    let count = |x: int| -> int:
+        return "many"
+    return "done"
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "incompatible return type: expected `string`, found `int`",
+            "incompatible return type: expected `int`, found `string`",
+        ]);
+    }
+
+    #[test]
+    fn test_impl_async_must_match_trait() {
+        let source = r#"
+trait Loader:
+    fn load(&self) -> string async
+    fn name(&self) -> string
+
+struct Disk:
+    path: string
+
+impl Loader for Disk:
+    fn load(&self) -> string async:
+        let data = await read(self.path)
+        return data
+
+    fn name(&self) -> string:
+        return "disk"
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+trait Loader:
+    fn load(&self) -> string async
+    fn name(&self) -> string
+
+struct Disk:
+    path: string
+
+impl Loader for Disk:
+    fn load(&self) -> string:
+        return self.path
+
+    fn name(&self) -> string async:
+        return "disk"
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "method `load` is `async` in trait `Loader` but not in `impl Loader for Disk`",
+            "method `name` is `async` in `impl Loader for Disk` but not in trait `Loader`",
+        ]);
+    }
+
+    #[test]
+    fn test_with_requires_closeable_resource() {
+        let source = r#"
+struct File:
+    path: string
+
+    fn close(&mut self):
+        print(self.path)
+
+trait Close:
+    fn close(&mut self)
+
+struct Socket:
+    port: int
+
+impl Close for Socket:
+    fn close(&mut self):
+        print("closed")
+
+fn work() -> int:
+    with File { path: "a.txt" } as file:
+        print(file.path)
+    with Socket { port: 80 } as socket:
+        return socket.port
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let diags = analyze_source("fn count() -> int:\n    return 1\n\nfn work():\n    with count() as n:\n        print(n)\n    print(n)\n");
+        let errors: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(errors[0], "`int` cannot be used in `with`: it has no `close()` method");
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_enum_cast_to_int() {
+        let source = r#"
+enum Color:
+    Red = 1
+    Green
+    Blue = 10
+
+fn encode(color: Color) -> int:
+    return color as int
+
+fn decode(code: int) -> Option<Color>:
+    return Color::from_int(code)
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+enum Color:
+    Red = 1
+    Green = 1
+
+enum Shape:
+    Circle(float)
+    Empty
+
+fn bad(color: Color, shape: Shape) -> int:
+    let a = color as float
+    let b = shape as int
+    let c = Shape::from_int(0)
+    return "x" as int
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "discriminant 1 is used by both `Red` and `Green`",
+            "cannot cast `Color` as `float`: enums can only be cast to `int`",
+            "cannot cast `Shape` as `int`: only enums without data have integer values",
+            "variant `from_int` not found in enum `Shape`",
+            "cannot cast `string` as `int`",
+        ]);
+    }
+
+    #[test]
+    fn test_std_module_imports() {
+        let source = r#"
+from std.math import sqrt, pi
+from std.string import upper as shout
+
+fn circle(r: float) -> float:
+    return sqrt(r) * pi
+
+fn greet(name: string) -> string:
+    return shout(name)
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+from std.maths import floor
+from std.math import sqr, sqrt
+
+fn area(r: float) -> float:
+    return sqrt("x")
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(errors, vec![
+            "unknown standard module `std.maths`",
+            "module `std.math` has no item `sqr`",
+            "incompatible argument: expected `float`, found `string`",
+        ]);
+    }
+
+    #[test]
+    fn test_numeric_casts() {
+        let source = r#"
+fn ratio(total: int, count: int) -> float:
+    let half: float = 3 as float
+    let whole: int = half as int
+    return total as float / count as float + half
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn bad(n: int, flag: bool) -> int:
+    let a = n as bool
+    let b = flag as int
+    let c = n as string
+    return "x" as int
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(errors, vec![
+            "cannot cast `int` as `bool`: only `int` and `float` are valid cast targets",
+            "cannot cast `bool` as `int`",
+            "cannot cast `int` as `string`: only `int` and `float` are valid cast targets",
+            "cannot cast `string` as `int`",
+        ]);
+    }
+
+    #[test]
+    fn test_const_fn_array_length() {
+        let source = r#"
+const fn square(x: int) -> int:
+    return x * x
+
+fn grid() -> [int]:
+    return [0; square(4)]
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn runtime() -> int:
+    return 4
+
+const fn describe(name: string) -> int:
+    print("describe")
+    return runtime() + name.len()
+
+fn grid(n: int) -> [int]:
+    let a = [0; n]
+    return [0; square(-2) - 8]
+
+const fn square(x: int) -> int:
+    return x * x
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "calling non-const fn `print` is not allowed in a `const fn`",
+            "calling non-const fn `runtime` is not allowed in a `const fn`",
+            "calling method `len` is not allowed in a `const fn`",
+            "array length must be a compile-time constant",
+            "array length cannot be negative, found -4",
+        ]);
+        let note = diags.iter().find(|d| d.message == "array length must be a compile-time constant")
+            .and_then(|d| d.notes.first().cloned());
+        assert_eq!(note.as_deref(), Some("`n` is not a compile-time constant"));
+    }
+
+    #[test]
+    fn test_misspelled_enum_variant_suggestion() {
+        let source = r#"
+enum Shape:
+    Circle(float)
+    Square(float)
+    Empty
+
+fn shapes() -> Shape:
+    let a: Shape = Shape::Empty
+    let b: Shape = Shape::Circle(1.0)
+    return Shape::Squar(2.0)
+
+fn blank() -> Shape:
+    return Shape::Emty
+"#;
+        let help = |d: &Diagnostic| d.suggestions.first().map(|s| s.message.clone());
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors.iter().map(|d| &d.message).collect::<Vec<_>>());
+        assert_eq!(errors[0].message, "variant `Squar` not found in enum `Shape`");
+        assert_eq!(help(errors[0]), Some("did you mean `Shape::Square`?".to_string()));
+        assert!(errors[0].notes.iter().any(|n| n == "`Shape` has variants `Circle`, `Square`, `Empty`"));
+        assert_eq!(help(errors[1]), Some("did you mean `Shape::Empty`?".to_string()));
+
+        // Patterns get the same suggestion; far-off names get none
+        let source = r#"
+enum Color:
+    Red
+    Green
+
+fn name(c: Color) -> int:
+    match c:
+        Color::Rd:
+            return 1
+        Color::Purple:
+            return 2
+        _:
+            return 0
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).collect();
+        assert_eq!(help(errors[0]), Some("did you mean `Color::Red`?".to_string()));
+        assert_eq!(errors[1].message, "variant `Purple` not found in enum `Color`");
+        assert_eq!(help(errors[1]), None);
+    }
+
+    #[test]
+    fn test_spread_into_non_variadic_parameter() {
+        let source = r#"
+fn sum(a: int, b: int) -> int:
+    return a + b
+
+fn main():
+    let nums = [1, 2]
+    let total = sum(..nums)
+    let bad = sum(..5)
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert!(errors.contains(&"cannot spread an array into a non-variadic parameter"), "{:?}", errors);
+        assert!(errors.contains(&"cannot spread `int`: expected an array"), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_map_method_types() {
+        let source = r#"
+fn use_map(ages: Map<string, int>) -> int:
+    let found: Option<int> = ages.get("ana")
+    let has: bool = ages.contains_key("ana")
+    let old: Option<int> = ages.insert("bia", 30)
+    let gone: Option<int> = ages.remove("bia")
+    let names: [string] = ages.keys()
+    let values: [int] = ages.values()
+    return values.len()
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn misuse(ages: Map<string, int>) -> int:
+    let a = ages.get(1)
+    let b = ages.insert("bia", "thirty")
+    let c: [int] = ages.keys()
+    ages.clear()
+    return 0
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(errors, vec![
+            "incompatible argument: expected `string`, found `int`",
+            "incompatible argument: expected `int`, found `string`",
+            "incompatible type: expected `[int]`, found `[string]`",
+            "method `clear` not found on type `Map<string, int>`",
+        ]);
+    }
+
+    #[test]
+    fn test_match_guard_uses_pattern_binding() {
+        let source = r#"
+fn sign(x: Option<int>) -> string:
+    let label = match x:
+        Some(n) if n > 0:
+            "positive"
+        Some(n) if n < 0:
+            "negative"
+        _:
+            "zero or none"
+    return label
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        // The binding is scoped to its own arm
+        let source = r#"
+fn sign(x: Option<int>) -> int:
+    let v = match x:
+        Some(n):
+            n
+        _ if n > 0:
+            1
+        _:
+            0
+    return v
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message.contains("`n`")));
+
+        // A guard that misuses the binding's type is reported
+        let source = r#"
+fn sign(x: Option<string>) -> int:
+    let v = match x:
+        Some(s) if s > 0:
+            1
+        _:
+            0
+    return v
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_match_exhaustiveness_on_enums() {
+        let non_exhaustive = |source: &str| -> Vec<String> {
+            analyze_source(source).iter()
+                .filter(|d| d.code == Some(ErrorCode::NON_EXHAUSTIVE_MATCH))
+                .map(|d| d.message.clone())
+                .collect()
+        };
+
+        let missing = r#"
+enum Status:
+    Active
+    Suspended(string)
+    Closed { reason: string }
+    Pending
+
+fn label(s: Status, o: Option<int>, r: Result<int, string>) -> int:
+    match s:
+        Active:
+            return 1
+        Status::Suspended(_):
+            return 2
+    match o:
+        Some(0):
+            return 0
+        None:
+            return 0
+    match r:
+        Ok(n):
+            return n
+    return 0
+"#;
+        assert_eq!(non_exhaustive(missing), vec![
+            "non-exhaustive match: `Closed` and `Pending` not covered",
+            "non-exhaustive match: `Some` not covered",
+            "non-exhaustive match: `Err` not covered",
+        ]);
+
+        let complete = r#"
+enum Status:
+    Active
+    Suspended(string)
+    Closed { reason: string }
+    Pending
+
+fn label(s: Status, o: Option<int>, r: Result<int, string>) -> int:
+    match s:
+        Active | Pending:
+            return 1
+        Suspended(why):
+            return 2
+        Closed { reason }:
+            return 3
+    match o:
+        Some(n):
+            return n
+        None:
+            return 0
+    match r:
+        Ok(_):
+            return 1
+        Err(e):
+            return 0
+    return 0
+"#;
+        let diags = analyze_source(complete);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let unreachable = r#"
+enum Status:
+    Active
+    Pending
+
+fn label(s: Status) -> int:
+    match s:
+        Active:
+            return 1
+        other:
+            return 2
+        Pending:
+            return 3
+"#;
+        let diags = analyze_source(unreachable);
+        assert!(!diags.has_errors());
+        let warnings: Vec<_> = diags.iter()
+            .filter(|d| d.code == Some(ErrorCode::UNREACHABLE_ARM))
+            .map(|d| d.level)
+            .collect();
+        assert_eq!(warnings, vec![mendes_error::Level::Warning]);
+    }
+
+    #[test]
+    fn test_unqualified_variant_uses_expected_enum() {
+        let errors = |source: &str| -> Vec<String> {
+            analyze_source(source).iter()
+                .filter(|d| d.level == mendes_error::Level::Error)
+                .map(|d| d.message.clone())
+                .collect()
+        };
+
+        // `Red` belongs to both enums; the return type, annotation, path or
+        // matched value says which one is meant
+        let resolved = r#"
+enum Color:
+    Red
+    Green
+
+enum Light:
+    Red
+    Yellow
+
+fn pick() -> Color:
+    return Red
+
+fn main():
+    let light: Light = Red
+    let color = Color::Red
+    match light:
+        Red:
+            println("stop")
+        Yellow:
+            println("slow")
+"#;
+        assert!(errors(resolved).is_empty(), "{:?}", errors(resolved));
+
+        let ambiguous = r#"
+enum Color:
+    Red
+
+enum Light:
+    Red
+
+fn main():
+    let c = Red
+"#;
+        let diags = analyze_source(ambiguous);
+        let diag = diags.iter().find(|d| d.code == Some(ErrorCode::AMBIGUOUS_VARIANT)).expect("ambiguous variant");
+        assert_eq!(diag.message, "ambiguous variant `Red`");
+        assert_eq!(diag.suggestions[0].message, "write `Color::Red` or `Light::Red`");
+    }
+
+    #[test]
+    fn test_tuple_variant_shared_by_enums() {
+        let source = r#"
+enum Maybe<T>:
+    Just(T)
+    Empty
+
+enum Wrapped:
+    Just(int)
+
+fn main():
+    let a: Maybe<string> = Just("x")
+    let w: Wrapped = Just(3)
+    let b = Just(1)
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).collect();
+        // The annotation picks the enum; without one the constructor is ambiguous
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].code, Some(ErrorCode::AMBIGUOUS_VARIANT));
+        assert_eq!(errors[0].labels[0].span.start.line, 12);
+        assert_eq!(errors[0].suggestions[0].message, "write `Maybe::Just` or `Wrapped::Just`");
+    }
+
+    #[test]
+    fn test_unqualified_struct_variant_pattern_binds_fields() {
+        let source = r#"
+enum Shape:
+    Circle(int)
+    Rect { w: int, h: string }
+
+fn area(s: Shape) -> int:
+    match s:
+        Circle(r):
+            return r
+        Rect { w, h }:
+            let label: int = h
+            return w
+"#;
+        let errors: Vec<_> = analyze_source(source).iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.clone())
+            .collect();
+        // `w` and `h` are bound with the variant's field types
+        assert_eq!(errors, vec!["incompatible type: expected `int`, found `string`"]);
+    }
+
+    #[test]
+    fn test_match_exhaustiveness_on_bool_and_int() {
+        let non_exhaustive = |source: &str| -> Vec<String> {
+            analyze_source(source).iter()
+                .filter(|d| d.code == Some(ErrorCode::NON_EXHAUSTIVE_MATCH))
+                .map(|d| d.message.clone())
+                .collect()
+        };
+
+        let bool_missing = r#"
+fn describe(flag: bool) -> string:
+    let text = match flag:
+        true:
+            "on"
+    return text
+"#;
+        assert_eq!(non_exhaustive(bool_missing), vec!["non-exhaustive match: `false` not covered"]);
+
+        let bool_complete = r#"
+fn describe(flag: bool) -> string:
+    let text = match flag:
+        true:
+            "on"
+        false:
+            "off"
+    return text
+"#;
+        assert!(non_exhaustive(bool_complete).is_empty());
+
+        let int_without_wildcard = r#"
+fn bucket(n: int) -> int:
+    let b = match n:
+        0:
+            0
+        1..10:
+            1
+        m if m > 100:
+            2
+    return b
+"#;
+        assert_eq!(non_exhaustive(int_without_wildcard), vec!["non-exhaustive match: `_` not covered"]);
+
+        let int_with_wildcard = r#"
+fn bucket(n: int) -> int:
+    let b = match n:
+        0:
+            0
+        1..10:
+            1
+        _:
+            2
+    return b
+"#;
+        assert!(non_exhaustive(int_with_wildcard).is_empty());
+    }
+
+    #[test]
+    fn test_try_catch_binds_propagated_error() {
+        let source = r#"
+fn double(s: string) -> int:
+    try:
+        let n: int = parse(s)?
+        return n * 2
+    catch err:
+        let message: string = err
+        print(message)
+    return 0
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        // The binding has the propagated error type
+        let source = r#"
+fn double(s: string) -> int:
+    try:
+        let n: int = parse(s)?
+        return n * 2
+    catch err:
+        let code: int = err
+        return code
+    return 0
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message.contains("expected `int`, found `string`")));
+
+        // Every `?` in the body must agree on the error type
+        let source = r#"
+fn fetch(s: string) -> Result<int, int>:
+    return Err(1)
+
+fn both(s: string) -> int:
+    try:
+        let a: int = parse(s)?
+        let b = fetch(s)?
+        return a + b
+    catch err:
+        return 0
+    return 0
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message == "mismatched error types in `try` block: expected `string`, found `int`"));
+    }
+
+    #[test]
+    fn test_let_tuple_binds_elements() {
+        let source = r#"
+fn minmax(a: int, b: int) -> (int, string):
+    return (a, "max")
+
+fn use_it() -> int:
+    let (lo, label) = minmax(1, 2)
+    let text: string = label
+    return lo
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+fn pair() -> (int, int):
+    return (1, 2)
+
+fn use_it() -> int:
+    let (a, b, c) = pair()
+    let (x, _) = 5
+    return a
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert!(errors.contains(&"cannot destructure a tuple of 2 elements into 3 names"));
+        assert!(errors.contains(&"cannot destructure `int`: expected a tuple"));
+    }
+
+    #[test]
+    fn test_for_over_user_iterator() {
+        let source = r#"
+struct Words:
+    left: int
+
+    fn next(&mut self) -> Option<string>:
+        if self.left > 0:
+            self.left = self.left - 1
+            return Some("word")
+        return None
+
+fn count() -> int:
+    let mut n = 0
+    for w in Words { left: 3 }:
+        let text: string = w
+        n = n + 1
+    return n
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        // `next` must take no arguments and return an Option
+        let source = r#"
+struct Ticker:
+    n: int
+
+    fn next(&mut self) -> int:
+        return self.n
+
+fn run():
+    for t in Ticker { n: 0 }:
+        print(t)
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message == "expected iterable type, found `Ticker`"));
+    }
+
+    #[test]
+    fn test_route_attribute_params() {
+        let source = r#"
+@route(GET, "/users/{id:int}")
+fn get_user(id: int) -> int:
+    return id
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        let source = r#"
+@route(GET, "/users/{id:int}")
+fn get_user(id: string, page: int) -> string:
+    return id
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert!(errors.contains(&"parameter `id` has type `string`, but the route declares it as `int`"));
+        assert!(errors.contains(&"parameter `page` of `get_user` is not a parameter of route `/users/{id:int}`"));
+
+        // Attributed routes take part in duplicate detection
+        let source = r#"
+api GET /health:
+    return string
+    return "ok"
+
+@route(GET, "/health")
+fn health() -> string:
+    return "ok"
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message == "route `GET /health` is defined more than once"));
+    }
+
+    #[test]
+    fn test_empty_body_warning() {
+        let source = r#"
+fn todo():
+    # not written yet
+
+fn _placeholder():
+    # intentionally empty
+
+fn main():
+    todo()
+    _placeholder()
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+        let empty: Vec<_> = diags.iter()
+            .filter(|d| d.code == Some(ErrorCode::EMPTY_BODY))
+            .collect();
+        assert_eq!(empty.len(), 1);
+        assert!(empty[0].message.contains("function `todo`"));
+    }
+
+    #[test]
+    fn test_dyn_trait_array() {
+        let shapes = r#"
+trait Shape:
+    fn area(&self) -> int
+
+struct Circle:
+    r: int
+
+struct Square:
+    side: int
+
+struct Label:
+    text: string
+
+impl Shape for Circle:
+    fn area(&self) -> int:
+        return self.r * self.r * 3
+
+impl Shape for Square:
+    fn area(&self) -> int:
+        return self.side * self.side
+"#;
+        let diags = analyze_source(&format!("{}{}", shapes, r#"
+fn total_area() -> int:
+    let shapes: [dyn Shape] = [Circle { r: 1 }, Square { side: 2 }]
+    let mut total = 0
+    for shape in shapes:
+        total = total + shape.area()
+    return total
+"#));
+        assert!(!diags.has_errors(), "{:?}", diags);
+
+        // Assigning an implementor to a `dyn` local coerces like `let` does
+        let diags = analyze_source(&format!("{}{}", shapes, r#"
+fn swap() -> int:
+    let mut s: dyn Shape = Circle { r: 1 }
+    s = Square { side: 3 }
+    s = Label { text: "x" }
+    return s.area()
+"#));
+        let messages: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, vec!["cannot assign `Label` to `dyn Shape`"]);
+
+        let diags = analyze_source(&format!("{}{}", shapes, r#"
+fn f() -> int:
+    let shapes: [dyn Shape] = [Circle { r: 1 }, Label { text: "x" }]
+    let named: [dyn Named] = []
+    for shape in shapes:
+        let s: string = shape.area()
+        shape.perimeter()
+    return 0
+"#));
+        let messages: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert!(messages.contains(&"`Label` cannot be used as `dyn Shape`"), "{:?}", messages);
+        assert!(messages.contains(&"trait `Named` not found"), "{:?}", messages);
+        assert!(messages.contains(&"incompatible type: expected `string`, found `int`"), "{:?}", messages);
+        assert!(messages.iter().any(|m| m.contains("perimeter")), "{:?}", messages);
+    }
+
+    #[test]
+    fn test_break_inside_match_inside_loop() {
+        let diags = analyze_source(r#"
+fn first_negative(items: [int]) -> int:
+    let mut i = 0
+    while i < 10:
+        match i:
+            3:
+                break
+            _:
+                if i > 5:
+                    continue
+        i = i + 1
+    for x in 0..3:
+        match x:
+            1:
+                if x > 0:
+                    break
+            _:
+                continue
+    return i
+"#);
+        assert!(!diags.iter().any(|d| d.code == Some(ErrorCode::BREAK_OUTSIDE_LOOP)), "{:?}", diags);
+
+        let diags = analyze_source(r#"
+fn f(x: int) -> int:
+    match x:
+        1:
+            break
+        _:
+            continue
+    while x > 0:
+        let g = |n: int|:
+            break
+    return x
+"#);
+        let lines: Vec<_> = diags.iter()
+            .filter(|d| d.code == Some(ErrorCode::BREAK_OUTSIDE_LOOP))
+            .map(|d| d.labels[0].span.start.line)
+            .collect();
+        assert_eq!(lines, vec![5, 7, 10]);
+    }
+
+    #[test]
+    fn test_top_level_break() {
+        let diags = analyze_source(r#"
+break
+
+fn count() -> int:
+    let mut n = 0
+    while true:
+        n += 1
+        if n > 3:
+            break
+        continue
+    return n
+"#);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| (d.message.as_str(), d.labels[0].span.start.line))
+            .collect();
+        assert_eq!(errors, vec![("`break` outside of a loop", 2)]);
+    }
+
+    #[test]
+    fn test_parse_infers_target_from_expected_type() {
+        let diags = analyze_source(r#"
+fn to_int(s: string) -> Result<int, string>:
+    let n: int = parse(s)?
+    return Ok(n + 1)
+
+fn to_float(s: string) -> Result<float, string>:
+    return parse(s)
+"#);
+        assert!(!diags.has_errors(), "{:?}", diags);
+
+        let diags = analyze_source(r#"
+fn f(s: string) -> Result<int, string>:
+    let n = parse(s)?
+    return Ok(1)
+"#);
+        assert!(diags.iter().any(|d| d.message == "cannot infer the type to parse"));
+
+        let diags = analyze_source(r#"
+fn f(s: string) -> Result<string, string>:
+    return parse(s)
+"#);
+        assert!(diags.iter().any(|d| d.message == "cannot parse a string into `string`"));
+    }
+
+    #[test]
+    fn test_format_named_placeholders() {
+        let diags = analyze_source(r#"
+fn greet(who: string, n: int) -> string:
+    return "Hello, {name}! You have {count} messages, {name}.".format(name: who, count: n)
+"#);
+        assert!(!diags.has_errors(), "{:?}", diags);
+
+        let diags = analyze_source(r#"
+fn greet(who: string) -> string:
+    return "Hello, {name}! {{not a placeholder}} {title}".format(name: who, extra: 1)
+"#);
+        let messages: Vec<_> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert!(messages.contains(&"no argument for placeholder `{title}`"), "{:?}", messages);
+        assert!(messages.contains(&"argument `extra` is not used by the format string"), "{:?}", messages);
+        assert_eq!(messages.len(), 2);
+
+        let diags = analyze_source("fn f(x: int) -> int:\n    let s = \"{x\".format(x: x)\n    return foo(x: 1)\n");
+        assert!(diags.iter().any(|d| d.message == "unclosed `{` in format string"));
+        assert!(diags.iter().any(|d| d.message.contains("named argument `x` is only allowed in `format` calls")));
+    }
+
+    #[test]
+    fn test_match_arm_without_value() {
+        let source = r#"
+fn describe(n: int) -> int:
+    let size = match n:
+        0:
+            return 0
+        1:
+            let doubled = n * 2
+        _:
+            return 2
+    return size
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.code == Some(ErrorCode::TYPE_MISMATCH)).collect();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].message, "match arm does not produce a value of type `int`");
+        // Points at the arm's last statement
+        assert_eq!(errors[0].labels[0].span.start.line, 7);
+    }
+
+    #[test]
+    fn test_range_to_array() {
+        let source = r#"
+fn numbers(n: int) -> [int]:
+    let xs: [int] = (0..n).to_array()
+    let ys: [int] = (1..=n).collect()
+    return xs
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags);
+
+        let diags = analyze_source("fn f() -> int:\n    let s: string = (0..3).to_array()\n    return 0\n");
+        assert!(diags.iter().any(|d| d.message.contains("expected `string`, found `[int]`")));
+
+        let diags = analyze_source("fn f() -> int:\n    let s = (0..3).sum()\n    return 0\n");
+        assert!(diags.iter().any(|d| d.message.contains("method `sum` not found on type `Range<int>`")));
+    }
+
+    #[test]
+    fn test_range_to_array_needs_an_end() {
+        let source = r#"
+fn numbers(n: int) -> [int]:
+    let r = 0..n
+    let rest = 5..
+    let xs: [int] = r.to_array()
+    let ys = rest.collect()
+    let zs = (1..).to_array()
+    return xs
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter().filter(|d| d.level == mendes_error::Level::Error).collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!(errors[0].message, "cannot `collect` a range without an end");
+        assert_eq!(errors[0].labels[0].span.start.line, 6);
+        assert_eq!(errors[1].message, "cannot `to_array` a range without an end");
+    }
+
+    #[test]
+    fn test_duplicate_route_after_group_prefix() {
+        let source = r#"
+group /api/v1:
+    api GET /users/{id:int}:
+        return int
+        return id
+
+api GET /api/v1/users/{user_id:int}:
+    return int
+    return user_id
+
+api POST /api/v1/users/{user_id:int}:
+    return int
+    return user_id
+"#;
+        let diags = analyze_source(source);
+        let duplicates: Vec<_> = diags.iter()
+            .filter(|d| d.code == Some(ErrorCode::DUPLICATE_DEFINITION))
+            .collect();
+        assert_eq!(duplicates.len(), 1);
+        assert!(duplicates[0].message.contains("GET /api/v1/users/{user_id:int}"));
+    }
+
+    #[test]
+    fn test_generic_enum() {
+        let source = r#"
+enum Maybe<T>:
+    Just(T)
+    Empty
+
+fn make() -> Maybe<int>:
+    let a: Maybe<int> = Just(5)
+    let b: Maybe<int> = Empty
+    return a
+
+fn unwrap_or(m: Maybe<int>, fallback: int) -> int:
+    match m:
+        Maybe::Just(v):
+            let doubled: int = v * 2
+            return doubled
+        _:
+            return fallback
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+
+        let source = r#"
+enum Maybe<T>:
+    Just(T)
+    Empty
+
+fn make() -> Maybe<int>:
+    let a: Maybe<int> = Just("five")
+    return a
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_type_argument_count() {
+        let prelude = r#"
+struct Pair<A, B>:
+    first: A
+    second: B
+
+struct Point:
+    x: int
+    y: int
+"#;
+        let arity_errors = |body: &str| {
+            let diags = analyze_source(&format!("{}{}", prelude, body));
+            diags.iter()
+                .filter(|d| d.code == Some(ErrorCode::WRONG_TYPE_ARG_COUNT))
+                .map(|d| d.message.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(arity_errors("\nfn f(p: Pair<int, string>) -> int:\n    return 1\n").is_empty());
+
+        let too_few = arity_errors("\nfn f(p: Pair<int>) -> int:\n    return 1\n");
+        assert_eq!(too_few, vec!["type `Pair` expects 2 type arguments, found 1".to_string()]);
+
+        let too_many = arity_errors("\nfn f(p: Pair<int, int, int>) -> int:\n    return 1\n");
+        assert_eq!(too_many, vec!["type `Pair` expects 2 type arguments, found 3".to_string()]);
+
+        let non_generic = arity_errors("\nfn f(p: Point<int>) -> int:\n    return 1\n");
+        assert_eq!(non_generic, vec!["type `Point` is not generic".to_string()]);
+    }
+
+    #[test]
+    fn test_struct_default() {
+        let source = r#"
+struct Point:
+    x: int
+    y: float
+    label: string
+    visible: bool
+
+fn origin() -> int:
+    let p: Point = Point::default()
+    return p.x
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+
+        let source = r#"
+enum Color:
+    Red
+    Green
+
+struct Pixel:
+    x: int
+    color: Color
+
+fn blank() -> Pixel:
+    return Pixel::default()
+"#;
+        let diags = analyze_source(source);
+        let error = diags.iter()
+            .find(|d| d.message == "`Pixel` has no default value")
+            .expect("expected a default error");
+        assert!(error.notes.iter().any(|n| n.contains("field `color`")));
+    }
+
+    #[test]
+    fn test_import_name_collision() {
+        let source = r#"
+from strings import helper
+from numbers import helper
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.has_errors());
+        assert!(diags.iter().any(|d| d.code == Some(ErrorCode::DUPLICATE_DEFINITION)));
+
+        let source = r#"
+from strings import helper
+from numbers import helper as number_helper
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+    }
+
+    #[test]
+    fn test_import_conflicts_with_local_definition() {
+        let source = r#"
+from strings import helper
+
+fn helper() -> int:
+    return 1
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_api_missing_return() {
+        let source = r#"
+api GET /name:
+    return string
+    let name = "mendes"
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_cfg_disabled_items_are_skipped() {
+        let source = r#"@cfg(test)
+fn helper() -> int:
+    return "not an int"
+
+fn answer() -> int:
+    return 42
+"#;
+        let mut lexer = Lexer::new(source, 0);
+        let (program, _) = parse(lexer.tokenize());
+        let diags = analyze(&program, &mut SemanticContext::new());
+        assert!(!diags.has_errors());
+
+        let cfg: HashSet<String> = ["test".to_string()].into_iter().collect();
+        let diags = analyze_with_cfg(&program, &mut SemanticContext::new(), &cfg);
+        assert!(diags.iter().any(|d| d.message == "incompatible return type: expected `int`, found `string`"));
+
+        // A disabled statement inside a body declares nothing
+        let source = r#"fn answer() -> int:
+    @cfg(debug)
+    let x = 5
+    return x
+"#;
+        let mut lexer = Lexer::new(source, 0);
+        let (program, _) = parse(lexer.tokenize());
+        let diags = analyze(&program, &mut SemanticContext::new());
+        assert!(diags.iter().any(|d| d.message == "variable not found: `x`"));
+
+        let cfg: HashSet<String> = ["debug".to_string()].into_iter().collect();
+        let diags = analyze_with_cfg(&program, &mut SemanticContext::new(), &cfg);
+        assert!(!diags.has_errors());
+    }
+
+    #[test]
+    fn test_api_returns_from_every_match_arm() {
+        let source = r#"
+api GET /check/{n:int}:
+    return string
+    match n > 0:
+        true:
+            return "positive"
+        false:
+            return "not positive"
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+
+        // An arm that falls through leaves a path without a value
+        let source = r#"
+api GET /check/{n:int}:
+    return string
+    match n:
+        0:
+            return "zero"
+        _:
+            let m = n
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message == "API handler does not return a value of type `string` on all paths"));
+
+        // Only a catch-all arm makes a match over strings exhaustive
+        let source = r#"
+api GET /greet/{name}:
+    return string
+    match name:
+        "ana":
+            return "oi"
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message == "API handler does not return a value of type `string` on all paths"));
+    }
+
+    #[test]
+    fn test_api_returns_on_all_paths() {
+        let source = r#"
+api GET /check/{n:int}:
+    return string
+    if n > 0:
+        return "positive"
+    else:
+        return "not positive"
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors());
+
+        let source = r#"
+api GET /check/{n:int}:
+    return string
+    if n > 0:
+        return "positive"
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_duplicate_fields_variants_and_params() {
+        let source = r#"
+struct User:
+    name: string
+    age: int
+    name: string
+
+enum Status:
+    Active
+    Banned(string)
+    Active
+
+fn area(width: int, height: int, width: int) -> int:
+    return height
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(errors, vec![
+            "field `name` is defined more than once",
+            "variant `Active` is defined more than once",
+            "parameter `width` is defined more than once",
+        ]);
+        assert!(diags.iter().all(|d| d.code != Some(ErrorCode::DUPLICATE_DEFINITION) || d.labels.len() == 2));
+    }
+
+    #[test]
+    fn test_distinct_fields_pass() {
+        let source = r#"
+struct User:
+    name: string
+    age: int
+
+fn describe(user: User, prefix: string) -> string:
+    return prefix
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.has_errors(), "{:?}", diags.iter().map(|d| &d.message).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_field_defaults() {
+        let source = r#"
+struct Config:
+    host: string = "localhost"
+    port: int = 8080
+    debug: bool
+
+fn main():
+    let a = Config { debug: true }
+    let b = Config { port: 9000, debug: false }
+    let c = Config { host: "example.com" }
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(errors, vec!["field `debug` missing in `Config`"]);
+
+        let source = r#"
+struct Config:
+    port: int = "8080"
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(errors, vec!["default value for field `port` has type `string`, expected `int`"]);
+    }
+
+    #[test]
+    fn test_use_after_move_across_call() {
+        let source = r#"
+struct Data:
+    value: int
+
+fn consume(d: Data) -> int:
+    return d.value
+
+fn run() -> int:
+    let d = Data { value: 1 }
+    consume(d)
+    return d.value
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "use of `d` after move");
+        assert_eq!(errors[0].labels.len(), 2);
+        // The primary label points at the use, the secondary one at the move
+        assert_eq!(errors[0].labels[0].span.start.line, 11);
+        assert_eq!(errors[0].labels[1].span.start.line, 10);
+
+        let source = r#"
+struct Data:
+    value: int
+
+fn inspect(d: &Data) -> int:
+    return d.value
+
+fn run() -> int:
+    let d = Data { value: 1 }
+    inspect(&d)
+    return d.value
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.iter().any(|d| d.level == mendes_error::Level::Error));
+
+        let source = r#"
+struct Point copy:
+    x: int
+
+fn take(p: Point) -> int:
+    return p.x
+
+fn run() -> int:
+    let p = Point { x: 1 }
+    take(p)
+    return p.x
+"#;
+        let diags = analyze_source(source);
+        assert!(!diags.iter().any(|d| d.level == mendes_error::Level::Error));
+    }
+
+    #[test]
+    fn test_if_let_variant_bindings() {
+        let source = r#"
+enum Shape:
+    Circle(float)
+    Rect(int, int)
+    Named { label: string, sides: int }
+
+fn area(s: Shape) -> int:
+    if let Shape::Rect(w, h) = s:
+        return w * h
+    if let Shape::Named { label, sides } = s:
+        let text: string = label
+        return sides
+    return 0
+
+fn radius(s: Shape) -> int:
+    if let Shape::Circle(r) = s:
+        return r
+    return 0
+
+fn count(s: Shape) -> int:
+    let mut n = 0
+    while let Rect(w, _) = s:
+        n = n + w
+    return n
+"#;
+        let diags = analyze_source(source);
+        let errors: Vec<_> = diags.iter()
+            .filter(|d| d.level == mendes_error::Level::Error)
+            .map(|d| d.message.as_str())
+            .collect();
+        // `r` is typed `float` from the variant, so it cannot be returned as `int`
+        assert_eq!(errors, vec!["incompatible return type: expected `int`, found `float`"]);
+
+        let source = r#"
+enum Shape:
+    Named { label: string, sides: int }
+
+fn sides(s: Shape) -> int:
+    if let Shape::Named { corners } = s:
+        return 0
+    return 1
+"#;
+        let diags = analyze_source(source);
+        assert!(diags.iter().any(|d| d.message == "variant `Shape::Named` has no field `corners`"));
+    }
 }
@@ -26,11 +26,16 @@ pub mod types;
 pub mod symbols;
 pub mod checker;
 pub mod ownership;
+pub mod suggest;
 
-pub use checker::{analyze, TypeChecker};
+pub use checker::{analyze, analyze_with_cfg, TypeChecker};
 pub use symbols::{Symbol, SymbolTable, SymbolKind};
 pub use types::{MendesType, TypeId};
 
+use mendes_error::{Position, Span};
+use mendes_parser::Visibility;
+use std::collections::HashMap;
+
 /// Semantic analysis context
 #[derive(Debug, Default)]
 pub struct SemanticContext {
@@ -38,6 +43,8 @@ pub struct SemanticContext {
     pub symbols: SymbolTable,
     /// Registered types
     pub types: types::TypeRegistry,
+    /// Resolved type of every checked expression, keyed by its span
+    pub expr_types: HashMap<Span, MendesType>,
 }
 
 impl SemanticContext {
@@ -47,6 +54,35 @@ impl SemanticContext {
         ctx
     }
 
+    /// Returns the type of the innermost expression under `pos` (editor hover)
+    pub fn hover(&self, pos: Position) -> Option<(MendesType, Span)> {
+        self.expr_types
+            .iter()
+            .filter(|(span, _)| span.contains(pos))
+            .min_by_key(|(span, _)| span.len())
+            .map(|(span, ty)| (ty.clone(), *span))
+    }
+
+    /// Lists every checked expression as `line:col type`, in source order
+    /// with enclosing expressions before the ones nested inside them
+    pub fn type_dump(&self) -> Vec<String> {
+        let mut entries: Vec<_> = self.expr_types.iter().collect();
+        entries.sort_by_key(|(span, _)| (span.start.offset, std::cmp::Reverse(span.len())));
+        entries
+            .into_iter()
+            .map(|(span, ty)| format!("{}:{} {}", span.start.line, span.start.column, ty))
+            .collect()
+    }
+
+    /// The resolved expression types that can be written in source, for
+    /// lowering to read instead of inferring them again
+    pub fn written_expr_types(&self) -> HashMap<Span, mendes_parser::Type> {
+        self.expr_types
+            .iter()
+            .filter_map(|(span, ty)| Some((*span, ty.to_ast()?)))
+            .collect()
+    }
+
     /// Registers built-in types and functions
     fn register_builtins(&mut self) {
         // Primitive types are already in the TypeRegistry by default
@@ -59,6 +95,7 @@ impl SemanticContext {
             ty: MendesType::Named("DatabaseNamespace".to_string()),
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Public,
         });
 
         // Built-in functions
@@ -78,6 +115,7 @@ impl SemanticContext {
             },
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Public,
         });
 
         // println(value: any) -> ()
@@ -95,6 +133,7 @@ impl SemanticContext {
             },
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Public,
         });
 
         // len(collection: any) -> int
@@ -112,6 +151,7 @@ impl SemanticContext {
             },
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Public,
         });
 
         // str(value: any) -> string
@@ -129,6 +169,7 @@ impl SemanticContext {
             },
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Public,
         });
 
         // int(value: string) -> int
@@ -146,6 +187,7 @@ impl SemanticContext {
             },
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Public,
         });
 
         // float(value: string) -> float
@@ -163,6 +205,7 @@ impl SemanticContext {
             },
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Public,
         });
 
         // log(msg: string) -> ()
@@ -180,6 +223,143 @@ impl SemanticContext {
             },
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Public,
+        });
+
+        // parse<T>(value: string) -> Result<T, string>, with `T` taken from the expected type
+        let result_of_t = MendesType::Generic {
+            name: "Result".to_string(),
+            args: vec![MendesType::Named("T".to_string()), MendesType::String],
+        };
+        self.symbols.define(Symbol {
+            name: "parse".to_string(),
+            kind: SymbolKind::Function {
+                generic_params: vec!["T".to_string()],
+                params: vec![("value".to_string(), MendesType::String)],
+                return_type: result_of_t.clone(),
+                is_async: false,
+            },
+            ty: MendesType::Function {
+                params: vec![MendesType::String],
+                ret: Box::new(result_of_t),
+            },
+            mutable: false,
+            defined_at: None,
+            visibility: Visibility::Public,
+        });
+
+        // stream(path: string) -> Response, sending the file without buffering it
+        self.symbols.define(Symbol {
+            name: "stream".to_string(),
+            kind: SymbolKind::Function {
+                generic_params: vec![],
+                params: vec![("path".to_string(), MendesType::String)],
+                return_type: MendesType::Named("Response".to_string()),
+                is_async: false,
+            },
+            ty: MendesType::Function {
+                params: vec![MendesType::String],
+                ret: Box::new(MendesType::Named("Response".to_string())),
+            },
+            mutable: false,
+            defined_at: None,
+            visibility: Visibility::Public,
+        });
+
+        // response(body: any) -> Response, built up with `with_header` and `with_status`
+        let response = MendesType::Named("Response".to_string());
+        self.symbols.define(Symbol {
+            name: "response".to_string(),
+            kind: SymbolKind::Function {
+                generic_params: vec![],
+                params: vec![("body".to_string(), MendesType::Any)],
+                return_type: response.clone(),
+                is_async: false,
+            },
+            ty: MendesType::Function {
+                params: vec![MendesType::Any],
+                ret: Box::new(response.clone()),
+            },
+            mutable: false,
+            defined_at: None,
+            visibility: Visibility::Public,
+        });
+        // redirect(location: string) -> Response (302) and
+        // redirect_permanent(location: string) -> Response (301)
+        for name in ["redirect", "redirect_permanent"] {
+            self.symbols.define(Symbol {
+                name: name.to_string(),
+                kind: SymbolKind::Function {
+                    generic_params: vec![],
+                    params: vec![("location".to_string(), MendesType::String)],
+                    return_type: response.clone(),
+                    is_async: false,
+                },
+                ty: MendesType::Function {
+                    params: vec![MendesType::String],
+                    ret: Box::new(response.clone()),
+                },
+                mutable: false,
+                defined_at: None,
+                visibility: Visibility::Public,
+            });
+        }
+        self.types.register_struct(types::StructDef {
+            name: "Response".to_string(),
+            generic_params: vec![],
+            fields: vec![],
+            defaults: vec![],
+            methods: vec![
+                (
+                    "with_header".to_string(),
+                    vec![("name".to_string(), MendesType::String), ("value".to_string(), MendesType::String)],
+                    response.clone(),
+                    false,
+                ),
+                ("with_status".to_string(), vec![("status".to_string(), MendesType::Int)], response, false),
+            ],
+            is_copy: false,
+        });
+
+        // spawn<T>(future: Future<T>) -> Task<T>
+        let task_of_t = MendesType::Generic {
+            name: "Task".to_string(),
+            args: vec![MendesType::Named("T".to_string())],
+        };
+        let future_of_t = MendesType::Future(Box::new(MendesType::Named("T".to_string())));
+        self.symbols.define(Symbol {
+            name: "spawn".to_string(),
+            kind: SymbolKind::Function {
+                generic_params: vec!["T".to_string()],
+                params: vec![("future".to_string(), future_of_t.clone())],
+                return_type: task_of_t.clone(),
+                is_async: false,
+            },
+            ty: MendesType::Function {
+                params: vec![future_of_t],
+                ret: Box::new(task_of_t.clone()),
+            },
+            mutable: false,
+            defined_at: None,
+            visibility: Visibility::Public,
+        });
+
+        // join<T>(task: Task<T>) -> T
+        self.symbols.define(Symbol {
+            name: "join".to_string(),
+            kind: SymbolKind::Function {
+                generic_params: vec!["T".to_string()],
+                params: vec![("task".to_string(), task_of_t.clone())],
+                return_type: MendesType::Named("T".to_string()),
+                is_async: false,
+            },
+            ty: MendesType::Function {
+                params: vec![task_of_t],
+                ret: Box::new(MendesType::Named("T".to_string())),
+            },
+            mutable: false,
+            defined_at: None,
+            visibility: Visibility::Public,
         });
 
         // HttpError(status: int, message: string) -> HttpError
@@ -200,6 +380,7 @@ impl SemanticContext {
             },
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Public,
         });
     }
 }
@@ -155,8 +155,8 @@ impl OwnershipChecker {
     /// Marks variable as moved
     pub fn mark_moved(&mut self, name: &str, moved_at: Span) {
         if let Some(info) = self.lookup_mut(name) {
-            // Copy types are not moved
-            if info.ty.is_copy() {
+            // Copy types and references are not moved
+            if info.ty.is_copy() || info.ty.is_ref() {
                 return;
             }
 
@@ -353,6 +353,17 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_references_not_moved() {
+        let mut checker = OwnershipChecker::new();
+
+        checker.define("r".to_string(), MendesType::Ref(Box::new(MendesType::String)), false, make_span(1));
+        checker.mark_moved("r", make_span(2));
+
+        let result = checker.check_use("r", make_span(3));
+        assert!(result.is_ok());
+    }
+
     #[test]
     fn test_borrow_conflict() {
         let mut checker = OwnershipChecker::new();
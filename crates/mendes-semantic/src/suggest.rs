@@ -0,0 +1,49 @@
+//! "Did you mean" suggestions for misspelled names
+
+/// Edit distance between two names (insertions, deletions and substitutions)
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Finds the candidate closest to `name`, if any is close enough to be a typo
+pub fn closest_match<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates.into_iter()
+        .map(|candidate| (levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("Red", "Red"), 0);
+        assert_eq!(levenshtein("Rd", "Red"), 1);
+        assert_eq!(levenshtein("Gren", "Green"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_closest_match() {
+        let variants = ["Red", "Green", "Blue"];
+        assert_eq!(closest_match("Rd", variants), Some("Red"));
+        assert_eq!(closest_match("Gren", variants), Some("Green"));
+        assert_eq!(closest_match("Purple", variants), None);
+    }
+}
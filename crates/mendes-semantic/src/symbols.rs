@@ -2,6 +2,7 @@
 
 use crate::types::MendesType;
 use mendes_error::Span;
+use mendes_parser::Visibility;
 use std::collections::HashMap;
 
 /// Symbol type
@@ -28,6 +29,8 @@ pub enum SymbolKind {
     },
     /// Enum
     Enum {
+        /// Generic type parameters (e.g., ["T"])
+        generic_params: Vec<String>,
         /// Variants: (name, associated types)
         variants: Vec<(String, Vec<MendesType>)>,
     },
@@ -59,6 +62,8 @@ pub struct Symbol {
     pub mutable: bool,
     /// Where it was defined
     pub defined_at: Option<Span>,
+    /// Declared visibility, for rejecting imports of private names
+    pub visibility: Visibility,
 }
 
 impl Symbol {
@@ -69,6 +74,7 @@ impl Symbol {
             ty,
             mutable: false,
             defined_at: Some(span),
+            visibility: Visibility::Private,
         }
     }
 
@@ -79,6 +85,7 @@ impl Symbol {
             ty,
             mutable,
             defined_at: Some(span),
+            visibility: Visibility::Private,
         }
     }
 
@@ -89,8 +96,14 @@ impl Symbol {
             ty,
             mutable: false,
             defined_at: Some(span),
+            visibility: Visibility::Private,
         }
     }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
 }
 
 /// Symbol scope
@@ -209,6 +222,7 @@ mod tests {
             ty: MendesType::Int,
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Private,
         });
 
         assert!(table.lookup("x").is_some());
@@ -226,6 +240,7 @@ mod tests {
             ty: MendesType::Int,
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Private,
         });
 
         assert!(table.lookup("y").is_some());
@@ -249,6 +264,7 @@ mod tests {
             ty: MendesType::Int,
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Private,
         });
 
         table.push_scope();
@@ -260,6 +276,7 @@ mod tests {
             ty: MendesType::String,
             mutable: false,
             defined_at: None,
+            visibility: Visibility::Private,
         });
 
         // In the inner scope, x is String
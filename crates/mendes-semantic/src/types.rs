@@ -23,6 +23,8 @@ pub enum MendesType {
     Bool,
     /// String
     String,
+    /// Time span, from a literal like `30s`
+    Duration,
     /// Named type (struct, enum)
     Named(std::string::String),
     /// Generic type: Result<T, E>, Option<T>
@@ -47,12 +49,14 @@ pub enum MendesType {
     Tuple(Vec<MendesType>),
     /// Range (for iteration)
     Range(Box<MendesType>),
+    /// Trait object: any type implementing the trait
+    Dyn(std::string::String),
 }
 
 impl MendesType {
     /// Checks if the type is primitive (copy by default)
     pub fn is_copy(&self) -> bool {
-        matches!(self, MendesType::Int | MendesType::Float | MendesType::Bool | MendesType::Unit)
+        matches!(self, MendesType::Int | MendesType::Float | MendesType::Bool | MendesType::Duration | MendesType::Unit)
     }
 
     /// Checks if it's a reference
@@ -78,7 +82,10 @@ impl MendesType {
             (MendesType::Float, MendesType::Float) => true,
             (MendesType::Bool, MendesType::Bool) => true,
             (MendesType::String, MendesType::String) => true,
+            (MendesType::Duration, MendesType::Duration) => true,
             (MendesType::Unit, MendesType::Unit) => true,
+            // `()` written as a value is an empty tuple, the same as no value
+            (MendesType::Unit, MendesType::Tuple(elems)) | (MendesType::Tuple(elems), MendesType::Unit) => elems.is_empty(),
             (MendesType::Named(a), MendesType::Named(b)) => a == b,
             (MendesType::Array(a), MendesType::Array(b)) => a.is_compatible_with(b),
             (MendesType::Ref(a), MendesType::Ref(b)) => a.is_compatible_with(b),
@@ -90,6 +97,8 @@ impl MendesType {
                 a.len() == b.len() && a.iter().zip(b).all(|(t1, t2)| t1.is_compatible_with(t2))
             }
             (MendesType::Range(a), MendesType::Range(b)) => a.is_compatible_with(b),
+            (MendesType::Dyn(a), MendesType::Dyn(b)) => a == b,
+            (MendesType::Future(a), MendesType::Future(b)) => a.is_compatible_with(b),
             (MendesType::Function { params: p1, ret: r1 }, MendesType::Function { params: p2, ret: r2 }) => {
                 p1.len() == p2.len() &&
                 p1.iter().zip(p2).all(|(t1, t2)| t1.is_compatible_with(t2)) &&
@@ -133,6 +142,7 @@ impl MendesType {
             mendes_parser::Type::Float => MendesType::Float,
             mendes_parser::Type::Bool => MendesType::Bool,
             mendes_parser::Type::String => MendesType::String,
+            mendes_parser::Type::Named(name) if name == "Duration" => MendesType::Duration,
             mendes_parser::Type::Named(name) => MendesType::Named(name.clone()),
             mendes_parser::Type::Generic { name, args } => MendesType::Generic {
                 name: name.clone(),
@@ -150,10 +160,45 @@ impl MendesType {
                     ret: Box::new(MendesType::from_ast(return_type)),
                 }
             }
+            mendes_parser::Type::Dyn(trait_name) => MendesType::Dyn(trait_name.clone()),
         }
     }
 }
 
+impl MendesType {
+    /// Converts back to an AST type, for passes after checking. Types that
+    /// are not fully known or cannot be written in source give `None`.
+    pub fn to_ast(&self) -> Option<mendes_parser::Type> {
+        use mendes_parser::Type;
+        let all = |types: &[MendesType]| types.iter().map(MendesType::to_ast).collect::<Option<Vec<_>>>();
+        Some(match self {
+            MendesType::Unknown | MendesType::Any | MendesType::Future(_) | MendesType::Range(_) => return None,
+            MendesType::Unit => Type::Tuple(Vec::new()),
+            MendesType::Int => Type::Int,
+            MendesType::Float => Type::Float,
+            MendesType::Bool => Type::Bool,
+            MendesType::String => Type::String,
+            MendesType::Duration => Type::Named("Duration".to_string()),
+            MendesType::Named(name) => Type::Named(name.clone()),
+            // Closures are `Fn<params..., ret>`
+            MendesType::Generic { name, args } if name == "Fn" => {
+                let (ret, params) = args.split_last()?;
+                Type::Function { params: all(params)?, return_type: Box::new(ret.to_ast()?) }
+            }
+            MendesType::Generic { name, args } => Type::Generic { name: name.clone(), args: all(args)? },
+            MendesType::Ref(inner) => Type::Ref(Box::new(inner.to_ast()?)),
+            MendesType::MutRef(inner) => Type::MutRef(Box::new(inner.to_ast()?)),
+            MendesType::Array(inner) => Type::Array(Box::new(inner.to_ast()?)),
+            MendesType::Tuple(types) => Type::Tuple(all(types)?),
+            MendesType::Function { params, ret } => Type::Function {
+                params: all(params)?,
+                return_type: Box::new(ret.to_ast()?),
+            },
+            MendesType::Dyn(trait_name) => Type::Dyn(trait_name.clone()),
+        })
+    }
+}
+
 impl fmt::Display for MendesType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -164,6 +209,7 @@ impl fmt::Display for MendesType {
             MendesType::Float => write!(f, "float"),
             MendesType::Bool => write!(f, "bool"),
             MendesType::String => write!(f, "string"),
+            MendesType::Duration => write!(f, "Duration"),
             MendesType::Named(name) => write!(f, "{}", name),
             MendesType::Generic { name, args } => {
                 write!(f, "{}<", name)?;
@@ -200,6 +246,7 @@ impl fmt::Display for MendesType {
                 write!(f, ")")
             }
             MendesType::Range(inner) => write!(f, "Range<{}>", inner),
+            MendesType::Dyn(trait_name) => write!(f, "dyn {}", trait_name),
         }
     }
 }
@@ -208,18 +255,44 @@ impl fmt::Display for MendesType {
 #[derive(Debug, Clone)]
 pub struct StructDef {
     pub name: String,
+    /// Generic type parameter names
+    pub generic_params: Vec<String>,
     pub fields: Vec<(String, MendesType)>,
-    /// Methods: (name, params, return_type, is_async)
-    pub methods: Vec<(String, Vec<(String, MendesType)>, MendesType, bool)>,
+    /// Fields that a struct literal may omit
+    pub defaults: Vec<String>,
+    /// Inherent methods
+    pub methods: Vec<MethodSig>,
     pub is_copy: bool,
 }
 
+/// Method signature: (name, params, return_type, is_async)
+pub type MethodSig = (String, Vec<(String, MendesType)>, MendesType, bool);
+
+/// Definition of a trait
+#[derive(Debug, Clone)]
+pub struct TraitDef {
+    pub name: String,
+    pub methods: Vec<MethodSig>,
+}
+
 /// Registry of user-defined types
 #[derive(Debug, Default)]
 pub struct TypeRegistry {
     structs: HashMap<String, StructDef>,
+    /// Enums with their generic parameter names
+    enums: HashMap<String, Vec<String>>,
     /// Currently active generic type parameters (e.g., T, U in struct<T, U>)
     generic_params: std::collections::HashSet<String>,
+    /// Traits by name
+    traits: HashMap<String, TraitDef>,
+    /// Implemented traits: (type name, trait name)
+    impls: std::collections::HashSet<(String, String)>,
+    /// Result types of operator trait impls: (type name, trait name)
+    operators: HashMap<(String, String), MendesType>,
+    /// Variant values of enums without data, usable with `as int`
+    discriminants: HashMap<String, Vec<(String, i64)>>,
+    /// Field names of struct variants: (enum name, variant name)
+    variant_fields: HashMap<(String, String), Vec<String>>,
 }
 
 impl TypeRegistry {
@@ -237,6 +310,46 @@ impl TypeRegistry {
         self.structs.get(name)
     }
 
+    /// Registers an enum with its generic parameter names
+    pub fn register_enum(&mut self, name: &str, generic_params: Vec<String>) {
+        self.enums.insert(name.to_string(), generic_params);
+    }
+
+    /// Checks if a name is a registered enum
+    pub fn is_enum(&self, name: &str) -> bool {
+        self.enums.contains_key(name)
+    }
+
+    /// Registers the variant values of an enum without data
+    pub fn register_discriminants(&mut self, name: &str, values: Vec<(String, i64)>) {
+        self.discriminants.insert(name.to_string(), values);
+    }
+
+    /// Variant values of `name`, if it is an enum without data
+    pub fn discriminants(&self, name: &str) -> Option<&[(String, i64)]> {
+        self.discriminants.get(name).map(Vec::as_slice)
+    }
+
+    /// Registers the field names of a struct variant, in declaration order
+    pub fn register_variant_fields(&mut self, enum_name: &str, variant: &str, fields: Vec<String>) {
+        self.variant_fields.insert((enum_name.to_string(), variant.to_string()), fields);
+    }
+
+    /// Field names of the struct variant `enum_name::variant`
+    pub fn variant_fields(&self, enum_name: &str, variant: &str) -> Option<&[String]> {
+        self.variant_fields.get(&(enum_name.to_string(), variant.to_string())).map(Vec::as_slice)
+    }
+
+    /// Returns the number of type parameters a named type declares, if known
+    pub fn generic_arity(&self, name: &str) -> Option<usize> {
+        match name {
+            "Result" | "Map" => Some(2),
+            "Option" | "Set" => Some(1),
+            _ => self.structs.get(name).map(|s| s.generic_params.len())
+                .or_else(|| self.enums.get(name).map(|params| params.len())),
+        }
+    }
+
     /// Registers a generic type parameter (e.g., T in struct Pair<T>)
     pub fn register_generic_param(&mut self, name: &str) {
         self.generic_params.insert(name.to_string());
@@ -252,18 +365,120 @@ impl TypeRegistry {
         self.generic_params.contains(name)
     }
 
+    /// Registers a trait
+    pub fn register_trait(&mut self, def: TraitDef) {
+        self.traits.insert(def.name.clone(), def);
+    }
+
+    /// Looks up a trait by name
+    pub fn get_trait(&self, name: &str) -> Option<&TraitDef> {
+        self.traits.get(name)
+    }
+
+    /// Records that `type_name` implements `trait_name`
+    pub fn register_impl(&mut self, type_name: &str, trait_name: &str) {
+        self.impls.insert((type_name.to_string(), trait_name.to_string()));
+    }
+
+    /// Checks if `type_name` implements `trait_name`
+    pub fn implements(&self, type_name: &str, trait_name: &str) -> bool {
+        self.impls.contains(&(type_name.to_string(), trait_name.to_string()))
+    }
+
+    /// Records that `type_name` overloads an operator trait, producing `result`
+    pub fn register_operator(&mut self, type_name: &str, trait_name: &str, result: MendesType) {
+        self.operators.insert((type_name.to_string(), trait_name.to_string()), result);
+    }
+
+    /// The result type of an operator trait implemented for `type_name`
+    pub fn operator_result(&self, type_name: &str, trait_name: &str) -> Option<&MendesType> {
+        self.operators.get(&(type_name.to_string(), trait_name.to_string()))
+    }
+
+    /// Finds the signature of a trait method implemented for `type_name`
+    pub fn impl_method(&self, type_name: &str, method: &str) -> Option<&MethodSig> {
+        self.traits.values()
+            .filter(|t| self.implements(type_name, &t.name))
+            .find_map(|t| t.methods.iter().find(|(name, ..)| name == method))
+    }
+
+    /// Element type of a user type usable in `for`: one with a
+    /// parameterless `next()` method returning `Option<T>`
+    pub fn iterator_item(&self, type_name: &str) -> Option<MendesType> {
+        let inherent = self.structs.get(type_name)
+            .and_then(|s| s.methods.iter().find(|(name, ..)| name == "next"))
+            .map(|(_, params, ret, _)| (params, ret));
+        let (params, ret) = inherent
+            .or_else(|| self.impl_method(type_name, "next").map(|(_, params, ret, _)| (params, ret)))?;
+        match ret {
+            MendesType::Generic { name, args } if name == "Option" && args.len() == 1 && params.is_empty() => {
+                Some(args[0].clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks if a value of type `from` can be used where `to` is expected,
+    /// allowing implementors to coerce to a `dyn Trait`
+    pub fn coerces_to(&self, from: &MendesType, to: &MendesType) -> bool {
+        match (from, to) {
+            (MendesType::Named(type_name), MendesType::Dyn(trait_name)) => self.implements(type_name, trait_name),
+            // A `{id:uuid}` path parameter holds the validated text of the UUID
+            (MendesType::Named(type_name), MendesType::String) if type_name == "Uuid" => true,
+            (MendesType::Array(a), MendesType::Array(b))
+            | (MendesType::Ref(a), MendesType::Ref(b))
+            | (MendesType::MutRef(a), MendesType::MutRef(b)) => self.coerces_to(a, b),
+            _ => to.is_compatible_with(from),
+        }
+    }
+
+    /// Finds a part of `ty` that cannot be hashed, looking through struct
+    /// fields, so `ty` can't be a map key or set element
+    pub fn unhashable_part(&self, ty: &MendesType) -> Option<MendesType> {
+        self.unhashable_part_inner(ty, &mut Vec::new())
+    }
+
+    fn unhashable_part_inner(&self, ty: &MendesType, visiting: &mut Vec<String>) -> Option<MendesType> {
+        match ty {
+            MendesType::Int | MendesType::Bool | MendesType::String
+            | MendesType::Unit | MendesType::Unknown => None,
+            MendesType::Array(inner) | MendesType::Ref(inner) => self.unhashable_part_inner(inner, visiting),
+            MendesType::Tuple(types) => types.iter().find_map(|t| self.unhashable_part_inner(t, visiting)),
+            MendesType::Generic { name, args } if name == "Option" => {
+                args.iter().find_map(|t| self.unhashable_part_inner(t, visiting))
+            }
+            MendesType::Named(name) if self.generic_params.contains(name) => None,
+            MendesType::Named(name) => {
+                let def = match self.structs.get(name) {
+                    Some(def) => def,
+                    None => return Some(ty.clone()),
+                };
+                if visiting.contains(name) {
+                    return None;
+                }
+                visiting.push(name.clone());
+                let bad = def.fields.iter().find_map(|(_, field)| self.unhashable_part_inner(field, visiting));
+                visiting.pop();
+                bad
+            }
+            _ => Some(ty.clone()),
+        }
+    }
+
     /// Checks if a type exists
     pub fn type_exists(&self, ty: &MendesType) -> bool {
         match ty {
             MendesType::Int | MendesType::Float | MendesType::Bool |
-            MendesType::String | MendesType::Unit | MendesType::Unknown | MendesType::Any => true,
+            MendesType::String | MendesType::Duration | MendesType::Unit | MendesType::Unknown | MendesType::Any => true,
             MendesType::Named(name) => {
                 self.structs.contains_key(name) ||
+                self.enums.contains_key(name) ||
                 self.generic_params.contains(name) ||
                 name == "HttpError" || name == "Response" // Built-in types
             },
             MendesType::Generic { name, args } => {
-                (name == "Result" || name == "Option") &&
+                (matches!(name.as_str(), "Result" | "Option" | "Map" | "Set") ||
+                    self.structs.contains_key(name) || self.enums.contains_key(name)) &&
                 args.iter().all(|a| self.type_exists(a))
             }
             MendesType::Ref(inner) | MendesType::MutRef(inner) | MendesType::Array(inner) => {
@@ -275,6 +490,7 @@ impl TypeRegistry {
             MendesType::Future(inner) => self.type_exists(inner),
             MendesType::Tuple(types) => types.iter().all(|t| self.type_exists(t)),
             MendesType::Range(inner) => self.type_exists(inner),
+            MendesType::Dyn(trait_name) => self.traits.contains_key(trait_name),
         }
     }
 }
@@ -288,6 +504,9 @@ mod tests {
         assert!(MendesType::Int.is_compatible_with(&MendesType::Int));
         assert!(!MendesType::Int.is_compatible_with(&MendesType::String));
         assert!(MendesType::Unknown.is_compatible_with(&MendesType::Int));
+        assert!(MendesType::Unit.is_compatible_with(&MendesType::Tuple(vec![])));
+        assert!(MendesType::Tuple(vec![]).is_compatible_with(&MendesType::Unit));
+        assert!(!MendesType::Unit.is_compatible_with(&MendesType::Tuple(vec![MendesType::Int])));
     }
 
     #[test]
@@ -303,6 +522,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_to_ast() {
+        let map = MendesType::Generic {
+            name: "Map".to_string(),
+            args: vec![MendesType::String, MendesType::Array(Box::new(MendesType::Int))],
+        };
+        assert_eq!(MendesType::from_ast(&map.to_ast().unwrap()), map);
+
+        let closure = MendesType::Generic { name: "Fn".to_string(), args: vec![MendesType::Int, MendesType::Bool] };
+        assert!(matches!(closure.to_ast(), Some(mendes_parser::Type::Function { params, .. }) if params.len() == 1));
+
+        let partial = MendesType::Array(Box::new(MendesType::Unknown));
+        assert!(partial.to_ast().is_none());
+    }
+
     #[test]
     fn test_is_copy() {
         assert!(MendesType::Int.is_copy());
@@ -5,10 +5,14 @@
 
 use mendes_codegen::{CodeGen, RustBackend};
 use mendes_error::Diagnostics;
-use mendes_ir::lower::lower_program;
+use mendes_ir::lower::{lower_program, lower_program_with_options, LowerOptions};
 use mendes_lexer::Lexer;
 use mendes_parser::parse;
 use mendes_semantic::{analyze, SemanticContext};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// Result of compiling a Mendes source file
 #[derive(Debug)]
@@ -25,6 +29,11 @@ pub struct CompileResult {
 
 /// Compiles Mendes source code through the full pipeline
 pub fn compile(source: &str) -> CompileResult {
+    compile_with_opt_level(source, 0)
+}
+
+/// Compiles Mendes source code with the Rust backend at the given optimization level
+pub fn compile_with_opt_level(source: &str, opt_level: u8) -> CompileResult {
     // Phase 1: Lexing
     let mut lexer = Lexer::new(source, 0);
     let tokens = lexer.tokenize();
@@ -53,11 +62,12 @@ pub fn compile(source: &str) -> CompileResult {
     }
 
     // Phase 4: IR Generation
-    let ir_module = lower_program(&program);
+    let options = LowerOptions { expr_types: ctx.written_expr_types(), ..Default::default() };
+    let ir_module = lower_program_with_options(&program, &options);
     let ir_debug = format!("{}", ir_module);
 
     // Phase 5: Code Generation (Rust backend)
-    let backend = RustBackend::new();
+    let backend = RustBackend::new().with_opt_level(opt_level);
     let rust_code = backend.generate(&ir_module);
 
     CompileResult {
@@ -123,6 +133,62 @@ pub fn assert_ir_contains(source: &str, expected: &str) {
     }
 }
 
+/// Asserts that source code compiles and that `cargo check` accepts the Rust
+/// output against the runtime. The scratch crates share one target
+/// directory, so the runtime's dependencies are only checked once
+pub fn assert_rust_builds(source: &str) {
+    static NEXT_CRATE: AtomicUsize = AtomicUsize::new(0);
+
+    let result = compile(source);
+    if !result.success {
+        panic!(
+            "Expected source to compile, but got errors:\n{:?}",
+            result.diagnostics
+        );
+    }
+    let rust_code = result.rust_code.unwrap();
+
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let dir = std::env::temp_dir().join(format!(
+        "mendes_check_{}_{}",
+        std::process::id(),
+        NEXT_CRATE.fetch_add(1, Ordering::SeqCst)
+    ));
+    fs::create_dir_all(dir.join("src")).unwrap();
+    let manifest = format!(
+        r#"[package]
+name = "generated"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+mendes-runtime = {{ path = "{}" }}
+tokio = {{ version = "1", features = ["full"] }}
+serde = {{ version = "1", features = ["derive"] }}
+
+[workspace]
+"#,
+        workspace.join("crates/mendes-runtime").display()
+    );
+    fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+    fs::copy(workspace.join("Cargo.lock"), dir.join("Cargo.lock")).unwrap();
+    fs::write(dir.join("src/main.rs"), &rust_code).unwrap();
+
+    let output = Command::new(env!("CARGO"))
+        .args(["check", "--offline", "--quiet"])
+        .current_dir(&dir)
+        .env("CARGO_TARGET_DIR", workspace.join("target/generated-check"))
+        .output()
+        .expect("cargo runs");
+    let _ = fs::remove_dir_all(&dir);
+    if !output.status.success() {
+        panic!(
+            "Expected the Rust output to build, but cargo check failed:\n{}\n\nGenerated code:\n{}",
+            String::from_utf8_lossy(&output.stderr), rust_code
+        );
+    }
+}
+
 #[cfg(test)]
 mod pipeline_tests {
     use super::*;
@@ -539,6 +605,1420 @@ server:
         );
     }
 
+    #[test]
+    fn test_codegen_async_block() {
+        assert_rust_contains(
+            r#"
+fn compute() -> int async:
+    let task = async:
+        return 42
+    return await task
+"#,
+            "async move {",
+        );
+    }
+
+    #[test]
+    fn test_codegen_async_block_captures_locals() {
+        let source = r#"
+fn compute(base: int) -> int async:
+    let offset = 2
+    let task = async:
+        return base + offset
+    return await task
+"#;
+        let rust = compile(source).rust_code.unwrap();
+        assert!(rust.contains("fn __async_block_0(base: i64, offset: i64) -> std::pin::Pin<Box<dyn std::future::Future<Output = i64> + Send>> {"), "{}", rust);
+        assert!(rust.contains("= __async_block_0(base, __t"), "{}", rust);
+        assert!(rust.contains("let mut task: std::pin::Pin<Box<dyn std::future::Future<Output = i64> + Send>>;"), "{}", rust);
+        // The future is moved into `.await`, never cloned
+        assert!(!rust.contains("task.clone()"), "{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_spawn() {
+        let source = r#"
+fn fetch() -> int async:
+    return 1
+
+fn compute() -> int async:
+    let task = spawn(fetch())
+    return join(task)
+"#;
+        assert_rust_contains(source, "let mut task: tokio::task::JoinHandle<i64>;");
+        assert_rust_builds(source);
+    }
+
+    #[test]
+    fn test_codegen_generic_enum() {
+        let source = r#"
+enum Maybe<T>:
+    Just(T)
+    Empty
+
+fn make() -> Maybe<int>:
+    return Just(5)
+"#;
+        assert_rust_contains(source, "pub enum Maybe<T>");
+        assert_rust_contains(source, "Maybe::Just(5)");
+    }
+
+    #[test]
+    fn test_codegen_route_group() {
+        let source = r#"
+middleware logger:
+    let x = 1
+
+group /api/v1:
+    use logger
+
+    api GET /users:
+        return int
+        return 1
+
+    api GET /:
+        return int
+        return 0
+"#;
+        assert_rust_contains(source, "router.get(\"/api/v1/users\"");
+        assert_rust_contains(source, "router.get(\"/api/v1\"");
+        assert_rust_contains(source, "// Middleware: logger");
+    }
+
+    #[test]
+    fn test_codegen_server_max_body() {
+        let source = r#"
+server:
+    host "0.0.0.0"
+    port 8080
+    max_body 1048576
+
+api GET /health:
+    return string
+    return "ok"
+"#;
+        assert_rust_contains(source, ".max_body_size(1048576)");
+    }
+
+    #[test]
+    fn test_codegen_destructured_param() {
+        let source = r#"
+fn dist((x, y): (int, int)) -> int:
+    return x * x + y * y
+
+fn hypot_sq() -> int:
+    return dist((3, 4))
+"#;
+        assert_rust_contains(source, "fn dist(__arg0: (i64, i64)) -> i64");
+        assert_rust_contains(source, "let __t0 = __arg0.0.clone();\n    x = __t0;");
+        assert_rust_builds(source);
+    }
+
+    #[test]
+    fn test_codegen_duration_literals() {
+        let source = r#"
+server:
+    port 8080
+    timeout 30s
+
+fn backoff() -> Duration:
+    return 250ms
+"#;
+        assert_rust_contains(source, ".request_timeout(std::time::Duration::from_millis(30000))");
+        assert_rust_contains(source, "fn backoff() -> std::time::Duration");
+        assert_rust_contains(source, "std::time::Duration::from_millis(250 as u64)");
+
+        // A db timeout bounds how long the pool waits for a connection
+        let source = r#"
+db postgres main:
+    url "postgres://localhost/app"
+    pool 20
+    timeout 5s
+
+db sqlite local:
+    url "sqlite://./app.db"
+"#;
+        assert_rust_contains(source, "main: match PostgresPool::connect(\"main\", \"postgres://localhost/app\", 20, Some(std::time::Duration::from_millis(5000))).await {");
+        assert_rust_contains(source, "local: match SqlitePool::connect(\"local\", \"sqlite://./app.db\", 10, None).await {");
+    }
+
+    #[test]
+    fn test_codegen_static_counter() {
+        let source = r#"
+static mut hits: int = 0
+static mut last_path: string = "/"
+
+server:
+    port 8080
+
+api GET /hits:
+    return int
+    hits += 1
+    last_path = "/hits"
+    let mut total = hits
+    total *= 2
+    return total
+"#;
+        assert_rust_contains(source, "static __static_hits: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(0);");
+        assert_rust_contains(source, "__static_hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);");
+        assert_rust_contains(source, "__static_hits.load(std::sync::atomic::Ordering::SeqCst)");
+        assert_rust_contains(source, "std::sync::LazyLock<std::sync::Mutex<MendesString>>");
+        assert_rust_contains(source, "*__static_last_path.lock().unwrap() = ");
+        // The compound assignment to a local is stored back
+        assert_rust_contains(source, "let __t2 = __t1 * 2;\n    total = __t2;");
+    }
+
+    #[test]
+    fn test_emit_json() {
+        let source = r#"
+fn double(n: int) -> int:
+    if n > 10:
+        return n
+    return n * 2
+"#;
+        let (program, diags) = parse(Lexer::new(source, 0).tokenize());
+        assert!(!diags.has_errors());
+        let json = mendes_codegen::JsonBackend::new().generate(&lower_program(&program));
+        let json: serde_json::Value = serde_json::from_str(&json).expect("valid JSON");
+
+        let function = &json["functions"][0];
+        assert_eq!(function["name"], "double");
+        let blocks = function["blocks"].as_array().unwrap();
+        let labels: Vec<_> = blocks.iter().map(|b| b["label"].as_str().unwrap()).collect();
+        // The branch targets name blocks of the same function
+        let branch = blocks[0]["instructions"].as_array().unwrap().iter()
+            .find(|inst| inst["op"] == "cond_branch")
+            .expect("the `if` branches");
+        assert!(labels.contains(&branch["then_label"].as_str().unwrap()));
+        assert!(labels.contains(&branch["else_label"].as_str().unwrap()));
+    }
+
+    #[test]
+    fn test_emit_graph() {
+        let source = r#"
+middleware auth:
+    return
+
+fn load(n: int) -> int:
+    return double(n)
+
+fn double(n: int) -> int:
+    return n * 2
+
+api GET /users:
+    use auth
+    return int
+    return load(3)
+"#;
+        let (program, diags) = parse(Lexer::new(source, 0).tokenize());
+        assert!(!diags.has_errors());
+        let dot = mendes_codegen::GraphBackend::new().generate(&lower_program(&program));
+
+        assert!(dot.contains("\"load\" -> \"double\";"));
+        assert!(dot.contains("\"GET /users\" -> \"__http_get__users0\" [label=\"handler\"];"));
+        assert!(dot.contains("\"GET /users\" -> \"__middleware_auth\" [style=dashed, label=\"use\"];"));
+        assert!(dot.contains("\"__http_get__users0\" -> \"load\";"));
+    }
+
+    #[test]
+    fn test_codegen_result_main() {
+        let source = r#"
+fn parse_port(s: string) -> Result<int, string>:
+    if s == "":
+        return Err("empty port")
+    return Ok(8080)
+
+fn main() -> Result<(), string>:
+    let port = parse_port("8080")?
+    println(port)
+    return Ok(())
+"#;
+        assert_rust_contains(source, "fn __mendes_main() -> MendesResult<(), MendesString> {");
+        assert_rust_contains(source, "MendesResult::Err(__t0.err().unwrap().into());");
+        assert_rust_contains(source, "async fn main() -> Result<(), Box<dyn std::error::Error>> {");
+        assert_rust_contains(source, "Result::from(__mendes_main()).map_err(|e| e.to_string())?;");
+        let rust = compile(source).rust_code.unwrap();
+        assert!(!rust.contains("No server configuration found"), "{}", rust);
+
+        // With a server, `main` runs before it starts
+        let source = r#"
+server:
+    port 8080
+
+fn main():
+    println("booting")
+
+api GET /health:
+    return int
+    return 1
+"#;
+        let rust = compile(source).rust_code.unwrap();
+        let setup = rust.find("    __mendes_main();").expect("main is called");
+        let serve = rust.find("Server::new(").expect("server starts");
+        assert!(setup < serve, "{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_string_slicing() {
+        let source = r#"
+fn pieces(word: string) -> string:
+    let first = word[0]
+    let last = word[-1]
+    return first + word[1..3] + word[2..] + word[0..=1] + last
+"#;
+        assert_rust_contains(source, ".char_at(0);");
+        assert_rust_contains(source, ".char_at(-1);");
+        assert_rust_contains(source, ".slice(1, Some(3));");
+        assert_rust_contains(source, ".slice(2, None);");
+        assert_rust_contains(source, ".slice(0, Some(2));");
+        let rust = compile(source).rust_code.unwrap();
+        assert!(!rust.contains("as usize]"), "strings must not be indexed by byte:\n{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_string_field_slicing() {
+        let source = r#"
+struct User:
+    name: string
+
+fn initials(u: User) -> string:
+    return u.name[0] + u.name[1..2]
+"#;
+        assert_rust_contains(source, ".char_at(0);");
+        assert_rust_contains(source, ".slice(1, Some(2));");
+        let rust = compile(source).rust_code.unwrap();
+        assert!(!rust.contains("as usize]") && !rust.contains(".start ="), "{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_optional_chaining() {
+        let source = r#"
+struct Address:
+    city: string
+
+struct User:
+    name: string
+    address: Option<Address>
+
+fn city_of(user: Option<User>) -> Option<string>:
+    return user?.address?.city
+
+fn name_of(user: Option<User>) -> Option<string>:
+    return user?.name
+"#;
+        // An optional field is flattened instead of nested
+        assert_rust_contains(source, "user.as_ref().and_then(|v| v.address.clone());");
+        assert_rust_contains(source, ".as_ref().map(|v| v.city.clone());");
+        assert_rust_contains(source, "user.as_ref().map(|v| v.name.clone());");
+    }
+
+    #[test]
+    fn test_codegen_optional_chaining_on_result_propagates() {
+        let source = r#"
+struct Config:
+    port: int
+
+fn load() -> Result<Config, string>:
+    return Ok(Config { port: 8080 })
+
+fn port() -> Result<int, string>:
+    let p: int = load()?.port
+    return Ok(p)
+"#;
+        let rust = compile(source).rust_code.unwrap();
+        assert!(!rust.contains("as_ref().map("), "{}", rust);
+        assert_rust_contains(source, ".is_ok();");
+        assert_rust_contains(source, "MendesResult::Err(");
+        assert_rust_contains(source, ".port;");
+    }
+
+    #[test]
+    fn test_codegen_array_reductions() {
+        let source = r#"
+fn main():
+    let nums = [3, 1, 4]
+    let words = ["a", "bb"]
+    let total = nums.fold(0, |acc, x| acc + x)
+    let text = words.fold("", |acc, w| acc + w)
+    let low = nums.min_by(|a, b| a - b)
+    let product = nums.reduce(|a, b| a * b)
+"#;
+        assert_rust_contains(source, ".iter().cloned().fold(0, __closure_");
+        assert_rust_contains(source, ".iter().cloned().min_by(|a, b| __closure_");
+        assert_rust_contains(source, ".iter().cloned().reduce(__closure_");
+        // Untyped closure parameters take the accumulator and element types
+        assert_rust_contains(source, "(acc: i64, x: i64) -> i64");
+        assert_rust_contains(source, "(acc: MendesString, w: MendesString) -> MendesString");
+        assert_rust_contains(source, "let mut low: MendesOption<i64>;");
+    }
+
+    #[test]
+    fn test_codegen_array_reductions_on_params() {
+        let source = r#"
+struct Item:
+    price: int
+
+fn total(ns: [int]) -> int:
+    return ns.fold(0, |acc, x| acc + x)
+
+fn cheapest(items: [Item]) -> Option<Item>:
+    return items.min_by(|a, b| a.price - b.price)
+"#;
+        let rust = compile(source).rust_code.unwrap();
+        assert!(!rust.contains("ns.fold("), "{}", rust);
+        assert!(!rust.contains("items.min_by("), "{}", rust);
+        assert_rust_contains(source, ".iter().cloned().fold(0, __closure_");
+        assert_rust_contains(source, ".iter().cloned().min_by(|a, b| __closure_");
+        assert_rust_contains(source, "(acc: i64, x: i64) -> i64");
+        assert_rust_contains(source, "(a: Item, b: Item) -> i64");
+    }
+
+    #[test]
+    fn test_codegen_error_enum() {
+        let source = r#"
+enum AppError:
+    NotFound
+    Invalid(string)
+
+fn find(id: int) -> Result<int, AppError>:
+    if id == 0:
+        return Err(AppError::NotFound)
+    return Ok(id)
+
+fn main() -> Result<(), AppError>:
+    let n = find(1)?
+    return Ok(())
+"#;
+        assert_rust_contains(source, "impl std::fmt::Display for AppError {");
+        assert_rust_contains(source, "impl std::error::Error for AppError {}");
+        assert_rust_contains(source, "impl From<AppError> for mendes_runtime::HttpError {");
+    }
+
+    #[test]
+    fn test_codegen_const() {
+        let source = r#"
+const MAX: int = 100
+const LIMIT: int = MAX / 4
+const ENABLED: bool = true
+
+fn clamp(n: int) -> int:
+    if ENABLED and n > LIMIT:
+        return LIMIT
+    return n + MAX
+"#;
+        assert_rust_contains(source, "const __const_MAX: i64 = 100;");
+        assert_rust_contains(source, "const __const_LIMIT: i64 = 25;");
+        assert_rust_contains(source, "const __const_ENABLED: bool = true;");
+        assert_rust_contains(source, "n + __const_MAX");
+
+        // A local or parameter may reuse a const's name
+        let source = r#"
+const MAX: int = 100
+
+fn clamp(LIMIT: int) -> int:
+    let MAX = LIMIT
+    return MAX
+
+fn scale(n: int) -> int:
+    return n * MAX
+
+const LIMIT: int = 10
+"#;
+        assert_rust_contains(source, "MAX = LIMIT;");
+        assert_rust_contains(source, "n * __const_MAX");
+        assert_rust_builds(source);
+    }
+
+    #[test]
+    fn test_codegen_closure_return() {
+        let source = r#"
+fn label(n: int) -> int:
+    let sign = |x: int|:
+        if x < 0:
+            return "negative"
+        return "positive"
+    let text = sign(n)
+    return n
+"#;
+        // The closure's `return`s belong to its own function, typed from them
+        assert_rust_contains(source, "fn __closure_0(x: i64) -> MendesString {");
+        assert_rust_contains(source, "let mut sign: fn(i64) -> MendesString;");
+        assert_rust_contains(source, "let mut text: MendesString;");
+        assert_rust_contains(source, "fn label(n: i64) -> i64 {");
+    }
+
+    #[test]
+    fn test_codegen_derive_attribute() {
+        let source = r#"
+@derive(Copy, Ord)
+struct Point:
+    x: int
+    y: int
+
+@derive(Copy, Eq)
+struct Reading:
+    label: string
+    value: float
+
+struct Plain:
+    x: int
+"#;
+        // Requested derives appear even though nothing in the program needs
+        // them, with the traits they depend on; `Copy` and `Eq` are dropped
+        // where the fields rule them out
+        assert_rust_contains(source, "#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]\npub struct Point {");
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default)]\npub struct Reading {");
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default)]\npub struct Plain {");
+    }
+
+    #[test]
+    fn test_codegen_if_let() {
+        let source = r#"
+enum Shape:
+    Circle(int)
+    Rect(int, int)
+
+fn width(s: Shape) -> int:
+    if let Shape::Rect(w, _) = s:
+        return w
+    return 0
+"#;
+        assert_rust_contains(source, "let __t0 = matches!(s, Shape::Rect { .. });\n    if __t0 {");
+        assert_rust_contains(source, "match &s { Shape::Rect { 0: __field, .. } => __field.clone(), _ => unreachable!() };");
+    }
+
+    #[test]
+    fn test_codegen_match_unqualified_struct_variant() {
+        let source = r#"
+enum Shape:
+    Circle(int)
+    Rect { w: int, h: int }
+
+fn width(s: Shape) -> int:
+    match s:
+        Circle(r):
+            return r
+        Rect { w, h }:
+            return w
+"#;
+        assert_rust_contains(source, "matches!(s, Shape::Rect { .. })");
+        assert_rust_contains(source, "match &s { Shape::Rect { w: __field, .. } => __field.clone(), _ => unreachable!() };");
+    }
+
+    #[test]
+    fn test_codegen_while_let() {
+        let source = r#"
+enum Step:
+    Next(int)
+    Done
+
+fn step(n: int) -> Step:
+    return Step::Done
+
+fn main():
+    let mut n = 0
+    while let Step::Next(v) = step(n):
+        n = n + v
+"#;
+        // The value is matched again on every iteration
+        assert_rust_contains(source, "loop {\n        let __t1 = n.clone();\n        let __t2 = step(__t1);\n        let __t3 = matches!(__t2, Step::Next { .. });\n        if !__t3 {\n            break;\n        }");
+    }
+
+    #[test]
+    fn test_codegen_field_defaults() {
+        let source = r#"
+struct Config:
+    port: int = 8080
+    debug: bool
+
+fn main():
+    let c = Config { debug: true }
+    println(c.port)
+"#;
+        // The omitted field is set from its default after the given ones
+        assert_rust_contains(source, "__t0.debug = true;\n    __t0.port = 8080;");
+    }
+
+    #[test]
+    fn test_codegen_hint_attributes() {
+        let source = r#"
+@inline
+fn square(x: int) -> int:
+    return x * x
+
+@cold
+@inline(never)
+fn fail() -> int:
+    return 0
+"#;
+        assert_rust_contains(source, "#[inline]\nfn square(x: i64) -> i64 {");
+        assert_rust_contains(source, "#[cold]\n#[inline(never)]\nfn fail() -> i64 {");
+    }
+
+    #[test]
+    fn test_codegen_range_to_array() {
+        let source = r#"
+fn numbers(n: int) -> int:
+    let xs = (0..n).to_array()
+    let ys = (1..=n).collect()
+    return 0
+"#;
+        assert_rust_contains(source, "(0..n).collect::<MendesArray<_>>()");
+        assert_rust_contains(source, "(1..=n).collect::<MendesArray<_>>()");
+    }
+
+    #[test]
+    fn test_codegen_range_local_to_array() {
+        let source = r#"
+fn numbers(n: int) -> int:
+    let r = 0..n
+    let xs = r.to_array()
+    let s = 1..=n
+    let ys = s.collect()
+    return 0
+"#;
+        assert_rust_contains(source, "let mut r: std::ops::Range<i64>;");
+        assert_rust_contains(source, "let mut s: std::ops::Range<i64>;");
+        // `1..=n` is kept as `1..n + 1`, so both locals share a type
+        assert_rust_contains(source, " = 1..n + 1;");
+        assert_rust_contains(source, ".collect::<MendesArray<_>>();");
+        let rust = compile(source).rust_code.unwrap();
+        assert!(!rust.contains(".to_array()") && !rust.contains(".start ="), "{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_dyn_trait_array() {
+        let source = r#"
+trait Shape:
+    fn area(&self) -> int
+
+struct Circle:
+    r: int
+
+struct Square:
+    side: int
+
+impl Shape for Circle:
+    fn area(&self) -> int:
+        return self.r * self.r * 3
+
+impl Shape for Square:
+    fn area(&self) -> int:
+        return self.side * self.side
+
+fn total_area() -> int:
+    let shapes: [dyn Shape] = [Circle { r: 1 }, Square { side: 2 }]
+    let mut total = 0
+    for shape in shapes:
+        total = total + shape.area()
+    return total
+"#;
+        assert_rust_contains(source, "Vec<Box<dyn Shape>>");
+        assert_rust_contains(source, "= Box::new(__t");
+        assert_rust_contains(source, ".area();");
+    }
+
+    #[test]
+    fn test_codegen_dyn_trait_coercion_sites() {
+        let source = r#"
+trait Shape:
+    fn area(&self) -> int
+
+struct Circle:
+    r: int
+
+impl Shape for Circle:
+    fn area(&self) -> int:
+        return self.r * self.r * 3
+
+fn measure(s: dyn Shape) -> int:
+    return s.area()
+
+fn unit() -> dyn Shape:
+    return Circle { r: 1 }
+
+fn total() -> int:
+    let c = Circle { r: 2 }
+    let mut s: dyn Shape = unit()
+    s = Circle { r: 3 }
+    return measure(c) + measure(s)
+"#;
+        let rust = compile(source).rust_code.unwrap();
+        // `measure(c)`, the assignment and the return each box the struct
+        assert_eq!(rust.matches("Box::new(").count(), 3, "{}", rust);
+        assert!(rust.contains("let mut s: Box<dyn Shape>;"), "{}", rust);
+        // A trait object is moved out of its local instead of cloned
+        assert!(!rust.contains("s.clone()"), "{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_unchecked_indexing_only_when_proven() {
+        let source = r#"
+fn sum(items: [int], k: int) -> int:
+    let fixed = [1, 2, 3]
+    let mut total = fixed[2] + fixed[3] + items[k]
+    for i in 0..items.len():
+        total = total + items[i]
+    return total
+"#;
+        let optimized = compile_with_opt_level(source, 3).rust_code.unwrap();
+        assert_eq!(optimized.matches(".get_unchecked(").count(), 2, "{}", optimized);
+
+        let default = compile(source).rust_code.unwrap();
+        assert!(!default.contains("get_unchecked"), "{}", default);
+
+        let mutated = r#"
+fn drain(items: [int]) -> int:
+    let mut total = 0
+    for i in 0..items.len():
+        items.pop()
+        total = total + items[i]
+    return total
+"#;
+        let optimized = compile_with_opt_level(mutated, 3).rust_code.unwrap();
+        assert!(!optimized.contains("get_unchecked"), "{}", optimized);
+
+        // A match arm or closure parameter named like the index rebinds it
+        let shadowed = r#"
+fn arm(items: [int], x: int) -> int:
+    let mut total = 0
+    for i in 0..items.len():
+        match x:
+            i:
+                total = total + items[i]
+    return total
+
+fn closure(items: [int]) -> int:
+    let mut total = 0
+    for i in 0..items.len():
+        let f = |i| i * 2
+        total = total + items[i]
+    return total
+"#;
+        let optimized = compile_with_opt_level(shadowed, 3).rust_code.unwrap();
+        assert!(!optimized.contains("get_unchecked"), "{}", optimized);
+    }
+
+    #[test]
+    fn test_codegen_bounds_check_kept_for_scoped_arrays() {
+        // The three-element `xs` only exists inside the branch, so after it
+        // `xs[2]` indexes the one-element array and stays checked
+        let source = r#"
+fn pick(flag: bool) -> int:
+    let xs = [1]
+    if flag:
+        let xs = [1, 2, 3]
+        print(xs[0])
+    return xs[2]
+"#;
+        let optimized = compile_with_opt_level(source, 3).rust_code.unwrap();
+        assert_eq!(optimized.matches(".get_unchecked(").count(), 1, "{}", optimized);
+        assert!(optimized.contains("[2 as usize];"), "{}", optimized);
+
+        // A loop variable or pattern binding shadows the array for its body
+        let source = r#"
+fn shadowed(rows: [[int]]) -> int:
+    let row = [1, 2, 3]
+    let mut total = 0
+    for row in rows:
+        total = total + row[2]
+    return total
+"#;
+        let optimized = compile_with_opt_level(source, 3).rust_code.unwrap();
+        assert!(!optimized.contains("get_unchecked"), "{}", optimized);
+    }
+
+    #[test]
+    fn test_codegen_parse_with_inferred_target() {
+        let source = r#"
+fn to_int(s: string) -> Result<int, string>:
+    let n: int = parse(s)?
+    return Ok(n)
+
+fn to_float(s: string) -> Result<float, string>:
+    return parse(s)
+"#;
+        assert_rust_contains(source, "s.as_str().parse::<i64>().map_err(");
+        assert_rust_contains(source, "s.as_str().parse::<f64>().map_err(");
+    }
+
+    #[test]
+    fn test_codegen_user_fn_shadows_parse() {
+        let source = r#"
+fn parse(s: string) -> Result<int, string>:
+    return Ok(len(s))
+
+fn size(s: string) -> Result<int, string>:
+    return parse(s)
+"#;
+        assert_rust_contains(source, "= parse(");
+        let rust = compile(source).rust_code.unwrap();
+        assert!(!rust.contains(".parse::<"), "{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_stream_response() {
+        let source = r#"
+api GET /download:
+    return Response
+    return stream("data/big.bin")
+"#;
+        assert_rust_contains(source, "-> Response {");
+        assert_rust_contains(source, "Response::stream_file(__str_0().as_str())");
+
+        // A user function named `stream` is called, not the builtin
+        let source = r#"
+fn stream(n: int) -> int:
+    return n * 2
+
+fn main():
+    print(stream(4))
+"#;
+        assert_rust_contains(source, "= stream(4);");
+        let rust = compile(source).rust_code.unwrap();
+        assert!(!rust.contains("Response::stream_file"), "{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_tuple_return_destructuring() {
+        let source = r#"
+fn minmax(xs: [int]) -> (int, int):
+    let lo = xs[0]
+    let hi = xs[xs.len() - 1]
+    return (lo, hi)
+
+fn spread(xs: [int]) -> int:
+    let (lo, hi) = minmax(xs)
+    return hi - lo
+"#;
+        assert_rust_contains(source, "-> (i64, i64) {");
+        assert_rust_contains(source, "let __t6 = (__t4, __t5,);");
+        assert_rust_contains(source, "let __t0 = minmax(xs);");
+        assert_rust_contains(source, "let __t1 = __t0.0.clone();");
+        assert_rust_contains(source, "let __t2 = __t0.1.clone();");
+    }
+
+    #[test]
+    fn test_codegen_for_over_user_iterator() {
+        let source = r#"
+struct Counter:
+    current: int
+    limit: int
+
+    fn next(&mut self) -> Option<int>:
+        if self.current < self.limit:
+            self.current = self.current + 1
+            return Some(self.current)
+        return None
+
+fn total(limit: int) -> int:
+    let mut sum = 0
+    let counter = Counter { current: 0, limit: limit }
+    for n in counter:
+        sum = sum + n
+    return sum
+"#;
+        assert_rust_contains(source, "let mut __iter_1: Counter;");
+        assert_rust_contains(source, "__iter_1 = __t2;");
+        assert_rust_contains(source, "while let MendesOption::Some(__t3) = __iter_1.next() {");
+        assert_rust_contains(source, "n = __t3;");
+
+        // The loop type-checks against an iterator implemented through a trait
+        let source = r#"
+struct Counter:
+    current: int
+    limit: int
+
+trait Countdown:
+    fn next(&mut self) -> Option<int>
+
+impl Countdown for Counter:
+    fn next(&mut self) -> Option<int>:
+        if self.current < self.limit:
+            return Some(self.current)
+        return None
+
+fn total(limit: int) -> int:
+    let mut sum = 0
+    let counter = Counter { current: 0, limit: limit }
+    for n in counter:
+        sum = sum + n
+    return sum
+"#;
+        assert_rust_contains(source, "let __t5 = MendesOption::None;");
+        assert_rust_builds(source);
+    }
+
+    #[test]
+    fn test_codegen_negative_index_reads_from_end() {
+        let source = r#"
+fn last(xs: [int]) -> int:
+    return xs[-1]
+"#;
+        assert_rust_contains(source, "let __t0 = xs.len() as i64;");
+        assert_rust_contains(source, "let __t1 = __t0 - 1;");
+        assert_rust_contains(source, "let __t2 = xs[__t1 as usize];");
+
+        // A literal array of known length proves `-3` in bounds
+        let source = r#"
+fn first() -> int:
+    let ys = [1, 2, 3]
+    return ys[-3]
+"#;
+        let optimized = compile_with_opt_level(source, 3).rust_code.unwrap();
+        assert!(optimized.contains(".get_unchecked("), "{}", optimized);
+    }
+
+    #[test]
+    fn test_codegen_struct_set_element_derives_hash() {
+        let source = r#"
+struct Point:
+    x: int
+    y: int
+
+struct Label:
+    text: string
+
+fn count(points: Set<Point>) -> int:
+    return 0
+"#;
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]\npub struct Point {");
+        assert_rust_contains(source, "points: std::collections::BTreeSet<Point>");
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default)]\npub struct Label {");
+    }
+
+    #[test]
+    fn test_codegen_nested_collection_types() {
+        let source = r#"
+struct Grid_Cell:
+    x: int
+
+fn index(groups: Map<string, [int]>, owners: Map<Grid_Cell, Option<Option<int>>>, seen: Set<Grid_Cell>) -> int:
+    return 0
+"#;
+        assert_rust_contains(source, "groups: std::collections::BTreeMap<MendesString, MendesArray<i64>>");
+        assert_rust_contains(source, "owners: std::collections::BTreeMap<Grid_Cell, MendesOption<MendesOption<i64>>>");
+        assert_rust_contains(source, "seen: std::collections::BTreeSet<Grid_Cell>");
+    }
+
+    #[test]
+    fn test_codegen_map_keys_in_key_order() {
+        let source = r#"
+fn main():
+    let m = {"b": 2, "a": 1}
+    for k in m.keys():
+        print(k)
+"#;
+        // An ordered map makes `keys()` and `values()` ascending by key
+        assert_rust_contains(source, "let mut m: std::collections::BTreeMap<MendesString, i64>;");
+        assert_rust_contains(source, "std::collections::BTreeMap::from([(__str_0(), 2), (__str_1(), 1)]);");
+        assert_rust_contains(source, ".keys().cloned().collect::<MendesArray<_>>()");
+        let rust = compile(source).rust_code.unwrap();
+        assert!(!rust.contains("HashMap"), "{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_map_methods() {
+        let source = r#"
+fn lookup(ages: Map<string, int>) -> int:
+    let found = ages.get("ana")
+    let has = ages.contains_key("ana")
+    let names = ages.keys()
+    return 0
+"#;
+        assert_rust_contains(source, "ages: std::collections::BTreeMap<MendesString, i64>");
+        assert_rust_contains(source, "MendesOption::from(ages.get(&__str_0()).cloned())");
+        assert_rust_contains(source, "let mut found: MendesOption<i64>;");
+        assert_rust_contains(source, "let mut has: bool;");
+        assert_rust_contains(source, "let __t1 = ages.contains_key(&__str_0());");
+        assert_rust_contains(source, "ages.keys().cloned().collect::<MendesArray<_>>()");
+    }
+
+    #[test]
+    fn test_codegen_operator_overloading() {
+        let source = r#"
+struct Vec2:
+    x: float
+    y: float
+
+impl Add for Vec2:
+    fn add(self, other: Vec2) -> Vec2:
+        return Vec2 { x: self.x + other.x, y: self.y + other.y }
+
+impl Ord for Vec2:
+    fn lt(&self, other: &Vec2) -> bool:
+        return self.x < other.x
+
+fn sum(a: Vec2, b: Vec2) -> Vec2:
+    return a + b
+"#;
+        assert_rust_contains(source, "impl std::ops::Add for Vec2 {\n    type Output = Vec2;\n    fn add(self, __arg1: Vec2) -> Vec2 {");
+        assert_rust_contains(source, "impl PartialOrd for Vec2 {");
+        assert_rust_contains(source, "fn lt(&self, __arg1: &Vec2) -> bool {");
+        assert_rust_contains(source, "let __t0 = a + b;");
+    }
+
+    #[test]
+    fn test_codegen_ord_impl_keeps_partial_eq() {
+        let source = r#"
+struct Money:
+    cents: int
+
+impl Eq for Money:
+    fn eq(&self, other: &Money) -> bool:
+        return self.cents == other.cents
+
+impl Ord for Money:
+    fn lt(&self, other: &Money) -> bool:
+        return self.cents < other.cents
+
+struct Rank:
+    level: int
+
+impl Ord for Rank:
+    fn lt(&self, other: &Rank) -> bool:
+        return self.level < other.level
+"#;
+        // The user `==` provides `PartialEq`; without one it is derived
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default)]\npub struct Money");
+        assert_rust_contains(source, "impl PartialEq for Money {");
+        assert_rust_contains(source, "impl PartialOrd for Money {");
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default, PartialEq)]\npub struct Rank");
+        assert_rust_contains(source, "impl PartialOrd for Rank {");
+    }
+
+    #[test]
+    fn test_codegen_async_trait_methods() {
+        let source = r#"
+trait Loader:
+    fn load(&self) -> string async
+
+struct Disk:
+    path: string
+
+impl Loader for Disk:
+    fn load(&self) -> string async:
+        return self.path
+"#;
+        assert_rust_contains(source, "    async fn load(&self) -> MendesString;");
+        assert_rust_contains(source, "    async fn load(&self) -> MendesString {");
+    }
+
+    #[test]
+    fn test_codegen_with_closes_resource() {
+        let source = r#"
+struct Conn:
+    open: bool
+
+    fn close(&mut self):
+        self.open = false
+
+fn fetch(fail: bool) -> int:
+    with Conn { open: true } as conn:
+        if fail:
+            return 0
+        print("querying")
+    return 1
+"#;
+        // Closed on the early return and when the block ends
+        assert_rust_contains(source, "        conn.close();\n        drop(conn);\n        return 0;");
+        assert_rust_contains(source, "print!(\"{}\", __str_0());\n        conn.close();\n        drop(conn);");
+    }
+
+    #[test]
+    fn test_codegen_enum_cast_to_int() {
+        let source = r#"
+enum Color:
+    Red = 1
+    Green
+    Blue = 10
+
+fn encode(color: Color) -> int:
+    return color as int
+
+fn decode(code: int) -> Option<Color>:
+    return Color::from_int(code)
+"#;
+        assert_rust_contains(source, "#[repr(i64)]\npub enum Color {\n    Red = 1,\n    Green = 2,\n    Blue = 10,\n}");
+        assert_rust_contains(source, "let __t0 = color as i64;");
+        assert_rust_contains(source, "            2 => MendesOption::Some(Color::Green),\n            10 => MendesOption::Some(Color::Blue),\n            _ => MendesOption::None,");
+        assert_rust_contains(source, "= Color::from_int(code);");
+    }
+
+    #[test]
+    fn test_codegen_response_headers() {
+        let source = r#"
+api GET /items:
+    return Response
+    return response("ok").with_header("X-Foo", "bar").with_status(201)
+"#;
+        assert_rust_contains(source, "let __t0 = Response::from(__str_0());");
+        assert_rust_contains(source, "let __t1 = __t0.with_header(__str_1(), __str_2());");
+        assert_rust_contains(source, "let __t2 = __t1.with_status(201);");
+    }
+
+    #[test]
+    fn test_codegen_redirect() {
+        let source = r#"
+api GET /old:
+    return Response
+    return redirect_permanent("/new")
+
+api GET /account:
+    return Response
+    return redirect("/login")
+"#;
+        assert_rust_contains(source, "Response::redirect_permanent(__str_0().as_str());");
+        assert_rust_contains(source, "Response::redirect(__str_1().as_str());");
+    }
+
+    #[test]
+    fn test_codegen_user_fn_shadows_redirect() {
+        let source = r#"
+fn redirect(n: int) -> int:
+    return n + 1
+
+fn main():
+    print(redirect(1))
+"#;
+        assert_rust_contains(source, "= redirect(1);");
+        let rust = compile(source).rust_code.unwrap();
+        assert!(!rust.contains("Response::redirect"), "{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_std_math_import() {
+        let source = r#"
+from std.math import sqrt, pi
+
+fn circle(r: float) -> float:
+    return sqrt(r) * pi
+"#;
+        assert_rust_contains(source, "let __t0 = f64::sqrt(r);");
+        assert_rust_contains(source, "let __t1 = std::f64::consts::PI;");
+    }
+
+    #[test]
+    fn test_codegen_std_collections_range() {
+        let source = r#"
+from std.collections import range
+
+fn total() -> int:
+    let xs = range(0, 4)
+    return xs.sum()
+"#;
+        assert_rust_contains(source, "(0..4).collect::<MendesArray<i64>>();");
+    }
+
+    #[test]
+    fn test_codegen_map_literal() {
+        let source = r#"
+fn ages() -> int:
+    let m = {"ana": 31, "bia": 27}
+    return m.len()
+"#;
+        assert_rust_contains(source, "let mut m: std::collections::BTreeMap<MendesString, i64>;");
+        assert_rust_contains(source, "std::collections::BTreeMap::from([(__str_0(), 31), (__str_1(), 27)]);");
+    }
+
+    #[test]
+    fn test_codegen_map_literal_entry_types() {
+        let source = r#"
+struct Point:
+    x: int
+    y: int
+
+fn count() -> int:
+    let origin = Point { x: 0, y: 0 }
+    let points = {"origin": origin, "one": Point { x: 1, y: 1 }}
+    let lists = {"a": [1, 2], "b": [3, 4, 5]}
+    return points.len() + lists.len()
+"#;
+        assert_rust_contains(source, "let mut points: std::collections::BTreeMap<MendesString, Point>;");
+        assert_rust_contains(source, "let mut lists: std::collections::BTreeMap<MendesString, MendesArray<i64>>;");
+    }
+
+    #[test]
+    fn test_codegen_tuple_index() {
+        let source = r#"
+fn second() -> string:
+    let p = (1, "a")
+    return p.1
+"#;
+        assert_rust_contains(source, "let __t2 = __t1.1.clone();");
+    }
+
+    #[test]
+    fn test_codegen_tuple_index_let_types() {
+        let source = r#"
+fn pair() -> (int, string):
+    return (1, "a")
+
+fn second(p: (int, string)) -> string:
+    let s = p.1
+    return s
+
+fn first() -> int:
+    let t = pair()
+    let n = t.0
+    return n
+"#;
+        let rust = compile(source).rust_code.unwrap();
+        assert!(rust.contains("let mut s: MendesString;"), "{}", rust);
+        assert!(rust.contains("let mut n: i64;"), "{}", rust);
+    }
+
+    #[test]
+    fn test_codegen_numeric_cast() {
+        let source = r#"
+fn average(total: int, count: int) -> float:
+    return total as float / count as float
+"#;
+        assert_rust_contains(source, "let __t0 = total as f64;");
+        assert_rust_contains(source, "let __t1 = count as f64;");
+    }
+
+    #[test]
+    fn test_codegen_const_fn_array_length() {
+        let source = r#"
+const fn square(x: int) -> int:
+    return x * x
+
+fn grid() -> [int]:
+    return [0; square(4)]
+"#;
+        assert_rust_contains(source, "const fn square(x: i64) -> i64 {");
+        assert_rust_contains(source, "= vec![0; 16 as usize];");
+    }
+
+    #[test]
+    fn test_codegen_qualified_enum_variants() {
+        let source = r#"
+enum Shape:
+    Circle(float)
+    Empty
+
+fn make(empty: bool) -> Shape:
+    if empty:
+        return Shape::Empty
+    return Shape::Circle(1.5)
+"#;
+        assert_rust_contains(source, "return Shape::Empty;");
+        assert_rust_contains(source, "= Shape::Circle(1.500000_f64);");
+    }
+
+    #[test]
+    fn test_codegen_visibility_levels() {
+        let source = r#"
+pub(crate) struct Config:
+    port: int
+
+pub(crate) fn default_port() -> int:
+    return 8080
+
+pub fn version() -> int:
+    return 1
+
+fn helper() -> int:
+    return 0
+
+pub const LIMIT: int = 10
+const STEP: int = 2
+"#;
+        assert_rust_contains(source, "pub(crate) struct Config {");
+        assert_rust_contains(source, "pub(crate) fn default_port() -> i64 {");
+        assert_rust_contains(source, "pub fn version() -> i64 {");
+        assert_rust_contains(source, "\nfn helper() -> i64 {");
+        assert_rust_contains(source, "pub const __const_LIMIT: i64 = 10;");
+        assert_rust_contains(source, "\nconst __const_STEP: i64 = 2;");
+    }
+
+    #[test]
+    fn test_codegen_str_with_precision() {
+        let source = r#"
+fn show(x: float, digits: int) -> string:
+    let fixed = str(x, 2)
+    return str(x, digits)
+"#;
+        assert_rust_contains(source, "MendesString::new(&format!(\"{:.*}\", 2 as usize, x))");
+        assert_rust_contains(source, "MendesString::new(&format!(\"{:.*}\", digits as usize, x))");
+    }
+
+    #[test]
+    fn test_codegen_format_named() {
+        let source = r#"
+fn greet(who: string) -> string:
+    return "Hello, {name}!".format(name: who)
+"#;
+        assert_rust_contains(source, "format!(\"Hello, {}!\", who)");
+    }
+
+    #[test]
+    fn test_codegen_string_interpolation() {
+        let source = r#"
+fn greet(who: string, n: int) -> string:
+    let braces = f"{{n}}"
+    return f"Hi {who}, {{n}} is {n + 1}"
+"#;
+        // One `format!` with the literal text inline and its braces escaped
+        assert_rust_contains(source, "MendesString::new(&format!(\"Hi {}, {{n}} is {}\", who, __t");
+        assert_rust_contains(source, "MendesString::new(\"{n}\")");
+    }
+
+    #[test]
+    fn test_codegen_string_lines_and_split() {
+        let source = r#"
+fn count(text: string) -> int:
+    let mut n = 0
+    for line in text.lines():
+        n = n + line.split(",").len()
+    return n
+"#;
+        assert_rust_contains(source, "= text.lines();");
+        assert_rust_contains(source, "let mut line: MendesString;");
+        assert_rust_contains(source, ".split(__str_0());");
+    }
+
+    #[test]
+    fn test_codegen_unit_return() {
+        let source = r#"
+fn nothing():
+    return ()
+"#;
+        assert_rust_contains(source, "fn nothing() -> () {");
+        assert_rust_contains(source, "let __t0 = ();");
+    }
+
+    #[test]
+    fn test_codegen_generator() {
+        let source = r#"
+fn counter(n: int) -> [int]:
+    for i in 0..n:
+        yield i * 10
+
+fn main():
+    let mut total = 0
+    for x in counter(5):
+        total = total + x
+    println(total)
+"#;
+        // The body runs lazily inside a `Generator`, consumed one item at a time
+        assert_rust_contains(source, "fn counter(n: i64) -> mendes_runtime::Generator<i64> {");
+        assert_rust_contains(source, "mendes_runtime::Generator::new(move |__yield| {");
+        assert_rust_contains(source, "__yield.send(__t");
+        assert_rust_contains(source, "= counter(5);");
+        assert_rust_contains(source, "while let Some(__t");
+        assert_rust_builds(source);
+    }
+
+    #[test]
+    fn test_codegen_api_body_struct_derives_serde() {
+        let source = r#"
+struct Address:
+    city: string
+
+struct Tag:
+    label: string
+
+enum Role:
+    Admin
+    Tagged(Tag)
+
+struct NewUser:
+    name: string
+    address: Option<Address>
+
+struct User:
+    id: int
+    name: string
+
+struct Stats:
+    count: int
+
+api POST /users:
+    body NewUser
+    return User
+    return User { id: 1, name: body.name }
+"#;
+        // The body, the response and the structs nested in them serialize,
+        // as do enum payloads since enums always derive serde
+        assert_rust_contains(source, "#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]\npub struct NewUser {");
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]\npub struct Address {");
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]\npub struct Tag {");
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]\npub struct User {");
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default)]\npub struct Stats {");
+    }
+
+    #[test]
+    fn test_codegen_unqualified_variant() {
+        let source = r#"
+enum Color:
+    Red
+    Green
+
+enum Light:
+    Red
+    Yellow
+
+fn pick() -> Light:
+    return Red
+
+fn is_red(c: Color) -> bool:
+    match c:
+        Red:
+            return true
+        Green:
+            return false
+"#;
+        // The return type picks the enum of the short name, and a bare
+        // variant in a pattern is tested rather than bound
+        assert_rust_contains(source, "return Light::Red;");
+        assert_rust_contains(source, "matches!(c, Color::Red { .. })");
+    }
+
+    #[test]
+    fn test_codegen_match_on_field_uses_checked_enum() {
+        let source = r#"
+enum Color:
+    Red
+    Green
+
+enum Light:
+    Red
+    Yellow
+
+struct Car:
+    paint: Color
+
+fn is_red(car: Car) -> bool:
+    match car.paint:
+        Red:
+            return true
+        Green:
+            return false
+"#;
+        // Both enums declare `Red`; the enum must come from the field type
+        // on every run, not from whichever enum a lookup finds first
+        for _ in 0..8 {
+            let rust = compile(source).rust_code.expect("compiles");
+            assert!(rust.contains("matches!(__t0, Color::Red { .. })"), "{}", rust);
+        }
+    }
+
+    #[test]
+    fn test_codegen_struct_default() {
+        let source = r#"
+struct Point:
+    x: int
+    y: int
+
+fn origin() -> Point:
+    return Point::default()
+"#;
+        assert_rust_contains(source, "#[derive(Debug, Clone, Default)]\npub struct Point {");
+        assert_rust_contains(source, "Point::default()");
+    }
+
     #[test]
     fn test_ir_function_generation() {
         assert_ir_contains(